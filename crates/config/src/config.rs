@@ -1,18 +1,26 @@
 //! The config module provides the internal representation of Bulwark's configuration.
 
-use crate::ResolutionError;
+use crate::{OciReferenceError, ResolutionError};
+use bulwark_decision::{Decision, Outcome, ThresholdError};
 use bytes::Bytes;
 use itertools::Itertools;
 use regex::Regex;
 use serde::Serialize;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::{Display, Formatter};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::path::PathBuf;
+use std::str::FromStr;
 use url::Url;
 use validator::Validate;
 
 lazy_static! {
     static ref RE_VALID_REFERENCE: Regex = Regex::new(r"^[a-z_]([a-z0-9_])*$").unwrap();
+    static ref RE_VALID_OCI_REPOSITORY: Regex =
+        Regex::new(r"^[a-z0-9]+((\.|_|__|-+)[a-z0-9]+)*(/[a-z0-9]+((\.|_|__|-+)[a-z0-9]+)*)*$")
+            .unwrap();
+    static ref RE_VALID_OCI_TAG: Regex =
+        Regex::new(r"^[a-zA-Z0-9_][a-zA-Z0-9._-]{0,127}$").unwrap();
 }
 
 /// The root of a Bulwark configuration.
@@ -28,10 +36,22 @@ pub struct Config {
     pub state: State,
     /// Configuration for the decision thresholds.
     pub thresholds: Thresholds,
+    /// Configuration for the headers sent to the interior service.
+    pub headers: Headers,
+    /// Configuration for the response sent when a request is blocked.
+    ///
+    /// Individual resources may override this.
+    pub block: BlockResponse,
+    /// Configuration for the action taken for each outcome.
+    ///
+    /// Individual resources may override this.
+    pub actions: Actions,
     /// Configuration for metrics collection.
     pub metrics: Metrics,
     /// A list of configurations for individual secrets.
     pub secrets: Vec<Secret>,
+    /// A list of sets of public keys that plugin signatures are checked against.
+    pub trust: Vec<Trust>,
     /// A list of configurations for individual plugins.
     pub plugins: Vec<Plugin>,
     /// A list of plugin groups that allows a plugin set to be loaded with a single reference.
@@ -56,6 +76,20 @@ impl Config {
             .find(|&secret| secret.reference == reference)
     }
 
+    /// Looks up the [`Trust`] corresponding to the `reference` string.
+    ///
+    /// # Arguments
+    ///
+    /// * `reference` - A string that corresponds to a [`Trust::reference`] value.
+    pub fn trust<'a>(&self, reference: &str) -> Option<&Trust>
+    where
+        Trust: 'a,
+    {
+        self.trust
+            .iter()
+            .find(|&trust| trust.reference == reference)
+    }
+
     /// Looks up the [`Plugin`] corresponding to the `reference` string.
     ///
     /// # Arguments
@@ -88,8 +122,26 @@ impl Config {
 /// Configuration for the services being launched.
 #[derive(Debug, Clone)]
 pub struct Service {
+    /// The IP address the primary service listens on.
+    ///
+    /// Use `::` to listen on all IPv4 and IPv6 interfaces, or `127.0.0.1` to only accept local connections.
+    pub address: IpAddr,
     /// The port for the primary service.
     pub port: u16,
+    /// A Unix domain socket the primary service listens on instead of [`address`](Service::address) and
+    /// [`port`](Service::port).
+    ///
+    /// A socket file left behind by a previous process is replaced, but the service fails to start if another
+    /// process is still accepting connections on it.
+    ///
+    /// This value is unused by the reverse proxy.
+    pub socket: Option<PathBuf>,
+    /// The TLS configuration for the primary service, or `None` if connections should be plaintext.
+    ///
+    /// This value is unused by the reverse proxy.
+    pub tls: Option<Tls>,
+    /// The IP address the admin service listens on.
+    pub admin_address: IpAddr,
     /// The port for the admin service and health checks.
     pub admin_port: u16,
     /// True if the admin service is enabled, false otherwise.
@@ -101,25 +153,86 @@ pub struct Service {
     /// headers are not spoofed. If this is set incorrectly, the client IP reported to plugins will be incorrect.
     pub proxy_hops: u8,
     // TODO: it may be useful to introduce an "auto" setting for `proxy_hops` since it's possible to auto-discover
+    /// The upstream service that requests are forwarded to when Bulwark is launched as a reverse proxy.
+    ///
+    /// Only `http` upstreams are supported. This value is unused by the Envoy external processor.
+    pub upstream: Option<Url>,
+    /// The maximum size in bytes of a request or response body buffered by the reverse proxy.
+    ///
+    /// Larger requests are rejected with a `413` status. This value is unused by the Envoy external processor.
+    pub max_body_size: usize,
+    /// The maximum amount of time in milliseconds the reverse proxy will wait for the upstream to respond.
+    ///
+    /// Requests that time out receive a `504` status. This value is unused by the Envoy external processor.
+    pub upstream_timeout: u64,
+    /// The maximum amount of time in milliseconds to wait for in-flight requests to finish during shutdown.
+    ///
+    /// Requests still running when this expires are dropped. This should be shorter than the grace period given by
+    /// the process supervisor, e.g. Kubernetes' `terminationGracePeriodSeconds`.
+    pub drain_timeout: u64,
 }
 
+/// The default [`Service::address`] and [`Service::admin_address`] value.
+pub const DEFAULT_ADDRESS: IpAddr = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
 /// The default [`Service::port`] value.
 pub const DEFAULT_PORT: u16 = 8089;
 /// The default [`Service::admin_port`] value.
 pub const DEFAULT_ADMIN_PORT: u16 = 8090;
+/// The default [`Service::max_body_size`] value.
+pub const DEFAULT_MAX_BODY_SIZE: usize = 8 * 1024 * 1024;
+/// The default [`Service::upstream_timeout`] value.
+pub const DEFAULT_UPSTREAM_TIMEOUT: u64 = 30_000;
+/// The default [`Service::drain_timeout`] value.
+pub const DEFAULT_DRAIN_TIMEOUT: u64 = 25_000;
 
 impl Default for Service {
     /// Default service config
     fn default() -> Self {
         Self {
+            address: DEFAULT_ADDRESS,
             port: DEFAULT_PORT,
+            socket: None,
+            tls: None,
+            admin_address: DEFAULT_ADDRESS,
             admin_port: DEFAULT_ADMIN_PORT,
             admin_enabled: true,
             proxy_hops: 0,
+            upstream: None,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            upstream_timeout: DEFAULT_UPSTREAM_TIMEOUT,
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
         }
     }
 }
 
+impl Service {
+    /// The socket address the primary service listens on when it isn't using a Unix domain socket.
+    pub fn socket_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.address, self.port)
+    }
+
+    /// The socket address the admin service listens on.
+    pub fn admin_socket_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.admin_address, self.admin_port)
+    }
+}
+
+/// Configuration for serving the primary service over TLS.
+///
+/// Paths are resolved relative to the config file that declared them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tls {
+    /// The PEM-encoded certificate chain presented to clients.
+    pub cert: PathBuf,
+    /// The PEM-encoded private key for the certificate.
+    pub key: PathBuf,
+    /// The PEM-encoded certificate authority that client certificates must be signed by.
+    ///
+    /// If set, clients must present a valid certificate (mutual TLS). Otherwise, client certificates aren't
+    /// requested.
+    pub client_ca: Option<PathBuf>,
+}
+
 /// Configuration for the runtime environment.
 #[derive(Debug, Clone)]
 pub struct Runtime {
@@ -127,6 +240,76 @@ pub struct Runtime {
     pub max_concurrent_requests: usize,
     /// The maximum number of concurrent plugin tasks that the runtime will launch.
     pub max_plugin_tasks: usize,
+    /// The number of threads the async runtime schedules tasks on, or `None` for one per CPU core.
+    pub worker_threads: Option<usize>,
+    /// The maximum number of threads the async runtime spawns for blocking operations, such as file I/O.
+    pub max_blocking_threads: usize,
+    /// The name given to the async runtime's threads.
+    pub thread_name: String,
+    /// The interval in milliseconds between HTTP/2 keepalive pings sent on gRPC connections, or `None` to disable
+    /// keepalive pings.
+    ///
+    /// This value is unused by the reverse proxy.
+    pub http2_keepalive_interval: Option<u64>,
+    /// The amount of time in milliseconds to wait for a keepalive ping to be acknowledged before closing a gRPC
+    /// connection, or `None` for the gRPC server's default of 20 seconds.
+    ///
+    /// This value is unused by the reverse proxy.
+    pub http2_keepalive_timeout: Option<u64>,
+    /// The maximum number of concurrent streams on each gRPC connection, or `None` for no limit.
+    ///
+    /// This value is unused by the reverse proxy.
+    pub max_concurrent_streams: Option<u32>,
+    /// True if Nagle's algorithm should be disabled on gRPC connections, sending small messages immediately.
+    ///
+    /// This value is unused by the reverse proxy.
+    pub tcp_nodelay: bool,
+    /// The number of pre-instantiated instances kept ready for each plugin, or zero to instantiate every plugin
+    /// when a request arrives.
+    ///
+    /// Each idle instance holds its own linear memory, so this trades memory for keeping instantiation and `init` off
+    /// the request path. The default matches the default `max_concurrent_requests`, so that a full set of concurrent
+    /// requests is served from warm instances while the pool refills in the background.
+    pub plugin_pool_size: usize,
+    /// The number of requests a pooled plugin instance handles before it's discarded and replaced.
+    ///
+    /// The default of one gives every request a fresh instance. Values above one leak state between requests. They
+    /// save the cost of instantiation, but only the host's side of an instance is reset when it's reused: the guest's
+    /// linear memory and globals are left as the previous request left them. Anything a plugin keeps in memory,
+    /// including data from earlier requests such as headers or bodies it buffered, is visible to the requests that
+    /// follow, so only raise this for plugins that are trusted not to retain request data.
+    pub plugin_instance_max_uses: usize,
+    /// True if plugin memory should be allocated by wasmtime's pooling allocator rather than on demand.
+    ///
+    /// The pooling allocator reserves space up front for `plugin_pool_size` plus `max_concurrent_requests` instances
+    /// of each plugin, which makes instantiation cheaper, but no more instances than that may exist at once.
+    pub pooling_allocator: bool,
+    /// A directory in which compiled plugins and remote plugin downloads are cached, or `None` to compile and
+    /// download every plugin each time it's loaded.
+    ///
+    /// Cached artifacts are keyed by the plugin's contents and the engine settings they were compiled with, so a
+    /// plugin that hasn't changed is only compiled once across restarts and reloads. The directory must only be
+    /// writable by Bulwark, since its contents are loaded as native code without any further verification.
+    ///
+    /// Remote plugins are cached by URL and expected digest. A remote plugin with a digest is loaded from the cache
+    /// without fetching it, and any remote plugin falls back to its last verified copy if it can't be fetched.
+    pub plugin_cache_dir: Option<PathBuf>,
+    /// The maximum time in milliseconds a single attempt to fetch a remote plugin may take.
+    pub plugin_fetch_timeout: u64,
+    /// The number of times a failed remote plugin fetch is retried, with exponential backoff, before giving up.
+    ///
+    /// Only timeouts, connection failures, and server errors are retried.
+    pub plugin_fetch_retries: u32,
+    /// True if every plugin must have a digest or signature to verify it against, whatever its location.
+    ///
+    /// A plugin with [`PluginVerification::None`] fails to load.
+    pub require_verification: bool,
+    /// True if a local plugin's precompiled `.pwasm` artifact should be loaded in place of compiling the plugin.
+    ///
+    /// A precompiled artifact is native code, and its header can't prove where it came from, so anyone who can
+    /// write the file can run arbitrary code in Bulwark. Its plugin's digest or signature is checked against the
+    /// `.wasm` file, not the artifact. Only enable this if the plugin directory is as trusted as the Bulwark binary.
+    pub trust_precompiled: bool,
 }
 
 /// The default [`Runtime::max_concurrent_requests`] value.
@@ -135,12 +318,45 @@ pub const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 8;
 /// The default [`Runtime::max_plugin_tasks`] value.
 pub const DEFAULT_MAX_PLUGIN_TASKS: usize = 16;
 
+/// The default [`Runtime::max_blocking_threads`] value.
+pub const DEFAULT_MAX_BLOCKING_THREADS: usize = 512;
+
+/// The default [`Runtime::thread_name`] value.
+pub const DEFAULT_THREAD_NAME: &str = "bulwark-worker";
+
+/// The default [`Runtime::plugin_pool_size`] value.
+pub const DEFAULT_PLUGIN_POOL_SIZE: usize = DEFAULT_MAX_CONCURRENT_REQUESTS;
+
+/// The default [`Runtime::plugin_instance_max_uses`] value.
+pub const DEFAULT_PLUGIN_INSTANCE_MAX_USES: usize = 1;
+
+/// The default [`Runtime::plugin_fetch_timeout`] value.
+pub const DEFAULT_PLUGIN_FETCH_TIMEOUT: u64 = 30_000;
+
+/// The default [`Runtime::plugin_fetch_retries`] value.
+pub const DEFAULT_PLUGIN_FETCH_RETRIES: u32 = 3;
+
 impl Default for Runtime {
     /// Default runtime config
     fn default() -> Self {
         Self {
             max_concurrent_requests: DEFAULT_MAX_CONCURRENT_REQUESTS,
             max_plugin_tasks: DEFAULT_MAX_PLUGIN_TASKS,
+            worker_threads: None,
+            max_blocking_threads: DEFAULT_MAX_BLOCKING_THREADS,
+            thread_name: String::from(DEFAULT_THREAD_NAME),
+            http2_keepalive_interval: None,
+            http2_keepalive_timeout: None,
+            max_concurrent_streams: None,
+            tcp_nodelay: false,
+            plugin_pool_size: DEFAULT_PLUGIN_POOL_SIZE,
+            plugin_instance_max_uses: DEFAULT_PLUGIN_INSTANCE_MAX_USES,
+            pooling_allocator: false,
+            plugin_cache_dir: None,
+            plugin_fetch_timeout: DEFAULT_PLUGIN_FETCH_TIMEOUT,
+            plugin_fetch_retries: DEFAULT_PLUGIN_FETCH_RETRIES,
+            require_verification: false,
+            trust_precompiled: false,
         }
     }
 }
@@ -169,7 +385,7 @@ impl Default for State {
 /// No threshold is necessary for the default `allowed` outcome because it is defined by the range between the
 /// `suspicious` threshold and the `trusted` threshold. The thresholds must have values in descending order, with
 /// `restrict` > `suspicious` > `trusted`. None of the threshold values may be equal.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Thresholds {
     /// True if the primary service should take no action in response to restrict decisions.
     pub observe_only: bool,
@@ -204,6 +420,164 @@ impl Default for Thresholds {
     }
 }
 
+impl Thresholds {
+    /// Checks that every threshold is within the 0.0 to 1.0 range and that `trust`, `suspicious`, and `restrict`
+    /// are in ascending order.
+    ///
+    /// Uses the same checks as [`Decision::outcome`](bulwark_decision::Decision::outcome).
+    pub fn validate(&self) -> Result<(), ThresholdError> {
+        Decision::default()
+            .outcome(self.trust, self.suspicious, self.restrict)
+            .map(|_| ())
+    }
+}
+
+/// Configuration for the headers sent to the interior service when a request is allowed.
+///
+/// Any header named here is removed from incoming requests if Bulwark has no value to send for it, which prevents
+/// clients from spoofing Bulwark's output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Headers {
+    /// The name of the header used to send the combined decision and outcome, or `None` if it should not be sent.
+    pub decision: Option<String>,
+    /// The name of the header used to send the combined tags, or `None` if it should not be sent.
+    pub tags: Option<String>,
+    /// Maps label keys to the names of the headers used to send their values.
+    pub labels: BTreeMap<String, String>,
+}
+
+/// The default [`Headers::decision`] value.
+pub const DEFAULT_DECISION_HEADER: &str = "Bulwark-Decision";
+/// The default [`Headers::tags`] value.
+pub const DEFAULT_TAGS_HEADER: &str = "Bulwark-Tags";
+
+impl Default for Headers {
+    /// Default headers config
+    fn default() -> Self {
+        Self {
+            decision: Some(String::from(DEFAULT_DECISION_HEADER)),
+            tags: Some(String::from(DEFAULT_TAGS_HEADER)),
+            labels: BTreeMap::new(),
+        }
+    }
+}
+
+/// Configuration for the response sent when a request is blocked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockResponse {
+    /// The HTTP status code of the response.
+    pub status: u16,
+    /// The value of the `Content-Type` header.
+    pub content_type: String,
+    /// The body template of the response.
+    ///
+    /// The `{{request_id}}` placeholder is replaced with the value of the request's `X-Request-Id` header and the
+    /// `{{tags}}` placeholder is replaced with a comma-separated list of the combined tags.
+    pub body: String,
+    /// Additional headers to send with the response.
+    pub headers: BTreeMap<String, String>,
+}
+
+/// The default [`BlockResponse::status`] value.
+pub const DEFAULT_BLOCK_STATUS: u16 = 403;
+/// The default [`BlockResponse::content_type`] value.
+pub const DEFAULT_BLOCK_CONTENT_TYPE: &str = "text/plain; charset=utf-8";
+/// The default [`BlockResponse::body`] value.
+pub const DEFAULT_BLOCK_BODY: &str = "Access Denied\n";
+
+impl Default for BlockResponse {
+    /// Default block response config
+    fn default() -> Self {
+        Self {
+            status: DEFAULT_BLOCK_STATUS,
+            content_type: String::from(DEFAULT_BLOCK_CONTENT_TYPE),
+            body: String::from(DEFAULT_BLOCK_BODY),
+            headers: BTreeMap::new(),
+        }
+    }
+}
+
+/// An action taken for a request once its outcome is known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// The request is sent to the interior service.
+    Allow,
+    /// The request is blocked with the resource's [`BlockResponse`].
+    Block,
+    /// The client is redirected to another URL, e.g. a challenge page.
+    Redirect {
+        /// The URL sent in the `Location` header.
+        location: String,
+        /// The HTTP status code of the redirect.
+        status: u16,
+    },
+    /// The request is delayed before being sent to the interior service.
+    Tarpit {
+        /// The delay in milliseconds.
+        delay: u64,
+    },
+    /// The request is sent to the interior service with an additional header.
+    AddHeader {
+        /// The header name.
+        name: String,
+        /// The header value.
+        value: String,
+    },
+}
+
+/// The default status code for a [`Action::Redirect`].
+pub const DEFAULT_REDIRECT_STATUS: u16 = 302;
+
+/// Configuration for the action taken for each outcome.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Actions {
+    /// The action taken for trusted requests.
+    pub trusted: Action,
+    /// The action taken for accepted requests.
+    pub accepted: Action,
+    /// The action taken for suspected requests.
+    pub suspected: Action,
+    /// The action taken for restricted requests.
+    pub restricted: Action,
+    /// The longest delay in milliseconds a tarpit may hold a request for.
+    ///
+    /// A tarpitted request keeps its place among the `max_concurrent_requests` while it waits, so longer tarpits
+    /// requested by plugins are shortened to this. Configured tarpits must not exceed it.
+    pub max_tarpit_delay: u64,
+}
+
+/// The default [`Actions::max_tarpit_delay`] value.
+pub const DEFAULT_MAX_TARPIT_DELAY: u64 = 10_000;
+
+impl Actions {
+    /// Returns the action configured for an outcome.
+    ///
+    /// # Arguments
+    ///
+    /// * `outcome` - The outcome of the combined decision.
+    pub fn action(&self, outcome: Outcome) -> &Action {
+        match outcome {
+            Outcome::Trusted => &self.trusted,
+            Outcome::Accepted => &self.accepted,
+            Outcome::Suspected => &self.suspected,
+            Outcome::Restricted => &self.restricted,
+        }
+    }
+}
+
+impl Default for Actions {
+    /// Default actions config, which only blocks restricted requests
+    fn default() -> Self {
+        Self {
+            trusted: Action::Allow,
+            accepted: Action::Allow,
+            suspected: Action::Allow,
+            restricted: Action::Block,
+            max_tarpit_delay: DEFAULT_MAX_TARPIT_DELAY,
+        }
+    }
+}
+
 /// Configuration for metrics collection.
 #[derive(Debug, Clone)]
 pub struct Metrics {
@@ -262,6 +636,18 @@ impl Default for SecretLocation {
     }
 }
 
+/// A set of public keys that are trusted to sign plugins.
+#[derive(Debug, Validate, Clone, Default)]
+pub struct Trust {
+    /// The trust reference key. Should be limited to ASCII lowercase a-z plus underscores. Maximum 96 characters.
+    #[validate(length(min = 1, max = 96), regex(path = "RE_VALID_REFERENCE"))]
+    pub reference: String,
+    /// The raw 32-byte Ed25519 public keys that plugin signatures are checked against.
+    ///
+    /// A signature from any one of the keys is accepted, so a new key can be added before plugins are signed with it.
+    pub ed25519: Vec<Bytes>,
+}
+
 /// The location where the plugin WASM can be loaded from.
 #[derive(Debug, Clone)]
 pub enum PluginLocation {
@@ -269,6 +655,8 @@ pub enum PluginLocation {
     Local(PathBuf),
     /// The plugin is a remote file served over HTTPS.
     Remote(Url),
+    /// The plugin is an artifact stored in an OCI registry.
+    Oci(OciReference),
     /// The plugin is an binary blob.
     Bytes(Bytes),
 }
@@ -285,11 +673,106 @@ impl Display for PluginLocation {
         match self {
             PluginLocation::Local(path) => write!(f, "[local: {}]", path.display()),
             PluginLocation::Remote(uri) => write!(f, "[remote: {}]", uri),
+            PluginLocation::Oci(reference) => write!(f, "[oci: {}]", reference),
             PluginLocation::Bytes(bytes) => write!(f, "[{} bytes]", bytes.len()),
         }
     }
 }
 
+/// A reference to a plugin artifact in an OCI registry, e.g. `registry.example.com/team/plugin:1.2`.
+///
+/// The registry host is always required, since there's no default registry for plugins. A reference may be pinned
+/// to a manifest digest by appending `@sha256:<hex>`, in which case the tag, if any, is informational only.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OciReference {
+    /// The registry host, including the port if there is one.
+    pub registry: String,
+    /// The repository within the registry, e.g. `team/plugin`.
+    pub repository: String,
+    /// The tag the plugin was published under.
+    pub tag: Option<String>,
+    /// The raw SHA-256 digest of the manifest the reference is pinned to.
+    pub digest: Option<Bytes>,
+}
+
+impl OciReference {
+    /// The tag used when a reference has neither a tag nor a digest.
+    pub const DEFAULT_TAG: &'static str = "latest";
+
+    /// Returns the tag or digest that the registry should resolve the manifest from.
+    ///
+    /// A digest takes precedence over a tag so that a pinned reference can never resolve to anything else.
+    pub fn manifest_reference(&self) -> String {
+        match (&self.digest, &self.tag) {
+            (Some(digest), _) => format!("sha256:{}", hex::encode(digest)),
+            (None, Some(tag)) => tag.clone(),
+            (None, None) => String::from(Self::DEFAULT_TAG),
+        }
+    }
+}
+
+impl FromStr for OciReference {
+    type Err = OciReferenceError;
+
+    fn from_str(reference: &str) -> Result<Self, Self::Err> {
+        let (name, digest) = match reference.split_once('@') {
+            Some((name, digest)) => (name, Some(digest)),
+            None => (reference, None),
+        };
+        let digest = digest
+            .map(|digest| match digest.split_once(':') {
+                Some(("sha256", hex_digest)) if hex_digest.len() == 64 => hex::decode(hex_digest)
+                    .map(Bytes::from)
+                    .map_err(|_| OciReferenceError::InvalidDigest(digest.to_string())),
+                _ => Err(OciReferenceError::InvalidDigest(digest.to_string())),
+            })
+            .transpose()?;
+
+        // Without a default registry, the first component must look like a host so that a reference like
+        // `team/plugin` isn't silently resolved against some registry the operator didn't intend.
+        let (registry, path) = match name.split_once('/') {
+            Some((registry, path))
+                if registry.contains('.') || registry.contains(':') || registry == "localhost" =>
+            {
+                (registry, path)
+            }
+            _ => return Err(OciReferenceError::MissingRegistry(reference.to_string())),
+        };
+        let (repository, tag) = match path.rsplit_once(':') {
+            Some((repository, tag)) if !tag.contains('/') => (repository, Some(tag)),
+            _ => (path, None),
+        };
+        if !RE_VALID_OCI_REPOSITORY.is_match(repository) {
+            return Err(OciReferenceError::InvalidRepository(repository.to_string()));
+        }
+        if let Some(tag) = tag {
+            if !RE_VALID_OCI_TAG.is_match(tag) {
+                return Err(OciReferenceError::InvalidTag(tag.to_string()));
+            }
+        }
+
+        Ok(Self {
+            registry: registry.to_string(),
+            repository: repository.to_string(),
+            tag: tag.map(String::from),
+            digest,
+        })
+    }
+}
+
+impl Display for OciReference {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        write!(f, "{}/{}", self.registry, self.repository)?;
+        if let Some(tag) = &self.tag {
+            write!(f, ":{}", tag)?;
+        }
+        if let Some(digest) = &self.digest {
+            write!(f, "@sha256:{}", hex::encode(digest))?;
+        }
+        Ok(())
+    }
+}
+
 /// The access control applied to the plugin.
 ///
 /// Typically used in conjunction with privately distributed remote plugins.
@@ -320,6 +803,17 @@ pub enum PluginVerification {
     None,
     /// The plugin is hashed with a SHA-256 digest.
     Sha256(Bytes),
+    /// The plugin is signed with an Ed25519 detached signature from one of the keys in a [`Trust`].
+    ///
+    /// Unlike a digest, the configuration doesn't need to change when the plugin is upgraded, as long as the new
+    /// version is signed by a trusted key.
+    Ed25519 {
+        /// The reference of the [`Trust`] whose keys the signature is checked against.
+        trust: String,
+        /// The raw 64-byte signature, or `None` to read it from alongside the plugin, at its path or URI with `.sig`
+        /// appended.
+        signature: Option<Bytes>,
+    },
 }
 
 impl Default for PluginVerification {
@@ -357,11 +851,46 @@ pub struct Plugin {
     ///
     /// Any attempt to perform an operation within the plugin sandbox that requires a permission to be set will fail.
     pub permissions: Permissions,
+    /// The maximum amount of fuel a single call into the plugin may consume, or `None` for no limit.
+    ///
+    /// Fuel is consumed roughly in proportion to the number of WASM instructions executed, so unlike a timeout, it
+    /// stops a plugin stuck in a tight loop. A call that runs out of fuel fails with a trap.
+    #[validate(range(min = 1))]
+    pub max_fuel: Option<u64>,
+    /// The maximum time in milliseconds a single call into the plugin may run before it's interrupted, or `None` for
+    /// no limit.
+    ///
+    /// The deadline is checked by epoch interruption, in increments of [`EPOCH_TICK_INTERVAL`] milliseconds, so
+    /// unlike a resource's `timeout`, it can preempt a plugin that never yields.
+    #[validate(range(min = 1))]
+    pub epoch_deadline: Option<u64>,
+    /// The maximum size in bytes that any one of the plugin's linear memories may grow to, or `None` for no limit.
+    ///
+    /// A call that tries to grow a memory past this size fails with a trap.
+    #[validate(range(min = 1))]
+    pub max_memory: Option<usize>,
+    /// The maximum number of elements that any one of the plugin's tables may grow to, or `None` for no limit.
+    #[validate(range(min = 1))]
+    pub max_table_elements: Option<u32>,
+    /// The maximum number of core WASM instances a single instance of the plugin may create, or `None` for no limit.
+    ///
+    /// Components are made up of one or more core instances, so this must leave room for all of them.
+    #[validate(range(min = 1))]
+    pub max_instances: Option<usize>,
+    /// The maximum number of bytes the plugin may write to each of `stdout` and `stderr` during a single request, or
+    /// `None` for no limit.
+    #[validate(range(min = 1))]
+    pub max_stdio_size: Option<usize>,
 }
 
 /// The default [`Plugin::weight`] value.
 pub const DEFAULT_PLUGIN_WEIGHT: f64 = 1.0;
 
+/// The interval in milliseconds at which plugin epoch deadlines are checked.
+///
+/// See [`Plugin::epoch_deadline`].
+pub const EPOCH_TICK_INTERVAL: u64 = 10;
+
 /// The permissions granted to an associated plugin.
 #[derive(Debug, Clone, Default)]
 pub struct Permissions {
@@ -455,6 +984,14 @@ pub struct Resource {
     pub plugins: Vec<Reference>,
     /// The maximum amount of time a plugin may take for each execution phase.
     pub timeout: Option<u64>,
+    /// The response sent when a request for this resource is blocked.
+    ///
+    /// Any fields the resource doesn't set are inherited from the global block response.
+    pub block: BlockResponse,
+    /// The action taken for each outcome of requests for this resource.
+    ///
+    /// Any outcomes the resource doesn't set are inherited from the global actions.
+    pub actions: Actions,
 }
 
 impl Resource {
@@ -23,12 +23,70 @@ pub enum ConfigFileError {
     CircularInclude(String),
     #[error("duplicate named plugin or preset: '{0}'")]
     Duplicate(String),
+    #[error("invalid service config: {0}")]
+    InvalidServiceConfig(String),
+    #[error("invalid runtime config: {0}")]
+    InvalidRuntimeConfig(String),
+    #[error("invalid thresholds config: {0}")]
+    InvalidThresholdsConfig(String),
+    #[error("invalid headers config: {0}")]
+    InvalidHeadersConfig(String),
+    #[error("invalid block config: {0}")]
+    InvalidBlockConfig(String),
+    #[error("invalid actions config: {0}")]
+    InvalidActionsConfig(String),
     #[error("invalid secret config: {0}")]
     InvalidSecretConfig(String),
+    #[error("invalid trust config: {0}")]
+    InvalidTrustConfig(String),
     #[error("invalid plugin config: {0}")]
     InvalidPluginConfig(String),
     #[error("invalid resource config: {0}")]
     InvalidResourceConfig(String),
+    #[error(transparent)]
+    Lockfile(#[from] LockfileError),
+}
+
+/// This error will be returned if a config can't be vendored.
+#[derive(thiserror::Error, Debug)]
+pub enum VendorError {
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
+    #[error(transparent)]
+    Parse(#[from] toml_edit::TomlError),
+    #[error("invalid config path: '{0}'")]
+    InvalidPath(String),
+    #[error("output directory must differ from the config directory")]
+    SameDirectory,
+    #[error("include must be a relative path within the config directory: '{0}'")]
+    EscapingInclude(String),
+    #[error("invalid circular include: '{0}'")]
+    CircularInclude(String),
+    #[error("file must be an absolute path or a relative path within the config directory: '{0}'")]
+    EscapingFile(String),
+    #[error("could not copy file: '{0}': {1}")]
+    FileCopy(String, std::io::Error),
+}
+
+/// This error will be returned if a lockfile can't be read, written, or applied to a config.
+#[derive(thiserror::Error, Debug)]
+pub enum LockfileError {
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
+    #[error(transparent)]
+    Deserialization(#[from] toml::de::Error),
+    #[error(transparent)]
+    Serialization(#[from] toml::ser::Error),
+    #[error(transparent)]
+    InvalidHexEncoding(#[from] hex::FromHexError),
+    #[error("unsupported lockfile version: {0}")]
+    UnsupportedVersion(u32),
+    #[error("plugin '{0}' is missing from the lockfile")]
+    Unlocked(String),
+    #[error("plugin '{0}' does not match the lockfile: expected sha256:{1}, got sha256:{2}")]
+    Mismatch(String, String, String),
+    #[error("could not read plugin '{0}': {1}")]
+    UnreadablePlugin(String, std::io::Error),
 }
 
 /// This error will be returned if an attempt to serialize a config structure fails.
@@ -38,6 +96,63 @@ pub enum ConfigSerializationError {
     Json(#[from] serde_json::Error),
 }
 
+/// This error will be returned if an attempt to convert the service config fails.
+#[derive(thiserror::Error, Debug)]
+pub enum ServiceConversionError {
+    #[error(transparent)]
+    InvalidUpstreamUri(#[from] url::ParseError),
+    #[error("upstream uri must be http: '{0}'")]
+    UnsupportedUpstreamUri(String),
+}
+
+/// This error will be returned if an attempt to convert the runtime config fails.
+#[derive(thiserror::Error, Debug)]
+pub enum RuntimeConversionError {
+    #[error("worker_threads must be greater than zero")]
+    ZeroWorkerThreads,
+    #[error("max_blocking_threads must be greater than zero")]
+    ZeroMaxBlockingThreads,
+    #[error("plugin_instance_max_uses must be greater than zero")]
+    ZeroPluginInstanceMaxUses,
+}
+
+/// This error will be returned if an attempt to convert the headers config fails.
+#[derive(thiserror::Error, Debug)]
+pub enum HeadersConversionError {
+    #[error("invalid header name: '{0}'")]
+    InvalidHeaderName(String),
+}
+
+/// This error will be returned if an attempt to convert a block response config fails.
+#[derive(thiserror::Error, Debug)]
+pub enum BlockConversionError {
+    #[error("invalid status code: {0}")]
+    InvalidStatus(u16),
+    #[error("invalid header name: '{0}'")]
+    InvalidHeaderName(String),
+    #[error("invalid value for header '{0}'")]
+    InvalidHeaderValue(String),
+    #[error("body and body_file cannot both be set")]
+    ConflictingBody,
+    #[error("could not read body file '{0}': {1}")]
+    BodyFile(String, std::io::Error),
+}
+
+/// This error will be returned if an attempt to convert an actions config fails.
+#[derive(thiserror::Error, Debug)]
+pub enum ActionsConversionError {
+    #[error("invalid redirect status code: {0}")]
+    InvalidRedirectStatus(u16),
+    #[error("invalid redirect location: '{0}'")]
+    InvalidLocation(String),
+    #[error("invalid header name: '{0}'")]
+    InvalidHeaderName(String),
+    #[error("invalid value for header '{0}'")]
+    InvalidHeaderValue(String),
+    #[error("tarpit delay of {0}ms exceeds max_tarpit_delay of {1}ms")]
+    TarpitDelayTooLong(u64, u64),
+}
+
 /// This error will be returned if an attempt to convert a secret fails.
 #[derive(thiserror::Error, Debug)]
 pub enum SecretConversionError {
@@ -45,15 +160,45 @@ pub enum SecretConversionError {
     InvalidSecretLocation,
 }
 
+/// This error will be returned if an attempt to convert a trust fails.
+#[derive(thiserror::Error, Debug)]
+pub enum TrustConversionError {
+    #[error(transparent)]
+    InvalidHexEncoding(#[from] hex::FromHexError),
+    #[error("ed25519 public keys must be 32 bytes, got {0}")]
+    InvalidKeyLength(usize),
+}
+
 /// This error will be returned if an attempt to convert a plugin fails.
 #[derive(thiserror::Error, Debug)]
 pub enum PluginConversionError {
-    #[error("one and only one of path, uri, or bytes must be set")]
+    #[error("one and only one of path, uri, oci, or bytes must be set")]
     InvalidLocation,
     #[error(transparent)]
     InvalidRemoteUri(#[from] url::ParseError),
     #[error(transparent)]
+    InvalidOciReference(#[from] OciReferenceError),
+    #[error(transparent)]
     InvalidHexEncoding(#[from] hex::FromHexError),
+    #[error("unsupported verification: '{0}'")]
+    UnsupportedVerification(String),
+    #[error("ed25519 signatures must be 64 bytes, got {0}")]
+    InvalidSignatureLength(usize),
+    #[error("signature requires ed25519 verification")]
+    UnexpectedSignature,
+}
+
+/// This error will be returned if an OCI plugin reference can't be parsed.
+#[derive(thiserror::Error, Debug)]
+pub enum OciReferenceError {
+    #[error("oci reference must begin with a registry host: '{0}'")]
+    MissingRegistry(String),
+    #[error("invalid oci repository: '{0}'")]
+    InvalidRepository(String),
+    #[error("invalid oci tag: '{0}'")]
+    InvalidTag(String),
+    #[error("invalid oci digest, only sha256 is supported: '{0}'")]
+    InvalidDigest(String),
 }
 
 /// This error will be returned if attempting to resolve references fails.
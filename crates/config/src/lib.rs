@@ -2,10 +2,14 @@
 
 mod config;
 mod errors;
+mod lock;
 pub mod toml;
+mod vendor;
 
 pub use crate::config::*;
 pub use crate::errors::*;
+pub use crate::lock::*;
+pub use crate::vendor::*;
 
 #[macro_use]
 extern crate lazy_static;
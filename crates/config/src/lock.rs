@@ -0,0 +1,154 @@
+//! The lock module reads, writes, and enforces lockfiles, which pin the contents of every plugin in a configuration.
+
+use crate::{Config, LockfileError, PluginLocation, PluginVerification};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// The name of a lockfile, which is kept alongside the root configuration file it pins.
+pub const LOCKFILE_NAME: &str = "bulwark.lock";
+
+/// The version of the lockfile format that's read and written.
+const LOCKFILE_VERSION: u32 = 1;
+
+/// The comment written at the top of every lockfile.
+const LOCKFILE_HEADER: &str =
+    "# This file is generated by `bulwark-cli lock` to pin the contents of each plugin.\n# It is not intended for manual editing.\n";
+
+/// The SHA-256 digests of the plugins in a configuration, as recorded by `bulwark-cli lock`.
+///
+/// Plugins verified by signature aren't locked, since a signature already allows a plugin to be upgraded without
+/// changing its configuration.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Lockfile {
+    /// The raw digest of each plugin's contents, keyed by the plugin's reference.
+    pub plugins: BTreeMap<String, Bytes>,
+}
+
+impl Lockfile {
+    /// Returns the path of the lockfile for a configuration file.
+    ///
+    /// # Arguments
+    ///
+    /// * `config_path` - The path to the root configuration file.
+    pub fn path_for(config_path: &Path) -> PathBuf {
+        config_path
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(LOCKFILE_NAME)
+    }
+
+    /// Reads a lockfile, returning `None` if there isn't one.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to the lockfile.
+    pub fn read(path: &Path) -> Result<Option<Self>, LockfileError> {
+        let data = match std::fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let lockfile: TomlLockfile = toml::from_str(&data)?;
+        if lockfile.version != LOCKFILE_VERSION {
+            return Err(LockfileError::UnsupportedVersion(lockfile.version));
+        }
+        Ok(Some(Self {
+            plugins: lockfile
+                .plugins
+                .into_iter()
+                .map(|plugin| Ok((plugin.reference, Bytes::from(hex::decode(plugin.sha256)?))))
+                .collect::<Result<_, LockfileError>>()?,
+        }))
+    }
+
+    /// Writes the lockfile.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to write the lockfile to.
+    pub fn write(&self, path: &Path) -> Result<(), LockfileError> {
+        let lockfile = TomlLockfile {
+            version: LOCKFILE_VERSION,
+            plugins: self
+                .plugins
+                .iter()
+                .map(|(reference, digest)| TomlLockedPlugin {
+                    reference: reference.clone(),
+                    sha256: hex::encode(digest),
+                })
+                .collect(),
+        };
+        let data = LOCKFILE_HEADER.to_string() + &toml::to_string(&lockfile)?;
+        std::fs::write(path, data)?;
+        Ok(())
+    }
+
+    /// Pins each plugin in a configuration to the digest it was locked with.
+    ///
+    /// Local plugins and plugins loaded from bytes are checked immediately. Remote and OCI plugins are checked when
+    /// they're fetched, since each is given `sha256` verification with its locked digest.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration whose plugins will be pinned.
+    pub fn apply(&self, config: &mut Config) -> Result<(), LockfileError> {
+        for plugin in &mut config.plugins {
+            if let PluginVerification::Ed25519 { .. } = plugin.verification {
+                continue;
+            }
+            let locked = self
+                .plugins
+                .get(&plugin.reference)
+                .ok_or_else(|| LockfileError::Unlocked(plugin.reference.clone()))?;
+            let mismatch = |digest: &[u8]| {
+                LockfileError::Mismatch(
+                    plugin.reference.clone(),
+                    hex::encode(locked),
+                    hex::encode(digest),
+                )
+            };
+
+            if let PluginVerification::Sha256(digest) = &plugin.verification {
+                if digest != locked {
+                    return Err(mismatch(digest));
+                }
+            }
+            let digest = match &plugin.location {
+                PluginLocation::Local(path) => {
+                    Some(Sha256::digest(std::fs::read(path).map_err(|err| {
+                        LockfileError::UnreadablePlugin(plugin.reference.clone(), err)
+                    })?))
+                }
+                PluginLocation::Bytes(bytes) => Some(Sha256::digest(bytes)),
+                PluginLocation::Remote(_) | PluginLocation::Oci(_) => None,
+            };
+            if let Some(digest) = digest {
+                if digest.as_slice() != &locked[..] {
+                    return Err(mismatch(&digest));
+                }
+            }
+
+            plugin.verification = PluginVerification::Sha256(locked.clone());
+        }
+        Ok(())
+    }
+}
+
+/// The TOML serialization for a [`Lockfile`].
+#[derive(Serialize, Deserialize)]
+struct TomlLockfile {
+    version: u32,
+    #[serde(default, rename(serialize = "plugin", deserialize = "plugin"))]
+    plugins: Vec<TomlLockedPlugin>,
+}
+
+/// The TOML serialization for a single plugin in a [`Lockfile`].
+#[derive(Serialize, Deserialize)]
+struct TomlLockedPlugin {
+    #[serde(rename(serialize = "ref", deserialize = "ref"))]
+    reference: String,
+    sha256: String,
+}
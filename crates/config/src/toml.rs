@@ -3,13 +3,17 @@
 // Due to the need for multiple serialization mappings, TOML deserialization is not done
 // directly in the [`bulwark_config`](crate) module's structs.
 
-use crate::ConfigFileError;
+use crate::{
+    ActionsConversionError, BlockConversionError, ConfigFileError, HeadersConversionError,
+    RuntimeConversionError, ServiceConversionError,
+};
 use bytes::Bytes;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::ffi::OsString;
 use std::fs;
+use std::net::IpAddr;
 use std::path::{Path, PathBuf};
 use url::Url;
 use validator::Validate;
@@ -30,11 +34,19 @@ struct Config {
     #[serde(default)]
     thresholds: Thresholds,
     #[serde(default)]
+    headers: Headers,
+    #[serde(default)]
+    block: BlockResponse,
+    #[serde(default)]
+    actions: Actions,
+    #[serde(default)]
     metrics: Metrics,
     #[serde(default, rename(serialize = "include", deserialize = "include"))]
     includes: Vec<Include>,
     #[serde(default, rename(serialize = "secret", deserialize = "secret"))]
     secrets: Vec<Secret>,
+    #[serde(default)]
+    trust: Vec<Trust>,
     #[serde(default, rename(serialize = "plugin", deserialize = "plugin"))]
     plugins: Vec<Plugin>,
     #[serde(default, rename(serialize = "preset", deserialize = "preset"))]
@@ -46,14 +58,37 @@ struct Config {
 /// The TOML serialization for a [Service](crate::Service) config structure.
 #[derive(Serialize, Deserialize)]
 struct Service {
+    #[serde(default = "default_address")]
+    address: IpAddr,
     #[serde(default = "default_port")]
     port: u16,
+    #[serde(default)]
+    socket: Option<String>,
+    #[serde(default)]
+    tls: Option<Tls>,
+    #[serde(default = "default_address")]
+    admin_address: IpAddr,
     #[serde(default = "default_admin_port")]
     admin_port: u16,
     #[serde(default = "default_admin")]
     admin_enabled: bool,
     #[serde(default = "default_proxy_hops")]
     proxy_hops: u8,
+    #[serde(default)]
+    upstream: Option<String>,
+    #[serde(default = "default_max_body_size")]
+    max_body_size: usize,
+    #[serde(default = "default_upstream_timeout")]
+    upstream_timeout: u64,
+    #[serde(default = "default_drain_timeout")]
+    drain_timeout: u64,
+}
+
+/// The default address for the primary and admin services.
+///
+/// See [`DEFAULT_ADDRESS`].
+fn default_address() -> IpAddr {
+    crate::DEFAULT_ADDRESS
 }
 
 /// The default port for the primary service.
@@ -80,24 +115,102 @@ fn default_proxy_hops() -> u8 {
     0
 }
 
+/// The default maximum body size buffered by the reverse proxy.
+///
+/// See [`DEFAULT_MAX_BODY_SIZE`].
+fn default_max_body_size() -> usize {
+    crate::DEFAULT_MAX_BODY_SIZE
+}
+
+/// The default upstream timeout for the reverse proxy.
+///
+/// See [`DEFAULT_UPSTREAM_TIMEOUT`].
+fn default_upstream_timeout() -> u64 {
+    crate::DEFAULT_UPSTREAM_TIMEOUT
+}
+
+/// The default drain timeout during shutdown.
+///
+/// See [`DEFAULT_DRAIN_TIMEOUT`].
+fn default_drain_timeout() -> u64 {
+    crate::DEFAULT_DRAIN_TIMEOUT
+}
+
 impl Default for Service {
     fn default() -> Self {
         Self {
+            address: default_address(),
             port: default_port(),
+            socket: None,
+            tls: None,
+            admin_address: default_address(),
             admin_port: default_admin_port(),
             admin_enabled: default_admin(),
             proxy_hops: default_proxy_hops(),
+            upstream: None,
+            max_body_size: default_max_body_size(),
+            upstream_timeout: default_upstream_timeout(),
+            drain_timeout: default_drain_timeout(),
         }
     }
 }
 
-impl From<Service> for crate::Service {
-    fn from(service: Service) -> Self {
-        Self {
+impl TryFrom<Service> for crate::Service {
+    type Error = ServiceConversionError;
+
+    fn try_from(service: Service) -> Result<Self, Self::Error> {
+        let upstream = service
+            .upstream
+            .as_ref()
+            .map(|upstream| -> Result<Url, ServiceConversionError> {
+                let parsed_upstream = upstream.parse::<Url>()?;
+                if parsed_upstream.scheme() == "http" {
+                    Ok(parsed_upstream)
+                } else {
+                    Err(ServiceConversionError::UnsupportedUpstreamUri(
+                        upstream.clone(),
+                    ))
+                }
+            })
+            .transpose()?;
+        Ok(Self {
+            address: service.address,
             port: service.port,
+            socket: service.socket.map(PathBuf::from),
+            tls: service.tls.map(|tls| crate::Tls {
+                cert: PathBuf::from(tls.cert),
+                key: PathBuf::from(tls.key),
+                client_ca: tls.client_ca.map(PathBuf::from),
+            }),
+            admin_address: service.admin_address,
             admin_port: service.admin_port,
             admin_enabled: service.admin_enabled,
             proxy_hops: service.proxy_hops,
+            upstream,
+            max_body_size: service.max_body_size,
+            upstream_timeout: service.upstream_timeout,
+            drain_timeout: service.drain_timeout,
+        })
+    }
+}
+
+/// The TOML serialization for a [Tls](crate::Tls) config structure.
+#[derive(Serialize, Deserialize, Clone)]
+struct Tls {
+    cert: String,
+    key: String,
+    #[serde(default)]
+    client_ca: Option<String>,
+}
+
+impl Tls {
+    /// Resolves the certificate and key paths relative to the directory of the config file that declared them.
+    fn resolve_paths(self, base: &Path) -> Self {
+        let resolve = |path: String| base.join(path).to_string_lossy().to_string();
+        Self {
+            cert: resolve(self.cert),
+            key: resolve(self.key),
+            client_ca: self.client_ca.map(resolve),
         }
     }
 }
@@ -109,6 +222,36 @@ struct Runtime {
     max_concurrent_requests: usize,
     #[serde(default = "default_max_plugin_tasks")]
     max_plugin_tasks: usize,
+    #[serde(default)]
+    worker_threads: Option<usize>,
+    #[serde(default = "default_max_blocking_threads")]
+    max_blocking_threads: usize,
+    #[serde(default = "default_thread_name")]
+    thread_name: String,
+    #[serde(default)]
+    http2_keepalive_interval: Option<u64>,
+    #[serde(default)]
+    http2_keepalive_timeout: Option<u64>,
+    #[serde(default)]
+    max_concurrent_streams: Option<u32>,
+    #[serde(default)]
+    tcp_nodelay: bool,
+    #[serde(default = "default_plugin_pool_size")]
+    plugin_pool_size: usize,
+    #[serde(default = "default_plugin_instance_max_uses")]
+    plugin_instance_max_uses: usize,
+    #[serde(default)]
+    pooling_allocator: bool,
+    #[serde(default)]
+    plugin_cache_dir: Option<String>,
+    #[serde(default = "default_plugin_fetch_timeout")]
+    plugin_fetch_timeout: u64,
+    #[serde(default = "default_plugin_fetch_retries")]
+    plugin_fetch_retries: u32,
+    #[serde(default)]
+    require_verification: bool,
+    #[serde(default)]
+    trust_precompiled: bool,
 }
 
 /// The default maximum number of concurrent incoming requests that the runtime will process before blocking.
@@ -125,21 +268,104 @@ fn default_max_plugin_tasks() -> usize {
     crate::DEFAULT_MAX_PLUGIN_TASKS
 }
 
+/// The default maximum number of threads for blocking operations.
+///
+/// See [`DEFAULT_MAX_BLOCKING_THREADS`].
+fn default_max_blocking_threads() -> usize {
+    crate::DEFAULT_MAX_BLOCKING_THREADS
+}
+
+/// The default name for the runtime's threads.
+///
+/// See [`DEFAULT_THREAD_NAME`].
+fn default_thread_name() -> String {
+    String::from(crate::DEFAULT_THREAD_NAME)
+}
+
+/// The default number of pre-instantiated instances kept ready for each plugin.
+///
+/// See [`DEFAULT_PLUGIN_POOL_SIZE`].
+fn default_plugin_pool_size() -> usize {
+    crate::DEFAULT_PLUGIN_POOL_SIZE
+}
+
+/// The default number of requests a pooled plugin instance handles before it's replaced.
+///
+/// See [`DEFAULT_PLUGIN_INSTANCE_MAX_USES`].
+fn default_plugin_instance_max_uses() -> usize {
+    crate::DEFAULT_PLUGIN_INSTANCE_MAX_USES
+}
+
+/// The default maximum time in milliseconds for a single attempt to fetch a remote plugin.
+///
+/// See [`DEFAULT_PLUGIN_FETCH_TIMEOUT`].
+fn default_plugin_fetch_timeout() -> u64 {
+    crate::DEFAULT_PLUGIN_FETCH_TIMEOUT
+}
+
+/// The default number of times a failed remote plugin fetch is retried.
+///
+/// See [`DEFAULT_PLUGIN_FETCH_RETRIES`].
+fn default_plugin_fetch_retries() -> u32 {
+    crate::DEFAULT_PLUGIN_FETCH_RETRIES
+}
+
 impl Default for Runtime {
     fn default() -> Self {
         Self {
             max_concurrent_requests: default_max_concurrent_requests(),
             max_plugin_tasks: default_max_plugin_tasks(),
+            worker_threads: None,
+            max_blocking_threads: default_max_blocking_threads(),
+            thread_name: default_thread_name(),
+            http2_keepalive_interval: None,
+            http2_keepalive_timeout: None,
+            max_concurrent_streams: None,
+            tcp_nodelay: false,
+            plugin_pool_size: default_plugin_pool_size(),
+            plugin_instance_max_uses: default_plugin_instance_max_uses(),
+            pooling_allocator: false,
+            plugin_cache_dir: None,
+            plugin_fetch_timeout: default_plugin_fetch_timeout(),
+            plugin_fetch_retries: default_plugin_fetch_retries(),
+            require_verification: false,
+            trust_precompiled: false,
         }
     }
 }
 
-impl From<Runtime> for crate::Runtime {
-    fn from(service: Runtime) -> Self {
-        Self {
-            max_concurrent_requests: service.max_concurrent_requests,
-            max_plugin_tasks: service.max_plugin_tasks,
+impl TryFrom<Runtime> for crate::Runtime {
+    type Error = RuntimeConversionError;
+
+    fn try_from(runtime: Runtime) -> Result<Self, Self::Error> {
+        if runtime.worker_threads == Some(0) {
+            return Err(RuntimeConversionError::ZeroWorkerThreads);
+        }
+        if runtime.max_blocking_threads == 0 {
+            return Err(RuntimeConversionError::ZeroMaxBlockingThreads);
+        }
+        if runtime.plugin_instance_max_uses == 0 {
+            return Err(RuntimeConversionError::ZeroPluginInstanceMaxUses);
         }
+        Ok(Self {
+            max_concurrent_requests: runtime.max_concurrent_requests,
+            max_plugin_tasks: runtime.max_plugin_tasks,
+            worker_threads: runtime.worker_threads,
+            max_blocking_threads: runtime.max_blocking_threads,
+            thread_name: runtime.thread_name,
+            http2_keepalive_interval: runtime.http2_keepalive_interval,
+            http2_keepalive_timeout: runtime.http2_keepalive_timeout,
+            max_concurrent_streams: runtime.max_concurrent_streams,
+            tcp_nodelay: runtime.tcp_nodelay,
+            plugin_pool_size: runtime.plugin_pool_size,
+            plugin_instance_max_uses: runtime.plugin_instance_max_uses,
+            pooling_allocator: runtime.pooling_allocator,
+            plugin_cache_dir: runtime.plugin_cache_dir.map(PathBuf::from),
+            plugin_fetch_timeout: runtime.plugin_fetch_timeout,
+            plugin_fetch_retries: runtime.plugin_fetch_retries,
+            require_verification: runtime.require_verification,
+            trust_precompiled: runtime.trust_precompiled,
+        })
     }
 }
 
@@ -235,6 +461,272 @@ impl From<Thresholds> for crate::Thresholds {
     }
 }
 
+/// The TOML serialization for a [Headers](crate::Headers) structure.
+///
+/// An empty header name disables that header.
+#[derive(Serialize, Deserialize)]
+struct Headers {
+    #[serde(default = "default_decision_header")]
+    decision: String,
+    #[serde(default = "default_tags_header")]
+    tags: String,
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+}
+
+/// The default name of the header used to send the combined decision.
+fn default_decision_header() -> String {
+    String::from(crate::DEFAULT_DECISION_HEADER)
+}
+
+/// The default name of the header used to send the combined tags.
+fn default_tags_header() -> String {
+    String::from(crate::DEFAULT_TAGS_HEADER)
+}
+
+impl Default for Headers {
+    fn default() -> Self {
+        Self {
+            decision: default_decision_header(),
+            tags: default_tags_header(),
+            labels: BTreeMap::new(),
+        }
+    }
+}
+
+/// Checks that a header name is a valid HTTP token.
+fn is_valid_header_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+/// Checks that a header name is a valid HTTP token.
+fn validate_header_name(name: &str) -> Result<(), HeadersConversionError> {
+    if is_valid_header_name(name) {
+        Ok(())
+    } else {
+        Err(HeadersConversionError::InvalidHeaderName(name.to_string()))
+    }
+}
+
+impl TryFrom<Headers> for crate::Headers {
+    type Error = HeadersConversionError;
+
+    fn try_from(headers: Headers) -> Result<Self, Self::Error> {
+        let optional_name = |name: String| -> Result<Option<String>, HeadersConversionError> {
+            if name.is_empty() {
+                Ok(None)
+            } else {
+                validate_header_name(&name)?;
+                Ok(Some(name))
+            }
+        };
+        for name in headers.labels.values() {
+            validate_header_name(name)?;
+        }
+        Ok(Self {
+            decision: optional_name(headers.decision)?,
+            tags: optional_name(headers.tags)?,
+            labels: headers.labels,
+        })
+    }
+}
+
+/// The TOML serialization for a [BlockResponse](crate::BlockResponse) structure.
+///
+/// Every field is optional so that a resource only needs to set the fields it overrides.
+#[derive(Serialize, Deserialize, Clone, Default)]
+struct BlockResponse {
+    status: Option<u16>,
+    content_type: Option<String>,
+    body: Option<String>,
+    body_file: Option<String>,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+}
+
+impl BlockResponse {
+    /// Reads the body file, if any, relative to the directory of the config file that declared it.
+    fn read_body_file(self, base: &Path) -> Result<Self, BlockConversionError> {
+        match self.body_file {
+            Some(_) if self.body.is_some() => Err(BlockConversionError::ConflictingBody),
+            Some(body_file) => {
+                let path = base.join(body_file);
+                let body = fs::read_to_string(&path).map_err(|err| {
+                    BlockConversionError::BodyFile(path.to_string_lossy().to_string(), err)
+                })?;
+                Ok(Self {
+                    body: Some(body),
+                    body_file: None,
+                    ..self
+                })
+            }
+            None => Ok(self),
+        }
+    }
+
+    /// Combines this block response with a resource's overrides.
+    fn merge(&self, overrides: &BlockResponse) -> BlockResponse {
+        let mut headers = self.headers.clone();
+        headers.extend(overrides.headers.clone());
+        BlockResponse {
+            status: overrides.status.or(self.status),
+            content_type: overrides
+                .content_type
+                .clone()
+                .or_else(|| self.content_type.clone()),
+            body: overrides.body.clone().or_else(|| self.body.clone()),
+            body_file: None,
+            headers,
+        }
+    }
+}
+
+impl TryFrom<BlockResponse> for crate::BlockResponse {
+    type Error = BlockConversionError;
+
+    fn try_from(block: BlockResponse) -> Result<Self, Self::Error> {
+        let status = block.status.unwrap_or(crate::DEFAULT_BLOCK_STATUS);
+        if !(200..=599).contains(&status) {
+            return Err(BlockConversionError::InvalidStatus(status));
+        }
+        for (name, value) in &block.headers {
+            if !is_valid_header_name(name) {
+                return Err(BlockConversionError::InvalidHeaderName(name.clone()));
+            }
+            if http::HeaderValue::from_str(value).is_err() {
+                return Err(BlockConversionError::InvalidHeaderValue(name.clone()));
+            }
+        }
+        let content_type = block
+            .content_type
+            .unwrap_or_else(|| String::from(crate::DEFAULT_BLOCK_CONTENT_TYPE));
+        if http::HeaderValue::from_str(&content_type).is_err() {
+            return Err(BlockConversionError::InvalidHeaderValue(String::from(
+                "Content-Type",
+            )));
+        }
+        Ok(Self {
+            status,
+            content_type,
+            body: block
+                .body
+                .unwrap_or_else(|| String::from(crate::DEFAULT_BLOCK_BODY)),
+            headers: block.headers,
+        })
+    }
+}
+
+/// The TOML serialization for an [Action](crate::Action) structure.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+enum Action {
+    Allow,
+    Block,
+    Redirect {
+        location: String,
+        status: Option<u16>,
+    },
+    Tarpit {
+        delay: u64,
+    },
+    AddHeader {
+        name: String,
+        value: String,
+    },
+}
+
+impl TryFrom<Action> for crate::Action {
+    type Error = ActionsConversionError;
+
+    fn try_from(action: Action) -> Result<Self, Self::Error> {
+        Ok(match action {
+            Action::Allow => crate::Action::Allow,
+            Action::Block => crate::Action::Block,
+            Action::Redirect { location, status } => {
+                let status = status.unwrap_or(crate::DEFAULT_REDIRECT_STATUS);
+                if !(300..=399).contains(&status) {
+                    return Err(ActionsConversionError::InvalidRedirectStatus(status));
+                }
+                if location.is_empty() || http::HeaderValue::from_str(&location).is_err() {
+                    return Err(ActionsConversionError::InvalidLocation(location));
+                }
+                crate::Action::Redirect { location, status }
+            }
+            Action::Tarpit { delay } => crate::Action::Tarpit { delay },
+            Action::AddHeader { name, value } => {
+                if !is_valid_header_name(&name) {
+                    return Err(ActionsConversionError::InvalidHeaderName(name));
+                }
+                if http::HeaderValue::from_str(&value).is_err() {
+                    return Err(ActionsConversionError::InvalidHeaderValue(name));
+                }
+                crate::Action::AddHeader { name, value }
+            }
+        })
+    }
+}
+
+/// The TOML serialization for an [Actions](crate::Actions) structure.
+///
+/// Every field is optional so that a resource only needs to set the outcomes it overrides.
+#[derive(Serialize, Deserialize, Clone, Default)]
+struct Actions {
+    trusted: Option<Action>,
+    accepted: Option<Action>,
+    suspected: Option<Action>,
+    restricted: Option<Action>,
+    max_tarpit_delay: Option<u64>,
+}
+
+impl Actions {
+    /// Combines these actions with a resource's overrides.
+    fn merge(&self, overrides: &Actions) -> Actions {
+        Actions {
+            trusted: overrides.trusted.clone().or_else(|| self.trusted.clone()),
+            accepted: overrides.accepted.clone().or_else(|| self.accepted.clone()),
+            suspected: overrides
+                .suspected
+                .clone()
+                .or_else(|| self.suspected.clone()),
+            restricted: overrides
+                .restricted
+                .clone()
+                .or_else(|| self.restricted.clone()),
+            max_tarpit_delay: overrides.max_tarpit_delay.or(self.max_tarpit_delay),
+        }
+    }
+}
+
+impl TryFrom<Actions> for crate::Actions {
+    type Error = ActionsConversionError;
+
+    fn try_from(actions: Actions) -> Result<Self, Self::Error> {
+        let defaults = crate::Actions::default();
+        let max_tarpit_delay = actions
+            .max_tarpit_delay
+            .unwrap_or(defaults.max_tarpit_delay);
+        let convert = |action: Option<Action>, default: crate::Action| {
+            let action = action.map(crate::Action::try_from).unwrap_or(Ok(default))?;
+            match action {
+                crate::Action::Tarpit { delay } if delay > max_tarpit_delay => Err(
+                    ActionsConversionError::TarpitDelayTooLong(delay, max_tarpit_delay),
+                ),
+                action => Ok(action),
+            }
+        };
+        Ok(Self {
+            trusted: convert(actions.trusted, defaults.trusted)?,
+            accepted: convert(actions.accepted, defaults.accepted)?,
+            suspected: convert(actions.suspected, defaults.suspected)?,
+            restricted: convert(actions.restricted, defaults.restricted)?,
+            max_tarpit_delay,
+        })
+    }
+}
+
 /// The TOML serialization for a [Metrics](crate::Metrics) structure.
 #[derive(Serialize, Deserialize)]
 struct Metrics {
@@ -319,6 +811,37 @@ impl TryFrom<&Secret> for crate::config::Secret {
         })
     }
 }
+/// The TOML serialization for a Trust structure.
+#[derive(Validate, Serialize, Deserialize, Clone)]
+struct Trust {
+    #[serde(rename(serialize = "ref", deserialize = "ref"))]
+    #[validate(length(min = 1, max = 96), regex(path = "RE_VALID_REFERENCE"))]
+    reference: String,
+    #[validate(length(min = 1))]
+    ed25519: Vec<String>,
+}
+
+impl TryFrom<&Trust> for crate::config::Trust {
+    type Error = crate::TrustConversionError;
+
+    fn try_from(trust: &Trust) -> Result<Self, Self::Error> {
+        Ok(Self {
+            reference: trust.reference.clone(),
+            ed25519: trust
+                .ed25519
+                .iter()
+                .map(|key| {
+                    let key = hex::decode(key)?;
+                    if key.len() != 32 {
+                        return Err(Self::Error::InvalidKeyLength(key.len()));
+                    }
+                    Ok(Bytes::from(key))
+                })
+                .collect::<Result<Vec<Bytes>, Self::Error>>()?,
+        })
+    }
+}
+
 /// The TOML serialization for a Plugin structure.
 #[derive(Validate, Serialize, Deserialize, Clone)]
 struct Plugin {
@@ -330,10 +853,14 @@ struct Plugin {
     #[validate(length(min = 1))]
     uri: Option<String>,
     #[validate(length(min = 1))]
+    oci: Option<String>,
+    #[validate(length(min = 1))]
     authorization_header: Option<String>,
     #[validate(length(min = 1))]
     verification: Option<String>,
     #[validate(length(min = 1))]
+    signature: Option<String>,
+    #[validate(length(min = 1))]
     bytes: Option<Vec<u8>>,
     #[serde(default = "default_plugin_weight")]
     #[validate(range(min = 0.0))]
@@ -342,6 +869,24 @@ struct Plugin {
     config: toml::map::Map<String, toml::Value>,
     #[serde(default)]
     permissions: TomlPermissions,
+    #[serde(default)]
+    #[validate(range(min = 1))]
+    max_fuel: Option<u64>,
+    #[serde(default)]
+    #[validate(range(min = 1))]
+    epoch_deadline: Option<u64>,
+    #[serde(default)]
+    #[validate(range(min = 1))]
+    max_memory: Option<usize>,
+    #[serde(default)]
+    #[validate(range(min = 1))]
+    max_table_elements: Option<u32>,
+    #[serde(default)]
+    #[validate(range(min = 1))]
+    max_instances: Option<usize>,
+    #[serde(default)]
+    #[validate(range(min = 1))]
+    max_stdio_size: Option<usize>,
 }
 
 /// The default weight for a plugin.
@@ -357,10 +902,13 @@ impl TryFrom<&Plugin> for crate::config::Plugin {
     fn try_from(plugin: &Plugin) -> Result<Self, Self::Error> {
         Ok(Self {
             reference: plugin.reference.clone(),
-            location: match (&plugin.path, &plugin.uri, &plugin.bytes) {
-                (Some(path), None, None) => crate::PluginLocation::Local(PathBuf::from(path)),
-                (None, Some(uri), None) => crate::PluginLocation::Remote(uri.parse::<Url>()?),
-                (None, None, Some(bytes)) => {
+            location: match (&plugin.path, &plugin.uri, &plugin.oci, &plugin.bytes) {
+                (Some(path), None, None, None) => crate::PluginLocation::Local(PathBuf::from(path)),
+                (None, Some(uri), None, None) => crate::PluginLocation::Remote(uri.parse::<Url>()?),
+                (None, None, Some(oci), None) => {
+                    crate::PluginLocation::Oci(oci.parse::<crate::OciReference>()?)
+                }
+                (None, None, None, Some(bytes)) => {
                     crate::PluginLocation::Bytes(Bytes::from(bytes.clone()))
                 }
                 _ => return Err(Self::Error::InvalidLocation),
@@ -369,23 +917,51 @@ impl TryFrom<&Plugin> for crate::config::Plugin {
                 Some(header) => crate::PluginAccess::Header(header.clone()),
                 None => crate::PluginAccess::None,
             },
-            verification: match &plugin.verification {
-                Some(verification) => match verification.split_once(':') {
+            verification: match (&plugin.verification, &plugin.signature) {
+                (None, None) => crate::PluginVerification::None,
+                (Some(verification), None) => match verification.split_once(':') {
                     Some(("sha256", digest)) => {
                         crate::PluginVerification::Sha256(bytes::Bytes::from(hex::decode(digest)?))
                     }
-                    Some((_, _)) => crate::PluginVerification::None,
-                    None => crate::PluginVerification::None,
+                    Some(("ed25519", trust)) => crate::PluginVerification::Ed25519 {
+                        trust: trust.to_string(),
+                        signature: None,
+                    },
+                    _ => return Err(Self::Error::UnsupportedVerification(verification.clone())),
+                },
+                (Some(verification), Some(signature)) => match verification.split_once(':') {
+                    Some(("ed25519", trust)) => crate::PluginVerification::Ed25519 {
+                        trust: trust.to_string(),
+                        signature: Some(decode_signature(signature)?),
+                    },
+                    _ => return Err(Self::Error::UnexpectedSignature),
                 },
-                None => crate::PluginVerification::None,
+                (None, Some(_)) => return Err(Self::Error::UnexpectedSignature),
             },
             weight: plugin.weight,
             config: toml_map_to_json(plugin.config.clone()),
             permissions: plugin.permissions.clone().into(),
+            max_fuel: plugin.max_fuel,
+            epoch_deadline: plugin.epoch_deadline,
+            max_memory: plugin.max_memory,
+            max_table_elements: plugin.max_table_elements,
+            max_instances: plugin.max_instances,
+            max_stdio_size: plugin.max_stdio_size,
         })
     }
 }
 
+/// Decodes a hex-encoded Ed25519 signature.
+fn decode_signature(signature: &str) -> Result<Bytes, crate::PluginConversionError> {
+    let signature = hex::decode(signature)?;
+    if signature.len() != 64 {
+        return Err(crate::PluginConversionError::InvalidSignatureLength(
+            signature.len(),
+        ));
+    }
+    Ok(Bytes::from(signature))
+}
+
 fn toml_map_to_json(
     map: toml::map::Map<String, toml::Value>,
 ) -> serde_json::map::Map<String, serde_json::Value> {
@@ -471,6 +1047,8 @@ struct Resource {
     plugins: Vec<String>,
     // TODO: default timeout
     timeout: Option<u64>,
+    block: Option<BlockResponse>,
+    actions: Option<Actions>,
 }
 
 /// Resources default to being a prefix.
@@ -502,8 +1080,24 @@ where
     Ok(fs::canonicalize(joined_path)?)
 }
 
-/// Loads a TOML config file into a [`Config`](crate::Config) structure.
+/// Loads a TOML config file into a [`Config`](crate::Config) structure, pinning its plugins to the
+/// [`Lockfile`](crate::Lockfile) alongside it, if there is one.
+///
+/// Plugins that are missing from the lockfile or don't match it are rejected. See [`load_unlocked_config`].
 pub fn load_config<'a, P>(config_path: &'a P) -> Result<crate::Config, ConfigFileError>
+where
+    P: 'a + ?Sized + AsRef<Path>,
+{
+    let mut config = load_unlocked_config(config_path)?;
+    if let Some(lockfile) = crate::Lockfile::read(&crate::Lockfile::path_for(config_path.as_ref()))?
+    {
+        lockfile.apply(&mut config)?;
+    }
+    Ok(config)
+}
+
+/// Loads a TOML config file into a [`Config`](crate::Config) structure, ignoring any lockfile alongside it.
+pub fn load_unlocked_config<'a, P>(config_path: &'a P) -> Result<crate::Config, ConfigFileError>
 where
     P: 'a + ?Sized + AsRef<Path>,
 {
@@ -532,7 +1126,7 @@ where
                         config_path.as_ref().to_path_buf(),
                     ));
                 }
-                _ => Err(ConfigFileError::IO(err.into())),
+                _ => Err(ConfigFileError::IO(err)),
             }?,
         };
         let mut root: Config = toml::from_str(&toml_data)?;
@@ -573,6 +1167,18 @@ where
         // Strip includes once processed
         root.includes = vec![];
 
+        // Read block response bodies relative to the config file that declared them.
+        let read_body_file = |block: BlockResponse| {
+            block
+                .read_body_file(base)
+                .map_err(|err| ConfigFileError::InvalidBlockConfig(err.to_string()))
+        };
+        root.block = read_body_file(root.block)?;
+        root.service.tls = root.service.tls.take().map(|tls| tls.resolve_paths(base));
+        for resource in root.resources.iter_mut() {
+            resource.block = resource.block.take().map(read_body_file).transpose()?;
+        }
+
         // Resolve plugins relative to config path or validate that remote URIs are secure.
         root.plugins = root
             .plugins
@@ -603,12 +1209,20 @@ where
                             )
                         })
                         .transpose()?,
+                    oci: plugin.oci.clone(),
                     authorization_header: plugin.authorization_header.clone(),
                     verification: plugin.verification.clone(),
+                    signature: plugin.signature.clone(),
                     bytes: plugin.bytes.clone(),
                     weight: plugin.weight,
                     config: plugin.config.clone(),
                     permissions: plugin.permissions.clone(),
+                    max_fuel: plugin.max_fuel,
+                    epoch_deadline: plugin.epoch_deadline,
+                    max_memory: plugin.max_memory,
+                    max_table_elements: plugin.max_table_elements,
+                    max_instances: plugin.max_instances,
+                    max_stdio_size: plugin.max_stdio_size,
                 })
             })
             .collect::<Result<Vec<Plugin>, ConfigFileError>>()?;
@@ -637,6 +1251,9 @@ where
             references.insert(&plugin.reference);
         }
     }
+    for trust in &root.trust {
+        trust.validate()?;
+    }
     let resolve_reference = |ref_name: &String| {
         let mut reference = crate::config::Reference::Missing(ref_name.clone());
         for preset in &root.presets {
@@ -653,10 +1270,40 @@ where
     };
     // Transfer to the public config type, checking reference enums
     let config = crate::Config {
-        service: root.service.into(),
-        runtime: root.runtime.into(),
+        service: root
+            .service
+            .try_into()
+            .map_err(|err: ServiceConversionError| {
+                ConfigFileError::InvalidServiceConfig(err.to_string())
+            })?,
+        runtime: root
+            .runtime
+            .try_into()
+            .map_err(|err: RuntimeConversionError| {
+                ConfigFileError::InvalidRuntimeConfig(err.to_string())
+            })?,
         state: root.state.into(),
         thresholds: root.thresholds.into(),
+        headers: root
+            .headers
+            .try_into()
+            .map_err(|err: HeadersConversionError| {
+                ConfigFileError::InvalidHeadersConfig(err.to_string())
+            })?,
+        block: root
+            .block
+            .clone()
+            .try_into()
+            .map_err(|err: BlockConversionError| {
+                ConfigFileError::InvalidBlockConfig(err.to_string())
+            })?,
+        actions: root
+            .actions
+            .clone()
+            .try_into()
+            .map_err(|err: ActionsConversionError| {
+                ConfigFileError::InvalidActionsConfig(err.to_string())
+            })?,
         metrics: root.metrics.into(),
         secrets: root
             .secrets
@@ -664,6 +1311,12 @@ where
             .map(|secret: &Secret| secret.try_into())
             .collect::<Result<Vec<crate::Secret>, _>>()
             .map_err(|err| ConfigFileError::InvalidSecretConfig(err.to_string()))?,
+        trust: root
+            .trust
+            .iter()
+            .map(|trust: &Trust| trust.try_into())
+            .collect::<Result<Vec<crate::Trust>, _>>()
+            .map_err(|err| ConfigFileError::InvalidTrustConfig(err.to_string()))?,
         plugins: root
             .plugins
             .iter()
@@ -681,17 +1334,40 @@ where
         resources: root
             .resources
             .iter()
-            .map(|resource| crate::config::Resource {
-                routes: crate::Resource::expand_routes(
-                    &resource.routes,
-                    resource.exact,
-                    resource.prefix,
-                ),
-                plugins: resource.plugins.iter().map(resolve_reference).collect(),
-                timeout: resource.timeout,
-            })
-            .collect(),
+            .map(
+                |resource| -> Result<crate::config::Resource, ConfigFileError> {
+                    let block = match &resource.block {
+                        Some(block) => root.block.merge(block),
+                        None => root.block.clone(),
+                    };
+                    let actions = match &resource.actions {
+                        Some(actions) => root.actions.merge(actions),
+                        None => root.actions.clone(),
+                    };
+                    Ok(crate::config::Resource {
+                        routes: crate::Resource::expand_routes(
+                            &resource.routes,
+                            resource.exact,
+                            resource.prefix,
+                        ),
+                        plugins: resource.plugins.iter().map(resolve_reference).collect(),
+                        timeout: resource.timeout,
+                        block: block.try_into().map_err(|err: BlockConversionError| {
+                            ConfigFileError::InvalidBlockConfig(err.to_string())
+                        })?,
+                        actions: actions.try_into().map_err(|err: ActionsConversionError| {
+                            ConfigFileError::InvalidActionsConfig(err.to_string())
+                        })?,
+                    })
+                },
+            )
+            .collect::<Result<Vec<crate::config::Resource>, ConfigFileError>>()?,
     };
+    // Validate thresholds here rather than when the first decision is combined
+    config
+        .thresholds
+        .validate()
+        .map_err(|err| ConfigFileError::InvalidThresholdsConfig(err.to_string()))?;
     for plugin in &config.plugins {
         // Read plugin configs to surface type errors immediately
         validate_plugin_config(&plugin.config)?;
@@ -835,7 +1511,10 @@ mod tests {
     "#,
         )?;
 
+        assert_eq!(root.service.address, crate::DEFAULT_ADDRESS);
         assert_eq!(root.service.port, 10002); // non-default
+        assert_eq!(root.service.socket, None);
+        assert!(root.service.tls.is_none());
         assert_eq!(root.service.admin_port, crate::DEFAULT_ADMIN_PORT);
         assert_eq!(
             root.state.redis_uri,
@@ -892,8 +1571,49 @@ mod tests {
 
         let root: crate::config::Config = load_config("tests/main.toml")?;
 
+        assert_eq!(root.service.address, crate::DEFAULT_ADDRESS);
         assert_eq!(root.service.port, 10002); // non-default
+        assert_eq!(root.service.socket, None);
+        assert_eq!(root.service.tls, None);
         assert_eq!(root.service.admin_port, crate::DEFAULT_ADMIN_PORT);
+        assert_eq!(
+            root.service.upstream,
+            Some(Url::parse("http://127.0.0.1:3000")?)
+        );
+        assert_eq!(root.service.max_body_size, crate::DEFAULT_MAX_BODY_SIZE);
+        assert_eq!(
+            root.service.upstream_timeout,
+            crate::DEFAULT_UPSTREAM_TIMEOUT
+        );
+        assert_eq!(root.service.drain_timeout, crate::DEFAULT_DRAIN_TIMEOUT);
+
+        assert_eq!(root.runtime.worker_threads, None);
+        assert_eq!(
+            root.runtime.max_blocking_threads,
+            crate::DEFAULT_MAX_BLOCKING_THREADS
+        );
+        assert_eq!(root.runtime.thread_name, crate::DEFAULT_THREAD_NAME);
+        assert!(!root.runtime.tcp_nodelay);
+        assert_eq!(
+            root.runtime.plugin_pool_size,
+            crate::DEFAULT_PLUGIN_POOL_SIZE
+        );
+        assert_eq!(
+            root.runtime.plugin_instance_max_uses,
+            crate::DEFAULT_PLUGIN_INSTANCE_MAX_USES
+        );
+        assert!(!root.runtime.pooling_allocator);
+        assert_eq!(root.runtime.plugin_cache_dir, None);
+        assert_eq!(
+            root.runtime.plugin_fetch_timeout,
+            crate::DEFAULT_PLUGIN_FETCH_TIMEOUT
+        );
+        assert_eq!(
+            root.runtime.plugin_fetch_retries,
+            crate::DEFAULT_PLUGIN_FETCH_RETRIES
+        );
+        assert!(!root.runtime.require_verification);
+        assert!(!root.runtime.trust_precompiled);
 
         assert_eq!(
             root.state.redis_uri,
@@ -914,11 +1634,22 @@ mod tests {
         );
         assert_eq!(root.thresholds.trust, crate::DEFAULT_TRUST_THRESHOLD);
 
+        assert_eq!(
+            root.headers.decision,
+            Some(String::from(crate::DEFAULT_DECISION_HEADER))
+        );
+        assert_eq!(root.headers.tags, None); // disabled
+        assert_eq!(
+            root.headers.labels,
+            BTreeMap::from([(String::from("user.id"), String::from("Bulwark-User-Id"))])
+        );
+
         assert_eq!(root.plugins.len(), 2);
         assert_eq!(root.plugins.first().unwrap().reference, "evil_bit");
         match root.plugins.first().unwrap().location.clone() {
             crate::PluginLocation::Local(path) => assert!(path.ends_with("bulwark_evil_bit.wasm")),
             crate::PluginLocation::Remote(_) => panic!("should not be https"),
+            crate::PluginLocation::Oci(_) => panic!("should not be oci"),
             crate::PluginLocation::Bytes(_) => panic!("should not be bytes"),
         }
 
@@ -953,6 +1684,43 @@ mod tests {
         );
         assert_eq!(root.resources.first().unwrap().timeout, Some(25));
 
+        assert_eq!(root.block.status, crate::DEFAULT_BLOCK_STATUS);
+        assert_eq!(root.block.content_type, "application/json");
+        assert_eq!(
+            root.block.body,
+            r#"{"error": "blocked", "request_id": "{{request_id}}"}"#
+        );
+        // Resources inherit anything they don't override from the global block response.
+        let block = &root.resources.first().unwrap().block;
+        assert_eq!(block.status, 429);
+        assert_eq!(block.content_type, root.block.content_type);
+        assert_eq!(block.body, root.block.body);
+        assert_eq!(
+            block.headers,
+            BTreeMap::from([
+                (String::from("Cache-Control"), String::from("no-store")),
+                (String::from("Retry-After"), String::from("60")),
+            ])
+        );
+
+        assert_eq!(root.actions.trusted, crate::Action::Allow);
+        assert_eq!(root.actions.suspected, crate::Action::Tarpit { delay: 500 });
+        assert_eq!(root.actions.restricted, crate::Action::Block);
+        assert_eq!(
+            root.actions.max_tarpit_delay,
+            crate::DEFAULT_MAX_TARPIT_DELAY
+        );
+        // Resources inherit any outcomes they don't override from the global actions.
+        let actions = &root.resources.first().unwrap().actions;
+        assert_eq!(actions.suspected, root.actions.suspected);
+        assert_eq!(
+            actions.restricted,
+            crate::Action::Redirect {
+                location: String::from("https://example.com/challenge"),
+                status: crate::DEFAULT_REDIRECT_STATUS,
+            }
+        );
+
         Ok(())
     }
 
@@ -1082,6 +1850,549 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_load_config_invalid_upstream() -> Result<(), Box<dyn std::error::Error>> {
+        build_plugins()?;
+
+        let result = load_config("tests/invalid_upstream.toml");
+        assert!(result.is_err());
+        let err = result.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid service config: upstream uri must be http: 'https://127.0.0.1:3000'"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_runtime() -> Result<(), Box<dyn std::error::Error>> {
+        build_plugins()?;
+
+        let root: crate::config::Config = load_config("tests/runtime.toml")?;
+
+        assert_eq!(
+            root.runtime.max_concurrent_requests,
+            crate::DEFAULT_MAX_CONCURRENT_REQUESTS
+        );
+        assert_eq!(root.runtime.worker_threads, Some(2));
+        assert_eq!(root.runtime.max_blocking_threads, 4);
+        assert_eq!(root.runtime.thread_name, "bulwark-sidecar");
+        assert_eq!(root.runtime.http2_keepalive_interval, Some(10000));
+        assert_eq!(root.runtime.http2_keepalive_timeout, Some(5000));
+        assert_eq!(root.runtime.max_concurrent_streams, Some(100));
+        assert!(root.runtime.tcp_nodelay);
+        assert_eq!(root.runtime.plugin_pool_size, 4);
+        assert_eq!(root.runtime.plugin_instance_max_uses, 100);
+        assert!(root.runtime.pooling_allocator);
+        assert_eq!(
+            root.runtime.plugin_cache_dir,
+            Some(PathBuf::from("/var/cache/bulwark"))
+        );
+        assert_eq!(root.runtime.plugin_fetch_timeout, 5000);
+        assert_eq!(root.runtime.plugin_fetch_retries, 5);
+        assert!(root.runtime.require_verification);
+        assert!(root.runtime.trust_precompiled);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_invalid_runtime() -> Result<(), Box<dyn std::error::Error>> {
+        build_plugins()?;
+
+        let result = load_config("tests/invalid_runtime.toml");
+        assert!(result.is_err());
+        let err = result.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid runtime config: worker_threads must be greater than zero"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_invalid_header_name() -> Result<(), Box<dyn std::error::Error>> {
+        build_plugins()?;
+
+        let result = load_config("tests/invalid_header_name.toml");
+        assert!(result.is_err());
+        let err = result.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid headers config: invalid header name: 'Bulwark User'"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_block_file() -> Result<(), Box<dyn std::error::Error>> {
+        build_plugins()?;
+
+        let root: crate::config::Config = load_config("tests/block_file.toml")?;
+
+        assert_eq!(root.block.status, crate::DEFAULT_BLOCK_STATUS);
+        assert_eq!(root.block.content_type, "text/html; charset=utf-8");
+        assert!(root
+            .block
+            .body
+            .contains("<p>Access Denied. Request ID: {{request_id}}</p>"));
+
+        let api_block = &root.resources.first().unwrap().block;
+        assert_eq!(api_block.status, 404);
+        assert_eq!(api_block.content_type, "text/plain");
+        assert_eq!(api_block.body, "Not Found\n");
+        assert_eq!(&root.resources.last().unwrap().block, &root.block);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_listen() -> Result<(), Box<dyn std::error::Error>> {
+        build_plugins()?;
+
+        let root: crate::config::Config = load_config("tests/listen.toml")?;
+
+        assert_eq!(root.service.socket_addr(), "[::1]:8089".parse()?);
+        assert_eq!(root.service.admin_socket_addr(), "127.0.0.1:8090".parse()?);
+        assert_eq!(
+            root.service.socket,
+            Some(PathBuf::from("/run/bulwark/bulwark.sock"))
+        );
+        assert_eq!(
+            root.service.tls,
+            Some(crate::Tls {
+                cert: PathBuf::from("tests/tls/server.pem"),
+                key: PathBuf::from("tests/tls/server.key"),
+                client_ca: Some(PathBuf::from("tests/tls/ca.pem")),
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_cpu_limits() -> Result<(), Box<dyn std::error::Error>> {
+        build_plugins()?;
+
+        let root: crate::config::Config = load_config("tests/cpu_limits.toml")?;
+
+        let plugin = root.plugins.first().unwrap();
+        assert_eq!(plugin.max_fuel, Some(1_000_000));
+        assert_eq!(plugin.epoch_deadline, Some(50));
+
+        let root: crate::config::Config = load_config("tests/listen.toml")?;
+
+        let plugin = root.plugins.first().unwrap();
+        assert_eq!(plugin.max_fuel, None);
+        assert_eq!(plugin.epoch_deadline, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_invalid_cpu_limits() -> Result<(), Box<dyn std::error::Error>> {
+        build_plugins()?;
+
+        let result = load_config("tests/invalid_cpu_limits.toml");
+        assert!(matches!(result, Err(ConfigFileError::Validations(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_memory_limits() -> Result<(), Box<dyn std::error::Error>> {
+        build_plugins()?;
+
+        let root: crate::config::Config = load_config("tests/memory_limits.toml")?;
+
+        let plugin = root.plugins.first().unwrap();
+        assert_eq!(plugin.max_memory, Some(16_777_216));
+        assert_eq!(plugin.max_table_elements, Some(10_000));
+        assert_eq!(plugin.max_instances, Some(20));
+        assert_eq!(plugin.max_stdio_size, Some(65_536));
+
+        let root: crate::config::Config = load_config("tests/listen.toml")?;
+
+        let plugin = root.plugins.first().unwrap();
+        assert_eq!(plugin.max_memory, None);
+        assert_eq!(plugin.max_table_elements, None);
+        assert_eq!(plugin.max_instances, None);
+        assert_eq!(plugin.max_stdio_size, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_invalid_memory_limits() -> Result<(), Box<dyn std::error::Error>> {
+        build_plugins()?;
+
+        let result = load_config("tests/invalid_memory_limits.toml");
+        assert!(matches!(result, Err(ConfigFileError::Validations(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_signature() -> Result<(), Box<dyn std::error::Error>> {
+        build_plugins()?;
+
+        let root: crate::config::Config = load_config("tests/signature.toml")?;
+
+        let trust = root.trust("release").unwrap();
+        assert_eq!(trust.ed25519.len(), 2);
+        assert_eq!(
+            trust.ed25519[0],
+            hex::decode("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a")?
+        );
+
+        let plugin = root.plugin("blank_slate").unwrap();
+        assert!(matches!(
+            &plugin.verification,
+            crate::PluginVerification::Ed25519 { trust, signature: None } if trust == "release"
+        ));
+
+        let plugin = root.plugin("signed_blank_slate").unwrap();
+        assert!(matches!(
+            &plugin.verification,
+            crate::PluginVerification::Ed25519 { trust, signature: Some(signature) }
+                if trust == "release" && signature.len() == 64
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_invalid_signature() -> Result<(), Box<dyn std::error::Error>> {
+        build_plugins()?;
+
+        let result = load_config("tests/invalid_trust.toml");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "invalid trust config: ed25519 public keys must be 32 bytes, got 16"
+        );
+
+        let result = load_config("tests/unexpected_signature.toml");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "invalid plugin config: signature requires ed25519 verification"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_oci() -> Result<(), Box<dyn std::error::Error>> {
+        let root: crate::config::Config = load_config("tests/oci.toml")?;
+
+        let plugin = root.plugin("tagged").unwrap();
+        let crate::PluginLocation::Oci(reference) = &plugin.location else {
+            panic!("should be oci");
+        };
+        assert_eq!(reference.registry, "registry.example.com");
+        assert_eq!(reference.repository, "team/blank_slate");
+        assert_eq!(reference.tag.as_deref(), Some("1.2"));
+        assert_eq!(reference.digest, None);
+        assert_eq!(reference.manifest_reference(), "1.2");
+        assert!(
+            matches!(&plugin.access, crate::PluginAccess::Header(secret) if secret == "registry_token")
+        );
+
+        let plugin = root.plugin("latest").unwrap();
+        let crate::PluginLocation::Oci(reference) = &plugin.location else {
+            panic!("should be oci");
+        };
+        assert_eq!(reference.registry, "localhost:5000");
+        assert_eq!(reference.repository, "blank-slate");
+        assert_eq!(reference.tag, None);
+        assert_eq!(reference.manifest_reference(), "latest");
+
+        let plugin = root.plugin("pinned").unwrap();
+        let crate::PluginLocation::Oci(reference) = &plugin.location else {
+            panic!("should be oci");
+        };
+        assert_eq!(reference.tag.as_deref(), Some("1.2"));
+        assert_eq!(
+            reference.manifest_reference(),
+            "sha256:46e2b6b5d0e8c0e7ed6a2d3e7a7bb5d2e5d8cfb6da3e32b8d1f2e4ad1b8a6f1c"
+        );
+        assert_eq!(
+            reference.to_string(),
+            "registry.example.com/team/blank_slate:1.2@sha256:46e2b6b5d0e8c0e7ed6a2d3e7a7bb5d2e5d8cfb6da3e32b8d1f2e4ad1b8a6f1c"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_invalid_oci() -> Result<(), Box<dyn std::error::Error>> {
+        build_plugins()?;
+
+        let result = load_config("tests/invalid_oci.toml");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "invalid plugin config: oci reference must begin with a registry host: 'team/blank_slate:1.2'"
+        );
+
+        let result = load_config("tests/conflicting_location.toml");
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "invalid plugin config: one and only one of path, uri, oci, or bytes must be set"
+        );
+
+        for (reference, err) in [
+            (
+                "registry.example.com/Team/plugin",
+                "invalid oci repository: 'Team/plugin'",
+            ),
+            (
+                "registry.example.com/team/plugin:-1",
+                "invalid oci tag: '-1'",
+            ),
+            (
+                "registry.example.com/team/plugin@sha512:00",
+                "invalid oci digest, only sha256 is supported: 'sha512:00'",
+            ),
+        ] {
+            assert_eq!(
+                reference
+                    .parse::<crate::OciReference>()
+                    .unwrap_err()
+                    .to_string(),
+                err
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_lockfile() -> Result<(), Box<dyn std::error::Error>> {
+        build_plugins()?;
+
+        let lock_dir =
+            std::env::temp_dir().join(format!("bulwark-lockfile-{}", std::process::id()));
+        fs::create_dir_all(&lock_dir)?;
+        let config_path = lock_dir.join("bulwark.toml");
+        let lockfile_path = crate::Lockfile::path_for(&config_path);
+        assert_eq!(lockfile_path, lock_dir.join(crate::LOCKFILE_NAME));
+        let plugin_path = fs::canonicalize("tests/bulwark_blank_slate.wasm")?;
+        fs::write(
+            &config_path,
+            format!(
+                "[[plugin]]\nref = \"blank_slate\"\npath = \"{}\"\n\n[[resource]]\nroutes = [\"/\"]\nplugins = [\"blank_slate\"]\n",
+                plugin_path.display()
+            ),
+        )?;
+
+        // Without a lockfile, plugins aren't pinned.
+        let root = load_config(&config_path)?;
+        assert!(matches!(
+            root.plugins[0].verification,
+            crate::PluginVerification::None
+        ));
+
+        let digest =
+            Bytes::from(<sha2::Sha256 as sha2::Digest>::digest(fs::read(&plugin_path)?).to_vec());
+        let mut lockfile = crate::Lockfile::default();
+        lockfile
+            .plugins
+            .insert(String::from("blank_slate"), digest.clone());
+        lockfile.write(&lockfile_path)?;
+        assert_eq!(
+            crate::Lockfile::read(&lockfile_path)?,
+            Some(lockfile.clone())
+        );
+
+        let root = load_config(&config_path)?;
+        assert!(matches!(
+            &root.plugins[0].verification,
+            crate::PluginVerification::Sha256(locked) if *locked == digest
+        ));
+
+        // A plugin that doesn't match the lockfile is rejected, unless the lockfile is ignored.
+        lockfile
+            .plugins
+            .insert(String::from("blank_slate"), Bytes::from(vec![0; 32]));
+        lockfile.write(&lockfile_path)?;
+        assert!(matches!(
+            load_config(&config_path),
+            Err(ConfigFileError::Lockfile(crate::LockfileError::Mismatch(
+                ..
+            )))
+        ));
+        let root = load_unlocked_config(&config_path)?;
+        assert!(matches!(
+            root.plugins[0].verification,
+            crate::PluginVerification::None
+        ));
+
+        // So is a plugin that's missing from the lockfile.
+        crate::Lockfile::default().write(&lockfile_path)?;
+        assert!(matches!(
+            load_config(&config_path),
+            Err(ConfigFileError::Lockfile(
+                crate::LockfileError::Unlocked(reference)
+            )) if reference == "blank_slate"
+        ));
+
+        fs::remove_dir_all(&lock_dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_vendor_config() -> Result<(), Box<dyn std::error::Error>> {
+        build_plugins()?;
+
+        let output_dir =
+            std::env::temp_dir().join(format!("bulwark-vendor-{}", std::process::id()));
+        let mut plugins = BTreeMap::new();
+        plugins.insert(
+            String::from("evil_bit"),
+            Bytes::from(fs::read("tests/bulwark_evil_bit.wasm")?),
+        );
+        plugins.insert(
+            String::from("blank_slate"),
+            Bytes::from(fs::read("tests/bulwark_blank_slate.wasm")?),
+        );
+
+        let written = crate::vendor_config(Path::new("tests/main.toml"), &output_dir, &plugins)?;
+        assert_eq!(
+            written,
+            vec![
+                output_dir.join("main.toml"),
+                output_dir.join("include.toml")
+            ]
+        );
+        let main = fs::read_to_string(output_dir.join("main.toml"))?;
+        assert!(main.contains("[[include]]\npath = \"include.toml\""));
+        assert!(main.contains("path = \"plugins/evil_bit.wasm\""));
+
+        // The vendored config loads its plugins from the output directory, pinned to their digests.
+        let root = load_config(&output_dir.join("main.toml"))?;
+        assert_eq!(root.service.port, 10002);
+        assert_eq!(root.plugins.len(), 2);
+        for plugin in &root.plugins {
+            let bytes = &plugins[&plugin.reference];
+            assert!(matches!(
+                &plugin.location,
+                crate::PluginLocation::Local(path) if fs::read(path)? == bytes[..]
+            ));
+            assert!(matches!(
+                &plugin.verification,
+                crate::PluginVerification::Sha256(digest)
+                    if digest[..] == <sha2::Sha256 as sha2::Digest>::digest(bytes)[..]
+            ));
+        }
+
+        let result =
+            crate::vendor_config(Path::new("tests/main.toml"), Path::new("tests"), &plugins);
+        assert!(matches!(result, Err(crate::VendorError::SameDirectory)));
+
+        fs::remove_dir_all(&output_dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_vendor_config_files() -> Result<(), Box<dyn std::error::Error>> {
+        build_plugins()?;
+
+        let output_dir =
+            std::env::temp_dir().join(format!("bulwark-vendor-files-{}", std::process::id()));
+        let mut plugins = BTreeMap::new();
+        plugins.insert(
+            String::from("blank_slate"),
+            Bytes::from(fs::read("tests/bulwark_blank_slate.wasm")?),
+        );
+
+        // Block response bodies are copied alongside the vendored config, so it loads from any directory.
+        crate::vendor_config(Path::new("tests/block_file.toml"), &output_dir, &plugins)?;
+        let root = load_config(&output_dir.join("block_file.toml"))?;
+        assert_eq!(root.block.body, fs::read_to_string("tests/block.html")?);
+
+        let result = crate::vendor_config(
+            Path::new("tests/escaping_block_file.toml"),
+            &output_dir,
+            &plugins,
+        );
+        assert!(matches!(
+            result,
+            Err(crate::VendorError::EscapingFile(path)) if path == "../Cargo.toml"
+        ));
+
+        fs::remove_dir_all(&output_dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_conflicting_block_body() -> Result<(), Box<dyn std::error::Error>> {
+        build_plugins()?;
+
+        let result = load_config("tests/conflicting_block_body.toml");
+        assert!(result.is_err());
+        let err = result.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid block config: body and body_file cannot both be set"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_invalid_block_header_value() -> Result<(), Box<dyn std::error::Error>> {
+        build_plugins()?;
+
+        let result = load_config("tests/invalid_block_header_value.toml");
+        assert!(result.is_err());
+        let err = result.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid block config: invalid value for header 'Retry-After'"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_invalid_actions() -> Result<(), Box<dyn std::error::Error>> {
+        build_plugins()?;
+
+        let result = load_config("tests/invalid_actions.toml");
+        assert!(result.is_err());
+        let err = result.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid actions config: invalid redirect status code: 200"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_invalid_tarpit_delay() -> Result<(), Box<dyn std::error::Error>> {
+        build_plugins()?;
+
+        let result = load_config("tests/invalid_tarpit_delay.toml");
+        assert!(result.is_err());
+        let err = result.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid actions config: tarpit delay of 60000ms exceeds max_tarpit_delay of 30000ms"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_config_invalid_thresholds() -> Result<(), Box<dyn std::error::Error>> {
+        build_plugins()?;
+
+        let result = load_config("tests/invalid_thresholds.toml");
+        assert!(result.is_err());
+        let err = result.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid thresholds config: invalid threshold order, must be trust < accept < suspicious < restrict"
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_load_config_valid_numeric_plugin_reference() -> Result<(), Box<dyn std::error::Error>> {
         build_plugins()?;
@@ -0,0 +1,185 @@
+//! The vendor module rewrites a configuration to load all of its plugins from local files, so that it can be
+//! deployed without network access.
+
+use crate::VendorError;
+use bytes::Bytes;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+
+/// The directory, relative to the root of a vendored configuration, that its plugins are copied into.
+pub const VENDORED_PLUGIN_DIR: &str = "plugins";
+
+/// Copies a TOML config file and every file it includes into a directory, along with the given plugins.
+///
+/// Each included file keeps its path relative to the root config file, so the include structure is unchanged. The
+/// same goes for the block response body files and TLS certificates and keys the config files refer to by relative
+/// path, so those paths don't need rewriting. Absolute paths are left as they are. Every plugin in `plugins` is written to the [`VENDORED_PLUGIN_DIR`] directory, and its config is rewritten to load
+/// it from there, pinned to its SHA-256 digest. Plugins aren't verified here, so they should be verified before
+/// they're vendored. Comments and formatting in the config files are preserved.
+///
+/// Returns the paths of the config files that were written, starting with the root config file.
+///
+/// # Arguments
+///
+/// * `config_path` - The path to the root configuration file.
+/// * `output_dir` - The directory to write the vendored configuration to.
+/// * `plugins` - The contents of each plugin to vendor, keyed by plugin reference.
+pub fn vendor_config(
+    config_path: &Path,
+    output_dir: &Path,
+    plugins: &BTreeMap<String, Bytes>,
+) -> Result<Vec<PathBuf>, VendorError> {
+    let config_dir = config_path.parent().unwrap_or(Path::new(""));
+    let file_name = config_path
+        .file_name()
+        .ok_or_else(|| VendorError::InvalidPath(config_path.display().to_string()))?;
+    std::fs::create_dir_all(output_dir)?;
+    if std::fs::canonicalize(output_dir)? == std::fs::canonicalize(config_dir.join("."))? {
+        return Err(VendorError::SameDirectory);
+    }
+
+    let plugin_dir = output_dir.join(VENDORED_PLUGIN_DIR);
+    std::fs::create_dir_all(&plugin_dir)?;
+    for (reference, bytes) in plugins {
+        std::fs::write(plugin_dir.join(format!("{}.wasm", reference)), bytes)?;
+    }
+
+    let mut vendored = HashSet::new();
+    let mut written = Vec::new();
+    let mut pending = vec![PathBuf::from(file_name)];
+    while let Some(relative_path) = pending.pop() {
+        if !vendored.insert(relative_path.clone()) {
+            return Err(VendorError::CircularInclude(
+                relative_path.display().to_string(),
+            ));
+        }
+        let mut document: toml_edit::DocumentMut =
+            std::fs::read_to_string(config_dir.join(&relative_path))?.parse()?;
+
+        if let Some(includes) = document
+            .get("include")
+            .and_then(|includes| includes.as_array_of_tables())
+        {
+            for include in includes {
+                let include_path = include
+                    .get("path")
+                    .and_then(|path| path.as_str())
+                    .unwrap_or_default();
+                pending.push(
+                    normalize(&relative_path.with_file_name(include_path))
+                        .ok_or_else(|| VendorError::EscapingInclude(include_path.to_string()))?,
+                );
+            }
+        }
+
+        for file_path in referenced_files(&document) {
+            vendor_file(config_dir, output_dir, &relative_path, file_path)?;
+        }
+
+        // Plugin paths are relative to the config file that declares them.
+        let depth = relative_path.components().count() - 1;
+        let plugin_dir = "../".repeat(depth) + VENDORED_PLUGIN_DIR;
+        if let Some(plugin_tables) = document
+            .get_mut("plugin")
+            .and_then(|plugins| plugins.as_array_of_tables_mut())
+        {
+            for plugin in plugin_tables.iter_mut() {
+                let Some(reference) = plugin.get("ref").and_then(|reference| reference.as_str())
+                else {
+                    continue;
+                };
+                let Some(bytes) = plugins.get(reference) else {
+                    continue;
+                };
+                let path = format!("{}/{}.wasm", plugin_dir, reference);
+                let verification = format!("sha256:{}", hex::encode(Sha256::digest(bytes)));
+                for key in ["uri", "oci", "authorization_header", "signature"] {
+                    plugin.remove(key);
+                }
+                plugin.insert("path", toml_edit::value(path));
+                plugin.insert("verification", toml_edit::value(verification));
+            }
+        }
+
+        let output_path = output_dir.join(&relative_path);
+        if let Some(parent) = output_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&output_path, document.to_string())?;
+        written.push(output_path);
+    }
+    Ok(written)
+}
+
+/// Returns the paths of the files, other than includes and plugins, that a config file refers to.
+fn referenced_files(document: &toml_edit::DocumentMut) -> Vec<&str> {
+    let resource_blocks = document
+        .get("resource")
+        .and_then(|resources| resources.as_array_of_tables())
+        .into_iter()
+        .flat_map(|resources| resources.iter())
+        .filter_map(|resource| resource.get("block"));
+    let body_files = document
+        .get("block")
+        .into_iter()
+        .chain(resource_blocks)
+        .filter_map(|block| block.get("body_file"));
+    let tls_files = document
+        .get("service")
+        .and_then(|service| service.get("tls"))
+        .into_iter()
+        .flat_map(|tls| ["cert", "key", "client_ca"].map(|key| tls.get(key)))
+        .flatten();
+    body_files
+        .chain(tls_files)
+        .filter_map(|path| path.as_str())
+        .collect()
+}
+
+/// Copies a file referred to by a config file to the same path relative to the vendored copy of that config file.
+///
+/// # Arguments
+///
+/// * `config_dir` - The directory of the root configuration file.
+/// * `output_dir` - The directory the configuration is being vendored to.
+/// * `config_path` - The path of the config file that refers to the file, relative to `config_dir`.
+/// * `file_path` - The path of the file, as written in the config file.
+fn vendor_file(
+    config_dir: &Path,
+    output_dir: &Path,
+    config_path: &Path,
+    file_path: &str,
+) -> Result<(), VendorError> {
+    if Path::new(file_path).is_absolute() {
+        return Ok(());
+    }
+    let relative_path = normalize(&config_path.with_file_name(file_path))
+        .ok_or_else(|| VendorError::EscapingFile(file_path.to_string()))?;
+    let output_path = output_dir.join(&relative_path);
+    if let Some(parent) = output_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::copy(config_dir.join(&relative_path), output_path)
+        .map_err(|err| VendorError::FileCopy(file_path.to_string(), err))?;
+    Ok(())
+}
+
+/// Resolves `.` and `..` components in a relative path, or returns `None` if the path is absolute or escapes the
+/// directory it's relative to.
+fn normalize(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => normalized.push(name),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(normalized)
+}
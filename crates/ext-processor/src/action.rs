@@ -0,0 +1,372 @@
+//! The action module determines what happens to a request once its outcome is known.
+//!
+//! Each outcome maps to a configured action, and plugins may request their own actions alongside their decisions.
+//! The strictest of these is taken, while headers requested by any of them are added to the forwarded request.
+
+use bulwark_config::DEFAULT_REDIRECT_STATUS;
+use http::header::LOCATION;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// The action taken for a request, resolved from the configured action and any actions requested by plugins.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// The request is sent to the interior service.
+    Allow,
+    /// The request is sent to the interior service after a delay.
+    Tarpit(Duration),
+    /// The client is redirected to another URL.
+    Redirect {
+        /// The URL sent in the `Location` header.
+        location: String,
+        /// The HTTP status code of the redirect.
+        status: u16,
+    },
+    /// The request is blocked with the resource's block response.
+    Block {
+        /// Replaces the configured status code, if set.
+        status: Option<u16>,
+        /// Additional headers to send with the block response.
+        headers: Vec<(String, String)>,
+    },
+}
+
+impl Action {
+    /// Ranks actions from least to most strict, with longer tarpits being stricter than shorter ones.
+    fn strictness(&self) -> (u8, Duration) {
+        match self {
+            Action::Allow => (0, Duration::ZERO),
+            Action::Tarpit(delay) => (1, *delay),
+            Action::Redirect { .. } => (2, Duration::ZERO),
+            Action::Block { .. } => (3, Duration::ZERO),
+        }
+    }
+
+    /// A short name for the action, used in logs.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Allow => "allow",
+            Action::Tarpit(_) => "tarpit",
+            Action::Redirect { .. } => "redirect",
+            Action::Block { .. } => "block",
+        }
+    }
+}
+
+/// The result of combining the configured action with any actions requested by plugins.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedAction {
+    /// The strictest action.
+    pub action: Action,
+    /// Headers to add to the request if it's sent to the interior service.
+    pub added_headers: Vec<(String, String)>,
+}
+
+/// Combines the action configured for an outcome with the actions requested by plugins.
+///
+/// The strictest action is taken. A requested action that is exactly as strict as the configured one replaces it,
+/// since it's more specific, e.g. a rate limiter asking for a 429 rather than the configured block response's
+/// status. Between equally strict requested actions, the first one wins. Requested actions that can't be sent over
+/// HTTP are ignored, and requested tarpits longer than `max_tarpit_delay` are shortened to it.
+///
+/// # Arguments
+///
+/// * `configured` - The action configured for the request's outcome.
+/// * `requested` - The actions requested by plugins.
+/// * `max_tarpit_delay` - The longest delay a tarpit may hold a request for.
+pub(crate) fn resolve_action<'a>(
+    configured: &bulwark_config::Action,
+    requested: impl IntoIterator<Item = &'a bulwark_sdk::Action>,
+    max_tarpit_delay: Duration,
+) -> ResolvedAction {
+    let mut added_headers = Vec::new();
+    let mut action = match configured {
+        bulwark_config::Action::Allow => Action::Allow,
+        bulwark_config::Action::Block => Action::Block {
+            status: None,
+            headers: vec![],
+        },
+        bulwark_config::Action::Redirect { location, status } => Action::Redirect {
+            location: location.clone(),
+            status: *status,
+        },
+        bulwark_config::Action::Tarpit { delay } => Action::Tarpit(Duration::from_millis(*delay)),
+        bulwark_config::Action::AddHeader { name, value } => {
+            added_headers.push((name.clone(), value.clone()));
+            Action::Allow
+        }
+    };
+
+    let mut configured = true;
+    for requested in requested {
+        let candidate = match requested {
+            bulwark_sdk::Action::Allow => Action::Allow,
+            bulwark_sdk::Action::Block { status, headers } => {
+                if !(400..=599).contains(status) {
+                    warn!(message = "ignoring block action", status = status);
+                    continue;
+                }
+                if let Some((name, _)) = headers
+                    .iter()
+                    .find(|(name, value)| !is_valid_header(name, value))
+                {
+                    warn!(message = "ignoring block action", header = name);
+                    continue;
+                }
+                Action::Block {
+                    status: Some(*status),
+                    headers: headers.clone(),
+                }
+            }
+            bulwark_sdk::Action::Redirect { location } => {
+                if location.is_empty() || http::HeaderValue::from_str(location).is_err() {
+                    warn!(message = "ignoring redirect action", location = location);
+                    continue;
+                }
+                Action::Redirect {
+                    location: location.clone(),
+                    status: DEFAULT_REDIRECT_STATUS,
+                }
+            }
+            bulwark_sdk::Action::Tarpit { delay } => {
+                let delay = Duration::from_millis(u64::from(*delay));
+                if delay > max_tarpit_delay {
+                    warn!(
+                        message = "shortening tarpit action",
+                        delay = delay.as_millis() as u64,
+                        max_tarpit_delay = max_tarpit_delay.as_millis() as u64,
+                    );
+                }
+                Action::Tarpit(delay.min(max_tarpit_delay))
+            }
+            bulwark_sdk::Action::AddHeader { name, value } => {
+                if is_valid_header(name, value) {
+                    added_headers.push((name.clone(), value.clone()));
+                } else {
+                    warn!(message = "ignoring add header action", header = name);
+                }
+                continue;
+            }
+        };
+        if candidate.strictness() > action.strictness()
+            || (configured && candidate.strictness() == action.strictness())
+        {
+            action = candidate;
+            configured = false;
+        }
+    }
+
+    ResolvedAction {
+        action,
+        added_headers,
+    }
+}
+
+/// Delays a request that is being tarpitted, unless Bulwark is in observe-only mode.
+///
+/// The request still holds its concurrency permit while it waits.
+///
+/// # Arguments
+///
+/// * `action` - The resolved action for the request.
+/// * `observe_only` - Whether actions are only being observed rather than enforced.
+pub(crate) async fn tarpit(action: &Action, observe_only: bool) {
+    if let Action::Tarpit(delay) = action {
+        if !observe_only {
+            info!(message = "tarpit request", delay = delay.as_millis() as u64);
+            tokio::time::sleep(*delay).await;
+        }
+    }
+}
+
+/// Checks that a header can be sent over HTTP.
+fn is_valid_header(name: &str, value: &str) -> bool {
+    http::HeaderName::from_bytes(name.as_bytes()).is_ok()
+        && http::HeaderValue::from_str(value).is_ok()
+}
+
+/// Generates a response redirecting the client to another URL.
+///
+/// # Arguments
+///
+/// * `location` - The URL sent in the `Location` header.
+/// * `status` - The HTTP status code of the redirect.
+pub(crate) fn render_redirect_response(
+    location: &str,
+    status: u16,
+) -> Result<bulwark_sdk::Response, http::Error> {
+    http::response::Builder::new()
+        .status(status)
+        .header(LOCATION, location)
+        .body(bytes::Bytes::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_TARPIT_DELAY: Duration =
+        Duration::from_millis(bulwark_config::DEFAULT_MAX_TARPIT_DELAY);
+
+    #[test]
+    fn test_resolve_action_configured() {
+        let resolved = resolve_action(
+            &bulwark_config::Action::Tarpit { delay: 250 },
+            [],
+            MAX_TARPIT_DELAY,
+        );
+        assert_eq!(resolved.action, Action::Tarpit(Duration::from_millis(250)));
+        assert!(resolved.added_headers.is_empty());
+
+        let resolved = resolve_action(
+            &bulwark_config::Action::AddHeader {
+                name: "X-Suspected".to_string(),
+                value: "true".to_string(),
+            },
+            [],
+            MAX_TARPIT_DELAY,
+        );
+        assert_eq!(resolved.action, Action::Allow);
+        assert_eq!(
+            resolved.added_headers,
+            vec![("X-Suspected".to_string(), "true".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_resolve_action_strictest() {
+        let requested = [
+            bulwark_sdk::Action::Tarpit { delay: 100 },
+            bulwark_sdk::Action::Block {
+                status: 429,
+                headers: vec![("Retry-After".to_string(), "60".to_string())],
+            },
+            bulwark_sdk::Action::Redirect {
+                location: "/challenge".to_string(),
+            },
+            bulwark_sdk::Action::AddHeader {
+                name: "X-Rate-Limited".to_string(),
+                value: "true".to_string(),
+            },
+        ];
+        let resolved = resolve_action(&bulwark_config::Action::Allow, &requested, MAX_TARPIT_DELAY);
+        assert_eq!(
+            resolved.action,
+            Action::Block {
+                status: Some(429),
+                headers: vec![("Retry-After".to_string(), "60".to_string())],
+            }
+        );
+        assert_eq!(
+            resolved.added_headers,
+            vec![("X-Rate-Limited".to_string(), "true".to_string())]
+        );
+
+        // A requested action can't loosen the configured one.
+        let resolved = resolve_action(
+            &bulwark_config::Action::Block,
+            &[bulwark_sdk::Action::Allow],
+            MAX_TARPIT_DELAY,
+        );
+        assert_eq!(
+            resolved.action,
+            Action::Block {
+                status: None,
+                headers: vec![]
+            }
+        );
+
+        // An equally strict requested action replaces the configured one, and the first requested one wins.
+        let resolved = resolve_action(
+            &bulwark_config::Action::Block,
+            &[
+                bulwark_sdk::Action::Block {
+                    status: 429,
+                    headers: vec![],
+                },
+                bulwark_sdk::Action::Block {
+                    status: 503,
+                    headers: vec![],
+                },
+            ],
+            MAX_TARPIT_DELAY,
+        );
+        assert_eq!(
+            resolved.action,
+            Action::Block {
+                status: Some(429),
+                headers: vec![]
+            }
+        );
+
+        // The longest tarpit wins.
+        let resolved = resolve_action(
+            &bulwark_config::Action::Tarpit { delay: 200 },
+            &[
+                bulwark_sdk::Action::Tarpit { delay: 100 },
+                bulwark_sdk::Action::Tarpit { delay: 300 },
+            ],
+            MAX_TARPIT_DELAY,
+        );
+        assert_eq!(resolved.action, Action::Tarpit(Duration::from_millis(300)));
+    }
+
+    #[test]
+    fn test_resolve_action_max_tarpit_delay() {
+        // A plugin can't hold a request for longer than the configured maximum.
+        let resolved = resolve_action(
+            &bulwark_config::Action::Allow,
+            &[bulwark_sdk::Action::Tarpit { delay: u32::MAX }],
+            MAX_TARPIT_DELAY,
+        );
+        assert_eq!(resolved.action, Action::Tarpit(MAX_TARPIT_DELAY));
+
+        let resolved = resolve_action(
+            &bulwark_config::Action::Tarpit { delay: 200 },
+            &[bulwark_sdk::Action::Tarpit { delay: 5000 }],
+            Duration::from_millis(1000),
+        );
+        assert_eq!(resolved.action, Action::Tarpit(Duration::from_millis(1000)));
+    }
+
+    #[test]
+    fn test_resolve_action_invalid() {
+        let requested = [
+            bulwark_sdk::Action::Block {
+                status: 99,
+                headers: vec![],
+            },
+            bulwark_sdk::Action::Block {
+                status: 200,
+                headers: vec![],
+            },
+            bulwark_sdk::Action::Block {
+                status: 429,
+                headers: vec![("Retry After".to_string(), "60".to_string())],
+            },
+            bulwark_sdk::Action::Redirect {
+                location: "/\n".to_string(),
+            },
+            bulwark_sdk::Action::AddHeader {
+                name: "X-Invalid".to_string(),
+                value: "\u{1}".to_string(),
+            },
+        ];
+        let resolved = resolve_action(&bulwark_config::Action::Allow, &requested, MAX_TARPIT_DELAY);
+        assert_eq!(resolved.action, Action::Allow);
+        assert!(resolved.added_headers.is_empty());
+    }
+
+    #[test]
+    fn test_render_redirect_response() -> Result<(), Box<dyn std::error::Error>> {
+        let response = render_redirect_response("https://example.com/challenge", 303)?;
+        assert_eq!(response.status(), 303);
+        assert_eq!(
+            response.headers()["location"],
+            "https://example.com/challenge"
+        );
+        assert!(response.body().is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,343 @@
+//! The authz module contains the Envoy external authorization service implementation.
+//!
+//! Unlike the external processor, the external authorization filter only ever sees the request, so the response
+//! phase is never executed. This makes it cheaper to run when response inspection is not needed. Decision feedback
+//! is executed whenever the resolved action yields a response of its own, such as a block or a redirect, since
+//! that response is the only one Bulwark ever sees.
+
+use crate::{
+    action::tarpit,
+    forwarded::{parse_forwarded_ip, parse_x_forwarded_for_ip},
+    headers::{forwarded_headers, header_value_option, ForwardedHeaders},
+    BulwarkProcessor, RequestError,
+};
+use bulwark_host::ForwardedIP;
+use std::{collections::HashMap, net::IpAddr};
+use tracing::{error, info, instrument, warn};
+
+use crate::protobuf::envoy::{
+    config::core::v3::{address, HeaderValue, HeaderValueOption},
+    r#type::v3::HttpStatus,
+    service::auth::v3::{
+        attribute_context::{HttpRequest, Peer},
+        authorization_server::Authorization,
+        check_response, CheckRequest, CheckResponse, DeniedHttpResponse, OkHttpResponse,
+    },
+};
+use crate::protobuf::google::rpc::Status;
+
+/// The `google.rpc.Code` value for an allowed request.
+const RPC_CODE_OK: i32 = 0;
+/// The `google.rpc.Code` value for a denied request.
+const RPC_CODE_PERMISSION_DENIED: i32 = 7;
+
+#[tonic::async_trait]
+impl Authorization for BulwarkProcessor {
+    /// Authorizes an incoming request by executing the request phases of the matching plugins.
+    #[instrument(name = "handle authorization", skip(self, tonic_request))]
+    async fn check(
+        &self,
+        tonic_request: tonic::Request<CheckRequest>,
+    ) -> Result<tonic::Response<CheckResponse>, tonic::Status> {
+        if self.is_draining() {
+            return Err(tonic::Status::unavailable("service is shutting down"));
+        }
+        let _permit = self
+            .request_semaphore()
+            .acquire_owned()
+            .await
+            .expect("semaphore closed");
+
+        let attributes = tonic_request.into_inner().attributes.unwrap_or_default();
+        let http_request = attributes
+            .request
+            .and_then(|request| request.http)
+            .ok_or_else(|| tonic::Status::invalid_argument("missing http request attributes"))?;
+        let request = prepare_request(&http_request, attributes.source.as_ref(), self.proxy_hops())
+            .map_err(|err| tonic::Status::invalid_argument(err.to_string()))?;
+
+        let evaluation = match self.evaluate(request, None).await {
+            Ok(Some(evaluation)) => evaluation,
+            Ok(None) => {
+                warn!(message = "no resource matched request");
+                // Unevaluated requests must not pass through headers that look like Bulwark's own.
+                return Ok(tonic::Response::new(ok_response(
+                    ForwardedHeaders::stripped(&self.headers()),
+                )));
+            }
+            Err(err) => {
+                error!(message = "could not evaluate request", error = ?err);
+                return Err(tonic::Status::internal("could not evaluate request"));
+            }
+        };
+
+        let verdict = evaluation.verdict;
+        let observe_only = self.thresholds().observe_only;
+        match evaluation.action_response {
+            Some(action_response) if !observe_only => {
+                // Labels are only forwarded to the interior service, they are never sent to the client.
+                let headers = forwarded_headers(&self.headers(), &verdict, &HashMap::new())
+                    .map_err(|err| tonic::Status::internal(err.to_string()))?;
+                info!(
+                    message = "process response",
+                    status = u16::from(action_response.status())
+                );
+                Ok(tonic::Response::new(denied_response(
+                    headers,
+                    &action_response,
+                )))
+            }
+            _ => {
+                tarpit(&evaluation.action.action, observe_only).await;
+                let mut headers = forwarded_headers(&self.headers(), &verdict, &evaluation.labels)
+                    .map_err(|err| tonic::Status::internal(err.to_string()))?;
+                headers.add(&evaluation.action.added_headers);
+                Ok(tonic::Response::new(ok_response(headers)))
+            }
+        }
+    }
+}
+
+/// Assembles a [`Request`](bulwark_sdk::Request) from the attributes Envoy sends with a [`CheckRequest`].
+fn prepare_request(
+    http_request: &HttpRequest,
+    source: Option<&Peer>,
+    proxy_hops: usize,
+) -> Result<bulwark_sdk::Request, RequestError> {
+    if http_request.method.is_empty() {
+        return Err(RequestError::MissingMethod);
+    }
+    if http_request.path.is_empty() {
+        return Err(RequestError::MissingPath);
+    }
+    let method = http::Method::from_bytes(http_request.method.as_bytes())?;
+    let mut request = http::Request::builder()
+        .method(method)
+        .uri(http_request.path.as_str());
+
+    // Raw headers are only sent if Envoy is configured with `encode_raw_headers`.
+    match &http_request.header_map {
+        Some(header_map) => {
+            for header in &header_map.headers {
+                // must not pass through Envoy pseudo headers here, http module treats them as invalid
+                if !header.key.starts_with(':') {
+                    request = request.header(&header.key, raw_header_value(header));
+                }
+            }
+        }
+        None => {
+            for (key, value) in &http_request.headers {
+                if !key.starts_with(':') {
+                    request = request.header(key, value);
+                }
+            }
+        }
+    }
+    let get_header_value = |name: &str| -> Option<&[u8]> {
+        match &http_request.header_map {
+            Some(header_map) => header_map
+                .headers
+                .iter()
+                .find(|header| header.key == name)
+                .map(raw_header_value),
+            None => http_request.headers.get(name).map(|value| value.as_bytes()),
+        }
+    };
+
+    // NOTE: header keys are sent in lower case
+    let forwarded_ip = if proxy_hops == 0 {
+        source.and_then(source_ip)
+    } else if let Some(forwarded) = get_header_value("forwarded") {
+        parse_forwarded_ip(forwarded, proxy_hops)
+    } else if let Some(forwarded) = get_header_value("x-forwarded-for") {
+        parse_x_forwarded_for_ip(forwarded, proxy_hops)
+    } else {
+        None
+    };
+    if let Some(ip_addr) = forwarded_ip {
+        request = request.extension(ForwardedIP(ip_addr));
+    }
+
+    // The body is only sent if Envoy is configured with `with_request_body`.
+    let body = if !http_request.raw_body.is_empty() {
+        bytes::Bytes::from(http_request.raw_body.clone())
+    } else {
+        bytes::Bytes::from(http_request.body.clone())
+    };
+
+    Ok(request.body(body)?)
+}
+
+/// Returns the raw value of a header, falling back to the string value if the raw value is empty.
+fn raw_header_value(header: &HeaderValue) -> &[u8] {
+    if !header.raw_value.is_empty() {
+        header.raw_value.as_slice()
+    } else {
+        header.value.as_bytes()
+    }
+}
+
+/// Parses the IP address of the downstream peer.
+fn source_ip(source: &Peer) -> Option<IpAddr> {
+    match source.address.as_ref()?.address.as_ref()? {
+        address::Address::SocketAddress(socket_address) => socket_address.address.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Converts forwarded headers into Envoy headers that replace any existing header with the same name.
+fn header_value_options(headers: Vec<(String, String)>) -> Vec<HeaderValueOption> {
+    headers
+        .into_iter()
+        .map(|(key, value)| header_value_option(&key, value))
+        .collect()
+}
+
+/// Builds a response that allows the request to continue to the interior service.
+fn ok_response(headers: ForwardedHeaders) -> CheckResponse {
+    CheckResponse {
+        status: Some(Status {
+            code: RPC_CODE_OK,
+            message: String::new(),
+            details: vec![],
+        }),
+        dynamic_metadata: None,
+        http_response: Some(check_response::HttpResponse::OkResponse(OkHttpResponse {
+            headers: header_value_options(headers.set),
+            headers_to_remove: headers.remove,
+            ..Default::default()
+        })),
+    }
+}
+
+/// Builds a response that blocks or redirects the request.
+fn denied_response(
+    headers: ForwardedHeaders,
+    block_response: &bulwark_sdk::Response,
+) -> CheckResponse {
+    let mut headers = header_value_options(headers.set);
+    headers.extend(block_response.headers().iter().map(|(name, value)| {
+        header_value_option(
+            name.as_str(),
+            String::from_utf8_lossy(value.as_bytes()).to_string(),
+        )
+    }));
+    CheckResponse {
+        status: Some(Status {
+            code: RPC_CODE_PERMISSION_DENIED,
+            message: String::new(),
+            details: vec![],
+        }),
+        dynamic_metadata: None,
+        http_response: Some(check_response::HttpResponse::DeniedResponse(
+            DeniedHttpResponse {
+                status: Some(HttpStatus {
+                    code: i32::from(block_response.status().as_u16()),
+                }),
+                headers,
+                body: String::from_utf8_lossy(block_response.body()).to_string(),
+            },
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protobuf::envoy::config::core::v3::{Address, HeaderMap, SocketAddress};
+    use std::collections::HashMap;
+
+    fn http_request() -> HttpRequest {
+        HttpRequest {
+            method: "POST".to_string(),
+            path: "/login?next=%2F".to_string(),
+            headers: HashMap::from([
+                (":method".to_string(), "POST".to_string()),
+                ("content-type".to_string(), "application/json".to_string()),
+                (
+                    "x-forwarded-for".to_string(),
+                    "203.0.113.1, 10.0.0.1".to_string(),
+                ),
+            ]),
+            body: "{}".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn peer(ip: &str) -> Peer {
+        Peer {
+            address: Some(Address {
+                address: Some(address::Address::SocketAddress(SocketAddress {
+                    address: ip.to_string(),
+                    ..Default::default()
+                })),
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_prepare_request() -> Result<(), Box<dyn std::error::Error>> {
+        let request = prepare_request(&http_request(), None, 2)?;
+        assert_eq!(request.method(), http::Method::POST);
+        assert_eq!(request.uri(), "/login?next=%2F");
+        assert_eq!(request.headers()["content-type"], "application/json");
+        assert!(!request.headers().contains_key(":method"));
+        assert_eq!(request.body(), "{}");
+        assert_eq!(
+            request.extensions().get::<ForwardedIP>().map(|ip| ip.0),
+            Some("203.0.113.1".parse()?)
+        );
+
+        // With no proxy hops, the forwarded IP is the downstream peer.
+        let request = prepare_request(&http_request(), Some(&peer("198.51.100.7")), 0)?;
+        assert_eq!(
+            request.extensions().get::<ForwardedIP>().map(|ip| ip.0),
+            Some("198.51.100.7".parse()?)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prepare_request_raw_headers() -> Result<(), Box<dyn std::error::Error>> {
+        let mut http_request = http_request();
+        http_request.headers = HashMap::new();
+        http_request.header_map = Some(HeaderMap {
+            headers: vec![
+                HeaderValue {
+                    key: ":path".to_string(),
+                    raw_value: b"/login".to_vec(),
+                    ..Default::default()
+                },
+                HeaderValue {
+                    key: "forwarded".to_string(),
+                    raw_value: b"for=192.0.2.43".to_vec(),
+                    ..Default::default()
+                },
+            ],
+        });
+        http_request.body = String::new();
+        http_request.raw_body = b"raw".to_vec();
+
+        let request = prepare_request(&http_request, None, 1)?;
+        assert_eq!(request.headers()["forwarded"], "for=192.0.2.43");
+        assert_eq!(request.body(), "raw");
+        assert_eq!(
+            request.extensions().get::<ForwardedIP>().map(|ip| ip.0),
+            Some("192.0.2.43".parse()?)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prepare_request_missing_method() {
+        let mut http_request = http_request();
+        http_request.method = String::new();
+        assert!(matches!(
+            prepare_request(&http_request, None, 1),
+            Err(RequestError::MissingMethod)
+        ));
+    }
+}
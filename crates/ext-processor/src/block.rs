@@ -0,0 +1,106 @@
+//! The block module renders the responses sent when a request is blocked.
+
+use bulwark_config::BlockResponse;
+use http::header::CONTENT_TYPE;
+use std::collections::HashSet;
+
+/// The header used to identify a request across services. Envoy generates it when it isn't already present.
+const REQUEST_ID_HEADER: &str = "x-request-id";
+/// The placeholder replaced with the request ID.
+const REQUEST_ID_PLACEHOLDER: &str = "{{request_id}}";
+/// The placeholder replaced with the combined tags.
+const TAGS_PLACEHOLDER: &str = "{{tags}}";
+
+/// Generates a response indicating the request has been blocked.
+///
+/// # Arguments
+///
+/// * `config` - The block response config for the matching resource.
+/// * `request` - The request being blocked.
+/// * `tags` - The combined tags for the request.
+pub(crate) fn render_block_response(
+    config: &BlockResponse,
+    request: &bulwark_sdk::Request,
+    tags: &HashSet<String>,
+) -> Result<bulwark_sdk::Response, http::Error> {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let mut tags: Vec<&str> = tags.iter().map(|tag| tag.as_str()).collect();
+    tags.sort_unstable();
+
+    let body = config
+        .body
+        .replace(REQUEST_ID_PLACEHOLDER, &sanitize(request_id))
+        .replace(TAGS_PLACEHOLDER, &sanitize(&tags.join(",")));
+
+    let mut response = http::response::Builder::new()
+        .status(config.status)
+        .header(CONTENT_TYPE, config.content_type.as_str());
+    for (name, value) in &config.headers {
+        response = response.header(name, value);
+    }
+    response.body(bytes::Bytes::from(body))
+}
+
+/// Strips characters that could break out of an HTML or JSON body template.
+///
+/// The request ID is supplied by the client, so it can't be substituted into the body as-is.
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || "-_.:,/*".contains(*c))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_render_block_response() -> Result<(), Box<dyn std::error::Error>> {
+        let config = BlockResponse {
+            status: 429,
+            content_type: "application/json".to_string(),
+            body: r#"{"request_id": "{{request_id}}", "tags": "{{tags}}"}"#.to_string(),
+            headers: BTreeMap::from([("Retry-After".to_string(), "60".to_string())]),
+        };
+        let request = http::Request::builder()
+            .uri("/")
+            .header("X-Request-Id", "9f2c6a1e-1c2b-4a4e-8c1e-7b1f0c5d2e3a")
+            .body(bytes::Bytes::new())?;
+        let tags = HashSet::from(["rate-limited".to_string(), "api".to_string()]);
+
+        let response = render_block_response(&config, &request, &tags)?;
+        assert_eq!(response.status(), 429);
+        assert_eq!(response.headers()["content-type"], "application/json");
+        assert_eq!(response.headers()["retry-after"], "60");
+        assert_eq!(
+            response.body(),
+            r#"{"request_id": "9f2c6a1e-1c2b-4a4e-8c1e-7b1f0c5d2e3a", "tags": "api,rate-limited"}"#
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_block_response_sanitizes_request_id() -> Result<(), Box<dyn std::error::Error>> {
+        let config = BlockResponse {
+            body: "<p>{{request_id}}</p>".to_string(),
+            ..Default::default()
+        };
+        let request = http::Request::builder()
+            .uri("/")
+            .header("X-Request-Id", "<script>alert(1)</script>")
+            .body(bytes::Bytes::new())?;
+
+        let response = render_block_response(&config, &request, &HashSet::new())?;
+        assert_eq!(response.status(), 403);
+        assert_eq!(response.body(), "<p>scriptalert1/script</p>");
+
+        Ok(())
+    }
+}
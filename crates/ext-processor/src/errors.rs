@@ -1,4 +1,4 @@
-use bulwark_host::{ContextInstantiationError, PluginInstantiationError};
+use bulwark_host::{ContextInstantiationError, PluginInstantiationError, PluginLoadError};
 
 /// Returned when trying to instantiate a plugin group and either the request context for a plugin or the plugin
 /// itself returns an instantiation error.
@@ -10,6 +10,15 @@ pub enum PluginGroupInstantiationError {
     PluginInstantiation(#[from] PluginInstantiationError),
 }
 
+/// Returned when a request could not be [evaluated](crate::BulwarkProcessor::evaluate).
+#[derive(thiserror::Error, Debug)]
+pub enum EvaluationError {
+    #[error(transparent)]
+    PluginGroupInstantiation(#[from] PluginGroupInstantiationError),
+    #[error(transparent)]
+    ActionResponse(#[from] http::Error),
+}
+
 /// Returned when the Envoy external processor is unable to handle an incoming message successfully.
 #[derive(thiserror::Error, Debug)]
 pub enum HandlerError {
@@ -94,3 +103,43 @@ pub enum ProcessingMessageError {
     #[error(transparent)]
     Http(#[from] http::Error),
 }
+
+/// Returned when the reverse proxy cannot be initialized from the service configuration.
+#[derive(thiserror::Error, Debug)]
+pub enum ProxyInitError {
+    #[error("missing upstream for reverse proxy")]
+    MissingUpstream,
+    #[error(transparent)]
+    InvalidUpstream(#[from] http::uri::InvalidUri),
+    #[error(transparent)]
+    PluginLoad(#[from] PluginLoadError),
+}
+
+/// Returned when the reverse proxy is unable to process a request or forward it to the upstream service.
+#[derive(thiserror::Error, Debug)]
+pub enum ProxyError {
+    #[error(transparent)]
+    PluginInstantiation(#[from] PluginGroupInstantiationError),
+    #[error(transparent)]
+    Http(#[from] http::Error),
+    #[error(transparent)]
+    InvalidUri(#[from] http::uri::InvalidUri),
+    #[error(transparent)]
+    InvalidUriParts(#[from] http::uri::InvalidUriParts),
+    #[error(transparent)]
+    InvalidHeaderName(#[from] http::header::InvalidHeaderName),
+    #[error(transparent)]
+    InvalidHeaderValue(#[from] http::header::InvalidHeaderValue),
+    #[error(transparent)]
+    Sfv(#[from] SfvError),
+    #[error("could not read body: {0}")]
+    Body(Box<dyn std::error::Error + Send + Sync>),
+    #[error("body exceeded the maximum size")]
+    BodyTooLarge,
+    #[error("request body exceeded the maximum size")]
+    RequestBodyTooLarge,
+    #[error("timed out waiting for upstream")]
+    UpstreamTimeout,
+    #[error(transparent)]
+    Upstream(#[from] hyper_util::client::legacy::Error),
+}
@@ -1,3 +1,10 @@
+/// Formats an `f64` to 3 decimal places for display in log events.
+macro_rules! format_f64 {
+    ($expression:expr) => {
+        tracing::field::display(crate::format::Float3Formatter($expression))
+    };
+}
+
 #[derive(Clone, Copy, Debug)]
 pub(crate) struct Float3Formatter(pub f64);
 
@@ -0,0 +1,117 @@
+//! The forwarded module extracts client IP addresses from the `Forwarded` and `X-Forwarded-For` headers.
+
+use forwarded_header_value::ForwardedHeaderValue;
+use std::net::IpAddr;
+
+/// Parses the `Forwarded` header, returning the IP address of the client `proxy_hops` from the end of the list.
+pub(crate) fn parse_forwarded_ip(forwarded: &[u8], proxy_hops: usize) -> Option<IpAddr> {
+    let forwarded = String::from_utf8_lossy(forwarded);
+    let value = ForwardedHeaderValue::from_forwarded(forwarded.as_ref()).ok();
+    value.and_then(|fhv| {
+        if proxy_hops > fhv.len() {
+            None
+        } else {
+            let item = fhv.iter().nth(fhv.len() - proxy_hops);
+            item.and_then(|fs| fs.forwarded_for_ip())
+        }
+    })
+}
+
+/// Parses the `X-Forwarded-For` header, returning the IP address of the client `proxy_hops` from the end of the list.
+pub(crate) fn parse_x_forwarded_for_ip(forwarded: &[u8], proxy_hops: usize) -> Option<IpAddr> {
+    let forwarded = String::from_utf8_lossy(forwarded);
+    let value = ForwardedHeaderValue::from_x_forwarded_for(forwarded.as_ref()).ok();
+    value.and_then(|fhv| {
+        if proxy_hops > fhv.len() {
+            None
+        } else {
+            let item = fhv.iter().nth(fhv.len() - proxy_hops);
+            item.and_then(|fs| fs.forwarded_for_ip())
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_forwarded() -> Result<(), Box<dyn std::error::Error>> {
+        let test_cases = [
+            ("", 0, None),
+            ("", 1, None),
+            ("bogus", 0, None),
+            ("213!04]d$7n2;31d4%,hbq#", 0, None),
+            (
+                "for=192.0.2.43",
+                1,
+                Some("192.0.2.43".parse::<IpAddr>().unwrap()),
+            ),
+            (
+                "for=192.0.2.43,for=198.51.100.17;by=203.0.113.60;proto=http;host=example.com",
+                2,
+                Some("192.0.2.43".parse::<IpAddr>().unwrap()),
+            ),
+            (
+                "   for=192.0.2.43, for=198.51.100.17;  by=203.0.113.60; proto=http;host=example.com",
+                2,
+                Some("192.0.2.43".parse::<IpAddr>().unwrap()),
+            ),
+            (
+                "for=192.0.2.43,for=198.51.100.17;by=203.0.113.60;proto=http;host=example.com",
+                1,
+                Some("198.51.100.17".parse::<IpAddr>().unwrap()),
+            ),
+            (
+                "for=192.0.2.43,for=198.51.100.17;by=203.0.113.60;proto=http;host=example.com",
+                3,
+                None,
+            ),
+        ];
+
+        for (forwarded, hops, expected) in test_cases {
+            let parsed = parse_forwarded_ip(forwarded.as_bytes(), hops);
+            assert_eq!(parsed, expected);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_x_forwarded_for() -> Result<(), Box<dyn std::error::Error>> {
+        let test_cases = [
+            ("", 0, None),
+            ("", 1, None),
+            ("bogus", 0, None),
+            ("213!04]d$7n2;31d4%,hbq#", 0, None),
+            (
+                "192.0.2.43",
+                1,
+                Some("192.0.2.43".parse::<IpAddr>().unwrap()),
+            ),
+            (
+                "192.0.2.43,198.51.100.17",
+                2,
+                Some("192.0.2.43".parse::<IpAddr>().unwrap()),
+            ),
+            (
+                "   192.0.2.43, 198.51.100.17 ",
+                2,
+                Some("192.0.2.43".parse::<IpAddr>().unwrap()),
+            ),
+            (
+                "192.0.2.43,198.51.100.17",
+                1,
+                Some("198.51.100.17".parse::<IpAddr>().unwrap()),
+            ),
+            ("192.0.2.43,198.51.100.17", 3, None),
+        ];
+
+        for (forwarded, hops, expected) in test_cases {
+            let parsed = parse_x_forwarded_for_ip(forwarded.as_bytes(), hops);
+            assert_eq!(parsed, expected);
+        }
+
+        Ok(())
+    }
+}
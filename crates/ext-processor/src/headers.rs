@@ -1,15 +1,20 @@
-use bulwark_sdk::{Decision, Outcome};
+use crate::protobuf::envoy::config::core::v3::{
+    header_value_option::HeaderAppendAction, HeaderValue, HeaderValueOption,
+};
+use crate::SfvError;
+use bulwark_sdk::{Decision, Outcome, Verdict};
 use sfv::{BareItem, Decimal, Dictionary, FromPrimitive, Item, List, ListEntry, SerializeValue};
+use std::collections::HashMap;
+use tracing::warn;
 
 // TODO: capture the entire outcome: accepted/suspicious/restricted + threshold values
 // TODO: should this error for invalid Decision values?
 
 /// Serialize a combined [`Decision`] into a [SFV](sfv) header value to be sent with the request to the interior service.
-#[allow(dead_code)] // TODO: remove once moved to ext-auth
 pub(crate) fn serialize_decision_sfv(
     decision: Decision,
     outcome: Outcome,
-) -> std::result::Result<String, &'static str> {
+) -> Result<String, SfvError> {
     let accept_value = Item::new(BareItem::Decimal(
         Decimal::from_f64(decision.accept).unwrap(),
     ));
@@ -32,16 +37,142 @@ pub(crate) fn serialize_decision_sfv(
     dict.insert("outcome".into(), outcome_value.into());
 
     dict.serialize_value()
+        .map_err(|err| SfvError::Serialization(err.to_string()))
 }
 
 /// Serialize a tag [`Vec`] into a [SFV](sfv) header value to be sent with the request to the interior service.
-#[allow(dead_code)] // TODO: remove once moved to ext-auth
-pub(crate) fn serialize_tags_sfv(tags: Vec<String>) -> std::result::Result<String, &'static str> {
+///
+/// Tags are serialized as tokens where possible. Tags that aren't valid tokens are serialized as strings instead,
+/// and tags that can't be represented as either are dropped, so that a plugin's choice of tag never fails a request.
+pub(crate) fn serialize_tags_sfv(tags: Vec<String>) -> Result<String, SfvError> {
     let list: List = tags
         .iter()
-        .map(|tag| ListEntry::from(Item::new(BareItem::Token(tag.to_string()))))
+        .filter_map(|tag| {
+            if is_token(tag) {
+                Some(BareItem::Token(tag.to_string()))
+            } else if tag.bytes().all(|b| (0x20..=0x7e).contains(&b)) {
+                Some(BareItem::String(tag.to_string()))
+            } else {
+                warn!(message = "tag cannot be serialized to a header", tag = tag);
+                None
+            }
+        })
+        .map(|item| ListEntry::from(Item::new(item)))
         .collect::<Vec<ListEntry>>();
     list.serialize_value()
+        .map_err(|err| SfvError::Serialization(err.to_string()))
+}
+
+/// Checks whether a tag is a valid [SFV](sfv) token.
+///
+/// See [RFC 8941, Section 3.3.4](https://www.rfc-editor.org/rfc/rfc8941#section-3.3.4).
+fn is_token(tag: &str) -> bool {
+    let mut bytes = tag.bytes();
+    match bytes.next() {
+        Some(b) if b.is_ascii_alphabetic() || b == b'*' => {
+            bytes.all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~:/".contains(&b))
+        }
+        _ => false,
+    }
+}
+
+/// The headers to set on and remove from a request before it is forwarded to the interior service.
+///
+/// Header names are lower-cased.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct ForwardedHeaders {
+    /// Headers that replace any existing header with the same name.
+    pub(crate) set: Vec<(String, String)>,
+    /// Headers that are configured but have no value for this request.
+    ///
+    /// These must be removed so that clients cannot spoof them.
+    pub(crate) remove: Vec<String>,
+}
+
+impl ForwardedHeaders {
+    /// Creates a set of headers that only removes every configured header.
+    ///
+    /// This is used for requests that aren't evaluated, so that clients can't spoof Bulwark's headers.
+    pub(crate) fn stripped(config: &bulwark_config::Headers) -> Self {
+        let mut headers = ForwardedHeaders::default();
+        for name in config.decision.iter().chain(&config.tags) {
+            headers.push(name, None);
+        }
+        for name in config.labels.values() {
+            headers.push(name, None);
+        }
+        headers
+    }
+
+    /// Adds headers requested by actions, skipping any that would overwrite one of Bulwark's own headers.
+    pub(crate) fn add(&mut self, headers: &[(String, String)]) {
+        for (name, value) in headers {
+            let name = name.to_ascii_lowercase();
+            if self.set.iter().any(|(existing, _)| *existing == name) || self.remove.contains(&name)
+            {
+                warn!(message = "ignoring added header", header = name);
+                continue;
+            }
+            self.set.push((name, value.clone()));
+        }
+    }
+
+    fn push(&mut self, name: &str, value: Option<String>) {
+        let name = name.to_ascii_lowercase();
+        match value {
+            Some(value) => self.set.push((name, value)),
+            None => self.remove.push(name),
+        }
+    }
+}
+
+/// Determines the headers to forward to the interior service for a [`Verdict`] and the combined labels.
+///
+/// # Arguments
+///
+/// * `config` - The configured header names.
+/// * `verdict` - The combined verdict for the request.
+/// * `labels` - The combined labels for the request. Only labels named in the config are forwarded.
+pub(crate) fn forwarded_headers(
+    config: &bulwark_config::Headers,
+    verdict: &Verdict,
+    labels: &HashMap<String, String>,
+) -> Result<ForwardedHeaders, SfvError> {
+    let mut headers = ForwardedHeaders::default();
+    if let Some(name) = &config.decision {
+        headers.push(
+            name,
+            Some(serialize_decision_sfv(verdict.decision, verdict.outcome)?),
+        );
+    }
+    if let Some(name) = &config.tags {
+        let value = if verdict.tags.is_empty() {
+            None
+        } else {
+            let mut tags = verdict.tags.clone();
+            tags.sort();
+            Some(serialize_tags_sfv(tags)?)
+        };
+        headers.push(name, value);
+    }
+    for (key, name) in &config.labels {
+        headers.push(name, labels.get(key).cloned());
+    }
+    Ok(headers)
+}
+
+/// Builds an Envoy header that replaces any existing header with the same name.
+pub(crate) fn header_value_option(key: &str, value: String) -> HeaderValueOption {
+    HeaderValueOption {
+        header: Some(HeaderValue {
+            key: key.to_string(),
+            value,
+            raw_value: vec![],
+        }),
+        // Overwriting prevents clients from spoofing the headers
+        append_action: HeaderAppendAction::OverwriteIfExistsOrAdd as i32,
+        ..Default::default()
+    }
 }
 
 #[cfg(test)]
@@ -123,4 +254,105 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_forwarded_headers() -> Result<(), Box<dyn std::error::Error>> {
+        let verdict = Verdict {
+            decision: Decision {
+                accept: 0.0,
+                restrict: 1.0,
+                unknown: 0.0,
+            },
+            outcome: Outcome::Restricted,
+            tags: vec!["scanner".to_string(), "evil".to_string()],
+        };
+        let config = bulwark_config::Headers {
+            labels: [
+                ("user.id".to_string(), "Bulwark-User-Id".to_string()),
+                ("session.id".to_string(), "Bulwark-Session-Id".to_string()),
+            ]
+            .into(),
+            ..Default::default()
+        };
+        let labels = HashMap::from([("user.id".to_string(), "42".to_string())]);
+
+        let headers = forwarded_headers(&config, &verdict, &labels)?;
+        assert_eq!(
+            headers.set,
+            vec![
+                (
+                    "bulwark-decision".to_string(),
+                    "accept=0.0, restrict=1.0, unknown=0.0, score=1.0, outcome=\"restricted\""
+                        .to_string()
+                ),
+                ("bulwark-tags".to_string(), "evil, scanner".to_string()),
+                ("bulwark-user-id".to_string(), "42".to_string()),
+            ]
+        );
+        assert_eq!(headers.remove, vec!["bulwark-session-id".to_string()]);
+
+        // Disabled headers are neither set nor removed, and empty tags are removed.
+        let config = bulwark_config::Headers {
+            decision: None,
+            ..Default::default()
+        };
+        let verdict = Verdict {
+            tags: vec![],
+            ..verdict
+        };
+        let headers = forwarded_headers(&config, &verdict, &HashMap::new())?;
+        assert!(headers.set.is_empty());
+        assert_eq!(headers.remove, vec!["bulwark-tags".to_string()]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_tags_sfv_non_token() -> Result<(), Box<dyn std::error::Error>> {
+        assert_eq!(
+            serialize_tags_sfv(vec![
+                "evil".to_string(),
+                "2fa".to_string(),
+                "rate limited".to_string(),
+                "caf\u{e9}".to_string(),
+            ])?,
+            "evil, \"2fa\", \"rate limited\""
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_stripped_headers() {
+        let config = bulwark_config::Headers {
+            labels: [("user.id".to_string(), "Bulwark-User-Id".to_string())].into(),
+            ..Default::default()
+        };
+        let headers = ForwardedHeaders::stripped(&config);
+        assert!(headers.set.is_empty());
+        assert_eq!(
+            headers.remove,
+            vec![
+                "bulwark-decision".to_string(),
+                "bulwark-tags".to_string(),
+                "bulwark-user-id".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_added_headers() {
+        let mut headers = ForwardedHeaders::stripped(&bulwark_config::Headers::default());
+        headers.add(&[
+            ("X-Challenge".to_string(), "passed".to_string()),
+            ("Bulwark-Tags".to_string(), "trusted".to_string()),
+        ]);
+        assert_eq!(
+            headers.set,
+            vec![("x-challenge".to_string(), "passed".to_string())]
+        );
+        assert_eq!(
+            headers.remove,
+            vec!["bulwark-decision".to_string(), "bulwark-tags".to_string()]
+        );
+    }
 }
@@ -1,11 +1,23 @@
-//! Provides an [Envoy external processing][1] service for Bulwark.
+//! Provides an [Envoy external processing][1] service and an [Envoy external authorization][2] service for
+//! Bulwark, as well as a standalone reverse proxy for deployments that don't use Envoy.
 //!
 //! [1]: https://www.envoyproxy.io/docs/envoy/latest/configuration/http/http_filters/ext_proc_filter
+//! [2]: https://www.envoyproxy.io/docs/envoy/latest/configuration/http/http_filters/ext_authz_filter
 
+mod action;
+mod authz;
+mod block;
 mod errors;
+#[macro_use]
 mod format;
+mod forwarded;
+mod headers;
+mod pipeline;
 pub mod protobuf;
+mod proxy;
 mod service;
 
+pub use action::{Action, ResolvedAction};
 pub use errors::*;
+pub use proxy::*;
 pub use service::*;
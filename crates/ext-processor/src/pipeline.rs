@@ -0,0 +1,686 @@
+//! The pipeline module drives a group of plugin instances through each execution phase of a request.
+//!
+//! It has no knowledge of how requests arrive or how responses are sent, which allows the Envoy external
+//! processor and the reverse proxy to share the same decision-making logic.
+
+use crate::{
+    action::{render_redirect_response, resolve_action, Action, ResolvedAction},
+    block::render_block_response,
+    headers::{forwarded_headers, ForwardedHeaders},
+    SfvError,
+};
+use bulwark_host::{HandlerOutput, PluginExecutionError, PluginInstance, PluginPool};
+use bulwark_sdk::{Decision, Outcome, Verdict};
+use futures::lock::Mutex;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{sync::Semaphore, task::JoinSet, time::timeout};
+use tracing::{error, info, instrument, warn, Instrument};
+
+/// Helper function that joins everything in a joinset, ignoring success and raising warnings as needed
+async fn join_all<T, F>(
+    mut join_set: JoinSet<Result<Result<T, PluginExecutionError>, tokio::time::error::Elapsed>>,
+    mut success: F,
+) where
+    F: FnMut(T),
+    T: 'static,
+{
+    // efficiently hand execution off to the the tasks we're joining
+    tokio::task::yield_now().await;
+
+    while let Some(r) = join_set.join_next().await {
+        match r {
+            Ok(Ok(Ok(output))) => success(output),
+            // These 3 errors are only logged, not bubbled up
+            Ok(Ok(Err(err))) => {
+                error!(
+                    message = "plugin execution error",
+                    elapsed = ?err,
+                );
+            }
+            Ok(Err(err)) => {
+                warn!(
+                    message = "timeout on plugin execution",
+                    elapsed = ?err,
+                );
+            }
+            Err(err) => {
+                warn!(
+                    message = "join error on plugin execution",
+                    error_message = ?err,
+                );
+            }
+        }
+    }
+}
+
+/// The `PipelineContext` wraps the plugin instances and intermediate results associated with a single
+/// request/response cycle.
+///
+/// Each phase is executed concurrently across all plugin instances in the group and the results are
+/// combined before the next phase begins. The plugin instances have already been initialized by their pools, and are
+/// released back to them when the `PipelineContext` is dropped.
+pub(crate) struct PipelineContext {
+    pub(crate) plugin_semaphore: Arc<Semaphore>,
+    /// The pools the plugin instances were taken from, in the same order as `plugin_instances`.
+    pub(crate) plugin_pools: Vec<Arc<PluginPool>>,
+    pub(crate) plugin_instances: Vec<Arc<Mutex<PluginInstance>>>,
+    pub(crate) router_labels: HashMap<String, String>,
+    pub(crate) request: Arc<bulwark_sdk::Request>,
+    pub(crate) response: Option<Arc<bulwark_sdk::Response>>,
+    pub(crate) verdict: Option<Verdict>,
+    pub(crate) combined_output: HandlerOutput,
+    pub(crate) plugin_outputs: HashMap<String, HandlerOutput>,
+    pub(crate) thresholds: bulwark_config::Thresholds,
+    pub(crate) headers: Arc<bulwark_config::Headers>,
+    pub(crate) block: Arc<bulwark_config::BlockResponse>,
+    pub(crate) actions: Arc<bulwark_config::Actions>,
+    pub(crate) timeout_duration: Duration,
+}
+
+impl Drop for PipelineContext {
+    fn drop(&mut self) {
+        for (pool, plugin_instance) in self
+            .plugin_pools
+            .iter()
+            .zip(self.plugin_instances.drain(..))
+        {
+            // An instance can only still be shared if a plugin task outlived the request, in which case it isn't
+            // safe to reuse anyway.
+            if let Ok(plugin_instance) = Arc::try_unwrap(plugin_instance) {
+                pool.release(plugin_instance.into_inner());
+            }
+        }
+    }
+}
+
+impl PipelineContext {
+    pub(crate) async fn execute_request_enrichment_phase(&mut self) {
+        let mut enrichment_phase_tasks = JoinSet::new();
+        for plugin_instance in self.plugin_instances.iter().cloned() {
+            let enrichment_phase_child_span =
+                tracing::info_span!("execute handle_request_enrichment",);
+            let permit = self
+                .plugin_semaphore
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("semaphore closed");
+            let request = self.request.clone();
+            let router_labels = self.router_labels.clone();
+            enrichment_phase_tasks.spawn(
+                timeout(self.timeout_duration, async move {
+                    let result =
+                        Self::dispatch_request_enrichment(plugin_instance, request, router_labels)
+                            .await;
+                    drop(permit);
+                    result
+                })
+                .instrument(enrichment_phase_child_span.or_current()),
+            );
+        }
+
+        let mut labels = self.router_labels.clone();
+        join_all(enrichment_phase_tasks, |new_labels| {
+            // Merge labels from each plugin
+            labels.extend(new_labels);
+        })
+        .await;
+        self.combined_output = HandlerOutput {
+            decision: Decision::default(),
+            tags: HashSet::new(),
+            labels,
+            action: None,
+        };
+    }
+
+    pub(crate) async fn execute_request_decision_phase(&mut self) {
+        let outputs: Arc<Mutex<Vec<HandlerOutput>>> =
+            Arc::new(Mutex::new(Vec::with_capacity(self.plugin_instances.len())));
+        let plugin_outputs: Arc<Mutex<HashMap<String, HandlerOutput>>> =
+            Arc::new(Mutex::new(HashMap::new()));
+        let mut decision_phase_tasks = JoinSet::new();
+        // The .iter().cloned() appears to be necessary
+        #[allow(clippy::unnecessary_to_owned)]
+        for plugin_instance in self.plugin_instances.iter().cloned() {
+            let decision_phase_child_span = tracing::info_span!("execute handle_request_decision",);
+            let permit = self
+                .plugin_semaphore
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("semaphore closed");
+            let outputs = outputs.clone();
+            let plugin_outputs = plugin_outputs.clone();
+            let request = self.request.clone();
+            // Need to be careful that we grab the labels emitted by the request phase and not the labels we started with.
+            let labels = self.combined_output.labels.clone();
+            decision_phase_tasks.spawn(
+                timeout(self.timeout_duration, async move {
+                    let output_result =
+                        Self::dispatch_request_decision(plugin_instance.clone(), request, labels)
+                            .await;
+                    if let Ok(output) = &output_result {
+                        // Re-weight the decision based on its weighting value from the configuration
+                        let plugin_instance = plugin_instance.lock().await;
+                        let mut output = output.clone();
+                        output.decision = output.decision.weight(plugin_instance.weight());
+
+                        let decision = &output.decision;
+                        info!(
+                            message = "plugin decision",
+                            name = plugin_instance.plugin_reference(),
+                            accept = format_f64!(decision.accept),
+                            restrict = format_f64!(decision.restrict),
+                            unknown = format_f64!(decision.unknown),
+                            score = format_f64!(decision.pignistic().restrict),
+                            weight = format_f64!(plugin_instance.weight()),
+                        );
+
+                        let mut outputs = outputs.lock().await;
+                        outputs.push(output.clone());
+                        let mut plugin_outputs = plugin_outputs.lock().await;
+                        plugin_outputs.insert(plugin_instance.plugin_reference(), output);
+                    } else if let Err(err) = &output_result {
+                        error!(message = "plugin error", error = err.to_string());
+                        let mut outputs = outputs.lock().await;
+                        outputs.push(HandlerOutput {
+                            decision: bulwark_sdk::UNKNOWN,
+                            tags: HashSet::from([String::from("error")]),
+                            labels: HashMap::new(),
+                            action: None,
+                        });
+                    }
+                    drop(permit);
+                    output_result.map(|output| output.labels)
+                })
+                .instrument(decision_phase_child_span.or_current()),
+            );
+        }
+
+        let mut labels = self.router_labels.clone();
+        join_all(decision_phase_tasks, |new_labels| {
+            // Merge labels from each plugin
+            labels.extend(new_labels);
+        })
+        .await;
+
+        let decision_vec: Vec<Decision>;
+        {
+            let outputs = outputs.lock().await;
+            decision_vec = outputs.iter().map(|dc| dc.decision).collect();
+            self.combined_output.tags.extend(
+                outputs
+                    .iter()
+                    .flat_map(|dc| dc.tags.clone())
+                    .collect::<HashSet<String>>(),
+            );
+        }
+        let decision = Decision::combine_murphy(&decision_vec);
+
+        let plugin_outputs = plugin_outputs.lock().await;
+        self.combined_output = HandlerOutput {
+            decision,
+            tags: self.combined_output.tags.clone(),
+            labels,
+            action: None,
+        };
+        self.plugin_outputs.clone_from(&plugin_outputs);
+    }
+
+    pub(crate) async fn execute_response_phase(&mut self) {
+        let outputs: Arc<Mutex<Vec<HandlerOutput>>> =
+            Arc::new(Mutex::new(Vec::with_capacity(self.plugin_instances.len())));
+        let new_plugin_outputs: Arc<Mutex<HashMap<String, HandlerOutput>>> =
+            Arc::new(Mutex::new(self.plugin_outputs.clone()));
+        let mut response_phase_tasks = JoinSet::new();
+        // The .iter().cloned() appears to be necessary
+        #[allow(clippy::unnecessary_to_owned)]
+        for plugin_instance in self.plugin_instances.iter().cloned() {
+            let response_phase_child_span =
+                tracing::info_span!("execute handle_response_decision",);
+            let permit = self
+                .plugin_semaphore
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("semaphore closed");
+            let request = self.request.clone();
+            let response = self
+                .response
+                .clone()
+                .expect("cannot execute response phase without response");
+            // Need to be careful that we grab the labels emitted by the request phase and not the labels we started with.
+            let labels = self.combined_output.labels.clone();
+            let outputs = outputs.clone();
+            let new_plugin_outputs = new_plugin_outputs.clone();
+            let prior_plugin_outputs = self
+                .plugin_outputs
+                .get(&plugin_instance.lock().await.plugin_reference())
+                .cloned();
+            response_phase_tasks.spawn(
+                timeout(self.timeout_duration, async move {
+                    let output_result = Self::dispatch_response_decision(
+                        plugin_instance.clone(),
+                        request,
+                        response,
+                        labels,
+                    )
+                    .await;
+                    if let Ok(output) = &output_result {
+                        // Re-weight the decision based on its weighting value from the configuration
+                        let plugin_instance = plugin_instance.lock().await;
+                        let mut output = output.clone();
+                        output.decision = output.decision.weight(plugin_instance.weight());
+
+                        if let Some(prior_plugin_outputs) = prior_plugin_outputs {
+                            // If the prior output was non-zero and the new output was zero, then keep the prior output.
+                            if !prior_plugin_outputs.decision.is_unknown()
+                                && output.decision.is_unknown()
+                            {
+                                // The prior decision was already weighted and does not need to have weights applied.
+                                output.decision = prior_plugin_outputs.decision;
+                            }
+                        }
+
+                        let decision = &output.decision;
+                        info!(
+                            message = "plugin decision",
+                            name = plugin_instance.plugin_reference(),
+                            accept = format_f64!(decision.accept),
+                            restrict = format_f64!(decision.restrict),
+                            unknown = format_f64!(decision.unknown),
+                            score = format_f64!(decision.pignistic().restrict),
+                            weight = format_f64!(plugin_instance.weight()),
+                        );
+
+                        let mut outputs = outputs.lock().await;
+                        outputs.push(output.clone());
+                        let mut new_plugin_outputs = new_plugin_outputs.lock().await;
+                        new_plugin_outputs.insert(plugin_instance.plugin_reference(), output);
+                    } else if let Err(err) = &output_result {
+                        error!(message = "plugin error", error = err.to_string());
+                        let mut outputs = outputs.lock().await;
+                        outputs.push(HandlerOutput {
+                            decision: bulwark_sdk::UNKNOWN,
+                            tags: HashSet::from([String::from("error")]),
+                            labels: HashMap::new(),
+                            action: None,
+                        });
+                    }
+                    drop(permit);
+                    output_result.map(|output| output.labels)
+                })
+                .instrument(response_phase_child_span.or_current()),
+            );
+        }
+
+        let mut labels = self.router_labels.clone();
+        join_all(response_phase_tasks, |new_labels| {
+            // Merge labels from each plugin
+            labels.extend(new_labels);
+        })
+        .await;
+
+        let decision_vec: Vec<Decision>;
+        {
+            let outputs = outputs.lock().await;
+            decision_vec = outputs.iter().map(|dc| dc.decision).collect();
+            self.combined_output.tags.extend(
+                outputs
+                    .iter()
+                    .flat_map(|dc| dc.tags.clone())
+                    .collect::<HashSet<String>>(),
+            );
+        }
+        let decision = Decision::combine_murphy(&decision_vec);
+
+        let new_plugin_outputs = new_plugin_outputs.lock().await;
+        self.combined_output = HandlerOutput {
+            decision,
+            tags: self.combined_output.tags.clone(),
+            labels,
+            action: None,
+        };
+        self.plugin_outputs.clone_from(&new_plugin_outputs);
+    }
+
+    pub(crate) async fn execute_decision_feedback(&mut self) {
+        let verdict = self
+            .verdict
+            .as_ref()
+            .expect("cannot execute feedback phase without verdict");
+        metrics::increment_counter!(
+            "combined_decision",
+            "outcome" => verdict.outcome.to_string(),
+        );
+        metrics::histogram!(
+            "combined_decision_score",
+            verdict.decision.pignistic().restrict
+        );
+
+        let mut decisions: Vec<Decision> = Vec::with_capacity(self.plugin_instances.len());
+        let mut feedback_phase_tasks = JoinSet::new();
+        for plugin_instance in self.plugin_instances.iter().cloned() {
+            let response_phase_child_span =
+                tracing::info_span!("execute handle_decision_feedback",);
+            let permit = self
+                .plugin_semaphore
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("semaphore closed");
+            {
+                // Make sure the plugin instance knows about the final combined decision
+                let plugin_instance = plugin_instance.lock().await;
+                let decision = self
+                    .plugin_outputs
+                    .get(&plugin_instance.plugin_reference())
+                    .map(|output| output.decision)
+                    // This could happen if the plugin panics.
+                    .unwrap_or_else(|| {
+                        warn!(
+                            message = "plugin decision missing",
+                            reference = &plugin_instance.plugin_reference(),
+                        );
+                        Decision::default()
+                    });
+                metrics::histogram!(
+                    "decision_score",
+                    decision.pignistic().restrict,
+                    "ref" => plugin_instance.plugin_reference(),
+                );
+                // Measure the conflict between each individual decision and the combined decision
+                metrics::histogram!(
+                    "decision_conflict",
+                    Decision::conflict(&[decision, verdict.decision]),
+                    "ref" => plugin_instance.plugin_reference(),
+                );
+                decisions.push(decision);
+            }
+            let request = self.request.clone();
+            let response = self
+                .response
+                .clone()
+                .expect("cannot execute feedback phase without response");
+            // Need to be careful that we grab the labels emitted by the request phase and not the labels we started with.
+            let labels = self.combined_output.labels.clone();
+            let verdict = verdict.clone();
+            feedback_phase_tasks.spawn(
+                timeout(self.timeout_duration, async move {
+                    let result = Self::dispatch_decision_feedback(
+                        plugin_instance,
+                        request,
+                        response,
+                        labels,
+                        verdict,
+                    )
+                    .await;
+                    drop(permit);
+                    result
+                })
+                .instrument(response_phase_child_span.or_current()),
+            );
+        }
+        join_all(feedback_phase_tasks, |_| {}).await;
+
+        // Measure total conflict in the combined decision
+        metrics::histogram!("combined_conflict", Decision::conflict(&decisions));
+
+        // Capturing stdio is always the last thing that happens and feedback should always be the second-to-last.
+        self.capture_stdio().await;
+    }
+
+    /// Determines the [`Outcome`] of the combined decision and records it.
+    ///
+    /// # Arguments
+    ///
+    /// * `metric` - The name of the counter that tracks outcomes for the phase that was just completed.
+    pub(crate) fn combined_outcome(&self, metric: &'static str) -> Outcome {
+        let decision = self.combined_output.decision;
+        let outcome = decision
+            .outcome(
+                self.thresholds.trust,
+                self.thresholds.suspicious,
+                self.thresholds.restrict,
+            )
+            .unwrap();
+
+        info!(
+            message = "combine decision",
+            accept = format_f64!(decision.accept),
+            restrict = format_f64!(decision.restrict),
+            unknown = format_f64!(decision.unknown),
+            score = format_f64!(decision.pignistic().restrict),
+            outcome = outcome.to_string(),
+            observe_only = self.thresholds.observe_only,
+            // array values aren't handled well unfortunately, coercing to comma-separated values seems to be the best option
+            tags = self
+                .combined_output
+                .tags
+                .iter()
+                .map(|s| s.as_str())
+                .collect::<Vec<&str>>()
+                .to_vec()
+                .join(","),
+        );
+        metrics::increment_counter!(
+            metric,
+            "outcome" => outcome.to_string(),
+            "observe_only" => self.thresholds.observe_only.to_string(),
+        );
+
+        outcome
+    }
+
+    /// Creates a [`Verdict`] from the combined decision and tags.
+    pub(crate) fn verdict(&self, outcome: Outcome) -> Verdict {
+        Verdict {
+            decision: self.combined_output.decision,
+            outcome,
+            tags: self.combined_output.tags.iter().cloned().collect(),
+        }
+    }
+
+    /// Determines the headers to forward to the interior service for an allowed request.
+    pub(crate) fn forwarded_headers(
+        &self,
+        outcome: Outcome,
+        action: &ResolvedAction,
+    ) -> Result<ForwardedHeaders, SfvError> {
+        let mut headers = forwarded_headers(
+            &self.headers,
+            &self.verdict(outcome),
+            &self.combined_output.labels,
+        )?;
+        headers.add(&action.added_headers);
+        Ok(headers)
+    }
+
+    /// Resolves the action for an outcome from the resource's config and the actions requested by plugins.
+    pub(crate) fn action(&self, outcome: Outcome) -> ResolvedAction {
+        let mut requested: Vec<(&String, &bulwark_sdk::Action)> = self
+            .plugin_outputs
+            .iter()
+            .filter_map(|(reference, output)| {
+                output.action.as_ref().map(|action| (reference, action))
+            })
+            .collect();
+        // Plugins run concurrently, so sort to make sure equally strict actions are always resolved the same way.
+        requested.sort_by_key(|(reference, _)| *reference);
+        let resolved = resolve_action(
+            self.actions.action(outcome),
+            requested.into_iter().map(|(_, action)| action),
+            Duration::from_millis(self.actions.max_tarpit_delay),
+        );
+        info!(
+            message = "resolve action",
+            outcome = outcome.to_string(),
+            action = resolved.action.name(),
+        );
+        resolved
+    }
+
+    /// Generates the response sent in place of the interior service's, if the action calls for one.
+    pub(crate) fn action_response(
+        &self,
+        action: &Action,
+    ) -> Result<Option<bulwark_sdk::Response>, http::Error> {
+        match action {
+            Action::Allow | Action::Tarpit(_) => Ok(None),
+            Action::Redirect { location, status } => {
+                render_redirect_response(location, *status).map(Some)
+            }
+            Action::Block { status, headers } => {
+                let mut response = self.block_response()?;
+                if let Some(status) = status {
+                    *response.status_mut() = http::StatusCode::from_u16(*status)?;
+                }
+                for (name, value) in headers {
+                    response.headers_mut().insert(
+                        http::HeaderName::from_bytes(name.as_bytes())?,
+                        http::HeaderValue::from_str(value)?,
+                    );
+                }
+                Ok(Some(response))
+            }
+        }
+    }
+
+    #[instrument(name = "plugin output", skip(self))]
+    async fn capture_stdio(&self) {
+        // TODO: refactor to process one plugin at a time and try to avoid having handle_decision_feedback join_all
+        for plugin_instance in self.plugin_instances.iter() {
+            let plugin_instance = plugin_instance.lock().await;
+            let stdout = plugin_instance.stdio().stdout_buffer();
+            let stderr = plugin_instance.stdio().stderr_buffer();
+            if !stdout.is_empty() {
+                let stdout = String::from_utf8_lossy(&stdout);
+                for line in stdout.lines() {
+                    info!(
+                        message = "stdout",
+                        plugin = plugin_instance.plugin_reference(),
+                        content = line
+                    );
+                }
+            }
+            if !stderr.is_empty() {
+                let stderr = String::from_utf8_lossy(&stderr);
+                for line in stderr.lines() {
+                    error!(
+                        message = "stderr",
+                        plugin = plugin_instance.plugin_reference(),
+                        content = line
+                    );
+                }
+            }
+        }
+    }
+
+    /// Generates a response indicating the request has been blocked, using the matching resource's config.
+    pub(crate) fn block_response(&self) -> Result<bulwark_sdk::Response, http::Error> {
+        render_block_response(&self.block, &self.request, &self.combined_output.tags)
+    }
+
+    async fn dispatch_request_enrichment(
+        plugin_instance: Arc<Mutex<PluginInstance>>,
+        request: Arc<bulwark_sdk::Request>,
+        labels: HashMap<String, String>,
+    ) -> Result<HashMap<String, String>, PluginExecutionError> {
+        let mut plugin_instance = plugin_instance.lock().await;
+        let result = plugin_instance
+            .handle_request_enrichment(request, labels)
+            .await;
+        match result {
+            Ok(_) => metrics::increment_counter!(
+                "plugin_wasm_on_request",
+                "ref" => plugin_instance.plugin_reference(),
+                "result" => "ok"
+            ),
+            Err(_) => metrics::increment_counter!(
+                "plugin_wasm_on_request",
+                "ref" => plugin_instance.plugin_reference(),
+                "result" => "error"
+            ),
+        }
+        result
+    }
+
+    async fn dispatch_request_decision(
+        plugin_instance: Arc<Mutex<PluginInstance>>,
+        request: Arc<bulwark_sdk::Request>,
+        labels: HashMap<String, String>,
+    ) -> Result<HandlerOutput, PluginExecutionError> {
+        let mut plugin_instance = plugin_instance.lock().await;
+        let result = plugin_instance
+            .handle_request_decision(request, labels)
+            .await;
+        match result {
+            Ok(_) => metrics::increment_counter!(
+                "plugin_wasm_on_request_decision",
+                "ref" => plugin_instance.plugin_reference(),
+                "result" => "ok"
+            ),
+            Err(_) => metrics::increment_counter!(
+                "plugin_wasm_on_request_decision",
+                "ref" => plugin_instance.plugin_reference(),
+                "result" => "error"
+            ),
+        }
+        result
+    }
+
+    async fn dispatch_response_decision(
+        plugin_instance: Arc<Mutex<PluginInstance>>,
+        request: Arc<bulwark_sdk::Request>,
+        response: Arc<bulwark_sdk::Response>,
+        labels: HashMap<String, String>,
+    ) -> Result<HandlerOutput, PluginExecutionError> {
+        let mut plugin_instance = plugin_instance.lock().await;
+        let result = plugin_instance
+            .handle_response_decision(request, response, labels)
+            .await;
+        match result {
+            Ok(_) => metrics::increment_counter!(
+                "plugin_wasm_on_response_decision",
+                "ref" => plugin_instance.plugin_reference(),
+                "result" => "ok"
+            ),
+            Err(_) => metrics::increment_counter!(
+                "plugin_wasm_on_response_decision",
+                "ref" => plugin_instance.plugin_reference(),
+                "result" => "error"
+            ),
+        }
+        result
+    }
+
+    async fn dispatch_decision_feedback(
+        plugin_instance: Arc<Mutex<PluginInstance>>,
+        request: Arc<bulwark_sdk::Request>,
+        response: Arc<bulwark_sdk::Response>,
+        labels: HashMap<String, String>,
+        verdict: bulwark_sdk::Verdict,
+    ) -> Result<(), PluginExecutionError> {
+        let mut plugin_instance = plugin_instance.lock().await;
+        let result = plugin_instance
+            .handle_decision_feedback(request, response, labels, verdict)
+            .await;
+        match result {
+            Ok(_) => metrics::increment_counter!(
+                "plugin_wasm_on_decision_feedback",
+                "ref" => plugin_instance.plugin_reference(),
+                "result" => "ok"
+            ),
+            Err(_) => metrics::increment_counter!(
+                "plugin_wasm_on_decision_feedback",
+                "ref" => plugin_instance.plugin_reference(),
+                "result" => "error"
+            ),
+        }
+        result
+    }
+}
@@ -0,0 +1,460 @@
+//! The proxy module contains a standalone HTTP reverse proxy that runs the Bulwark plugin pipeline without Envoy.
+
+use crate::{
+    action::tarpit,
+    forwarded::{parse_forwarded_ip, parse_x_forwarded_for_ip},
+    headers::ForwardedHeaders,
+    BulwarkProcessor, ProxyError, ProxyInitError,
+};
+use bulwark_config::Config;
+use bulwark_host::{ForwardedIP, RemoteIP};
+use bytes::Bytes;
+use http::{header, HeaderMap, HeaderName, HeaderValue, StatusCode, Uri, Version};
+use http_body_util::{BodyExt, Full, LengthLimitError, Limited};
+use hyper::{body::Incoming, service::service_fn};
+use hyper_util::{
+    client::legacy::{connect::HttpConnector, Client},
+    rt::{TokioExecutor, TokioIo},
+    server::conn::auto,
+};
+use std::{convert::Infallible, net::SocketAddr, sync::Arc, time::Duration};
+use tokio::net::TcpListener;
+use tracing::{debug, error, info, instrument, warn, Instrument};
+
+/// Headers that apply only to a single transport-level connection and must not be forwarded by proxies.
+///
+/// See [RFC 9110, Section 7.6.1](https://www.rfc-editor.org/rfc/rfc9110#section-7.6.1).
+const HOP_BY_HOP_HEADERS: [&str; 9] = [
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    // Upgrades are not supported since bodies are buffered.
+    "upgrade",
+];
+
+/// The `BulwarkProxy` is an HTTP reverse proxy that evaluates each request with Bulwark's plugins before forwarding
+/// it to an upstream service.
+///
+/// It accepts both HTTP/1.1 and HTTP/2 connections and forwards requests to the upstream over HTTP/1.1. Request and
+/// response bodies are buffered in full so that they can be made available to plugins.
+#[derive(Clone)]
+pub struct BulwarkProxy {
+    processor: BulwarkProcessor,
+    upstream: Uri,
+    client: Client<HttpConnector, Full<Bytes>>,
+    max_body_size: usize,
+    upstream_timeout: Duration,
+}
+
+impl BulwarkProxy {
+    /// Creates a new [`BulwarkProxy`].
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The root of the Bulwark configuration structure to be used to initialize the service.
+    pub async fn new(config: Config) -> Result<Self, ProxyInitError> {
+        let upstream = config
+            .service
+            .upstream
+            .as_ref()
+            .ok_or(ProxyInitError::MissingUpstream)?
+            .as_str()
+            .parse::<Uri>()?;
+        let max_body_size = config.service.max_body_size;
+        let upstream_timeout = Duration::from_millis(config.service.upstream_timeout);
+        let processor = BulwarkProcessor::new(config).await?;
+        let client = Client::builder(TokioExecutor::new()).build_http();
+
+        Ok(Self {
+            processor,
+            upstream,
+            client,
+            max_body_size,
+            upstream_timeout,
+        })
+    }
+
+    /// The processor evaluating requests for the proxy.
+    ///
+    /// The processor shares its router with the proxy, so it can be used to [reload](BulwarkProcessor::reload) the
+    /// proxy's resources and plugins.
+    pub fn processor(&self) -> &BulwarkProcessor {
+        &self.processor
+    }
+
+    /// Accepts connections on the given address and serves them until an I/O error occurs on the listener.
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The socket address to listen on.
+    pub async fn serve(self, addr: SocketAddr) -> Result<(), std::io::Error> {
+        self.serve_with_shutdown(addr, std::future::pending()).await
+    }
+
+    /// Accepts connections on the given address and serves them until the `signal` future completes or an I/O
+    /// error occurs on the listener.
+    ///
+    /// Connections that were already accepted continue to be served after the signal, but requests on them are
+    /// rejected once the processor starts [draining](BulwarkProcessor::drain).
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - The socket address to listen on.
+    /// * `signal` - A future that completes when the proxy should stop accepting connections.
+    pub async fn serve_with_shutdown(
+        self,
+        addr: SocketAddr,
+        signal: impl std::future::Future<Output = ()>,
+    ) -> Result<(), std::io::Error> {
+        let listener = TcpListener::bind(addr).await?;
+        tokio::pin!(signal);
+        loop {
+            let (stream, remote_addr) = tokio::select! {
+                accepted = listener.accept() => accepted?,
+                _ = &mut signal => return Ok(()),
+            };
+            let proxy = self.clone();
+            tokio::task::spawn(async move {
+                let service = service_fn(move |request| {
+                    let proxy = proxy.clone();
+                    async move { proxy.handle(request, remote_addr).await }
+                });
+                if let Err(err) = auto::Builder::new(TokioExecutor::new())
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await
+                {
+                    debug!(message = "connection error", error_message = ?err);
+                }
+            });
+        }
+    }
+
+    /// Processes an incoming request, forwarding it to the upstream unless it is blocked.
+    #[instrument(name = "handle request", skip(self, request))]
+    async fn handle(
+        &self,
+        request: hyper::Request<Incoming>,
+        remote_addr: SocketAddr,
+    ) -> Result<hyper::Response<Full<Bytes>>, Infallible> {
+        if self.processor.is_draining() {
+            info!(
+                message = "process response",
+                status = u16::from(StatusCode::SERVICE_UNAVAILABLE)
+            );
+            return Ok(hyper::Response::builder()
+                .status(StatusCode::SERVICE_UNAVAILABLE)
+                .header(header::CONNECTION, "close")
+                .body(Full::new(Bytes::from_static(b"Service Unavailable\n")))
+                .expect("static response should be valid"));
+        }
+        let child_span = tracing::info_span!("route request");
+        let permit = self
+            .processor
+            .request_semaphore()
+            .acquire_owned()
+            .await
+            .expect("semaphore closed");
+        let result = self
+            .process(request, remote_addr)
+            .instrument(child_span.or_current())
+            .await;
+        drop(permit);
+
+        Ok(match result {
+            Ok(response) => response.map(Full::new),
+            Err(err) => {
+                let (status, body): (StatusCode, &'static [u8]) = match err {
+                    ProxyError::RequestBodyTooLarge => {
+                        (StatusCode::PAYLOAD_TOO_LARGE, b"Payload Too Large\n")
+                    }
+                    ProxyError::UpstreamTimeout => {
+                        (StatusCode::GATEWAY_TIMEOUT, b"Gateway Timeout\n")
+                    }
+                    _ => (StatusCode::BAD_GATEWAY, b"Bad Gateway\n"),
+                };
+                error!(message = "error during processing", error =?err);
+                info!(message = "process response", status = u16::from(status));
+                hyper::Response::builder()
+                    .status(status)
+                    .body(Full::new(Bytes::from_static(body)))
+                    .expect("static response should be valid")
+            }
+        })
+    }
+
+    async fn process(
+        &self,
+        request: hyper::Request<Incoming>,
+        remote_addr: SocketAddr,
+    ) -> Result<bulwark_sdk::Response, ProxyError> {
+        let request = Arc::new(self.prepare_request(request, remote_addr).await?);
+
+        let mut pipeline = match self.processor.route_request(request.clone()).await? {
+            Some(pipeline) => pipeline,
+            None => {
+                warn!(message = "no resource matched request",);
+                let response = self
+                    .forward(
+                        &request,
+                        remote_addr,
+                        ForwardedHeaders::stripped(&self.processor.headers()),
+                    )
+                    .await?;
+                info!(
+                    message = "process response",
+                    status = u16::from(response.status())
+                );
+                return Ok(response);
+            }
+        };
+
+        pipeline.execute_request_enrichment_phase().await;
+        pipeline.execute_request_decision_phase().await;
+
+        let outcome = pipeline.combined_outcome("plugin_request_phase_decision");
+        let action = pipeline.action(outcome);
+        let observe_only = pipeline.thresholds.observe_only;
+        if let Some(action_response) = pipeline.action_response(&action.action)? {
+            pipeline.verdict = Some(pipeline.verdict(outcome));
+            if !observe_only {
+                info!(
+                    message = "process response",
+                    status = u16::from(action_response.status())
+                );
+
+                // Normally we initiate feedback after the response phase, but if we're blocking the request
+                // in the request phase, we're also skipping the response phase and we need to do it here
+                // instead.
+                pipeline.response = Some(Arc::new(copy_response(&action_response)?));
+                pipeline.execute_decision_feedback().await;
+
+                return Ok(action_response);
+            }
+
+            // In observe-only mode, we still perform decision feedback against the response that would have been
+            // sent, and the response phase is skipped just as it would be for a blocked request.
+            pipeline.response = Some(Arc::new(action_response));
+            pipeline.execute_decision_feedback().await;
+
+            let response = self
+                .forward(
+                    &request,
+                    remote_addr,
+                    pipeline.forwarded_headers(outcome, &action)?,
+                )
+                .await?;
+            info!(
+                message = "process response",
+                status = u16::from(response.status())
+            );
+            return Ok(response);
+        }
+
+        tarpit(&action.action, observe_only).await;
+        let response = self
+            .forward(
+                &request,
+                remote_addr,
+                pipeline.forwarded_headers(outcome, &action)?,
+            )
+            .await?;
+        pipeline.response = Some(Arc::new(copy_response(&response)?));
+        pipeline.execute_response_phase().await;
+
+        let outcome = pipeline.combined_outcome("plugin_response_phase_decision");
+        // The request has already been sent upstream, so tarpits and added headers no longer apply.
+        let action = pipeline.action(outcome);
+        pipeline.verdict = Some(pipeline.verdict(outcome));
+        pipeline.execute_decision_feedback().await;
+
+        match pipeline.action_response(&action.action)? {
+            Some(action_response) if !observe_only => {
+                info!(
+                    message = "process response",
+                    status = u16::from(action_response.status())
+                );
+                Ok(action_response)
+            }
+            _ => {
+                info!(
+                    message = "process response",
+                    status = u16::from(response.status())
+                );
+                Ok(response)
+            }
+        }
+    }
+
+    /// Buffers the incoming request body and attaches the remote and forwarded IP addresses as extensions.
+    async fn prepare_request(
+        &self,
+        request: hyper::Request<Incoming>,
+        remote_addr: SocketAddr,
+    ) -> Result<bulwark_sdk::Request, ProxyError> {
+        let (mut parts, body) = request.into_parts();
+        let body = collect_limited(body, self.max_body_size)
+            .await
+            .map_err(|err| match err {
+                ProxyError::BodyTooLarge => ProxyError::RequestBodyTooLarge,
+                err => err,
+            })?;
+
+        let proxy_hops = self.processor.proxy_hops();
+        let forwarded_ip = if proxy_hops == 0 {
+            // With no proxies exterior to Bulwark, the forwarding headers can't be trusted.
+            Some(remote_addr.ip())
+        } else if let Some(forwarded) = parts.headers.get(header::FORWARDED) {
+            parse_forwarded_ip(forwarded.as_bytes(), proxy_hops)
+        } else if let Some(forwarded) = parts.headers.get("x-forwarded-for") {
+            parse_x_forwarded_for_ip(forwarded.as_bytes(), proxy_hops)
+        } else {
+            None
+        };
+        parts.extensions.insert(RemoteIP(remote_addr.ip()));
+        if let Some(ip_addr) = forwarded_ip {
+            parts.extensions.insert(ForwardedIP(ip_addr));
+        }
+
+        Ok(http::Request::from_parts(parts, body))
+    }
+
+    /// Sends a request to the upstream service, along with Bulwark's forwarded headers, and buffers its response.
+    async fn forward(
+        &self,
+        request: &bulwark_sdk::Request,
+        remote_addr: SocketAddr,
+        forwarded_headers: ForwardedHeaders,
+    ) -> Result<bulwark_sdk::Response, ProxyError> {
+        let mut uri_parts = self.upstream.clone().into_parts();
+        let upstream_prefix = self.upstream.path().trim_end_matches('/');
+        let path_and_query = request
+            .uri()
+            .path_and_query()
+            .map(|path_and_query| path_and_query.as_str())
+            .unwrap_or("/");
+        uri_parts.path_and_query = Some(format!("{}{}", upstream_prefix, path_and_query).parse()?);
+
+        let mut upstream_request = http::Request::builder()
+            .method(request.method())
+            .uri(Uri::from_parts(uri_parts)?)
+            .version(Version::HTTP_11)
+            .body(Full::new(request.body().clone()))?;
+        let headers = upstream_request.headers_mut();
+        for (name, value) in request.headers() {
+            headers.append(name, value.clone());
+        }
+        if headers.get(header::HOST).is_none() {
+            // HTTP/2 requests carry the host in the :authority pseudo-header rather than the Host header.
+            if let Some(authority) = request.uri().authority() {
+                headers.insert(header::HOST, HeaderValue::from_str(authority.as_str())?);
+            }
+        }
+        remove_hop_by_hop_headers(headers);
+        let forwarded_for = match headers.get("x-forwarded-for") {
+            Some(value) => format!(
+                "{}, {}",
+                String::from_utf8_lossy(value.as_bytes()),
+                remote_addr.ip()
+            ),
+            None => remote_addr.ip().to_string(),
+        };
+        headers.insert("x-forwarded-for", HeaderValue::from_str(&forwarded_for)?);
+        for name in forwarded_headers.remove {
+            headers.remove(name);
+        }
+        for (name, value) in forwarded_headers.set {
+            headers.insert(
+                HeaderName::from_bytes(name.as_bytes())?,
+                HeaderValue::from_str(&value)?,
+            );
+        }
+
+        let upstream_response = async {
+            let (parts, body) = self.client.request(upstream_request).await?.into_parts();
+            let body = collect_limited(body, self.max_body_size).await?;
+            Ok::<_, ProxyError>((parts, body))
+        };
+        let (mut parts, body) = tokio::time::timeout(self.upstream_timeout, upstream_response)
+            .await
+            .map_err(|_| ProxyError::UpstreamTimeout)??;
+        remove_hop_by_hop_headers(&mut parts.headers);
+        parts.version = request.version();
+
+        Ok(http::Response::from_parts(parts, body))
+    }
+}
+
+/// Buffers a body in full, failing with [`ProxyError::BodyTooLarge`] if it exceeds the maximum size.
+async fn collect_limited(body: Incoming, max_body_size: usize) -> Result<Bytes, ProxyError> {
+    match Limited::new(body, max_body_size).collect().await {
+        Ok(collected) => Ok(collected.to_bytes()),
+        Err(err) if err.is::<LengthLimitError>() => Err(ProxyError::BodyTooLarge),
+        Err(err) => Err(ProxyError::Body(err)),
+    }
+}
+
+/// Copies a buffered response so that it can be shared with plugins while the original is sent to the client.
+///
+/// Extensions are not copied.
+fn copy_response(response: &bulwark_sdk::Response) -> Result<bulwark_sdk::Response, http::Error> {
+    let mut builder = http::Response::builder()
+        .status(response.status())
+        .version(response.version());
+    if let Some(headers) = builder.headers_mut() {
+        headers.clone_from(response.headers());
+    }
+    builder.body(response.body().clone())
+}
+
+/// Strips hop-by-hop headers, including any headers nominated by the `Connection` header.
+fn remove_hop_by_hop_headers(headers: &mut HeaderMap) {
+    let nominated: Vec<HeaderName> = headers
+        .get_all(header::CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|name| HeaderName::from_bytes(name.trim().as_bytes()).ok())
+        .collect();
+    for name in nominated {
+        headers.remove(name);
+    }
+    for name in HOP_BY_HOP_HEADERS {
+        headers.remove(name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remove_hop_by_hop_headers() -> Result<(), Box<dyn std::error::Error>> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONNECTION,
+            HeaderValue::from_static("close, x-custom"),
+        );
+        headers.insert("keep-alive", HeaderValue::from_static("timeout=5"));
+        headers.insert(
+            header::TRANSFER_ENCODING,
+            HeaderValue::from_static("chunked"),
+        );
+        headers.insert("x-custom", HeaderValue::from_static("value"));
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+
+        remove_hop_by_hop_headers(&mut headers);
+
+        assert_eq!(headers.len(), 1);
+        assert_eq!(
+            headers.get(header::CONTENT_TYPE),
+            Some(&HeaderValue::from_static("text/plain"))
+        );
+
+        Ok(())
+    }
+}
@@ -1,15 +1,18 @@
 //! The service module contains the main Envoy external processor service implementation.
 
 use crate::{
-    PhaseError, PluginGroupInstantiationError, ProcessingMessageError, RequestError, ResponseError,
+    action::{tarpit, ResolvedAction},
+    forwarded::{parse_forwarded_ip, parse_x_forwarded_for_ip},
+    headers::{header_value_option, ForwardedHeaders},
+    pipeline::PipelineContext,
+    EvaluationError, PhaseError, PluginGroupInstantiationError, ProcessingMessageError,
+    RequestError, ResponseError,
 };
 use bulwark_config::Config;
 use bulwark_host::{
-    ForwardedIP, HandlerOutput, Plugin, PluginCtx, PluginExecutionError, PluginInstance,
-    PluginLoadError, RedisCtx, ScriptRegistry,
+    ForwardedIP, HandlerOutput, Plugin, PluginEngine, PluginInstance, PluginLoadError, PluginPool,
+    RedisCtx, ScriptRegistry,
 };
-use bulwark_sdk::Decision;
-use bulwark_sdk::Verdict;
 
 use crate::protobuf::envoy::{
     config::core::v3::HeaderMap,
@@ -17,80 +20,66 @@ use crate::protobuf::envoy::{
     r#type::v3::HttpStatus,
     service::ext_proc::v3::{
         external_processor_server::ExternalProcessor, processing_request, processing_response,
-        BodyResponse, CommonResponse, HeadersResponse, HttpBody, HttpHeaders, ImmediateResponse,
-        ProcessingRequest, ProcessingResponse,
+        BodyResponse, CommonResponse, HeaderMutation, HeadersResponse, HttpBody, HttpHeaders,
+        ImmediateResponse, ProcessingRequest, ProcessingResponse,
     },
 };
-use forwarded_header_value::ForwardedHeaderValue;
 use futures::lock::Mutex;
 use futures::{channel::mpsc::UnboundedSender, SinkExt, Stream};
 use matchit::Router;
 use std::{
-    collections::{HashMap, HashSet},
-    net::IpAddr,
+    collections::HashMap,
     pin::Pin,
-    sync::Arc,
+    sync::atomic::{AtomicBool, Ordering},
+    sync::{Arc, RwLock},
     time::Duration,
 };
-use tokio::{sync::RwLock, sync::Semaphore, task::JoinSet, time::timeout};
+use tokio::sync::Semaphore;
 use tonic::Streaming;
-use tracing::{debug, error, info, instrument, trace, warn, Instrument};
-
-macro_rules! format_f64 {
-    ($expression:expr) => {
-        tracing::field::display(crate::format::Float3Formatter($expression))
-    };
-}
+use tracing::{error, info, instrument, trace, warn, Instrument};
 
 extern crate redis;
 
 type ExternalProcessorStream =
     Pin<Box<dyn Stream<Item = Result<ProcessingResponse, tonic::Status>> + Send>>;
-type PluginList = Vec<Arc<Plugin>>;
+type PluginList = Vec<Arc<PluginPool>>;
 
 /// A RouteTarget allows a router to map from a routing pattern to a plugin group and associated config values.
 ///
 /// See [`bulwark_config::Resource`] for its configuration.
+#[derive(Clone)]
 struct RouteTarget {
     plugins: PluginList,
     timeout: Option<u64>,
+    block: Arc<bulwark_config::BlockResponse>,
+    actions: Arc<bulwark_config::Actions>,
 }
 
-/// Helper function that joins everything in a joinset, ignoring success and raising warnings as needed
-async fn join_all<T, F>(
-    mut join_set: JoinSet<Result<Result<T, PluginExecutionError>, tokio::time::error::Elapsed>>,
-    mut success: F,
-) where
-    F: FnMut(T),
-    T: 'static,
-{
-    // efficiently hand execution off to the the tasks we're joining
-    tokio::task::yield_now().await;
-
-    while let Some(r) = join_set.join_next().await {
-        match r {
-            Ok(Ok(Ok(output))) => success(output),
-            // These 3 errors are only logged, not bubbled up
-            Ok(Ok(Err(err))) => {
-                error!(
-                    message = "plugin execution error",
-                    elapsed = ?err,
-                );
-            }
-            Ok(Err(err)) => {
-                warn!(
-                    message = "timeout on plugin execution",
-                    elapsed = ?err,
-                );
-            }
-            Err(err) => {
-                warn!(
-                    message = "join error on plugin execution",
-                    error_message = ?err,
-                );
-            }
-        }
-    }
+/// The resources and the settings applied to every request, which are replaced together when the config is reloaded.
+struct Routing {
+    router: Router<RouteTarget>,
+    thresholds: bulwark_config::Thresholds,
+    headers: Arc<bulwark_config::Headers>,
+}
+
+/// The result of [evaluating](BulwarkProcessor::evaluate) a request outside of a live service.
+#[derive(Clone)]
+pub struct Evaluation {
+    /// The combined verdict from the final phase that was executed.
+    pub verdict: bulwark_sdk::Verdict,
+    /// The labels emitted by the router and all plugins.
+    pub labels: HashMap<String, String>,
+    /// The weighted output of each plugin, keyed by plugin reference.
+    ///
+    /// A `None` value indicates that the plugin did not produce a decision, e.g. due to an error or timeout.
+    pub plugin_outputs: HashMap<String, Option<HandlerOutput>>,
+    /// The action resolved for the final phase that was executed.
+    pub action: ResolvedAction,
+    /// The response that would be sent in place of the interior service's if the action blocks or redirects the
+    /// request.
+    ///
+    /// This is populated even in observe-only mode.
+    pub action_response: Option<Arc<bulwark_sdk::Response>>,
 }
 
 /// The `BulwarkProcessor` implements the primary envoy processing service logic via the [`ExternalProcessor`] trait.
@@ -99,11 +88,17 @@ async fn join_all<T, F>(
 #[derive(Clone)]
 pub struct BulwarkProcessor {
     // TODO: may need to have a plugin registry at some point
-    router: Arc<RwLock<Router<RouteTarget>>>,
+    routing: Arc<RwLock<Routing>>,
+    /// Every plugin pool in the router, used to report on plugin initialization.
+    plugin_pools: Arc<RwLock<PluginList>>,
+    /// The engine shared by every loaded plugin, kept across reloads so compiled plugins don't need recompiling.
+    engine: Arc<RwLock<PluginEngine>>,
     redis_ctx: RedisCtx,
     request_semaphore: Arc<tokio::sync::Semaphore>,
     plugin_semaphore: Arc<tokio::sync::Semaphore>,
-    thresholds: bulwark_config::Thresholds,
+    max_concurrent_requests: usize,
+    /// Set once the service starts shutting down, after which new requests are turned away.
+    draining: Arc<AtomicBool>,
     proxy_hops: usize,
     // TODO: redis circuit breaker for health monitoring
 }
@@ -118,10 +113,11 @@ impl ExternalProcessor for BulwarkProcessor {
         &self,
         tonic_request: tonic::Request<Streaming<ProcessingRequest>>,
     ) -> Result<tonic::Response<ExternalProcessorStream>, tonic::Status> {
+        if self.is_draining() {
+            return Err(tonic::Status::unavailable("service is shutting down"));
+        }
         let bulwark_processor = self.clone();
-        let thresholds = self.thresholds;
         let proxy_hops = self.proxy_hops;
-        let plugin_semaphore = self.plugin_semaphore.clone();
 
         let stream = tonic_request.into_inner();
         let (sender, receiver) = futures::channel::mpsc::unbounded();
@@ -145,69 +141,41 @@ impl ExternalProcessor for BulwarkProcessor {
                 )
                 .await
                 {
-                    let request = Arc::new(request);
-
-                    info!(
-                        message = "process request",
-                        method = request.method().to_string(),
-                        uri = request.uri().to_string(),
-                        user_agent = request
-                            .headers()
-                            .get("User-Agent")
-                            .map(|ua: &http::HeaderValue| ua.to_str().unwrap_or_default())
-                    );
-
-                    let router = bulwark_processor.router.read().await;
-                    let mut router_labels = HashMap::new();
-                    let route_result = router.at(request.uri().path());
-                    // TODO: router needs to point to a struct that bundles the plugin set and associated config like timeout duration
-                    // TODO: put default timeout in a constant somewhere central
-                    let mut timeout_duration = Duration::from_millis(10);
-                    let route_target = route_result.ok().map(|route_match| {
-                        // TODO: may want to expose labels to logging after redaction
-                        for (key, value) in route_match.params.iter() {
-                            router_labels.insert(format!("route.{}", key), value.to_string());
+                    // TODO: figure out how best to bubble the error out of the task and up to the parent
+                    // TODO: figure out if tonic-error or some other option is the best way to convert to a tonic Status error
+                    let end_of_stream = request.body().is_empty();
+                    match bulwark_processor.route_request(Arc::new(request)).await {
+                        Ok(Some(pipeline)) => {
+                            let mut ctx = ProcessorContext {
+                                sender: arc_sender,
+                                stream: arc_stream,
+                                pipeline,
+                            };
+
+                            ctx.pipeline.execute_request_enrichment_phase().await;
+                            ctx.pipeline.execute_request_decision_phase().await;
+
+                            if let Err(err) = ctx.complete_request_phase().await {
+                                error!(message = "error during processing", error =?err);
+                            }
                         }
-                        route_match.value
-                    });
-
-                    if let Some(route_target) = route_target {
-                        // TODO: figure out how best to bubble the error out of the task and up to the parent
-                        // TODO: figure out if tonic-error or some other option is the best way to convert to a tonic Status error
-                        // TODO: we probably want to be initializing only when necessary now rather than on every request
-                        let plugin_instances = bulwark_processor
-                            .instantiate_plugins(&route_target.plugins)
+                        Ok(None) => {
+                            warn!(message = "no resource matched request",);
+                            // The request isn't evaluated, but Bulwark's headers still can't be passed through.
+                            let headers = ForwardedHeaders::stripped(&bulwark_processor.headers());
+                            if let Err(err) = ProcessorContext::send_allow_request_message(
+                                arc_sender,
+                                end_of_stream,
+                                headers,
+                            )
                             .await
-                            .unwrap();
-                        if let Some(millis) = route_target.timeout {
-                            timeout_duration = Duration::from_millis(millis);
+                            {
+                                error!(message = "error during processing", error =?err);
+                            }
                         }
-
-                        let mut ctx = ProcessorContext {
-                            sender: arc_sender,
-                            stream: arc_stream,
-                            plugin_semaphore,
-                            plugin_instances: plugin_instances.clone(),
-                            router_labels,
-                            request: request.clone(),
-                            response: None,
-                            verdict: None,
-                            combined_output: HandlerOutput::default(),
-                            plugin_outputs: HashMap::new(),
-                            thresholds,
-                            timeout_duration,
-                        };
-
-                        ctx.execute_init_phase().await;
-
-                        ctx.execute_request_enrichment_phase().await;
-                        ctx.execute_request_decision_phase().await;
-
-                        if let Err(err) = ctx.complete_request_phase().await {
-                            error!(message = "error during processing", error =?err);
+                        Err(err) => {
+                            error!(message = "could not instantiate plugins", error =?err);
                         }
-                    } else {
-                        warn!(message = "no resource matched request",);
                     }
                 }
                 drop(permit);
@@ -268,24 +236,153 @@ impl BulwarkProcessor {
             registry: Arc::new(ScriptRegistry::default()),
         };
 
+        // All plugins share one engine, and a plugin used by several resources is only loaded once.
+        let engine = PluginEngine::new(&config)?;
+        let (router, plugin_pools) = Self::build_router(&config, &engine, &redis_ctx).await?;
+        Ok(Self {
+            routing: Arc::new(RwLock::new(Routing {
+                router,
+                thresholds: config.thresholds,
+                headers: Arc::new(config.headers.clone()),
+            })),
+            plugin_pools: Arc::new(RwLock::new(plugin_pools)),
+            engine: Arc::new(RwLock::new(engine)),
+            request_semaphore: Arc::new(Semaphore::new(config.runtime.max_concurrent_requests)),
+            plugin_semaphore: Arc::new(Semaphore::new(config.runtime.max_plugin_tasks)),
+            max_concurrent_requests: config.runtime.max_concurrent_requests,
+            draining: Arc::new(AtomicBool::new(false)),
+            proxy_hops: usize::from(config.service.proxy_hops),
+            redis_ctx,
+        })
+    }
+
+    /// Replaces the resources, plugins, thresholds and headers with those from a newly loaded config.
+    ///
+    /// The new router is fully built before it's swapped in, so a config that fails to load leaves the current one
+    /// in place. Thresholds and headers are swapped in along with the router, so each request is routed and decided
+    /// with settings from the same config. Requests that have already been routed finish on the plugins and settings
+    /// they started with. Other settings only take effect on restart.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The newly loaded root of the Bulwark configuration structure.
+    pub async fn reload(&self, config: Config) -> Result<(), PluginLoadError> {
+        // Plugins that haven't changed are loaded from the current engine's compiled components, unless the new
+        // config needs engine settings it wasn't created with.
+        let current_engine = self.engine.read().expect("poisoned lock").clone();
+        let engine = if current_engine.supports(&config) {
+            current_engine
+        } else {
+            info!(message = "rebuild plugin engine");
+            PluginEngine::new(&config)?
+        };
+        let (router, plugin_pools) = Self::build_router(&config, &engine, &self.redis_ctx).await?;
+        *self.routing.write().expect("poisoned lock") = Routing {
+            router,
+            thresholds: config.thresholds,
+            headers: Arc::new(config.headers),
+        };
+        *self.plugin_pools.write().expect("poisoned lock") = plugin_pools;
+        *self.engine.write().expect("poisoned lock") = engine;
+        Ok(())
+    }
+
+    /// Stops accepting new requests and waits for in-flight requests to finish.
+    ///
+    /// Requests are finished once their decision feedback has run. New requests are rejected as soon as draining
+    /// starts, so that Envoy can retry them elsewhere or apply its failure mode.
+    ///
+    /// Returns `true` if all in-flight requests finished before the timeout expired.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The maximum amount of time to wait for in-flight requests.
+    pub async fn drain(&self, timeout: Duration) -> bool {
+        self.draining.store(true, Ordering::SeqCst);
+        // Every permit is only available once no requests are being processed.
+        let permits = u32::try_from(self.max_concurrent_requests).unwrap_or(u32::MAX);
+        tokio::time::timeout(timeout, self.request_semaphore.acquire_many(permits))
+            .await
+            .is_ok()
+    }
+
+    /// True if every instance of every loaded plugin ran its `init` function successfully.
+    ///
+    /// Plugins are initialized as they're loaded, so an `init` failure is reported here until the plugin is loaded
+    /// again by a reload, even if instances created after the failure initialize successfully.
+    pub fn plugins_initialized(&self) -> bool {
+        self.plugin_pools
+            .read()
+            .expect("poisoned lock")
+            .iter()
+            .all(|pool| pool.init_error().is_none())
+    }
+
+    /// Builds a router mapping each resource's routes to its plugins, loading and initializing the plugins and filling
+    /// their instance pools as it goes.
+    ///
+    /// Returns the router along with every plugin pool in it.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The root of the Bulwark configuration structure.
+    /// * `engine` - The engine to load every plugin with.
+    /// * `redis_ctx` - The Redis connection pool shared by all plugin instances.
+    async fn build_router(
+        config: &Config,
+        engine: &PluginEngine,
+        redis_ctx: &RedisCtx,
+    ) -> Result<(Router<RouteTarget>, PluginList), PluginLoadError> {
         let mut router: Router<RouteTarget> = Router::new();
+        let mut plugin_pools: PluginList = Vec::new();
         if config.resources.is_empty() {
             // TODO: return an init error not a plugin load error
             return Err(PluginLoadError::ResourceMissing);
         }
+        // A plugin used by several resources is only loaded once.
+        let mut loaded: HashMap<String, Arc<PluginPool>> = HashMap::new();
         for resource in &config.resources {
-            let plugin_configs = resource.resolve_plugins(&config)?;
+            let plugin_configs = resource.resolve_plugins(config)?;
             let mut plugins: PluginList = Vec::with_capacity(plugin_configs.len());
             for plugin_config in plugin_configs {
+                if let Some(pool) = loaded.get(&plugin_config.reference) {
+                    plugins.push(pool.clone());
+                    continue;
+                }
                 // TODO: pass in the plugin config
-                debug!(
+                let plugin = Arc::new(Plugin::from_engine(engine, config, plugin_config).await?);
+                // The digest is logged so that operators can pin the plugin with `sha256` verification.
+                info!(
                     message = "load plugin",
+                    plugin = plugin.reference(),
                     location = tracing::field::display(&plugin_config.location),
+                    digest = format!("sha256:{}", hex::encode(plugin.digest())),
+                    world = tracing::field::display(plugin.world_version()),
                     resource = tracing::field::debug(&resource.routes),
                 );
-                let plugin = Plugin::from_config(&config, plugin_config)?;
-                plugins.push(Arc::new(plugin));
+                let pool = PluginPool::new(
+                    plugin.clone(),
+                    plugin_environment(&plugin),
+                    redis_ctx.clone(),
+                    config.runtime.plugin_pool_size,
+                    config.runtime.plugin_instance_max_uses,
+                );
+                // Failures are reported through readiness until the next reload. The plugin's other handlers still
+                // run for each request, each on an instance of its own.
+                if let Err(err) = pool.initialize().await {
+                    error!(
+                        message = "could not initialize plugin",
+                        plugin = plugin.reference(),
+                        error_message = %err,
+                    );
+                }
+                let pool = Arc::new(pool);
+                loaded.insert(plugin_config.reference.clone(), pool.clone());
+                plugin_pools.push(pool.clone());
+                plugins.push(pool);
             }
+            let block = Arc::new(resource.block.clone());
+            let actions = Arc::new(resource.actions.clone());
             for route in &resource.routes {
                 router
                     .insert(
@@ -294,181 +391,227 @@ impl BulwarkProcessor {
                         RouteTarget {
                             timeout: resource.timeout,
                             plugins: plugins.clone(),
+                            block: block.clone(),
+                            actions: actions.clone(),
                         },
                     )
                     .ok();
             }
         }
-        Ok(Self {
-            router: Arc::new(RwLock::new(router)),
-            request_semaphore: Arc::new(Semaphore::new(config.runtime.max_concurrent_requests)),
-            plugin_semaphore: Arc::new(Semaphore::new(config.runtime.max_plugin_tasks)),
-            thresholds: config.thresholds,
-            proxy_hops: usize::from(config.service.proxy_hops),
-            redis_ctx,
-        })
+        Ok((router, plugin_pools))
     }
 
-    async fn instantiate_plugins(
+    /// Matches a request against the router and instantiates the plugin group for the matching resource.
+    ///
+    /// Returns `None` if no resource matched the request.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The incoming request.
+    pub(crate) async fn route_request(
         &self,
-        plugins: &PluginList,
-    ) -> Result<Vec<Arc<Mutex<PluginInstance>>>, PluginGroupInstantiationError> {
-        let mut plugin_instances = Vec::with_capacity(plugins.len());
-        for plugin in plugins {
-            let mut environment = HashMap::new();
-            for key in &plugin.permissions().env {
-                match std::env::var(key) {
-                    Ok(value) => {
-                        environment.insert(key.clone(), value);
-                    }
-                    Err(err) => {
-                        warn!("plugin requested environment variable '{}' but it could not be provided: {}", key, err);
-                    }
+        request: Arc<bulwark_sdk::Request>,
+    ) -> Result<Option<PipelineContext>, PluginGroupInstantiationError> {
+        info!(
+            message = "process request",
+            method = request.method().to_string(),
+            uri = request.uri().to_string(),
+            user_agent = request
+                .headers()
+                .get("User-Agent")
+                .map(|ua: &http::HeaderValue| ua.to_str().unwrap_or_default())
+        );
+
+        let mut router_labels = HashMap::new();
+        // TODO: put default timeout in a constant somewhere central
+        let mut timeout_duration = Duration::from_millis(10);
+        // The route target and settings are cloned out so that a reload isn't held up while plugins are instantiated.
+        let (route_target, thresholds, headers) = {
+            let routing = self.routing.read().expect("poisoned lock");
+            let route_result = routing.router.at(request.uri().path());
+            let route_target = route_result.ok().map(|route_match| {
+                // TODO: may want to expose labels to logging after redaction
+                for (key, value) in route_match.params.iter() {
+                    router_labels.insert(format!("route.{}", key), value.to_string());
                 }
+                route_match.value.clone()
+            });
+            (route_target, routing.thresholds, routing.headers.clone())
+        };
+
+        if let Some(route_target) = route_target {
+            let plugin_instances = self.instantiate_plugins(&route_target.plugins).await?;
+            if let Some(millis) = route_target.timeout {
+                timeout_duration = Duration::from_millis(millis);
             }
-            let request_context =
-                PluginCtx::new(plugin.clone(), environment, self.redis_ctx.clone())?;
-            plugin_instances.push(Arc::new(Mutex::new(
-                PluginInstance::new(plugin.clone(), request_context).await?,
-            )));
+
+            Ok(Some(PipelineContext {
+                plugin_semaphore: self.plugin_semaphore.clone(),
+                plugin_pools: route_target.plugins,
+                plugin_instances,
+                router_labels,
+                request,
+                response: None,
+                verdict: None,
+                combined_output: HandlerOutput::default(),
+                plugin_outputs: HashMap::new(),
+                thresholds,
+                headers,
+                block: route_target.block,
+                actions: route_target.actions,
+                timeout_duration,
+            }))
+        } else {
+            Ok(None)
         }
-        Ok(plugin_instances)
     }
 
-    async fn dispatch_init(
-        plugin_instance: Arc<Mutex<PluginInstance>>,
-    ) -> Result<(), PluginExecutionError> {
-        let mut plugin_instance = plugin_instance.lock().await;
-        let result = plugin_instance.handle_init().await;
-        match result {
-            Ok(_) => metrics::increment_counter!(
-                "plugin_wasm_on_init",
-                "ref" => plugin_instance.plugin_reference(),
-                "result" => "ok"
-            ),
-            Err(_) => metrics::increment_counter!(
-                "plugin_wasm_on_init",
-                "ref" => plugin_instance.plugin_reference(),
-                "result" => "error"
-            ),
+    /// Evaluates a request, and optionally its response, by driving the plugins for the matching resource through
+    /// the same phases the Envoy external processor would, without sending anything over the wire.
+    ///
+    /// The response phase only runs if a response is provided and the request phase did not block or redirect the
+    /// request. Decision feedback only runs if a response is available, either the provided one or the response
+    /// that would have been sent in its place.
+    ///
+    /// Returns `None` if no resource matched the request.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The request to evaluate.
+    /// * `response` - The response the interior service would have returned, if any.
+    pub async fn evaluate(
+        &self,
+        request: bulwark_sdk::Request,
+        response: Option<bulwark_sdk::Response>,
+    ) -> Result<Option<Evaluation>, EvaluationError> {
+        let mut pipeline = match self.route_request(Arc::new(request)).await? {
+            Some(pipeline) => pipeline,
+            None => return Ok(None),
+        };
+
+        pipeline.execute_request_enrichment_phase().await;
+        pipeline.execute_request_decision_phase().await;
+        let mut outcome = pipeline.combined_outcome("plugin_request_phase_decision");
+        let mut action = pipeline.action(outcome);
+        let mut action_response = pipeline.action_response(&action.action)?.map(Arc::new);
+
+        if let Some(action_response) = &action_response {
+            // The response phase is skipped for blocked or redirected requests, even in observe-only mode.
+            pipeline.response = Some(action_response.clone());
+        } else if let Some(response) = response {
+            pipeline.response = Some(Arc::new(response));
+            pipeline.execute_response_phase().await;
+            outcome = pipeline.combined_outcome("plugin_response_phase_decision");
+            action = pipeline.action(outcome);
+            action_response = pipeline.action_response(&action.action)?.map(Arc::new);
         }
-        result
-    }
 
-    async fn dispatch_request_enrichment(
-        plugin_instance: Arc<Mutex<PluginInstance>>,
-        request: Arc<bulwark_sdk::Request>,
-        labels: HashMap<String, String>,
-    ) -> Result<HashMap<String, String>, PluginExecutionError> {
-        let mut plugin_instance = plugin_instance.lock().await;
-        let result = plugin_instance
-            .handle_request_enrichment(request, labels)
-            .await;
-        match result {
-            Ok(_) => metrics::increment_counter!(
-                "plugin_wasm_on_request",
-                "ref" => plugin_instance.plugin_reference(),
-                "result" => "ok"
-            ),
-            Err(_) => metrics::increment_counter!(
-                "plugin_wasm_on_request",
-                "ref" => plugin_instance.plugin_reference(),
-                "result" => "error"
-            ),
+        let verdict = pipeline.verdict(outcome);
+        if pipeline.response.is_some() {
+            pipeline.verdict = Some(verdict.clone());
+            pipeline.execute_decision_feedback().await;
         }
-        result
-    }
 
-    async fn dispatch_request_decision(
-        plugin_instance: Arc<Mutex<PluginInstance>>,
-        request: Arc<bulwark_sdk::Request>,
-        labels: HashMap<String, String>,
-    ) -> Result<HandlerOutput, PluginExecutionError> {
-        let mut plugin_instance = plugin_instance.lock().await;
-        let result = plugin_instance
-            .handle_request_decision(request, labels)
-            .await;
-        match result {
-            Ok(_) => metrics::increment_counter!(
-                "plugin_wasm_on_request_decision",
-                "ref" => plugin_instance.plugin_reference(),
-                "result" => "ok"
-            ),
-            Err(_) => metrics::increment_counter!(
-                "plugin_wasm_on_request_decision",
-                "ref" => plugin_instance.plugin_reference(),
-                "result" => "error"
-            ),
+        let mut plugin_outputs = HashMap::with_capacity(pipeline.plugin_instances.len());
+        for plugin_instance in &pipeline.plugin_instances {
+            let reference = plugin_instance.lock().await.plugin_reference();
+            let output = pipeline.plugin_outputs.get(&reference).cloned();
+            plugin_outputs.insert(reference, output);
         }
-        result
+
+        Ok(Some(Evaluation {
+            verdict,
+            labels: std::mem::take(&mut pipeline.combined_output.labels),
+            plugin_outputs,
+            action,
+            action_response,
+        }))
     }
 
-    async fn dispatch_response_decision(
-        plugin_instance: Arc<Mutex<PluginInstance>>,
-        request: Arc<bulwark_sdk::Request>,
-        response: Arc<bulwark_sdk::Response>,
-        labels: HashMap<String, String>,
-    ) -> Result<HandlerOutput, PluginExecutionError> {
-        let mut plugin_instance = plugin_instance.lock().await;
-        let result = plugin_instance
-            .handle_response_decision(request, response, labels)
-            .await;
-        match result {
-            Ok(_) => metrics::increment_counter!(
-                "plugin_wasm_on_response_decision",
-                "ref" => plugin_instance.plugin_reference(),
-                "result" => "ok"
-            ),
-            Err(_) => metrics::increment_counter!(
-                "plugin_wasm_on_response_decision",
-                "ref" => plugin_instance.plugin_reference(),
-                "result" => "error"
-            ),
+    /// The semaphore limiting the number of requests that may be processed concurrently.
+    pub(crate) fn request_semaphore(&self) -> Arc<Semaphore> {
+        self.request_semaphore.clone()
+    }
+
+    /// True if the service has started shutting down and new requests should be rejected.
+    pub(crate) fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    /// The decision thresholds used to determine outcomes.
+    pub(crate) fn thresholds(&self) -> bulwark_config::Thresholds {
+        self.routing.read().expect("poisoned lock").thresholds
+    }
+
+    /// The headers sent to the interior service.
+    pub(crate) fn headers(&self) -> Arc<bulwark_config::Headers> {
+        self.routing.read().expect("poisoned lock").headers.clone()
+    }
+
+    /// The number of trusted proxy hops expected to be exterior to Bulwark.
+    pub(crate) fn proxy_hops(&self) -> usize {
+        self.proxy_hops
+    }
+
+    /// Takes an instance of each plugin from its pool, refilling the pools in the background as they empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `plugins` - The instance pools for the plugins in the group.
+    async fn instantiate_plugins(
+        &self,
+        plugins: &PluginList,
+    ) -> Result<Vec<Arc<Mutex<PluginInstance>>>, PluginGroupInstantiationError> {
+        let mut plugin_instances = Vec::with_capacity(plugins.len());
+        for pool in plugins {
+            plugin_instances.push(Arc::new(Mutex::new(pool.acquire().await?)));
+            if pool.needs_fill() {
+                let pool = pool.clone();
+                tokio::task::spawn(async move {
+                    if let Err(err) = pool.fill().await {
+                        warn!(
+                            message = "could not refill plugin instance pool",
+                            plugin = pool.plugin().reference(),
+                            error_message = %err,
+                        );
+                    }
+                });
+            }
         }
-        result
+        Ok(plugin_instances)
     }
+}
 
-    async fn dispatch_decision_feedback(
-        plugin_instance: Arc<Mutex<PluginInstance>>,
-        request: Arc<bulwark_sdk::Request>,
-        response: Arc<bulwark_sdk::Response>,
-        labels: HashMap<String, String>,
-        verdict: bulwark_sdk::Verdict,
-    ) -> Result<(), PluginExecutionError> {
-        let mut plugin_instance = plugin_instance.lock().await;
-        let result = plugin_instance
-            .handle_decision_feedback(request, response, labels, verdict)
-            .await;
-        match result {
-            Ok(_) => metrics::increment_counter!(
-                "plugin_wasm_on_decision_feedback",
-                "ref" => plugin_instance.plugin_reference(),
-                "result" => "ok"
-            ),
-            Err(_) => metrics::increment_counter!(
-                "plugin_wasm_on_decision_feedback",
-                "ref" => plugin_instance.plugin_reference(),
-                "result" => "error"
-            ),
+/// Reads the environment variables a plugin has been granted access to.
+///
+/// Variables that aren't set are skipped with a warning.
+///
+/// # Arguments
+///
+/// * `plugin` - The plugin whose permissions list the environment variables to read.
+fn plugin_environment(plugin: &Plugin) -> HashMap<String, String> {
+    let mut environment = HashMap::new();
+    for key in &plugin.permissions().env {
+        match std::env::var(key) {
+            Ok(value) => {
+                environment.insert(key.clone(), value);
+            }
+            Err(err) => {
+                warn!(
+                    "plugin requested environment variable '{}' but it could not be provided: {}",
+                    key, err
+                );
+            }
         }
-        result
     }
+    environment
 }
 
 /// The `ProcessorContext` wraps values associated with a single request/response cycle.
 struct ProcessorContext {
     sender: Arc<Mutex<UnboundedSender<Result<ProcessingResponse, tonic::Status>>>>,
     stream: Arc<Mutex<Streaming<ProcessingRequest>>>,
-    plugin_semaphore: Arc<tokio::sync::Semaphore>,
-    plugin_instances: Vec<Arc<Mutex<PluginInstance>>>,
-    router_labels: HashMap<String, String>,
-    request: Arc<bulwark_sdk::Request>,
-    response: Option<Arc<bulwark_sdk::Response>>,
-    verdict: Option<Verdict>,
-    combined_output: HandlerOutput,
-    plugin_outputs: HashMap<String, HandlerOutput>,
-    thresholds: bulwark_config::Thresholds,
-    timeout_duration: Duration,
+    pipeline: PipelineContext,
 }
 
 impl ProcessorContext {
@@ -531,13 +674,13 @@ impl ProcessorContext {
             // TODO: remote IP should probably be received via an external attribute, but that doesn't seem to be currently supported by envoy?
             // NOTE: header keys must be sent in lower case
             if let Some(forwarded) = Self::get_header_value(&header_msg.headers, "forwarded") {
-                if let Some(ip_addr) = Self::parse_forwarded_ip(forwarded, proxy_hops) {
+                if let Some(ip_addr) = parse_forwarded_ip(forwarded, proxy_hops) {
                     request = request.extension(ForwardedIP(ip_addr));
                 }
             } else if let Some(forwarded) =
                 Self::get_header_value(&header_msg.headers, "x-forwarded-for")
             {
-                if let Some(ip_addr) = Self::parse_x_forwarded_for_ip(forwarded, proxy_hops) {
+                if let Some(ip_addr) = parse_x_forwarded_for_ip(forwarded, proxy_hops) {
                     request = request.extension(ForwardedIP(ip_addr));
                 }
             }
@@ -570,7 +713,9 @@ impl ProcessorContext {
             } else {
                 bytes::Bytes::new()
             };
-            response = response.status(status).version(self.request.version());
+            response = response
+                .status(status)
+                .version(self.pipeline.request.version());
             match &header_msg.headers {
                 Some(headers) => {
                     for header in &headers.headers {
@@ -592,581 +737,129 @@ impl ProcessorContext {
         Err(ResponseError::Disconnected)
     }
 
-    async fn execute_init_phase(&self) {
-        let mut init_phase_tasks = JoinSet::new();
-        for plugin_instance in self.plugin_instances.iter().cloned() {
-            let init_phase_child_span = tracing::info_span!("execute handle_init",);
-            let permit = self
-                .plugin_semaphore
-                .clone()
-                .acquire_owned()
-                .await
-                .expect("semaphore closed");
-            init_phase_tasks.spawn(
-                timeout(self.timeout_duration, async move {
-                    let result = BulwarkProcessor::dispatch_init(plugin_instance).await;
-                    drop(permit);
-                    result
-                })
-                .instrument(init_phase_child_span.or_current()),
-            );
-        }
-        join_all(init_phase_tasks, |_| {}).await;
-    }
-
-    async fn execute_request_enrichment_phase(&mut self) {
-        let mut enrichment_phase_tasks = JoinSet::new();
-        for plugin_instance in self.plugin_instances.iter().cloned() {
-            let enrichment_phase_child_span =
-                tracing::info_span!("execute handle_request_enrichment",);
-            let permit = self
-                .plugin_semaphore
-                .clone()
-                .acquire_owned()
-                .await
-                .expect("semaphore closed");
-            let request = self.request.clone();
-            let router_labels = self.router_labels.clone();
-            enrichment_phase_tasks.spawn(
-                timeout(self.timeout_duration, async move {
-                    let result = BulwarkProcessor::dispatch_request_enrichment(
-                        plugin_instance,
-                        request,
-                        router_labels,
-                    )
-                    .await;
-                    drop(permit);
-                    result
-                })
-                .instrument(enrichment_phase_child_span.or_current()),
-            );
-        }
-
-        let mut labels = self.router_labels.clone();
-        join_all(enrichment_phase_tasks, |new_labels| {
-            // Merge labels from each plugin
-            labels.extend(new_labels);
-        })
-        .await;
-        self.combined_output = HandlerOutput {
-            decision: Decision::default(),
-            tags: HashSet::new(),
-            labels,
-        };
-    }
-
-    async fn execute_request_decision_phase(&mut self) {
-        let outputs: Arc<Mutex<Vec<HandlerOutput>>> =
-            Arc::new(Mutex::new(Vec::with_capacity(self.plugin_instances.len())));
-        let plugin_outputs: Arc<Mutex<HashMap<String, HandlerOutput>>> =
-            Arc::new(Mutex::new(HashMap::new()));
-        let mut decision_phase_tasks = JoinSet::new();
-        // The .iter().cloned() appears to be necessary
-        #[allow(clippy::unnecessary_to_owned)]
-        for plugin_instance in self.plugin_instances.iter().cloned() {
-            let decision_phase_child_span = tracing::info_span!("execute handle_request_decision",);
-            let permit = self
-                .plugin_semaphore
-                .clone()
-                .acquire_owned()
-                .await
-                .expect("semaphore closed");
-            let outputs = outputs.clone();
-            let plugin_outputs = plugin_outputs.clone();
-            let request = self.request.clone();
-            // Need to be careful that we grab the labels emitted by the request phase and not the labels we started with.
-            let labels = self.combined_output.labels.clone();
-            decision_phase_tasks.spawn(
-                timeout(self.timeout_duration, async move {
-                    let output_result = BulwarkProcessor::dispatch_request_decision(
-                        plugin_instance.clone(),
-                        request,
-                        labels,
-                    )
-                    .await;
-                    if let Ok(output) = &output_result {
-                        // Re-weight the decision based on its weighting value from the configuration
-                        let plugin_instance = plugin_instance.lock().await;
-                        let mut output = output.clone();
-                        output.decision = output.decision.weight(plugin_instance.weight());
-
-                        let decision = &output.decision;
-                        info!(
-                            message = "plugin decision",
-                            name = plugin_instance.plugin_reference(),
-                            accept = format_f64!(decision.accept),
-                            restrict = format_f64!(decision.restrict),
-                            unknown = format_f64!(decision.unknown),
-                            score = format_f64!(decision.pignistic().restrict),
-                            weight = format_f64!(plugin_instance.weight()),
-                        );
-
-                        let mut outputs = outputs.lock().await;
-                        outputs.push(output.clone());
-                        let mut plugin_outputs = plugin_outputs.lock().await;
-                        plugin_outputs.insert(plugin_instance.plugin_reference(), output);
-                    } else if let Err(err) = &output_result {
-                        error!(message = "plugin error", error = err.to_string());
-                        let mut outputs = outputs.lock().await;
-                        outputs.push(HandlerOutput {
-                            decision: bulwark_sdk::UNKNOWN,
-                            tags: HashSet::from([String::from("error")]),
-                            labels: HashMap::new(),
-                        });
-                    }
-                    drop(permit);
-                    output_result.map(|output| output.labels)
-                })
-                .instrument(decision_phase_child_span.or_current()),
-            );
-        }
-
-        let mut labels = self.router_labels.clone();
-        join_all(decision_phase_tasks, |new_labels| {
-            // Merge labels from each plugin
-            labels.extend(new_labels);
-        })
-        .await;
-
-        let decision_vec: Vec<Decision>;
-        {
-            let outputs = outputs.lock().await;
-            decision_vec = outputs.iter().map(|dc| dc.decision).collect();
-            self.combined_output.tags.extend(
-                outputs
-                    .iter()
-                    .flat_map(|dc| dc.tags.clone())
-                    .collect::<HashSet<String>>(),
-            );
-        }
-        let decision = Decision::combine_murphy(&decision_vec);
-
-        let plugin_outputs = plugin_outputs.lock().await;
-        self.combined_output = HandlerOutput {
-            decision,
-            tags: self.combined_output.tags.clone(),
-            labels,
-        };
-        self.plugin_outputs.clone_from(&plugin_outputs);
-    }
-
-    async fn execute_response_phase(&mut self) {
-        let outputs: Arc<Mutex<Vec<HandlerOutput>>> =
-            Arc::new(Mutex::new(Vec::with_capacity(self.plugin_instances.len())));
-        let new_plugin_outputs: Arc<Mutex<HashMap<String, HandlerOutput>>> =
-            Arc::new(Mutex::new(self.plugin_outputs.clone()));
-        let mut response_phase_tasks = JoinSet::new();
-        // The .iter().cloned() appears to be necessary
-        #[allow(clippy::unnecessary_to_owned)]
-        for plugin_instance in self.plugin_instances.iter().cloned() {
-            let response_phase_child_span =
-                tracing::info_span!("execute handle_response_decision",);
-            let permit = self
-                .plugin_semaphore
-                .clone()
-                .acquire_owned()
-                .await
-                .expect("semaphore closed");
-            let request = self.request.clone();
-            let response = self
-                .response
-                .clone()
-                .expect("cannot execute response phase without response");
-            // Need to be careful that we grab the labels emitted by the request phase and not the labels we started with.
-            let labels = self.combined_output.labels.clone();
-            let outputs = outputs.clone();
-            let new_plugin_outputs = new_plugin_outputs.clone();
-            let prior_plugin_outputs = self
-                .plugin_outputs
-                .get(&plugin_instance.lock().await.plugin_reference())
-                .cloned();
-            response_phase_tasks.spawn(
-                timeout(self.timeout_duration, async move {
-                    let output_result = BulwarkProcessor::dispatch_response_decision(
-                        plugin_instance.clone(),
-                        request,
-                        response,
-                        labels,
-                    )
-                    .await;
-                    if let Ok(output) = &output_result {
-                        // Re-weight the decision based on its weighting value from the configuration
-                        let plugin_instance = plugin_instance.lock().await;
-                        let mut output = output.clone();
-                        output.decision = output.decision.weight(plugin_instance.weight());
-
-                        if let Some(prior_plugin_outputs) = prior_plugin_outputs {
-                            // If the prior output was non-zero and the new output was zero, then keep the prior output.
-                            if !prior_plugin_outputs.decision.is_unknown()
-                                && output.decision.is_unknown()
-                            {
-                                // The prior decision was already weighted and does not need to have weights applied.
-                                output.decision = prior_plugin_outputs.decision;
-                            }
-                        }
-
-                        let decision = &output.decision;
-                        info!(
-                            message = "plugin decision",
-                            name = plugin_instance.plugin_reference(),
-                            accept = format_f64!(decision.accept),
-                            restrict = format_f64!(decision.restrict),
-                            unknown = format_f64!(decision.unknown),
-                            score = format_f64!(decision.pignistic().restrict),
-                            weight = format_f64!(plugin_instance.weight()),
-                        );
-
-                        let mut outputs = outputs.lock().await;
-                        outputs.push(output.clone());
-                        let mut new_plugin_outputs = new_plugin_outputs.lock().await;
-                        new_plugin_outputs.insert(plugin_instance.plugin_reference(), output);
-                    } else if let Err(err) = &output_result {
-                        error!(message = "plugin error", error = err.to_string());
-                        let mut outputs = outputs.lock().await;
-                        outputs.push(HandlerOutput {
-                            decision: bulwark_sdk::UNKNOWN,
-                            tags: HashSet::from([String::from("error")]),
-                            labels: HashMap::new(),
-                        });
-                    }
-                    drop(permit);
-                    output_result.map(|output| output.labels)
-                })
-                .instrument(response_phase_child_span.or_current()),
-            );
-        }
-
-        let mut labels = self.router_labels.clone();
-        join_all(response_phase_tasks, |new_labels| {
-            // Merge labels from each plugin
-            labels.extend(new_labels);
-        })
-        .await;
-
-        let decision_vec: Vec<Decision>;
-        {
-            let outputs = outputs.lock().await;
-            decision_vec = outputs.iter().map(|dc| dc.decision).collect();
-            self.combined_output.tags.extend(
-                outputs
-                    .iter()
-                    .flat_map(|dc| dc.tags.clone())
-                    .collect::<HashSet<String>>(),
-            );
-        }
-        let decision = Decision::combine_murphy(&decision_vec);
-
-        let new_plugin_outputs = new_plugin_outputs.lock().await;
-        self.combined_output = HandlerOutput {
-            decision,
-            tags: self.combined_output.tags.clone(),
-            labels,
-        };
-        self.plugin_outputs.clone_from(&new_plugin_outputs);
-    }
-
-    async fn execute_decision_feedback(&mut self) {
-        let verdict = self
-            .verdict
-            .as_ref()
-            .expect("cannot execute feedback phase without verdict");
-        metrics::increment_counter!(
-            "combined_decision",
-            "outcome" => verdict.outcome.to_string(),
-        );
-        metrics::histogram!(
-            "combined_decision_score",
-            verdict.decision.pignistic().restrict
-        );
-
-        let mut decisions: Vec<Decision> = Vec::with_capacity(self.plugin_instances.len());
-        let mut feedback_phase_tasks = JoinSet::new();
-        for plugin_instance in self.plugin_instances.iter().cloned() {
-            let response_phase_child_span =
-                tracing::info_span!("execute handle_decision_feedback",);
-            let permit = self
-                .plugin_semaphore
-                .clone()
-                .acquire_owned()
-                .await
-                .expect("semaphore closed");
-            {
-                // Make sure the plugin instance knows about the final combined decision
-                let plugin_instance = plugin_instance.lock().await;
-                let decision = self
-                    .plugin_outputs
-                    .get(&plugin_instance.plugin_reference())
-                    .map(|output| output.decision)
-                    // This could happen if the plugin panics.
-                    .unwrap_or_else(|| {
-                        warn!(
-                            message = "plugin decision missing",
-                            reference = &plugin_instance.plugin_reference(),
-                        );
-                        Decision::default()
-                    });
-                metrics::histogram!(
-                    "decision_score",
-                    decision.pignistic().restrict,
-                    "ref" => plugin_instance.plugin_reference(),
-                );
-                // Measure the conflict between each individual decision and the combined decision
-                metrics::histogram!(
-                    "decision_conflict",
-                    Decision::conflict(&[decision, verdict.decision]),
-                    "ref" => plugin_instance.plugin_reference(),
-                );
-                decisions.push(decision);
-            }
-            let request = self.request.clone();
-            let response = self
-                .response
-                .clone()
-                .expect("cannot execute feedback phase without response");
-            // Need to be careful that we grab the labels emitted by the request phase and not the labels we started with.
-            let labels = self.combined_output.labels.clone();
-            let verdict = verdict.clone();
-            feedback_phase_tasks.spawn(
-                timeout(self.timeout_duration, async move {
-                    let result = BulwarkProcessor::dispatch_decision_feedback(
-                        plugin_instance,
-                        request,
-                        response,
-                        labels,
-                        verdict,
-                    )
-                    .await;
-                    drop(permit);
-                    result
-                })
-                .instrument(response_phase_child_span.or_current()),
-            );
-        }
-        join_all(feedback_phase_tasks, |_| {}).await;
-
-        // Measure total conflict in the combined decision
-        metrics::histogram!("combined_conflict", Decision::conflict(&decisions));
-
-        // Capturing stdio is always the last thing that happens and feedback should always be the second-to-last.
-        self.capture_stdio().await;
-    }
-
     async fn complete_request_phase(&mut self) -> Result<(), PhaseError> {
-        let decision = self.combined_output.decision;
-        let outcome = decision
-            .outcome(
-                self.thresholds.trust,
-                self.thresholds.suspicious,
-                self.thresholds.restrict,
-            )
-            .unwrap();
-
-        info!(
-            message = "combine decision",
-            accept = format_f64!(decision.accept),
-            restrict = format_f64!(decision.restrict),
-            unknown = format_f64!(decision.unknown),
-            score = format_f64!(decision.pignistic().restrict),
-            outcome = outcome.to_string(),
-            observe_only = self.thresholds.observe_only,
-            // array values aren't handled well unfortunately, coercing to comma-separated values seems to be the best option
-            tags = self
-                .combined_output
-                .tags
-                .iter()
-                .map(|s| s.as_str())
-                .collect::<Vec<&str>>()
-                .to_vec()
-                .join(","),
-        );
-        metrics::increment_counter!(
-            "plugin_request_phase_decision",
-            "outcome" => outcome.to_string(),
-            "observe_only" => self.thresholds.observe_only.to_string(),
-        );
-
-        let mut restricted = false;
-        let end_of_stream = self.request.body().is_empty();
-        match outcome {
-            bulwark_sdk::Outcome::Trusted
-            | bulwark_sdk::Outcome::Accepted
-            // suspected requests are monitored but not rejected
-            | bulwark_sdk::Outcome::Suspected => {
-                Self::send_allow_request_message(self.sender.clone(), end_of_stream).await?;
-            },
-            bulwark_sdk::Outcome::Restricted => {
-                restricted = true;
-                if !self.thresholds.observe_only {
-                    info!(message = "process response", status = 403);
-                    let response = Self::send_block_request_message(self.sender.clone()).await?;
+        let outcome = self
+            .pipeline
+            .combined_outcome("plugin_request_phase_decision");
+        let action = self.pipeline.action(outcome);
+        let observe_only = self.pipeline.thresholds.observe_only;
+
+        let end_of_stream = self.pipeline.request.body().is_empty();
+        match self
+            .pipeline
+            .action_response(&action.action)
+            .map_err(ProcessingMessageError::from)?
+        {
+            Some(response) => {
+                self.pipeline.verdict = Some(self.pipeline.verdict(outcome));
+                if !observe_only {
+                    info!(
+                        message = "process response",
+                        status = u16::from(response.status())
+                    );
+                    Self::send_block_request_message(self.sender.clone(), &response).await?;
 
                     // Normally we initiate feedback after the response phase, but if we're blocking the request
                     // in the request phase, we're also skipping the response phase and we need to do it here
                     // instead.
-                    let verdict = Verdict {
-                        decision,
-                        outcome,
-                        tags: self.combined_output.tags.iter().cloned().collect(),
-                    };
-                    self.response = Some(Arc::new(response));
-                    self.verdict = Some(verdict);
-                    self.execute_decision_feedback().await;
-
-                    // Short-circuit if restricted, we can skip the response phase
+                    self.pipeline.response = Some(Arc::new(response));
+                    self.pipeline.execute_decision_feedback().await;
                     return Ok(());
-                } else {
-                    // In observe-only mode, we still perform decision feedback, but there won't be a response.
-                    let verdict = Verdict {
-                        decision,
-                        outcome,
-                        tags: self.combined_output.tags.iter().cloned().collect(),
-                    };
-                    self.verdict = Some(verdict);
-
-                    // We need to set a response or decision feedback will panic.
-                    // This response is what would have been sent if we had blocked, rather than what will actually
-                    // be sent, since we're about to call send_allow_request_message and that instructs envoy that
-                    // the processor no longer needs to continue processing the request or response.
-                    let code: i32 = 403;
-                    let body = "Access Denied\n";
-                    // The generate function should be infallible here.
-                    let response = Self::generate_block_response(code, body).expect("could not generate block response");
-                    self.response = Some(Arc::new(response));
-
-                    self.execute_decision_feedback().await;
                 }
 
-                Self::send_allow_request_message(self.sender.clone(), end_of_stream).await?;
-            },
-        }
-
-        // There's only a response phase if we haven't blocked.
-        // Observe-only mode should also behave the same way as normal mode here.
-        if !restricted {
-            let response = self.prepare_response().await?;
-            self.response = Some(Arc::new(response));
-            self.execute_response_phase().await;
-            self.complete_response_phase().await?;
+                // In observe-only mode, we still perform decision feedback, but there won't be a response.
+                // This response is what would have been sent if we had blocked, rather than what will actually
+                // be sent, since we're about to call send_allow_request_message and that instructs envoy that
+                // the processor no longer needs to continue processing the request or response.
+                self.pipeline.response = Some(Arc::new(response));
+                self.pipeline.execute_decision_feedback().await;
+
+                let headers = self
+                    .pipeline
+                    .forwarded_headers(outcome, &action)
+                    .map_err(ProcessingMessageError::from)?;
+                Self::send_allow_request_message(self.sender.clone(), end_of_stream, headers)
+                    .await?;
+
+                // Observe-only mode skips the response phase just as it would be skipped for a blocked request.
+                Ok(())
+            }
+            None => {
+                tarpit(&action.action, observe_only).await;
+                let headers = self
+                    .pipeline
+                    .forwarded_headers(outcome, &action)
+                    .map_err(ProcessingMessageError::from)?;
+                Self::send_allow_request_message(self.sender.clone(), end_of_stream, headers)
+                    .await?;
+
+                let response = self.prepare_response().await?;
+                self.pipeline.response = Some(Arc::new(response));
+                self.pipeline.execute_response_phase().await;
+                self.complete_response_phase().await
+            }
         }
-        Ok(())
     }
 
     async fn complete_response_phase(&mut self) -> Result<(), PhaseError> {
-        let decision = self.combined_output.decision;
-        let outcome = decision
-            .outcome(
-                self.thresholds.trust,
-                self.thresholds.suspicious,
-                self.thresholds.restrict,
-            )
-            .unwrap();
-
-        info!(
-            message = "combine decision",
-            accept = format_f64!(decision.accept),
-            restrict = format_f64!(decision.restrict),
-            unknown = format_f64!(decision.unknown),
-            score = format_f64!(decision.pignistic().restrict),
-            outcome = outcome.to_string(),
-            observe_only = self.thresholds.observe_only,
-            // array values aren't handled well unfortunately, coercing to comma-separated values seems to be the best option
-            tags = self
-                .combined_output
-                .tags
-                .iter()
-                .map(|s| s.as_str())
-                .collect::<Vec<&str>>()
-                .to_vec()
-                .join(","),
-        );
-        metrics::increment_counter!(
-            "plugin_response_phase_decision",
-            "outcome" => outcome.to_string(),
-            "observe_only" => self.thresholds.observe_only.to_string(),
-        );
+        let outcome = self
+            .pipeline
+            .combined_outcome("plugin_response_phase_decision");
+        // The request has already been sent to the interior service, so tarpits and added headers no longer apply.
+        let action = self.pipeline.action(outcome);
 
         let response = self
+            .pipeline
             .response
             .clone()
             .expect("cannot complete response phase without response");
         let end_of_stream = response.body().is_empty();
-        match outcome {
-            bulwark_sdk::Outcome::Trusted
-            | bulwark_sdk::Outcome::Accepted
-            // suspected requests are monitored but not rejected
-            | bulwark_sdk::Outcome::Suspected => {
-                info!(message = "process response", status = u16::from(response.status()));
+        match self
+            .pipeline
+            .action_response(&action.action)
+            .map_err(ProcessingMessageError::from)?
+        {
+            Some(action_response) if !self.pipeline.thresholds.observe_only => {
+                info!(
+                    message = "process response",
+                    status = u16::from(action_response.status())
+                );
+                Self::send_block_response_message(self.sender.clone(), &action_response).await?;
+            }
+            _ => {
+                info!(
+                    message = "process response",
+                    status = u16::from(response.status())
+                );
+                // In observe-only mode, don't receive a body when we would have otherwise blocked
                 Self::send_allow_response_message(self.sender.clone(), end_of_stream).await?;
-            },
-            bulwark_sdk::Outcome::Restricted => {
-                if !self.thresholds.observe_only {
-                    info!(message = "process response", status = 403);
-                    Self::send_block_response_message(self.sender.clone()).await?;
-                } else {
-                    info!(message = "process response", status = u16::from(response.status()));
-                    // Don't receive a body when we would have otherwise blocked if we weren't in monitor-only mode
-                    Self::send_allow_response_message(self.sender.clone(), end_of_stream).await?;
-                }
             }
         }
 
-        let verdict = bulwark_sdk::Verdict {
-            decision,
-            outcome,
-            tags: self.combined_output.tags.iter().cloned().collect(),
-        };
-        self.verdict = Some(verdict);
-        self.execute_decision_feedback().await;
+        self.pipeline.verdict = Some(self.pipeline.verdict(outcome));
+        self.pipeline.execute_decision_feedback().await;
         Ok(())
     }
 
-    #[instrument(name = "plugin output", skip(self))]
-    async fn capture_stdio(&self) {
-        // TODO: refactor to process one plugin at a time and try to avoid having handle_decision_feedback join_all
-        for plugin_instance in self.plugin_instances.iter() {
-            let plugin_instance = plugin_instance.lock().await;
-            let stdout = plugin_instance.stdio().stdout_buffer();
-            let stderr = plugin_instance.stdio().stderr_buffer();
-            if !stdout.is_empty() {
-                let stdout = String::from_utf8_lossy(&stdout);
-                for line in stdout.lines() {
-                    info!(
-                        message = "stdout",
-                        plugin = plugin_instance.plugin_reference(),
-                        content = line
-                    );
-                }
-            }
-            if !stderr.is_empty() {
-                let stderr = String::from_utf8_lossy(&stdout);
-                for line in stderr.lines() {
-                    error!(
-                        message = "stderr",
-                        plugin = plugin_instance.plugin_reference(),
-                        content = line
-                    );
-                }
-            }
-        }
-    }
-
-    /// Generates a response indicating the request has been blocked.
-    fn generate_block_response(
-        code: i32,
-        body: &str,
-    ) -> Result<bulwark_sdk::Response, http::Error> {
-        http::response::Builder::new()
-            .status(code as u16)
-            .body(bytes::Bytes::from(body.to_string()))
-    }
-
     async fn send_allow_request_message(
         sender: Arc<Mutex<UnboundedSender<Result<ProcessingResponse, tonic::Status>>>>,
         end_of_stream: bool,
+        headers: ForwardedHeaders,
     ) -> Result<(), ProcessingMessageError> {
         let mut sender = sender.lock().await;
 
         trace!("send_allow_request_message (ProcessingResponse)");
+        let common_response = CommonResponse {
+            header_mutation: Some(HeaderMutation {
+                set_headers: headers
+                    .set
+                    .into_iter()
+                    .map(|(key, value)| header_value_option(&key, value))
+                    .collect(),
+                remove_headers: headers.remove,
+            }),
+            ..Default::default()
+        };
         let processing_reply = ProcessingResponse {
             // If the request did not have a body, we're responding to a
             // RequestHeaders message, otherwise we're responding to a
@@ -1174,13 +867,13 @@ impl ProcessorContext {
             response: if end_of_stream {
                 Some(processing_response::Response::RequestHeaders(
                     HeadersResponse {
-                        response: Some(CommonResponse::default()),
+                        response: Some(common_response),
                     },
                 ))
             } else {
-                Some(processing_response::Response::RequestBody(
-                    BodyResponse::default(),
-                ))
+                Some(processing_response::Response::RequestBody(BodyResponse {
+                    response: Some(common_response),
+                }))
             },
             ..Default::default()
         };
@@ -1189,30 +882,18 @@ impl ProcessorContext {
 
     async fn send_block_request_message(
         sender: Arc<Mutex<UnboundedSender<Result<ProcessingResponse, tonic::Status>>>>,
-    ) -> Result<bulwark_sdk::Response, ProcessingMessageError> {
+        response: &bulwark_sdk::Response,
+    ) -> Result<(), ProcessingMessageError> {
         let mut sender = sender.lock().await;
 
         trace!("send_block_request_message (ProcessingResponse)");
-        // TODO: better default response + customizability
-        let code: i32 = 403;
-        let body = "Access Denied\n";
-        let response = Self::generate_block_response(code, body)?;
-
         let processing_reply = ProcessingResponse {
             response: Some(processing_response::Response::ImmediateResponse(
-                ImmediateResponse {
-                    status: Some(HttpStatus { code }),
-                    // TODO: add decision debug
-                    details: "blocked by bulwark".to_string(),
-                    body: body.to_string().into(),
-                    headers: None,
-                    grpc_status: None,
-                },
+                Self::immediate_response(response),
             )),
             ..Default::default()
         };
-        sender.send(Ok(processing_reply)).await?;
-        Ok(response)
+        Ok(sender.send(Ok(processing_reply)).await?)
     }
 
     async fn send_allow_response_message(
@@ -1242,32 +923,45 @@ impl ProcessorContext {
 
     async fn send_block_response_message(
         sender: Arc<Mutex<UnboundedSender<Result<ProcessingResponse, tonic::Status>>>>,
-    ) -> Result<bulwark_sdk::Response, ProcessingMessageError> {
+        response: &bulwark_sdk::Response,
+    ) -> Result<(), ProcessingMessageError> {
         let mut sender = sender.lock().await;
 
         trace!("send_block_response_message (ProcessingResponse)");
         // Send back a response indicating the request has been blocked.
-        let code: i32 = 403;
-        // TODO: better default response + customizability
-        let body = "Access Denied\n";
-        let response: bulwark_sdk::Response = http::response::Builder::new()
-            .status(code as u16)
-            .body(bytes::Bytes::from(body))?;
         let processing_reply = ProcessingResponse {
             response: Some(processing_response::Response::ImmediateResponse(
-                ImmediateResponse {
-                    status: Some(HttpStatus { code }),
-                    // TODO: add decision debug
-                    details: "blocked by bulwark".to_string(),
-                    body: body.to_string().into(),
-                    headers: None,
-                    grpc_status: None,
-                },
+                Self::immediate_response(response),
             )),
             ..Default::default()
         };
-        sender.send(Ok(processing_reply)).await?;
-        Ok(response)
+        Ok(sender.send(Ok(processing_reply)).await?)
+    }
+
+    /// Converts a block response into an [`ImmediateResponse`] that Envoy sends in place of the interior service's.
+    fn immediate_response(response: &bulwark_sdk::Response) -> ImmediateResponse {
+        ImmediateResponse {
+            status: Some(HttpStatus {
+                code: i32::from(response.status().as_u16()),
+            }),
+            // TODO: add decision debug
+            details: "blocked by bulwark".to_string(),
+            body: response.body().to_vec(),
+            headers: Some(HeaderMutation {
+                set_headers: response
+                    .headers()
+                    .iter()
+                    .map(|(name, value)| {
+                        header_value_option(
+                            name.as_str(),
+                            String::from_utf8_lossy(value.as_bytes()).to_string(),
+                        )
+                    })
+                    .collect(),
+                remove_headers: vec![],
+            }),
+            grpc_status: None,
+        }
     }
 
     async fn get_request_header_message(
@@ -1384,115 +1078,4 @@ impl ProcessorContext {
             None => None,
         }
     }
-
-    fn parse_forwarded_ip(forwarded: &[u8], proxy_hops: usize) -> Option<IpAddr> {
-        let forwarded = String::from_utf8_lossy(forwarded);
-        let value = ForwardedHeaderValue::from_forwarded(forwarded.as_ref()).ok();
-        value.and_then(|fhv| {
-            if proxy_hops > fhv.len() {
-                None
-            } else {
-                let item = fhv.iter().nth(fhv.len() - proxy_hops);
-                item.and_then(|fs| fs.forwarded_for_ip())
-            }
-        })
-    }
-
-    fn parse_x_forwarded_for_ip(forwarded: &[u8], proxy_hops: usize) -> Option<IpAddr> {
-        let forwarded = String::from_utf8_lossy(forwarded);
-        let value = ForwardedHeaderValue::from_x_forwarded_for(forwarded.as_ref()).ok();
-        value.and_then(|fhv| {
-            if proxy_hops > fhv.len() {
-                None
-            } else {
-                let item = fhv.iter().nth(fhv.len() - proxy_hops);
-                item.and_then(|fs| fs.forwarded_for_ip())
-            }
-        })
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_parse_forwarded() -> Result<(), Box<dyn std::error::Error>> {
-        let test_cases = [
-            ("", 0, None),
-            ("", 1, None),
-            ("bogus", 0, None),
-            ("213!04]d$7n2;31d4%,hbq#", 0, None),
-            (
-                "for=192.0.2.43",
-                1,
-                Some("192.0.2.43".parse::<IpAddr>().unwrap()),
-            ),
-            (
-                "for=192.0.2.43,for=198.51.100.17;by=203.0.113.60;proto=http;host=example.com",
-                2,
-                Some("192.0.2.43".parse::<IpAddr>().unwrap()),
-            ),
-            (
-                "   for=192.0.2.43, for=198.51.100.17;  by=203.0.113.60; proto=http;host=example.com",
-                2,
-                Some("192.0.2.43".parse::<IpAddr>().unwrap()),
-            ),
-            (
-                "for=192.0.2.43,for=198.51.100.17;by=203.0.113.60;proto=http;host=example.com",
-                1,
-                Some("198.51.100.17".parse::<IpAddr>().unwrap()),
-            ),
-            (
-                "for=192.0.2.43,for=198.51.100.17;by=203.0.113.60;proto=http;host=example.com",
-                3,
-                None,
-            ),
-        ];
-
-        for (forwarded, hops, expected) in test_cases {
-            let parsed = ProcessorContext::parse_forwarded_ip(forwarded.as_bytes(), hops);
-            assert_eq!(parsed, expected);
-        }
-
-        Ok(())
-    }
-
-    #[test]
-    fn test_parse_x_forwarded_for() -> Result<(), Box<dyn std::error::Error>> {
-        let test_cases = [
-            ("", 0, None),
-            ("", 1, None),
-            ("bogus", 0, None),
-            ("213!04]d$7n2;31d4%,hbq#", 0, None),
-            (
-                "192.0.2.43",
-                1,
-                Some("192.0.2.43".parse::<IpAddr>().unwrap()),
-            ),
-            (
-                "192.0.2.43,198.51.100.17",
-                2,
-                Some("192.0.2.43".parse::<IpAddr>().unwrap()),
-            ),
-            (
-                "   192.0.2.43, 198.51.100.17 ",
-                2,
-                Some("192.0.2.43".parse::<IpAddr>().unwrap()),
-            ),
-            (
-                "192.0.2.43,198.51.100.17",
-                1,
-                Some("198.51.100.17".parse::<IpAddr>().unwrap()),
-            ),
-            ("192.0.2.43,198.51.100.17", 3, None),
-        ];
-
-        for (forwarded, hops, expected) in test_cases {
-            let parsed = ProcessorContext::parse_x_forwarded_for_ip(forwarded.as_bytes(), hops);
-            assert_eq!(parsed, expected);
-        }
-
-        Ok(())
-    }
 }
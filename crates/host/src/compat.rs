@@ -0,0 +1,252 @@
+//! Adapters that let plugins built against deprecated versions of the `bulwark:plugin` world run on the current host.
+//!
+//! A deprecated version's interfaces are implemented by converting their arguments to the current version's types,
+//! delegating to the current version's implementation, and converting the results back. Its handlers' results are
+//! converted to the current version's types in the same way, so that the rest of the host only deals with one version.
+
+use crate::bindings::bulwark::plugin as current;
+use crate::bindings::v0_5::bulwark::plugin as v0_5;
+use crate::PluginCtx;
+use bulwark_sdk::{Decision, Outcome, Verdict};
+
+impl v0_5::types::Host for PluginCtx {}
+
+#[async_trait::async_trait]
+impl v0_5::config::Host for PluginCtx {
+    async fn config_keys(&mut self) -> Vec<String> {
+        current::config::Host::config_keys(self).await
+    }
+
+    async fn config_var(&mut self, key: String) -> Option<v0_5::config::Value> {
+        current::config::Host::config_var(self, key)
+            .await
+            .map(Into::into)
+    }
+
+    async fn proxy_hops(&mut self) -> u8 {
+        current::config::Host::proxy_hops(self).await
+    }
+}
+
+#[async_trait::async_trait]
+impl v0_5::redis::Host for PluginCtx {
+    async fn get(&mut self, key: String) -> Result<Option<Vec<u8>>, v0_5::redis::Error> {
+        Ok(current::redis::Host::get(self, key).await?)
+    }
+
+    async fn set(&mut self, key: String, value: Vec<u8>) -> Result<(), v0_5::redis::Error> {
+        Ok(current::redis::Host::set(self, key, value).await?)
+    }
+
+    async fn del(&mut self, keys: Vec<String>) -> Result<u32, v0_5::redis::Error> {
+        Ok(current::redis::Host::del(self, keys).await?)
+    }
+
+    async fn incr(&mut self, key: String) -> Result<i64, v0_5::redis::Error> {
+        Ok(current::redis::Host::incr(self, key).await?)
+    }
+
+    async fn incr_by(&mut self, key: String, delta: i64) -> Result<i64, v0_5::redis::Error> {
+        Ok(current::redis::Host::incr_by(self, key, delta).await?)
+    }
+
+    async fn sadd(&mut self, key: String, values: Vec<String>) -> Result<u32, v0_5::redis::Error> {
+        Ok(current::redis::Host::sadd(self, key, values).await?)
+    }
+
+    async fn smembers(&mut self, key: String) -> Result<Vec<String>, v0_5::redis::Error> {
+        Ok(current::redis::Host::smembers(self, key).await?)
+    }
+
+    async fn srem(&mut self, key: String, values: Vec<String>) -> Result<u32, v0_5::redis::Error> {
+        Ok(current::redis::Host::srem(self, key, values).await?)
+    }
+
+    async fn expire(&mut self, key: String, ttl: u64) -> Result<(), v0_5::redis::Error> {
+        Ok(current::redis::Host::expire(self, key, ttl).await?)
+    }
+
+    async fn expire_at(&mut self, key: String, unix_time: u64) -> Result<(), v0_5::redis::Error> {
+        Ok(current::redis::Host::expire_at(self, key, unix_time).await?)
+    }
+
+    async fn incr_rate_limit(
+        &mut self,
+        key: String,
+        delta: i64,
+        window: i64,
+    ) -> Result<v0_5::redis::Rate, v0_5::redis::Error> {
+        Ok(
+            current::redis::Host::incr_rate_limit(self, key, delta, window)
+                .await?
+                .into(),
+        )
+    }
+
+    async fn check_rate_limit(
+        &mut self,
+        key: String,
+    ) -> Result<Option<v0_5::redis::Rate>, v0_5::redis::Error> {
+        Ok(current::redis::Host::check_rate_limit(self, key)
+            .await?
+            .map(Into::into))
+    }
+
+    async fn incr_breaker(
+        &mut self,
+        key: String,
+        success_delta: i64,
+        failure_delta: i64,
+        window: i64,
+    ) -> Result<v0_5::redis::Breaker, v0_5::redis::Error> {
+        Ok(
+            current::redis::Host::incr_breaker(self, key, success_delta, failure_delta, window)
+                .await?
+                .into(),
+        )
+    }
+
+    async fn check_breaker(
+        &mut self,
+        key: String,
+    ) -> Result<Option<v0_5::redis::Breaker>, v0_5::redis::Error> {
+        Ok(current::redis::Host::check_breaker(self, key)
+            .await?
+            .map(Into::into))
+    }
+}
+
+impl From<current::config::Value> for v0_5::config::Value {
+    fn from(value: current::config::Value) -> Self {
+        match value {
+            current::config::Value::Null => v0_5::config::Value::Null,
+            current::config::Value::Boolean(b) => v0_5::config::Value::Boolean(b),
+            current::config::Value::Num(n) => v0_5::config::Value::Num(n.into()),
+            current::config::Value::Str(s) => v0_5::config::Value::Str(s),
+            current::config::Value::Arr(values) => {
+                v0_5::config::Value::Arr(values.into_iter().map(Into::into).collect())
+            }
+            current::config::Value::Obj(obj) => v0_5::config::Value::Obj(
+                obj.into_iter()
+                    .map(|(key, value)| (key, value.into()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<current::config::PrimitiveValue> for v0_5::config::PrimitiveValue {
+    fn from(value: current::config::PrimitiveValue) -> Self {
+        match value {
+            current::config::PrimitiveValue::Null => v0_5::config::PrimitiveValue::Null,
+            current::config::PrimitiveValue::Boolean(b) => v0_5::config::PrimitiveValue::Boolean(b),
+            current::config::PrimitiveValue::Num(n) => v0_5::config::PrimitiveValue::Num(n.into()),
+            current::config::PrimitiveValue::Str(s) => v0_5::config::PrimitiveValue::Str(s),
+        }
+    }
+}
+
+impl From<current::config::Number> for v0_5::config::Number {
+    fn from(number: current::config::Number) -> Self {
+        match number {
+            current::config::Number::Posint(n) => v0_5::config::Number::Posint(n),
+            current::config::Number::Negint(n) => v0_5::config::Number::Negint(n),
+            current::config::Number::Float(n) => v0_5::config::Number::Float(n),
+        }
+    }
+}
+
+impl From<current::redis::Error> for v0_5::redis::Error {
+    fn from(error: current::redis::Error) -> Self {
+        match error {
+            current::redis::Error::Permission(message) => v0_5::redis::Error::Permission(message),
+            current::redis::Error::Remote(message) => v0_5::redis::Error::Remote(message),
+            current::redis::Error::InvalidArgument(message) => {
+                v0_5::redis::Error::InvalidArgument(message)
+            }
+            current::redis::Error::TypeError => v0_5::redis::Error::TypeError,
+            current::redis::Error::Other(message) => v0_5::redis::Error::Other(message),
+        }
+    }
+}
+
+impl From<current::redis::Rate> for v0_5::redis::Rate {
+    fn from(rate: current::redis::Rate) -> Self {
+        v0_5::redis::Rate {
+            attempts: rate.attempts,
+            expiration: rate.expiration,
+        }
+    }
+}
+
+impl From<current::redis::Breaker> for v0_5::redis::Breaker {
+    fn from(breaker: current::redis::Breaker) -> Self {
+        v0_5::redis::Breaker {
+            generation: breaker.generation,
+            successes: breaker.successes,
+            failures: breaker.failures,
+            consecutive_successes: breaker.consecutive_successes,
+            consecutive_failures: breaker.consecutive_failures,
+            expiration: breaker.expiration,
+        }
+    }
+}
+
+impl From<Decision> for v0_5::types::Decision {
+    fn from(decision: Decision) -> Self {
+        v0_5::types::Decision {
+            accepted: decision.accept,
+            restricted: decision.restrict,
+            unknown: decision.unknown,
+        }
+    }
+}
+
+impl From<Verdict> for v0_5::types::Verdict {
+    fn from(verdict: Verdict) -> Self {
+        v0_5::types::Verdict {
+            outcome: verdict.outcome.into(),
+            decision: verdict.decision.into(),
+            tags: verdict.tags.clone(),
+        }
+    }
+}
+
+impl From<Outcome> for v0_5::types::Outcome {
+    fn from(outcome: Outcome) -> Self {
+        match outcome {
+            Outcome::Trusted => v0_5::types::Outcome::Trusted,
+            Outcome::Accepted => v0_5::types::Outcome::Accepted,
+            Outcome::Suspected => v0_5::types::Outcome::Suspected,
+            Outcome::Restricted => v0_5::types::Outcome::Restricted,
+        }
+    }
+}
+
+/// Plugins built against `0.5` can't request actions, so their output never includes one.
+impl From<v0_5::types::HandlerOutput> for current::types::HandlerOutput {
+    fn from(output: v0_5::types::HandlerOutput) -> Self {
+        current::types::HandlerOutput {
+            labels: output.labels,
+            decision: current::types::Decision {
+                accepted: output.decision.accepted,
+                restricted: output.decision.restricted,
+                unknown: output.decision.unknown,
+            },
+            tags: output.tags,
+            action: None,
+        }
+    }
+}
+
+impl From<crate::bindings::v0_5::exports::bulwark::plugin::http_handlers::Error>
+    for crate::bindings::exports::bulwark::plugin::http_handlers::Error
+{
+    fn from(error: crate::bindings::v0_5::exports::bulwark::plugin::http_handlers::Error) -> Self {
+        match error {
+            crate::bindings::v0_5::exports::bulwark::plugin::http_handlers::Error::Other(
+                message,
+            ) => crate::bindings::exports::bulwark::plugin::http_handlers::Error::Other(message),
+        }
+    }
+}
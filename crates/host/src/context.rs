@@ -1,3 +1,4 @@
+use crate::limits::PluginLimiter;
 use crate::{ContextInstantiationError, Plugin, PluginStdio};
 
 use chrono::Utc;
@@ -23,6 +24,8 @@ pub struct PluginCtx {
     wasi_table: ResourceTable,
     /// The standard I/O buffers used by WASI and captured for logging.
     pub(crate) stdio: PluginStdio,
+    /// Enforces the plugin's memory limits on the store.
+    pub(crate) limiter: PluginLimiter,
     /// All host configuration.
     host_config: Arc<bulwark_config::Config>,
     /// Plugin-specific configuration. Stored as bytes and deserialized as JSON values by the SDK.
@@ -183,7 +186,7 @@ impl PluginCtx {
         environment: HashMap<String, String>,
         redis_ctx: RedisCtx,
     ) -> Result<PluginCtx, ContextInstantiationError> {
-        let stdio = PluginStdio::default();
+        let stdio = plugin.new_stdio();
         let wasi_ctx = WasiCtxBuilder::new()
             .stdout(stdio.stdout.clone())
             .stderr(stdio.stderr.clone())
@@ -201,6 +204,7 @@ impl PluginCtx {
             wasi_http: WasiHttpCtx::new(),
             wasi_table: ResourceTable::new(),
             stdio,
+            limiter: plugin.new_limiter(),
             host_config: Arc::new(plugin.host_config().clone()),
             guest_config: Arc::new(plugin.guest_config().clone()),
             permissions: plugin.permissions().clone(),
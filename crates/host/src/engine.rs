@@ -0,0 +1,405 @@
+use crate::PluginLoadError;
+use anyhow::Context as _;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use wasmtime::component::Component;
+use wasmtime::{Config, Engine, InstanceAllocationStrategy, PoolingAllocationConfig};
+
+/// The maximum number of core WASM instances, memories, or tables a component may contain when the pooling
+/// allocator is in use.
+///
+/// This matches wasmtime's own default. A plugin built with the Bulwark SDK needs only a handful of each.
+const MAX_POOLED_ITEMS_PER_COMPONENT: u32 = 20;
+
+/// The file extension given to compiled plugins in the plugin cache directory.
+const CACHED_COMPONENT_EXTENSION: &str = "cwasm";
+
+/// The file extension of a precompiled plugin, which sits alongside the plugin's `*.wasm` file.
+///
+/// See [`PluginEngine::precompile`].
+pub const PRECOMPILED_EXTENSION: &str = "pwasm";
+
+/// The first line of a precompiled plugin's header, identifying the file and the header's format.
+const PRECOMPILED_MAGIC: &str = "bulwark-precompiled-v1";
+
+/// The version of wasmtime that plugins are compiled with, recorded in the header of precompiled plugins.
+///
+/// This must match the workspace's `wasmtime` dependency. Wasmtime also checks its version when loading a
+/// precompiled plugin, so a stale value can't cause an incompatible plugin to be loaded.
+const WASMTIME_VERSION: &str = "21.0.1";
+
+/// A wasmtime [`Engine`] shared by a set of plugins, along with the components it has compiled.
+///
+/// Compiling a plugin is the slowest part of loading it, so an engine compiles identical plugin contents only once,
+/// and if a [cache directory](bulwark_config::Runtime::plugin_cache_dir) is configured, stores the compiled
+/// artifacts there so that later loads, including those after a restart, can skip compilation entirely.
+///
+/// Cloning a `PluginEngine` is cheap and shares the underlying engine.
+#[derive(Clone)]
+pub struct PluginEngine {
+    engine: Engine,
+    /// True if the engine's stores meter fuel.
+    consume_fuel: bool,
+    /// True if the engine's stores are subject to epoch deadlines.
+    epoch_interruption: bool,
+    /// The directory that compiled components are cached in.
+    cache_dir: Option<PathBuf>,
+    /// The number of component instances the pooling allocator was sized for, if the engine uses it.
+    pooled_instances: Option<u32>,
+    /// True if precompiled artifacts found alongside local plugins may be loaded.
+    trust_precompiled: bool,
+    /// The components compiled so far, keyed by the SHA-256 digest of their contents.
+    components: Arc<Mutex<HashMap<Vec<u8>, Component>>>,
+}
+
+impl PluginEngine {
+    /// Creates an engine able to run every plugin in the configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `host_config` - The root of the Bulwark configuration structure.
+    pub fn new(host_config: &bulwark_config::Config) -> Result<Self, PluginLoadError> {
+        Self::for_plugins(host_config, &host_config.plugins)
+    }
+
+    /// Creates an engine able to run the given plugins.
+    ///
+    /// Fuel metering and epoch interruption are only enabled if at least one of the plugins needs them, since both
+    /// slow down the code that's compiled for every plugin.
+    ///
+    /// # Arguments
+    ///
+    /// * `host_config` - The root of the Bulwark configuration structure.
+    /// * `guest_configs` - The configuration of each plugin the engine will run.
+    pub fn for_plugins<'a>(
+        host_config: &bulwark_config::Config,
+        guest_configs: impl IntoIterator<Item = &'a bulwark_config::Plugin>,
+    ) -> Result<Self, PluginLoadError> {
+        let mut plugin_count = 0;
+        let mut consume_fuel = false;
+        let mut epoch_interruption = false;
+        for guest_config in guest_configs {
+            plugin_count += 1;
+            consume_fuel |= guest_config.max_fuel.is_some();
+            epoch_interruption |= guest_config.epoch_deadline.is_some();
+        }
+
+        let mut wasm_config = Config::new();
+        wasm_config.wasm_backtrace_details(wasmtime::WasmBacktraceDetails::Enable);
+        wasm_config.wasm_multi_memory(true);
+        wasm_config.wasm_component_model(true);
+        wasm_config.async_support(true);
+        wasm_config.consume_fuel(consume_fuel);
+        wasm_config.epoch_interruption(epoch_interruption);
+        let pooled_instances = host_config
+            .runtime
+            .pooling_allocator
+            .then(|| Self::pooled_instances(&host_config.runtime, plugin_count));
+        if let Some(instances) = pooled_instances {
+            wasm_config.allocation_strategy(InstanceAllocationStrategy::Pooling(
+                Self::pooling_config(instances),
+            ));
+        }
+
+        let engine = Engine::new(&wasm_config)?;
+        if epoch_interruption {
+            Self::spawn_epoch_ticker(&engine)?;
+        }
+
+        Ok(PluginEngine {
+            engine,
+            consume_fuel,
+            epoch_interruption,
+            cache_dir: host_config.runtime.plugin_cache_dir.clone(),
+            pooled_instances,
+            trust_precompiled: host_config.runtime.trust_precompiled,
+            components: Arc::default(),
+        })
+    }
+
+    /// True if the engine can run every plugin in a configuration, so that a reloaded configuration can keep using it
+    /// along with the components it has already compiled.
+    ///
+    /// An engine that meters fuel or enforces epoch deadlines can still run plugins without limits, but not the other
+    /// way around, and a pooling allocator can't grow past the number of instances it was sized for.
+    ///
+    /// # Arguments
+    ///
+    /// * `host_config` - The root of the Bulwark configuration structure.
+    pub fn supports(&self, host_config: &bulwark_config::Config) -> bool {
+        let plugins = &host_config.plugins;
+        let limits_supported = plugins.iter().all(|plugin| {
+            (self.consume_fuel || plugin.max_fuel.is_none())
+                && (self.epoch_interruption || plugin.epoch_deadline.is_none())
+        });
+        let pooling_supported = match self.pooled_instances {
+            Some(instances) => {
+                host_config.runtime.pooling_allocator
+                    && Self::pooled_instances(&host_config.runtime, plugins.len()) <= instances
+            }
+            None => !host_config.runtime.pooling_allocator,
+        };
+        limits_supported
+            && pooling_supported
+            && self.cache_dir == host_config.runtime.plugin_cache_dir
+            && self.trust_precompiled == host_config.runtime.trust_precompiled
+    }
+
+    /// Returns the underlying wasmtime engine.
+    pub(crate) fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    /// True if the engine's stores meter fuel.
+    pub(crate) fn consume_fuel(&self) -> bool {
+        self.consume_fuel
+    }
+
+    /// True if the engine's stores are subject to epoch deadlines.
+    pub(crate) fn epoch_interruption(&self) -> bool {
+        self.epoch_interruption
+    }
+
+    /// Compiles a component from either binary WASM or WAT, reusing an earlier compilation of the same contents if
+    /// there is one.
+    pub(crate) fn compile(&self, bytes: &[u8]) -> Result<Component, PluginLoadError> {
+        let digest = Sha256::digest(bytes).to_vec();
+        if let Some(component) = self.components.lock().expect("poisoned mutex").get(&digest) {
+            return Ok(component.clone());
+        }
+        self.compile_uncached(bytes, digest)
+    }
+
+    /// Loads a plugin that was precompiled by [`PluginEngine::precompile`], or compiles it if the precompiled
+    /// artifact is missing, or was built for different plugin contents or an incompatible engine.
+    ///
+    /// The artifact is ignored unless [`trust_precompiled`](bulwark_config::Runtime::trust_precompiled) is set.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The plugin's binary WASM or WAT.
+    /// * `precompiled_path` - The path the plugin's precompiled artifact would be found at.
+    pub(crate) fn compile_precompiled(
+        &self,
+        bytes: &[u8],
+        precompiled_path: &Path,
+    ) -> Result<Component, PluginLoadError> {
+        let digest = Sha256::digest(bytes).to_vec();
+        if let Some(component) = self.components.lock().expect("poisoned mutex").get(&digest) {
+            return Ok(component.clone());
+        }
+        if !self.trust_precompiled {
+            return self.compile_uncached(bytes, digest);
+        }
+        if let Ok(artifact) = std::fs::read(precompiled_path) {
+            if let Some(component) = self.deserialize_precompiled(&digest, &artifact) {
+                metrics::increment_counter!("plugin_precompiled", "result" => "loaded");
+                self.components
+                    .lock()
+                    .expect("poisoned mutex")
+                    .insert(digest, component.clone());
+                return Ok(component);
+            }
+            metrics::increment_counter!("plugin_precompiled", "result" => "mismatch");
+        }
+        self.compile_uncached(bytes, digest)
+    }
+
+    /// Compiles a plugin ahead of time, for loading by an engine with the same configuration.
+    ///
+    /// The artifact starts with a header that records the wasmtime version, a fingerprint of the engine's
+    /// configuration, and a digest of the plugin's contents, so that it's only ever loaded in place of the exact
+    /// plugin it was compiled from, by a compatible engine. It should be written alongside the plugin's `*.wasm` file
+    /// with the [`PRECOMPILED_EXTENSION`] extension.
+    pub fn precompile(&self, bytes: &[u8]) -> Result<Vec<u8>, PluginLoadError> {
+        let digest = Sha256::digest(bytes);
+        let mut artifact = self.precompiled_header(&digest).into_bytes();
+        artifact.extend(self.engine.precompile_component(bytes)?);
+        Ok(artifact)
+    }
+
+    /// Returns the header a precompiled plugin with the given digest must start with to be loaded by this engine.
+    fn precompiled_header(&self, digest: &[u8]) -> String {
+        format!(
+            "{}\nwasmtime {}\nengine {}\nsha256 {}\n\n",
+            PRECOMPILED_MAGIC,
+            WASMTIME_VERSION,
+            self.compatibility_key(),
+            hex::encode(digest)
+        )
+    }
+
+    /// Loads a precompiled plugin if its header matches this engine and the plugin's contents.
+    ///
+    /// The header only keeps a stale artifact from being loaded in place of a rebuilt plugin or by an incompatible
+    /// engine. It isn't authenticated, so it says nothing about who wrote the artifact.
+    fn deserialize_precompiled(&self, digest: &[u8], artifact: &[u8]) -> Option<Component> {
+        let serialized = artifact.strip_prefix(self.precompiled_header(digest).as_bytes())?;
+        // SAFETY: Deserializing runs the artifact as native code, and neither the header nor wasmtime's own checks
+        // can tell a forged artifact from a genuine one. Artifacts are only loaded once the operator has opted in
+        // with `trust_precompiled`, which documents that the plugin directory must be as trusted as the Bulwark
+        // binary, since its digest and signature checks only cover the `.wasm` file.
+        unsafe { Component::deserialize(&self.engine, serialized) }.ok()
+    }
+
+    /// Compiles a plugin that this engine hasn't compiled before, using the cache directory if there is one.
+    fn compile_uncached(
+        &self,
+        bytes: &[u8],
+        digest: Vec<u8>,
+    ) -> Result<Component, PluginLoadError> {
+        let component = match &self.cache_dir {
+            Some(cache_dir) => {
+                let cache_path = cache_dir.join(format!(
+                    "{}-{}.{}",
+                    hex::encode(&digest),
+                    self.compatibility_key(),
+                    CACHED_COMPONENT_EXTENSION
+                ));
+                self.compile_cached(bytes, &cache_path)?
+            }
+            None => Component::new(&self.engine, bytes)?,
+        };
+
+        self.components
+            .lock()
+            .expect("poisoned mutex")
+            .insert(digest, component.clone());
+        Ok(component)
+    }
+
+    /// Loads a compiled component from the cache, or compiles it and adds it to the cache if it isn't there.
+    ///
+    /// An artifact that can't be loaded, e.g. because it was written by a different version of wasmtime, is
+    /// replaced. Failing to write to the cache doesn't prevent the plugin from loading.
+    fn compile_cached(
+        &self,
+        bytes: &[u8],
+        cache_path: &Path,
+    ) -> Result<Component, PluginLoadError> {
+        if cache_path.exists() {
+            // SAFETY: Deserializing runs the artifact as native code. The artifact is named by the digest of plugin
+            // contents that have already passed verification, but nothing ties its contents to that name, so this
+            // relies on the cache directory only being writable by Bulwark, as `plugin_cache_dir` documents. Given
+            // that, every artifact in it was serialized by an engine like this one, and wasmtime rejects artifacts
+            // built for an incompatible engine configuration.
+            if let Ok(component) = unsafe { Component::deserialize_file(&self.engine, cache_path) }
+            {
+                metrics::increment_counter!("plugin_cache", "result" => "hit");
+                return Ok(component);
+            }
+        }
+        metrics::increment_counter!("plugin_cache", "result" => "miss");
+
+        let component = Component::new(&self.engine, bytes)?;
+        if write_atomically(cache_path, &component.serialize()?).is_err() {
+            metrics::increment_counter!("plugin_cache", "result" => "write_error");
+        }
+        Ok(component)
+    }
+
+    /// Identifies the engine settings that affect compiled code, so that artifacts compiled with different settings
+    /// are cached separately.
+    ///
+    /// The key needs to be stable across builds of Bulwark, so it's hashed with SHA-256 rather than Rust's default
+    /// hasher, whose algorithm is unspecified.
+    fn compatibility_key(&self) -> String {
+        let mut hasher = Sha256Hasher::default();
+        self.engine
+            .precompile_compatibility_hash()
+            .hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
+    }
+
+    /// Counts the component instances the pooling allocator needs for a set of plugins.
+    ///
+    /// Each in-flight request holds one instance of each plugin, in addition to the instances idling in the plugin's
+    /// pool and one more being instantiated to refill it. The engine is kept across reloads, so the pool is sized
+    /// for a reloaded configuration's idle instances to be created while the current ones are still around.
+    fn pooled_instances(runtime: &bulwark_config::Runtime, plugin_count: usize) -> u32 {
+        u32::try_from(
+            runtime
+                .plugin_pool_size
+                .saturating_mul(2)
+                .saturating_add(runtime.max_concurrent_requests)
+                .saturating_add(1)
+                .saturating_mul(plugin_count.max(1)),
+        )
+        .unwrap_or(u32::MAX)
+    }
+
+    /// Sizes the pooling allocator for a number of component instances.
+    fn pooling_config(instances: u32) -> PoolingAllocationConfig {
+        let items = instances.saturating_mul(MAX_POOLED_ITEMS_PER_COMPONENT);
+
+        let mut pooling_config = PoolingAllocationConfig::default();
+        pooling_config
+            .total_component_instances(instances)
+            .total_stacks(instances)
+            .max_core_instances_per_component(MAX_POOLED_ITEMS_PER_COMPONENT)
+            .max_memories_per_component(MAX_POOLED_ITEMS_PER_COMPONENT)
+            .max_tables_per_component(MAX_POOLED_ITEMS_PER_COMPONENT)
+            .total_core_instances(items)
+            .total_tables(items)
+            // Linear memory reserves the most address space, and components rarely have more than one or two.
+            .total_memories(instances.saturating_mul(2))
+            // Allow memories to grow to the 4 GiB limit of 32-bit WASM, as they could without pooling.
+            .memory_pages(65536);
+        pooling_config
+    }
+
+    /// Starts a thread that increments the engine's epoch every [`bulwark_config::EPOCH_TICK_INTERVAL`] milliseconds,
+    /// for as long as the engine exists.
+    fn spawn_epoch_ticker(engine: &Engine) -> Result<(), PluginLoadError> {
+        let engine = engine.weak();
+        std::thread::Builder::new()
+            .name(String::from("bulwark-epoch"))
+            .spawn(move || {
+                while let Some(engine) = engine.upgrade() {
+                    engine.increment_epoch();
+                    // Don't hold on to the engine while sleeping, so that the thread exits once the plugins are
+                    // dropped.
+                    drop(engine);
+                    std::thread::sleep(Duration::from_millis(bulwark_config::EPOCH_TICK_INTERVAL));
+                }
+            })
+            .context("failed to start epoch ticker")?;
+        Ok(())
+    }
+}
+
+/// Writes a file to the plugin cache directory, creating the directory if needed.
+///
+/// The contents are written to a temporary file and renamed into place, so that a concurrent load never sees a
+/// partially written file.
+pub(crate) fn write_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let temp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+    std::fs::write(&temp_path, contents)?;
+    std::fs::rename(&temp_path, path).inspect_err(|_| {
+        std::fs::remove_file(&temp_path).ok();
+    })
+}
+
+/// Adapts SHA-256 to the [`Hasher`] trait.
+#[derive(Default)]
+struct Sha256Hasher(Sha256);
+
+impl Hasher for Sha256Hasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        let digest = self.0.clone().finalize();
+        let mut prefix = [0u8; 8];
+        prefix.copy_from_slice(&digest[..8]);
+        u64::from_be_bytes(prefix)
+    }
+}
@@ -21,8 +21,22 @@ pub enum PluginLoadError {
     HttpError(#[from] reqwest::Error),
     #[error("expected {0}:{1}, got {0}:{2}")]
     VerificationError(String, String, String),
+    #[error("verification required: '{0}' has no digest or signature")]
+    VerificationRequired(String),
+    #[error("missing trust: '{0}'")]
+    TrustMissing(String),
+    #[error("missing signature: {0}")]
+    SignatureMissing(String),
+    #[error("invalid signature: not signed by any key in trust '{0}'")]
+    InvalidSignature(String),
+    #[error("invalid oci manifest: {0}")]
+    InvalidOciManifest(String),
     #[error(transparent)]
     AnyError(#[from] anyhow::Error),
+    #[error("does not implement bulwark:plugin/http-detection: {0}")]
+    IncompatibleWorld(anyhow::Error),
+    #[error("unsupported world version: bulwark:plugin@{0}")]
+    UnsupportedWorldVersion(String),
 }
 
 /// Returned when an attempt to instantiate a plugin fails.
@@ -36,6 +50,10 @@ pub enum PluginInstantiationError {
     ContextInstantiation(#[from] ContextInstantiationError),
     #[error(transparent)]
     AnyError(#[from] anyhow::Error),
+    #[error("does not implement bulwark:plugin/http-detection: {0}")]
+    IncompatibleWorld(anyhow::Error),
+    #[error("init failed: {0}")]
+    Initialization(String),
 }
 
 /// Returned when an attempt to execute a function within a plugin environment fails.
@@ -49,6 +67,10 @@ pub enum PluginExecutionError {
     StringArray(#[from] wasi_common::StringArrayError),
     #[error("function not implemented '{expected}'")]
     NotImplementedError { expected: String },
+    #[error("exceeded {0} limit")]
+    CpuLimitExceeded(String),
+    #[error("exceeded {0} limit")]
+    MemoryLimitExceeded(String),
     #[error(transparent)]
     AnyError(#[from] anyhow::Error),
 }
@@ -0,0 +1,258 @@
+use crate::oci::fetch_oci;
+use crate::verification::verify_digest;
+use crate::PluginLoadError;
+use anyhow::Context as _;
+use bulwark_config::{PluginAccess, PluginLocation, PluginVerification};
+use bytes::Bytes;
+use secrecy::ExposeSecret;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use url::Url;
+
+/// The subdirectory of the plugin cache directory that remote plugins are downloaded to.
+const REMOTE_CACHE_DIR: &str = "remote";
+
+/// The delay before the first retry of a failed fetch. Each later retry waits twice as long as the one before.
+const INITIAL_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Reads a plugin's contents from wherever it's configured to be loaded from.
+///
+/// Remote and OCI plugins are fetched the same way they are when loading them, so one with a SHA-256 digest is
+/// verified and may come from the plugin cache. Other plugins aren't verified.
+///
+/// # Arguments
+///
+/// * `host_config` - The root of the Bulwark configuration structure.
+/// * `guest_config` - The configuration of the plugin being read.
+pub async fn fetch_plugin(
+    host_config: &bulwark_config::Config,
+    guest_config: &bulwark_config::Plugin,
+) -> Result<Bytes, PluginLoadError> {
+    Ok(match &guest_config.location {
+        PluginLocation::Local(path) => Bytes::from(
+            tokio::fs::read(path)
+                .await
+                .with_context(|| format!("failed to read plugin: {}", path.display()))?,
+        ),
+        PluginLocation::Remote(uri) => fetch_remote(host_config, guest_config, uri).await?,
+        PluginLocation::Oci(reference) => fetch_oci(host_config, guest_config, reference).await?,
+        PluginLocation::Bytes(bytes) => bytes.clone(),
+    })
+}
+
+/// Fetches a remote plugin, retrying failed attempts and falling back to the last fetched copy in the plugin cache
+/// directory if the remote can't be reached.
+///
+/// A plugin with a SHA-256 digest is loaded straight from the cache when it's there, since the cached copy is
+/// identical to what the remote would return. Cached bytes are verified again before they're used. A plugin without
+/// a digest can only fall back to whatever copy was fetched last, which may be older than what the remote now
+/// serves, so a warning is logged when that happens. A signed plugin's fallback copy still has to match its
+/// signature.
+///
+/// # Arguments
+///
+/// * `host_config` - The root of the Bulwark configuration structure.
+/// * `guest_config` - The configuration of the plugin being fetched.
+/// * `uri` - The location of the plugin.
+async fn fetch_remote(
+    host_config: &bulwark_config::Config,
+    guest_config: &bulwark_config::Plugin,
+    uri: &Url,
+) -> Result<Bytes, PluginLoadError> {
+    let digest = match &guest_config.verification {
+        PluginVerification::Sha256(digest) => Some(&digest[..]),
+        _ => None,
+    };
+    let cache_path = cache_path(host_config, uri, digest);
+    if let (Some(_), Some(cache_path)) = (digest, &cache_path) {
+        if let Some(bytes) = read_cache(guest_config, cache_path).await {
+            metrics::increment_counter!("plugin_fetch", "result" => "cache");
+            return Ok(bytes);
+        }
+    }
+
+    let err = match fetch_with_retries(host_config, guest_config, uri, None).await {
+        Ok(bytes) => {
+            verify_digest(guest_config, &bytes)?;
+            metrics::increment_counter!("plugin_fetch", "result" => "ok");
+            if let Some(cache_path) = &cache_path {
+                // The cache is only an optimization, so the plugin still loads if it can't be written.
+                write_cache(cache_path, &bytes).await;
+            }
+            return Ok(bytes);
+        }
+        Err(err) => err,
+    };
+
+    if let Some(cache_path) = &cache_path {
+        if let Some(bytes) = read_cache(guest_config, cache_path).await {
+            if digest.is_none() {
+                tracing::warn!(
+                    message = "load unpinned plugin from cache",
+                    plugin = guest_config.reference,
+                    uri = uri.as_str(),
+                    error_message = %err,
+                );
+            }
+            metrics::increment_counter!("plugin_fetch", "result" => "fallback");
+            return Ok(bytes);
+        }
+    }
+    metrics::increment_counter!("plugin_fetch", "result" => "error");
+    Err(err)
+}
+
+/// Fetches the detached signature published alongside a remote plugin, at the plugin's URI with `.sig` appended.
+///
+/// Like the plugin itself, the signature is cached so that the plugin can still be loaded if the remote can't be
+/// reached. It doesn't need to be verified, since a stale or tampered signature simply fails to match.
+pub(crate) async fn fetch_signature(
+    host_config: &bulwark_config::Config,
+    guest_config: &bulwark_config::Plugin,
+    uri: &Url,
+) -> Result<Bytes, PluginLoadError> {
+    let mut signature_uri = uri.clone();
+    signature_uri.set_path(&format!("{}.{}", uri.path(), crate::SIGNATURE_EXTENSION));
+    let cache_path = cache_path(host_config, &signature_uri, None);
+
+    match fetch_with_retries(host_config, guest_config, &signature_uri, None).await {
+        Ok(bytes) => {
+            if let Some(cache_path) = &cache_path {
+                write_cache(cache_path, &bytes).await;
+            }
+            Ok(bytes)
+        }
+        Err(err) => match cache_path {
+            Some(cache_path) => tokio::fs::read(cache_path)
+                .await
+                .map(Bytes::from)
+                .map_err(|_| err),
+            None => Err(err),
+        },
+    }
+}
+
+/// Fetches a remote file, retrying timeouts, connection failures, and server errors with exponential backoff.
+///
+/// The plugin's `Authorization` header is sent with every request. If `accept` is set, it's sent as the `Accept`
+/// header.
+pub(crate) async fn fetch_with_retries(
+    host_config: &bulwark_config::Config,
+    guest_config: &bulwark_config::Plugin,
+    uri: &Url,
+    accept: Option<&str>,
+) -> Result<Bytes, PluginLoadError> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_millis(
+            host_config.runtime.plugin_fetch_timeout,
+        ))
+        .build()?;
+    let authorization = authorization(host_config, guest_config).await?;
+
+    let mut delay = INITIAL_RETRY_DELAY;
+    let mut retries = host_config.runtime.plugin_fetch_retries;
+    loop {
+        let mut request = client.get(uri.clone());
+        if let Some(accept) = accept {
+            request = request.header(reqwest::header::ACCEPT, accept);
+        }
+        if let Some(authorization) = &authorization {
+            request = request.header(
+                reqwest::header::AUTHORIZATION,
+                authorization.expose_secret(),
+            );
+        }
+        let result = match request.send().await {
+            Ok(response) => match response.error_for_status() {
+                Ok(response) => response.bytes().await,
+                Err(err) => Err(err),
+            },
+            Err(err) => Err(err),
+        };
+        match result {
+            Ok(bytes) => return Ok(bytes),
+            Err(err) if retries > 0 && is_retryable(&err) => {
+                metrics::increment_counter!("plugin_fetch", "result" => "retry");
+                tokio::time::sleep(delay).await;
+                delay = delay.saturating_mul(2);
+                retries -= 1;
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+/// True if a failed fetch might succeed if it's tried again.
+fn is_retryable(err: &reqwest::Error) -> bool {
+    match err.status() {
+        Some(status) => status.is_server_error(),
+        None => err.is_timeout() || err.is_connect() || err.is_request() || err.is_body(),
+    }
+}
+
+/// Reads the `Authorization` header value for a plugin from its secret, if it has one.
+async fn authorization(
+    host_config: &bulwark_config::Config,
+    guest_config: &bulwark_config::Plugin,
+) -> Result<Option<secrecy::Secret<String>>, PluginLoadError> {
+    let PluginAccess::Header(authorization_secret) = &guest_config.access else {
+        return Ok(None);
+    };
+    let secret = host_config
+        .secret(authorization_secret)
+        .ok_or_else(|| PluginLoadError::SecretMissing(authorization_secret.clone()))?;
+    // In this case, secrecy::Secret might be overkill, because we immediately discard the value,
+    // but it's probably a good habit to be using it anytime we touch a secret.
+    let authorization_value = match &secret.location {
+        bulwark_config::SecretLocation::EnvVar(env_var) => std::env::var_os(env_var)
+            .map(|value| secrecy::Secret::from(value.to_string_lossy().to_string()))
+            .ok_or(PluginLoadError::SecretMissing(secret.reference.clone()))?,
+        bulwark_config::SecretLocation::File(path) => secrecy::Secret::from(
+            tokio::fs::read(path)
+                .await
+                .map(|value| String::from_utf8_lossy(value.as_slice()).to_string())
+                .map_err(|err| PluginLoadError::SecretUnreadable(secret.reference.clone(), err))?,
+        ),
+    };
+    Ok(Some(authorization_value))
+}
+
+/// Returns the path a remote file is cached at, or `None` if there's no plugin cache directory.
+///
+/// The path is keyed by the file's URL and, if it has one, its expected digest, so that changing either one
+/// never loads a copy fetched for the other.
+pub(crate) fn cache_path(
+    host_config: &bulwark_config::Config,
+    uri: &Url,
+    digest: Option<&[u8]>,
+) -> Option<PathBuf> {
+    let cache_dir = host_config.runtime.plugin_cache_dir.as_ref()?;
+    let digest = match digest {
+        Some(digest) => hex::encode(digest),
+        None => String::from("unverified"),
+    };
+    Some(cache_dir.join(REMOTE_CACHE_DIR).join(format!(
+        "{}-{}.wasm",
+        hex::encode(Sha256::digest(uri.as_str().as_bytes())),
+        digest
+    )))
+}
+
+/// Reads a cached copy of a remote plugin, as long as it still passes verification.
+async fn read_cache(guest_config: &bulwark_config::Plugin, cache_path: &Path) -> Option<Bytes> {
+    let bytes = tokio::fs::read(cache_path).await.ok()?;
+    verify_digest(guest_config, &bytes).ok()?;
+    Some(Bytes::from(bytes))
+}
+
+/// Writes a fetched file to the plugin cache directory without blocking the runtime.
+///
+/// The cache is only an optimization, so failing to write it is ignored.
+pub(crate) async fn write_cache(cache_path: &Path, bytes: &Bytes) {
+    let cache_path = cache_path.to_path_buf();
+    let bytes = bytes.clone();
+    tokio::task::spawn_blocking(move || crate::engine::write_atomically(&cache_path, &bytes))
+        .await
+        .ok();
+}
@@ -1,5 +1,5 @@
 use crate::HandlerOutput;
-use bulwark_sdk::{Decision, Outcome, Verdict};
+use bulwark_sdk::{Action, Decision, Outcome, Verdict};
 use std::collections::{HashMap, HashSet};
 
 impl TryFrom<serde_json::Value> for crate::bindings::bulwark::plugin::config::Value {
@@ -145,6 +145,28 @@ impl From<crate::bindings::bulwark::plugin::types::HandlerOutput> for HandlerOut
             decision: output.decision.into(),
             tags: HashSet::from_iter(output.tags),
             labels: HashMap::from_iter(output.labels),
+            action: output.action.map(Action::from),
+        }
+    }
+}
+
+impl From<crate::bindings::bulwark::plugin::types::Action> for Action {
+    fn from(action: crate::bindings::bulwark::plugin::types::Action) -> Self {
+        match action {
+            crate::bindings::bulwark::plugin::types::Action::Allow => Action::Allow,
+            crate::bindings::bulwark::plugin::types::Action::Block(block) => Action::Block {
+                status: block.status,
+                headers: block.headers,
+            },
+            crate::bindings::bulwark::plugin::types::Action::Redirect(location) => {
+                Action::Redirect { location }
+            }
+            crate::bindings::bulwark::plugin::types::Action::Tarpit(delay) => {
+                Action::Tarpit { delay }
+            }
+            crate::bindings::bulwark::plugin::types::Action::AddHeader((name, value)) => {
+                Action::AddHeader { name, value }
+            }
         }
     }
 }
@@ -0,0 +1,197 @@
+use crate::world::package_version;
+use crate::{Plugin, PluginCtx, PluginLoadError};
+use sha2::{Digest, Sha256};
+use wasmtime::component::types::ComponentItem;
+use wasmtime::component::{Component, Linker, LinkerInstance, ResourceType};
+use wasmtime::Engine;
+
+/// The custom section the SDK records a plugin's implemented handlers in, as a comma-separated list of handler
+/// names.
+pub const HANDLERS_SECTION: &str = "bulwark-handlers";
+
+/// A description of a plugin component's interface with the host, for diagnosing plugins that won't load.
+pub struct PluginInspection {
+    /// The size of the plugin in bytes.
+    pub size: usize,
+    /// The SHA-256 digest of the plugin's contents.
+    pub digest: [u8; 32],
+    /// The interfaces and functions the plugin imports.
+    pub imports: Vec<ComponentInterface>,
+    /// The interfaces and functions the plugin exports.
+    pub exports: Vec<ComponentInterface>,
+    /// The version of the `bulwark:plugin` world the plugin was built against, if it uses the world at all.
+    pub world_version: Option<String>,
+    /// The handlers the plugin implements itself, rather than leaving to the SDK's no-op defaults.
+    ///
+    /// This is `None` if the plugin doesn't record its handlers, which is the case for plugins built with an older
+    /// SDK or without the `bulwark_plugin` macro.
+    pub handlers: Option<Vec<String>>,
+    /// The plugin's imports that the host doesn't provide.
+    pub unsatisfied_imports: Vec<UnsatisfiedImport>,
+}
+
+/// An interface, or a lone function, that a component imports or exports.
+pub struct ComponentInterface {
+    /// The fully-qualified name of the interface, e.g. `bulwark:plugin/config@0.6.0`.
+    pub name: String,
+    /// The names of the interface's functions. Empty for a lone function.
+    pub functions: Vec<String>,
+}
+
+/// An import that the host can't provide to a plugin.
+pub struct UnsatisfiedImport {
+    /// The name of the import.
+    pub name: String,
+    /// Why the host's definition, if it has one, doesn't match the import.
+    pub reason: String,
+}
+
+/// Stands in for the resources of imports that are stubbed out while checking other imports.
+struct StubResource;
+
+impl PluginInspection {
+    /// Inspects a plugin component without instantiating it.
+    ///
+    /// Unlike loading the plugin, which stops at the first import the host can't provide, every import is checked.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The plugin's binary WASM.
+    pub fn new(bytes: &[u8]) -> Result<Self, PluginLoadError> {
+        let mut wasm_config = wasmtime::Config::new();
+        wasm_config.wasm_multi_memory(true);
+        wasm_config.wasm_component_model(true);
+        wasm_config.async_support(true);
+        let engine = Engine::new(&wasm_config)?;
+        let component = Component::new(&engine, bytes)?;
+        let component_type = component.component_type();
+
+        let interfaces = |items: &mut dyn Iterator<Item = (&str, ComponentItem)>| {
+            items
+                .map(|(name, item)| ComponentInterface {
+                    name: name.to_string(),
+                    functions: match item {
+                        ComponentItem::ComponentInstance(instance) => instance
+                            .exports(&engine)
+                            .filter(|(_, item)| matches!(item, ComponentItem::ComponentFunc(_)))
+                            .map(|(name, _)| name.to_string())
+                            .collect(),
+                        _ => vec![],
+                    },
+                })
+                .collect::<Vec<_>>()
+        };
+        let imports = interfaces(&mut component_type.imports(&engine));
+        let exports = interfaces(&mut component_type.exports(&engine));
+
+        // The handlers interface is the best indication of the version, since it's what the host calls into, but a
+        // plugin that fails to export it may still import the world's other interfaces.
+        let world_version = package_version(
+            exports
+                .iter()
+                .chain(imports.iter())
+                .map(|interface| interface.name.as_str()),
+        )
+        .map(String::from);
+
+        Ok(Self {
+            size: bytes.len(),
+            digest: Sha256::digest(bytes).into(),
+            imports,
+            exports,
+            world_version,
+            handlers: Self::handlers(bytes)?,
+            unsatisfied_imports: Self::unsatisfied_imports(&engine, &component)?,
+        })
+    }
+
+    /// Reads the handlers the SDK recorded in the plugin, if it recorded any.
+    fn handlers(bytes: &[u8]) -> Result<Option<Vec<String>>, PluginLoadError> {
+        // Nested modules are parsed too, which is where the SDK's section ends up once a plugin is componentized.
+        for payload in wasmparser::Parser::new(0).parse_all(bytes) {
+            if let wasmparser::Payload::CustomSection(section) =
+                payload.map_err(anyhow::Error::from)?
+            {
+                if section.name() == HANDLERS_SECTION {
+                    return Ok(Some(
+                        String::from_utf8_lossy(section.data())
+                            .split(',')
+                            .filter(|handler| !handler.is_empty())
+                            .map(String::from)
+                            .collect(),
+                    ));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Checks each of a component's imports against the host's definitions.
+    ///
+    /// Linking stops at the first unsatisfied import, so each import is checked separately, with the host's
+    /// definitions layered over stubs for every other import. This way, an import is only reported if the host
+    /// lacks it or defines it differently.
+    fn unsatisfied_imports(
+        engine: &Engine,
+        component: &Component,
+    ) -> Result<Vec<UnsatisfiedImport>, PluginLoadError> {
+        let mut linker: Linker<PluginCtx> = Linker::new(engine);
+        Plugin::add_to_linker(&mut linker)?;
+        if linker.instantiate_pre(component).is_ok() {
+            return Ok(vec![]);
+        }
+
+        let component_type = component.component_type();
+        let mut unsatisfied_imports = vec![];
+        for (name, _) in component_type.imports(engine) {
+            let mut linker: Linker<PluginCtx> = Linker::new(engine);
+            linker.allow_shadowing(true);
+            for (other_name, item) in component_type.imports(engine) {
+                if other_name != name {
+                    Self::stub(engine, &mut linker.root(), other_name, item)?;
+                }
+            }
+            Plugin::add_to_linker(&mut linker)?;
+            if let Err(err) = linker.instantiate_pre(component) {
+                // The outermost context only repeats the name of the import.
+                let reason = err
+                    .chain()
+                    .skip(1)
+                    .map(|cause| cause.to_string())
+                    .collect::<Vec<_>>()
+                    .join(": ");
+                unsatisfied_imports.push(UnsatisfiedImport {
+                    name: name.to_string(),
+                    reason,
+                });
+            }
+        }
+        Ok(unsatisfied_imports)
+    }
+
+    /// Defines a placeholder for an import that fails if it's ever called.
+    fn stub(
+        engine: &Engine,
+        linker: &mut LinkerInstance<PluginCtx>,
+        name: &str,
+        item: ComponentItem,
+    ) -> Result<(), PluginLoadError> {
+        match item {
+            ComponentItem::ComponentFunc(_) => {
+                linker.func_new(name, |_, _, _| anyhow::bail!("stub called"))?
+            }
+            ComponentItem::Resource(_) => {
+                linker.resource(name, ResourceType::host::<StubResource>(), |_, _| Ok(()))?
+            }
+            ComponentItem::ComponentInstance(instance) => {
+                let mut linker = linker.instance(name)?;
+                for (name, item) in instance.exports(engine) {
+                    Self::stub(engine, &mut linker, name, item)?;
+                }
+            }
+            // Types don't need definitions, and plugins don't import modules or components.
+            _ => {}
+        }
+        Ok(())
+    }
+}
@@ -1,8 +1,23 @@
+mod compat;
 mod context;
+mod engine;
 mod errors;
+mod fetch;
 mod from;
+mod inspect;
+mod limits;
+mod oci;
 mod plugin;
+mod pool;
+mod verification;
+mod world;
 
 pub use context::*;
+pub use engine::*;
 pub use errors::*;
+pub use fetch::fetch_plugin;
+pub use inspect::*;
 pub use plugin::*;
+pub use pool::*;
+pub use verification::{verify_plugin, SIGNATURE_EXTENSION};
+pub use world::{WorldVersion, PLUGIN_WORLD_VERSION};
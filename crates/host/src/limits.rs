@@ -0,0 +1,65 @@
+use wasmtime::ResourceLimiter;
+
+/// The error a plugin traps with when it exceeds one of its memory limits.
+///
+/// The field names the limit that was exceeded, e.g. `"memory"` or `"stdout"`.
+#[derive(thiserror::Error, Debug)]
+#[error("exceeded {0} limit")]
+pub(crate) struct LimitExceeded(pub(crate) &'static str);
+
+/// Enforces a plugin's memory, table, and instance limits on each of its stores.
+///
+/// Unlike [`wasmtime::StoreLimits`], growing a memory or table past its limit traps with a [`LimitExceeded`] error
+/// rather than an untyped one, so the host can tell a limit breach apart from other failures.
+pub(crate) struct PluginLimiter {
+    /// The maximum size in bytes of any one linear memory.
+    max_memory: Option<usize>,
+    /// The maximum number of elements in any one table.
+    max_table_elements: Option<u32>,
+    /// The maximum number of core instances in the store.
+    max_instances: usize,
+}
+
+impl PluginLimiter {
+    /// Creates a [`PluginLimiter`] from a plugin's configured limits.
+    pub(crate) fn new(guest_config: &bulwark_config::Plugin) -> Self {
+        Self {
+            max_memory: guest_config.max_memory,
+            max_table_elements: guest_config.max_table_elements,
+            max_instances: guest_config
+                .max_instances
+                .unwrap_or(wasmtime::DEFAULT_INSTANCE_LIMIT),
+        }
+    }
+}
+
+impl ResourceLimiter for PluginLimiter {
+    fn memory_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        maximum: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        if self.max_memory.is_some_and(|limit| desired > limit) {
+            return Err(LimitExceeded("memory").into());
+        }
+        // Growing past a memory's own declared maximum isn't a breach of our limits, so it fails the usual way.
+        Ok(!matches!(maximum, Some(max) if desired > max))
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: u32,
+        desired: u32,
+        maximum: Option<u32>,
+    ) -> anyhow::Result<bool> {
+        if self.max_table_elements.is_some_and(|limit| desired > limit) {
+            return Err(LimitExceeded("table").into());
+        }
+        Ok(!matches!(maximum, Some(max) if desired > max))
+    }
+
+    fn instances(&self) -> usize {
+        self.max_instances
+    }
+}
@@ -0,0 +1,158 @@
+use crate::fetch::{cache_path, fetch_with_retries, write_cache};
+use crate::verification::verify_digest;
+use crate::PluginLoadError;
+use anyhow::Context as _;
+use bulwark_config::OciReference;
+use bytes::Bytes;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use url::{Host, Url};
+
+/// The manifest media type requested from registries.
+const OCI_MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+
+/// The layer media types that a plugin's WASM may be published under.
+const WASM_LAYER_MEDIA_TYPES: &[&str] = &[
+    "application/wasm",
+    "application/vnd.wasm.content.layer.v1+wasm",
+];
+
+/// The parts of an OCI image manifest needed to find a plugin's WASM.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    layers: Vec<Descriptor>,
+}
+
+/// An OCI content descriptor, identifying a blob by its digest.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Descriptor {
+    media_type: String,
+    digest: String,
+}
+
+/// Fetches a plugin from an OCI registry.
+///
+/// The reference's tag is resolved to a manifest, which must match the reference's digest if it's pinned to one.
+/// The resolved manifest digest is logged so that the reference can be pinned to it. The plugin's WASM layer is
+/// then fetched and checked against the digest the manifest gives for it, as well as the plugin's own verification.
+///
+/// Layers are addressed by digest, so they're cached under the plugin cache directory and never refetched. The
+/// manifest is always fetched, since a tag can be moved to a different manifest at any time.
+///
+/// # Arguments
+///
+/// * `host_config` - The root of the Bulwark configuration structure.
+/// * `guest_config` - The configuration of the plugin being fetched.
+/// * `reference` - The location of the plugin in the registry.
+pub(crate) async fn fetch_oci(
+    host_config: &bulwark_config::Config,
+    guest_config: &bulwark_config::Plugin,
+    reference: &OciReference,
+) -> Result<Bytes, PluginLoadError> {
+    let manifest_uri = registry_url(
+        reference,
+        &format!("manifests/{}", reference.manifest_reference()),
+    )?;
+    let manifest = fetch_with_retries(
+        host_config,
+        guest_config,
+        &manifest_uri,
+        Some(OCI_MANIFEST_MEDIA_TYPE),
+    )
+    .await
+    .map_err(count_error)?;
+    let manifest_digest = Sha256::digest(&manifest);
+    if let Some(digest) = &reference.digest {
+        check_digest(digest, &manifest)?;
+    }
+    tracing::info!(
+        message = "resolve oci plugin",
+        plugin = guest_config.reference,
+        reference = tracing::field::display(reference),
+        digest = format!("sha256:{}", hex::encode(manifest_digest)),
+    );
+
+    let manifest: Manifest = serde_json::from_slice(&manifest)
+        .map_err(|err| PluginLoadError::InvalidOciManifest(err.to_string()))?;
+    let layer = manifest
+        .layers
+        .iter()
+        .find(|layer| WASM_LAYER_MEDIA_TYPES.contains(&layer.media_type.as_str()))
+        .ok_or_else(|| PluginLoadError::InvalidOciManifest(String::from("no wasm layer")))?;
+    let layer_digest = match layer.digest.split_once(':') {
+        Some(("sha256", digest)) => hex::decode(digest).ok().filter(|digest| digest.len() == 32),
+        _ => None,
+    }
+    .ok_or_else(|| {
+        PluginLoadError::InvalidOciManifest(format!("unsupported layer digest: '{}'", layer.digest))
+    })?;
+
+    let blob_uri = registry_url(reference, &format!("blobs/{}", layer.digest))?;
+    let cache_path = cache_path(host_config, &blob_uri, Some(&layer_digest));
+    if let Some(cache_path) = &cache_path {
+        if let Ok(bytes) = tokio::fs::read(cache_path).await {
+            if check_digest(&layer_digest, &bytes).is_ok()
+                && verify_digest(guest_config, &bytes).is_ok()
+            {
+                metrics::increment_counter!("plugin_fetch", "result" => "cache");
+                return Ok(Bytes::from(bytes));
+            }
+        }
+    }
+
+    let bytes = fetch_with_retries(host_config, guest_config, &blob_uri, None)
+        .await
+        .map_err(count_error)?;
+    check_digest(&layer_digest, &bytes)?;
+    verify_digest(guest_config, &bytes)?;
+    metrics::increment_counter!("plugin_fetch", "result" => "ok");
+    if let Some(cache_path) = &cache_path {
+        // The cache is only an optimization, so the plugin still loads if it can't be written.
+        write_cache(cache_path, &bytes).await;
+    }
+    Ok(bytes)
+}
+
+/// Returns the URL of a path under a repository in a registry's API.
+///
+/// Registries are reached over HTTPS, except on the loopback interface, where a local registry rarely has a
+/// certificate.
+fn registry_url(reference: &OciReference, path: &str) -> Result<Url, PluginLoadError> {
+    let mut url = Url::parse(&format!(
+        "https://{}/v2/{}/{}",
+        reference.registry, reference.repository, path
+    ))
+    .with_context(|| format!("invalid oci registry: '{}'", reference.registry))?;
+    let loopback = match url.host() {
+        Some(Host::Domain(domain)) => domain == "localhost",
+        Some(Host::Ipv4(ip)) => ip.is_loopback(),
+        Some(Host::Ipv6(ip)) => ip.is_loopback(),
+        None => false,
+    };
+    if loopback {
+        // Switching between special schemes always succeeds.
+        url.set_scheme("http").ok();
+    }
+    Ok(url)
+}
+
+/// Checks content fetched from a registry against the digest it was addressed by.
+fn check_digest(digest: &[u8], bytes: &[u8]) -> Result<(), PluginLoadError> {
+    let actual = Sha256::digest(bytes);
+    if actual.as_slice() != digest {
+        return Err(PluginLoadError::VerificationError(
+            "sha256".to_string(),
+            hex::encode(digest),
+            hex::encode(actual.as_slice()),
+        ));
+    }
+    Ok(())
+}
+
+/// Counts a failed fetch from a registry, passing the error through.
+fn count_error(err: PluginLoadError) -> PluginLoadError {
+    metrics::increment_counter!("plugin_fetch", "result" => "error");
+    err
+}
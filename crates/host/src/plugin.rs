@@ -1,18 +1,19 @@
-use crate::PluginCtx;
+use crate::fetch::fetch_plugin;
+use crate::limits::{LimitExceeded, PluginLimiter};
+use crate::verification::verify_plugin;
+use crate::{PluginCtx, PluginEngine, WorldVersion, PRECOMPILED_EXTENSION};
 use crate::{PluginExecutionError, PluginInstantiationError, PluginLoadError};
 use anyhow::Context as _;
-use bulwark_config::{PluginAccess, PluginVerification};
-use bulwark_sdk::Decision;
+use bulwark_sdk::{Action, Decision};
 use http_body_util::{combinators::BoxBody, BodyExt, Empty, Full};
-use secrecy::ExposeSecret;
 use sha2::{Digest, Sha256};
 use std::collections::{HashMap, HashSet};
 use std::net::IpAddr;
 use std::path::Path;
 use std::sync::Arc;
-use wasmtime::component::{Component, Linker};
-use wasmtime::{AsContextMut, Config, Engine, Store};
-use wasmtime_wasi::{pipe::MemoryOutputPipe, HostOutputStream, StdoutStream};
+use wasmtime::component::{Component, InstancePre, Linker};
+use wasmtime::{AsContextMut, Engine, Store};
+use wasmtime_wasi::{HostOutputStream, StdoutStream, StreamError, Subscribe};
 use wasmtime_wasi_http::body::HyperIncomingBody;
 use wasmtime_wasi_http::WasiHttpView;
 
@@ -24,22 +25,52 @@ mod latest {
 
 #[doc(hidden)]
 pub(crate) mod bindings {
-    wasmtime::component::bindgen!({
-        world: "bulwark:plugin/http-detection",
-        async: true,
-        with: {
-            "wasi:http/types/incoming-response": super::latest::http::types::IncomingResponse,
-            "wasi:http/types/incoming-request": super::latest::http::types::IncomingRequest,
-            "wasi:http/types/incoming-body": super::latest::http::types::IncomingBody,
-            "wasi:http/types/outgoing-response": super::latest::http::types::OutgoingResponse,
-            "wasi:http/types/outgoing-request": super::latest::http::types::OutgoingRequest,
-            "wasi:http/types/outgoing-body": super::latest::http::types::OutgoingBody,
-            "wasi:http/types/fields": super::latest::http::types::Fields,
-            "wasi:http/types/response-outparam": super::latest::http::types::ResponseOutparam,
-            "wasi:http/types/future-incoming-response": super::latest::http::types::FutureIncomingResponse,
-            "wasi:http/types/future-trailers": super::latest::http::types::FutureTrailers,
-        }
-    });
+    // The host's own interfaces are implemented against the current version's bindings.
+    pub(crate) use v0_6::*;
+
+    /// Bindings for `bulwark:plugin@0.6`, the current version of the world.
+    pub(crate) mod v0_6 {
+        wasmtime::component::bindgen!({
+            world: "bulwark:plugin/http-detection",
+            async: true,
+            with: {
+                "wasi:http/types/incoming-response": super::super::latest::http::types::IncomingResponse,
+                "wasi:http/types/incoming-request": super::super::latest::http::types::IncomingRequest,
+                "wasi:http/types/incoming-body": super::super::latest::http::types::IncomingBody,
+                "wasi:http/types/outgoing-response": super::super::latest::http::types::OutgoingResponse,
+                "wasi:http/types/outgoing-request": super::super::latest::http::types::OutgoingRequest,
+                "wasi:http/types/outgoing-body": super::super::latest::http::types::OutgoingBody,
+                "wasi:http/types/fields": super::super::latest::http::types::Fields,
+                "wasi:http/types/response-outparam": super::super::latest::http::types::ResponseOutparam,
+                "wasi:http/types/future-incoming-response": super::super::latest::http::types::FutureIncomingResponse,
+                "wasi:http/types/future-trailers": super::super::latest::http::types::FutureTrailers,
+            }
+        });
+    }
+
+    /// Bindings for `bulwark:plugin@0.5`, which predates plugin-requested actions.
+    ///
+    /// Generated from a copy of the world as it was released, since the host's own WIT files only describe the
+    /// current version.
+    pub(crate) mod v0_5 {
+        wasmtime::component::bindgen!({
+            world: "bulwark:plugin/http-detection",
+            path: "wit-0.5",
+            async: true,
+            with: {
+                "wasi:http/types/incoming-response": super::super::latest::http::types::IncomingResponse,
+                "wasi:http/types/incoming-request": super::super::latest::http::types::IncomingRequest,
+                "wasi:http/types/incoming-body": super::super::latest::http::types::IncomingBody,
+                "wasi:http/types/outgoing-response": super::super::latest::http::types::OutgoingResponse,
+                "wasi:http/types/outgoing-request": super::super::latest::http::types::OutgoingRequest,
+                "wasi:http/types/outgoing-body": super::super::latest::http::types::OutgoingBody,
+                "wasi:http/types/fields": super::super::latest::http::types::Fields,
+                "wasi:http/types/response-outparam": super::super::latest::http::types::ResponseOutparam,
+                "wasi:http/types/future-incoming-response": super::super::latest::http::types::FutureIncomingResponse,
+                "wasi:http/types/future-trailers": super::super::latest::http::types::FutureTrailers,
+            }
+        });
+    }
 }
 
 extern crate redis;
@@ -71,6 +102,10 @@ pub struct HandlerOutput {
     pub tags: HashSet<String>,
     /// The labels applied by plugins to enrich the request.
     pub labels: HashMap<String, String>,
+    /// The action requested by a plugin, if any.
+    ///
+    /// This is always `None` for the combined output of a group of plugins.
+    pub action: Option<Action>,
 }
 
 impl HandlerOutput {
@@ -91,8 +126,13 @@ pub struct Plugin {
     reference: String,
     host_config: Arc<bulwark_config::Config>,
     guest_config: Arc<bulwark_config::Plugin>,
-    engine: Engine,
-    component: Component,
+    engine: PluginEngine,
+    /// The SHA-256 digest of the plugin's contents.
+    digest: [u8; 32],
+    /// The version of the `bulwark:plugin` world the plugin was built against.
+    world_version: WorldVersion,
+    /// The component with all of its imports already resolved, so that instantiation doesn't need to link it again.
+    instance_pre: InstancePre<PluginCtx>,
 }
 
 impl Plugin {
@@ -104,167 +144,225 @@ impl Plugin {
         host_config: &bulwark_config::Config,
         guest_config: &bulwark_config::Plugin,
     ) -> Result<Self, PluginLoadError> {
+        let engine = PluginEngine::for_plugins(host_config, [guest_config])?;
+        let component = engine.compile(wat.as_bytes())?;
         Self::from_component(
             name,
+            &engine,
             host_config,
             guest_config,
-            |engine| -> Result<Component, PluginLoadError> {
-                Ok(Component::new(engine, wat.as_bytes())?)
-            },
+            wat.as_bytes(),
+            &component,
         )
     }
 
     /// Creates and compiles a new [`Plugin`] from a byte slice of WASM.
     ///
     /// The bytes it expects are what you'd get if you read in a `*.wasm` file.
-    /// See [`Component::from_binary`].
+    /// See [`Component::new`].
     pub fn from_bytes(
         name: String,
         bytes: &[u8],
         host_config: &bulwark_config::Config,
         guest_config: &bulwark_config::Plugin,
     ) -> Result<Self, PluginLoadError> {
-        Self::from_component(
-            name,
-            host_config,
-            guest_config,
-            |engine| -> Result<Component, PluginLoadError> {
-                Ok(Component::from_binary(engine, bytes)?)
-            },
-        )
+        let engine = PluginEngine::for_plugins(host_config, [guest_config])?;
+        let component = engine.compile(bytes)?;
+        Self::from_component(name, &engine, host_config, guest_config, bytes, &component)
     }
 
     /// Creates and compiles a new [`Plugin`] by reading in a file in either `*.wasm` or `*.wat` format.
     ///
-    /// See [`Component::from_file`].
+    /// See [`Component::new`].
     pub fn from_file(
         path: impl AsRef<Path>,
         host_config: &bulwark_config::Config,
         guest_config: &bulwark_config::Plugin,
     ) -> Result<Self, PluginLoadError> {
-        let name = guest_config.reference.clone();
+        let engine = PluginEngine::for_plugins(host_config, [guest_config])?;
+        let path = path.as_ref();
+        let bytes = Self::read_file(path)?;
+        let component =
+            engine.compile_precompiled(&bytes, &path.with_extension(PRECOMPILED_EXTENSION))?;
         Self::from_component(
-            name,
+            guest_config.reference.clone(),
+            &engine,
             host_config,
             guest_config,
-            |engine| -> Result<Component, PluginLoadError> {
-                Ok(Component::from_file(engine, &path)?)
-            },
+            &bytes,
+            &component,
         )
     }
 
     /// Creates and compiles a new [`Plugin`] from configuration.
     ///
+    /// The plugin gets an engine of its own. Use [`Plugin::from_engine`] to load several plugins into one engine.
+    ///
     /// See [`bulwark_config::Plugin`].
-    pub fn from_config(
+    pub async fn from_config(
         host_config: &bulwark_config::Config,
         guest_config: &bulwark_config::Plugin,
     ) -> Result<Self, PluginLoadError> {
+        let engine = PluginEngine::for_plugins(host_config, [guest_config])?;
+        Self::from_engine(&engine, host_config, guest_config).await
+    }
+
+    /// Creates a new [`Plugin`] from configuration, compiling it with a shared [`PluginEngine`].
+    ///
+    /// The engine must have been created with configuration that includes this plugin, so that it supports the
+    /// plugin's limits. Plugins with identical contents are only compiled once per engine, and a local plugin that was
+    /// [precompiled](PluginEngine::precompile) for a compatible engine isn't compiled at all.
+    ///
+    /// See [`bulwark_config::Plugin`].
+    pub async fn from_engine(
+        engine: &PluginEngine,
+        host_config: &bulwark_config::Config,
+        guest_config: &bulwark_config::Plugin,
+    ) -> Result<Self, PluginLoadError> {
+        let bytes = fetch_plugin(host_config, guest_config).await?;
+        verify_plugin(host_config, guest_config, &bytes).await?;
+        let component = match &guest_config.location {
+            bulwark_config::PluginLocation::Local(path) => {
+                engine.compile_precompiled(&bytes, &path.with_extension(PRECOMPILED_EXTENSION))?
+            }
+            _ => engine.compile(&bytes)?,
+        };
         Self::from_component(
             guest_config.reference.clone(),
+            engine,
             host_config,
             guest_config,
-            |engine| -> Result<Component, PluginLoadError> {
-                match &guest_config.location {
-                    bulwark_config::PluginLocation::Local(path) => {
-                        Ok(Component::from_file(engine, path)?)
-                    }
-                    bulwark_config::PluginLocation::Remote(uri) => {
-                        let client = reqwest::blocking::Client::new();
-                        let mut request = client.get(uri.clone());
-                        if let PluginAccess::Header(authorization_secret) = &guest_config.access {
-                            let secret =
-                                host_config.secret(authorization_secret).ok_or_else(|| {
-                                    PluginLoadError::SecretMissing(authorization_secret.clone())
-                                })?;
-                            // In this case, secrecy::Secret might be overkill, because we immediately discard the value,
-                            // but it's probably a good habit to be using it anytime we touch a secret.
-                            let authorization_value = match &secret.location {
-                                bulwark_config::SecretLocation::EnvVar(env_var) => {
-                                    std::env::var_os(env_var)
-                                        .map(|value| {
-                                            secrecy::Secret::from(
-                                                value.to_string_lossy().to_string(),
-                                            )
-                                        })
-                                        .ok_or(PluginLoadError::SecretMissing(
-                                            secret.reference.clone(),
-                                        ))?
-                                }
-                                bulwark_config::SecretLocation::File(path) => {
-                                    secrecy::Secret::from(
-                                        std::fs::read(path)
-                                            .map(|value| {
-                                                String::from_utf8_lossy(value.as_slice())
-                                                    .to_string()
-                                            })
-                                            .map_err(|err| {
-                                                PluginLoadError::SecretUnreadable(
-                                                    secret.reference.clone(),
-                                                    err,
-                                                )
-                                            })?,
-                                    )
-                                }
-                            };
-                            request = request.header(
-                                reqwest::header::AUTHORIZATION,
-                                authorization_value.expose_secret(),
-                            );
-                        }
-                        let bytes = request.send()?.bytes()?;
-                        if let PluginVerification::Sha256(digest) = &guest_config.verification {
-                            // The expected digest should already be in raw byte form here, not hex-encoded.
-                            let mut hasher = Sha256::new();
-                            hasher.update(&bytes[..]);
-                            let plugin_digest = hasher.finalize();
-                            if plugin_digest.as_slice() != &digest[..] {
-                                // Need to make both sides hex-encoded to make the error message readable.
-                                return Err(PluginLoadError::VerificationError(
-                                    "sha256".to_string(),
-                                    hex::encode(&digest[..]),
-                                    hex::encode(plugin_digest.as_slice()),
-                                ));
-                            }
-                        }
-                        Ok(Component::from_binary(engine, &bytes[..])?)
-                    }
-                    bulwark_config::PluginLocation::Bytes(bytes) => {
-                        Ok(Component::from_binary(engine, &bytes[..])?)
-                    }
-                }
-            },
+            &bytes,
+            &component,
         )
     }
 
+    /// Reads a plugin from a local file.
+    fn read_file(path: &Path) -> Result<Vec<u8>, PluginLoadError> {
+        Ok(std::fs::read(path)
+            .with_context(|| format!("failed to read plugin: {}", path.display()))?)
+    }
+
     /// Helper method for the other `from_*` functions.
-    fn from_component<F>(
+    fn from_component(
         reference: String,
+        engine: &PluginEngine,
         host_config: &bulwark_config::Config,
         guest_config: &bulwark_config::Plugin,
-        mut get_component: F,
-    ) -> Result<Self, PluginLoadError>
-    where
-        F: FnMut(&Engine) -> Result<Component, PluginLoadError>,
-    {
-        let mut wasm_config = Config::new();
-        wasm_config.wasm_backtrace_details(wasmtime::WasmBacktraceDetails::Enable);
-        wasm_config.wasm_multi_memory(true);
-        wasm_config.wasm_component_model(true);
-        wasm_config.async_support(true);
-
-        let engine = Engine::new(&wasm_config)?;
-        let component = get_component(&engine)?;
+        bytes: &[u8],
+        component: &Component,
+    ) -> Result<Self, PluginLoadError> {
+        let world_version = WorldVersion::detect(engine.engine(), component)?;
+        if world_version.is_deprecated() {
+            tracing::warn!(
+                message = "deprecated plugin world",
+                plugin = reference,
+                world = tracing::field::display(world_version),
+                current = tracing::field::display(WorldVersion::CURRENT),
+            );
+            metrics::increment_counter!(
+                "plugin_world_deprecated",
+                "ref" => reference.clone(), "world" => world_version.version()
+            );
+        }
+        let instance_pre = Self::link(engine.engine(), component)?;
 
         Ok(Plugin {
             reference,
             host_config: Arc::new(host_config.clone()),
             guest_config: Arc::new(guest_config.clone()),
-            engine,
-            component,
+            engine: engine.clone(),
+            digest: Sha256::digest(bytes).into(),
+            world_version,
+            instance_pre,
         })
     }
 
+    /// Gives a store the plugin's full fuel and epoch deadline budget, ahead of a call into the guest.
+    ///
+    /// A plugin without a limit of its own still needs an unlimited budget if it shares an engine with plugins that
+    /// have one.
+    fn reset_cpu_limits(&self, store: &mut Store<PluginCtx>) -> anyhow::Result<()> {
+        if self.engine.consume_fuel() {
+            store.set_fuel(self.guest_config.max_fuel.unwrap_or(u64::MAX))?;
+        }
+        if self.engine.epoch_interruption() {
+            store.set_epoch_deadline(
+                self.guest_config
+                    .epoch_deadline
+                    .map_or(u64::MAX, |epoch_deadline| {
+                        epoch_deadline.div_ceil(bulwark_config::EPOCH_TICK_INTERVAL)
+                    }),
+            );
+        }
+        Ok(())
+    }
+
+    /// Links the host's interfaces into a component so it can be instantiated repeatedly without relinking.
+    fn link(
+        engine: &Engine,
+        component: &Component,
+    ) -> Result<InstancePre<PluginCtx>, PluginLoadError> {
+        let mut linker: Linker<PluginCtx> = Linker::new(engine);
+        Self::add_to_linker(&mut linker)?;
+
+        // Missing imports are reported separately from other load failures, since they almost always mean the
+        // plugin was built against an incompatible SDK.
+        linker
+            .instantiate_pre(component)
+            .map_err(PluginLoadError::IncompatibleWorld)
+    }
+
+    /// Defines every interface the host provides to plugins in a linker.
+    ///
+    /// Each supported world version's interfaces have distinct versioned names, so a deprecated version's adapters
+    /// are defined alongside the current version's interfaces.
+    pub(crate) fn add_to_linker(linker: &mut Linker<PluginCtx>) -> Result<(), PluginLoadError> {
+        fn host_getter(ctx: &mut PluginCtx) -> &mut PluginCtx {
+            ctx
+        }
+
+        wasmtime_wasi::add_to_linker_async(linker)?;
+        wasmtime_wasi_http::bindings::wasi::http::types::add_to_linker_get_host(
+            linker,
+            host_getter,
+        )
+        .context("failed to link `wasi:http/types` interface")?;
+        wasmtime_wasi_http::bindings::wasi::http::outgoing_handler::add_to_linker_get_host(
+            linker,
+            host_getter,
+        )
+        .context("failed to link `wasi:http/outgoing-handler` interface")?;
+        bindings::bulwark::plugin::config::add_to_linker(linker, |t| t)
+            .context("failed to link `bulwark:plugin/config` interface")?;
+        bindings::bulwark::plugin::redis::add_to_linker(linker, |t| t)
+            .context("failed to link `bulwark:plugin/redis` interface")?;
+        bindings::bulwark::plugin::types::add_to_linker(linker, |t| t)
+            .context("failed to link `bulwark:plugin/types` interface")?;
+        bindings::v0_5::bulwark::plugin::config::add_to_linker(linker, |t| t)
+            .context("failed to link `bulwark:plugin/config@0.5.0` interface")?;
+        bindings::v0_5::bulwark::plugin::redis::add_to_linker(linker, |t| t)
+            .context("failed to link `bulwark:plugin/redis@0.5.0` interface")?;
+        bindings::v0_5::bulwark::plugin::types::add_to_linker(linker, |t| t)
+            .context("failed to link `bulwark:plugin/types@0.5.0` interface")?;
+        Ok(())
+    }
+
+    /// Returns the plugin's identifier.
+    pub fn reference(&self) -> &str {
+        &self.reference
+    }
+
+    /// Returns the SHA-256 digest of the plugin's contents, which can be used to pin it with `sha256` verification.
+    pub fn digest(&self) -> &[u8] {
+        &self.digest
+    }
+
+    /// Returns the version of the `bulwark:plugin` world the plugin was built against.
+    pub fn world_version(&self) -> WorldVersion {
+        self.world_version
+    }
+
     /// Makes the host's configuration available to host functions.
     pub(crate) fn host_config(&self) -> &bulwark_config::Config {
         &self.host_config
@@ -279,28 +377,82 @@ impl Plugin {
     pub fn permissions(&self) -> &bulwark_config::Permissions {
         &self.guest_config.permissions
     }
+
+    /// Creates the buffers that capture an instance's stdio, sized to the plugin's configured limit.
+    pub(crate) fn new_stdio(&self) -> PluginStdio {
+        PluginStdio::new(self.guest_config.max_stdio_size)
+    }
+
+    /// Creates a limiter that enforces the plugin's configured memory limits on an instance's store.
+    pub(crate) fn new_limiter(&self) -> PluginLimiter {
+        PluginLimiter::new(&self.guest_config)
+    }
 }
 
 /// Allows the host to capture plugin standard IO and record it to the log.
+///
+/// Unlike [`wasmtime_wasi::pipe::MemoryOutputPipe`], the buffer can be emptied, so that a recycled
+/// [`PluginInstance`] doesn't log the previous request's output again.
 #[derive(Clone)]
-pub(crate) struct BufStdoutStream(MemoryOutputPipe);
+pub(crate) struct BufStdoutStream {
+    /// The captured output.
+    buffer: Arc<std::sync::Mutex<bytes::BytesMut>>,
+    /// The maximum number of bytes the buffer may hold.
+    limit: usize,
+    /// The name of the stream, used to identify it when its limit is exceeded.
+    name: &'static str,
+}
 
 impl BufStdoutStream {
+    pub(crate) fn new(name: &'static str, limit: Option<usize>) -> Self {
+        Self {
+            buffer: Arc::default(),
+            limit: limit.unwrap_or(usize::MAX),
+            name,
+        }
+    }
+
     pub fn contents(&self) -> bytes::Bytes {
-        self.0.contents()
+        self.buffer.lock().expect("poisoned mutex").clone().freeze()
+    }
+
+    pub(crate) fn clear(&self) {
+        self.buffer.lock().expect("poisoned mutex").clear();
     }
 
     pub(crate) fn writer(&self) -> impl HostOutputStream {
-        self.0.clone()
+        self.clone()
     }
 }
 
-impl Default for BufStdoutStream {
-    fn default() -> Self {
-        Self(MemoryOutputPipe::new(usize::MAX))
+impl HostOutputStream for BufStdoutStream {
+    fn write(&mut self, bytes: bytes::Bytes) -> Result<(), StreamError> {
+        let mut buffer = self.buffer.lock().expect("poisoned mutex");
+        if bytes.len() > self.limit - buffer.len() {
+            return Err(StreamError::Trap(LimitExceeded(self.name).into()));
+        }
+        buffer.extend_from_slice(bytes.as_ref());
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), StreamError> {
+        // The buffer is always flushed
+        Ok(())
+    }
+
+    fn check_write(&mut self) -> Result<usize, StreamError> {
+        // Always permit writes, even past the limit, so that the write that exceeds it traps rather than blocking
+        // forever on a full buffer.
+        let consumed = self.buffer.lock().expect("poisoned mutex").len();
+        Ok(usize::MAX - consumed)
     }
 }
 
+#[async_trait::async_trait]
+impl Subscribe for BufStdoutStream {
+    async fn ready(&mut self) {}
+}
+
 impl StdoutStream for BufStdoutStream {
     fn stream(&self) -> Box<dyn HostOutputStream> {
         Box::new(self.writer())
@@ -312,13 +464,21 @@ impl StdoutStream for BufStdoutStream {
 }
 
 /// Wraps buffers to capture plugin stdio.
-#[derive(Clone, Default)]
+#[derive(Clone)]
 pub struct PluginStdio {
     pub(crate) stdout: BufStdoutStream,
     pub(crate) stderr: BufStdoutStream,
 }
 
 impl PluginStdio {
+    /// Creates empty buffers that each hold at most `limit` bytes, or any amount if `None`.
+    pub(crate) fn new(limit: Option<usize>) -> Self {
+        Self {
+            stdout: BufStdoutStream::new("stdout", limit),
+            stderr: BufStdoutStream::new("stderr", limit),
+        }
+    }
+
     pub fn stdout_buffer(&self) -> Vec<u8> {
         self.stdout.contents().to_vec()
     }
@@ -326,6 +486,21 @@ impl PluginStdio {
     pub fn stderr_buffer(&self) -> Vec<u8> {
         self.stderr.contents().to_vec()
     }
+
+    /// Empties both buffers.
+    pub(crate) fn clear(&self) {
+        self.stdout.clear();
+        self.stderr.clear();
+    }
+}
+
+/// A plugin's handlers, bound through the bindings for the world version the plugin targets.
+///
+/// Calls through a deprecated version's bindings adapt their arguments and results to and from the current version's
+/// types, so that callers only ever deal with the current version.
+enum HttpHandlers {
+    V0_6(bindings::v0_6::HttpDetection),
+    V0_5(bindings::v0_5::HttpDetection),
 }
 
 /// An instance of a [`Plugin`], associated with a [`PluginCtx`].
@@ -334,10 +509,14 @@ pub struct PluginInstance {
     plugin: Arc<Plugin>,
     /// The WASM store that holds state associated with the incoming request.
     store: Store<PluginCtx>,
-    /// The HTTP detection world from the WIT interface.
-    http_detection: bindings::HttpDetection,
+    /// The plugin's handlers, bound for the world version it targets.
+    handlers: HttpHandlers,
     /// The buffers for `stdin`, `stdout`, and `stderr` used by the plugin for I/O.
     stdio: PluginStdio,
+    /// The number of requests this instance has been used for.
+    pub(crate) uses: usize,
+    /// False if a call into the guest trapped or was cancelled, leaving the guest in an unknown state.
+    pub(crate) reusable: bool,
 }
 
 impl PluginInstance {
@@ -351,45 +530,66 @@ impl PluginInstance {
         plugin: Arc<Plugin>,
         plugin_ctx: PluginCtx,
     ) -> Result<PluginInstance, PluginInstantiationError> {
-        fn host_getter(ctx: &mut PluginCtx) -> &mut PluginCtx {
-            ctx
-        }
-
         // Clone the stdio so we can read the captured stdout and stderr buffers after execution has completed.
         let stdio = plugin_ctx.stdio.clone();
 
-        let mut linker: Linker<PluginCtx> = Linker::new(&plugin.engine);
-        let mut store = Store::new(&plugin.engine, plugin_ctx);
-
-        wasmtime_wasi::add_to_linker_async(&mut linker)?;
-        wasmtime_wasi_http::bindings::wasi::http::types::add_to_linker_get_host(
-            &mut linker,
-            host_getter,
-        )
-        .context("failed to link `wasi:http/types` interface")?;
-        wasmtime_wasi_http::bindings::wasi::http::outgoing_handler::add_to_linker_get_host(
-            &mut linker,
-            host_getter,
-        )
-        .context("failed to link `wasi:http/outgoing-handler` interface")?;
-        bindings::bulwark::plugin::config::add_to_linker(&mut linker, |t| t)
-            .context("failed to link `bulwark:plugin/config` interface")?;
-        bindings::bulwark::plugin::redis::add_to_linker(&mut linker, |t| t)
-            .context("failed to link `bulwark:plugin/redis` interface")?;
-        bindings::bulwark::plugin::types::add_to_linker(&mut linker, |t| t)
-            .context("failed to link `bulwark:plugin/types` interface")?;
+        let mut store = Store::new(plugin.engine.engine(), plugin_ctx);
+        store.limiter(|ctx| &mut ctx.limiter);
+        // Instantiation may run guest code too, which would immediately exhaust an empty budget.
+        plugin.reset_cpu_limits(&mut store)?;
 
         // We discard the instance for this because we only use the generated interface to make calls
-
-        let (http_detection, _) =
-            bindings::HttpDetection::instantiate_async(&mut store, &plugin.component, &linker)
-                .await?;
+        let instance = plugin.instance_pre.instantiate_async(&mut store).await?;
+        let handlers = match plugin.world_version {
+            WorldVersion::V0_6 => HttpHandlers::V0_6(
+                bindings::v0_6::HttpDetection::new(&mut store, &instance)
+                    .map_err(PluginInstantiationError::IncompatibleWorld)?,
+            ),
+            WorldVersion::V0_5 => HttpHandlers::V0_5(
+                bindings::v0_5::HttpDetection::new(&mut store, &instance)
+                    .map_err(PluginInstantiationError::IncompatibleWorld)?,
+            ),
+        };
 
         Ok(PluginInstance {
             plugin,
             store,
-            http_detection,
+            handlers,
             stdio,
+            uses: 0,
+            reusable: true,
+        })
+    }
+
+    /// Prepares a reusable instance for its next request by discarding the output captured during the last one.
+    ///
+    /// Only the host's state is reset. The guest's linear memory and globals can't be rolled back, so they carry
+    /// over to the next request.
+    pub(crate) fn reset(&mut self) {
+        self.stdio.clear();
+    }
+
+    /// Distinguishes traps caused by the plugin's CPU and memory limits from other failures, counting them in the
+    /// `plugin_cpu_limit_exceeded` and `plugin_memory_limit_exceeded` metrics.
+    fn check_limits<T>(&self, result: wasmtime::Result<T>) -> Result<T, PluginExecutionError> {
+        result.map_err(|err| {
+            if let Some(LimitExceeded(limit)) = err.downcast_ref::<LimitExceeded>() {
+                metrics::increment_counter!(
+                    "plugin_memory_limit_exceeded",
+                    "ref" => self.plugin_reference(), "limit" => *limit
+                );
+                return PluginExecutionError::MemoryLimitExceeded(String::from(*limit));
+            }
+            let limit = match err.downcast_ref::<wasmtime::Trap>() {
+                Some(wasmtime::Trap::OutOfFuel) => "fuel",
+                Some(wasmtime::Trap::Interrupt) => "epoch",
+                _ => return PluginExecutionError::from(err),
+            };
+            metrics::increment_counter!(
+                "plugin_cpu_limit_exceeded",
+                "ref" => self.plugin_reference(), "limit" => limit
+            );
+            PluginExecutionError::CpuLimitExceeded(String::from(limit))
         })
     }
 
@@ -410,11 +610,22 @@ impl PluginInstance {
 
     /// Executes the guest's `init` function.
     pub async fn handle_init(&mut self) -> Result<(), PluginExecutionError> {
-        let result = self
-            .http_detection
-            .bulwark_plugin_http_handlers()
-            .call_handle_init(self.store.as_context_mut())
-            .await;
+        self.plugin.reset_cpu_limits(&mut self.store)?;
+        self.reusable = false;
+        let result = match &self.handlers {
+            HttpHandlers::V0_6(bindings) => {
+                bindings
+                    .bulwark_plugin_http_handlers()
+                    .call_handle_init(self.store.as_context_mut())
+                    .await
+            }
+            HttpHandlers::V0_5(bindings) => bindings
+                .bulwark_plugin_http_handlers()
+                .call_handle_init(self.store.as_context_mut())
+                .await
+                .map(|result| result.map_err(Into::into)),
+        };
+        self.reusable = result.is_ok();
         match result {
             Ok(Ok(_)) => metrics::increment_counter!(
                 "plugin_on_init",
@@ -427,7 +638,7 @@ impl PluginInstance {
         }
 
         // Initialization doesn't return anything unless there's an error
-        result??;
+        self.check_limits(result)??;
         Ok(())
     }
 
@@ -453,15 +664,30 @@ impl PluginInstance {
 
         // TODO: need to determine if automatic calls to remove_forbidden_headers are going to be a problem
         let labels: Vec<(String, String)> = labels.into_iter().collect();
-        let result = self
-            .http_detection
-            .bulwark_plugin_http_handlers()
-            .call_handle_request_enrichment(
-                self.store.as_context_mut(),
-                incoming_request_handle,
-                labels.as_slice(),
-            )
-            .await;
+        self.plugin.reset_cpu_limits(&mut self.store)?;
+        self.reusable = false;
+        let result = match &self.handlers {
+            HttpHandlers::V0_6(bindings) => {
+                bindings
+                    .bulwark_plugin_http_handlers()
+                    .call_handle_request_enrichment(
+                        self.store.as_context_mut(),
+                        incoming_request_handle,
+                        labels.as_slice(),
+                    )
+                    .await
+            }
+            HttpHandlers::V0_5(bindings) => bindings
+                .bulwark_plugin_http_handlers()
+                .call_handle_request_enrichment(
+                    self.store.as_context_mut(),
+                    incoming_request_handle,
+                    labels.as_slice(),
+                )
+                .await
+                .map(|result| result.map_err(Into::into)),
+        };
+        self.reusable = result.is_ok();
         match result {
             Ok(Ok(_)) => metrics::increment_counter!(
                 "plugin_on_request",
@@ -472,7 +698,7 @@ impl PluginInstance {
                 "ref" => self.plugin_reference(), "result" => "error"
             ),
         }
-        let labels: HashMap<String, String> = result??.into_iter().collect();
+        let labels: HashMap<String, String> = self.check_limits(result)??.into_iter().collect();
 
         Ok(labels)
     }
@@ -498,15 +724,30 @@ impl PluginInstance {
             .new_incoming_request(incoming_request)?;
 
         let labels: Vec<(String, String)> = labels.into_iter().collect();
-        let result = self
-            .http_detection
-            .bulwark_plugin_http_handlers()
-            .call_handle_request_decision(
-                self.store.as_context_mut(),
-                incoming_request_handle,
-                labels.as_slice(),
-            )
-            .await;
+        self.plugin.reset_cpu_limits(&mut self.store)?;
+        self.reusable = false;
+        let result = match &self.handlers {
+            HttpHandlers::V0_6(bindings) => {
+                bindings
+                    .bulwark_plugin_http_handlers()
+                    .call_handle_request_decision(
+                        self.store.as_context_mut(),
+                        incoming_request_handle,
+                        labels.as_slice(),
+                    )
+                    .await
+            }
+            HttpHandlers::V0_5(bindings) => bindings
+                .bulwark_plugin_http_handlers()
+                .call_handle_request_decision(
+                    self.store.as_context_mut(),
+                    incoming_request_handle,
+                    labels.as_slice(),
+                )
+                .await
+                .map(|result| result.map(Into::into).map_err(Into::into)),
+        };
+        self.reusable = result.is_ok();
         match result {
             Ok(Ok(_)) => metrics::increment_counter!(
                 "plugin_on_request_decision",
@@ -518,7 +759,7 @@ impl PluginInstance {
             ),
         }
 
-        Ok(result??.into())
+        Ok(self.check_limits(result)??.into())
     }
 
     /// Executes the guest's `on_response_decision` function.
@@ -563,16 +804,32 @@ impl PluginInstance {
             .new_incoming_response(incoming_response)?;
 
         let labels: Vec<(String, String)> = labels.into_iter().collect();
-        let result = self
-            .http_detection
-            .bulwark_plugin_http_handlers()
-            .call_handle_response_decision(
-                self.store.as_context_mut(),
-                incoming_request_handle,
-                incoming_response_handle,
-                labels.as_slice(),
-            )
-            .await;
+        self.plugin.reset_cpu_limits(&mut self.store)?;
+        self.reusable = false;
+        let result = match &self.handlers {
+            HttpHandlers::V0_6(bindings) => {
+                bindings
+                    .bulwark_plugin_http_handlers()
+                    .call_handle_response_decision(
+                        self.store.as_context_mut(),
+                        incoming_request_handle,
+                        incoming_response_handle,
+                        labels.as_slice(),
+                    )
+                    .await
+            }
+            HttpHandlers::V0_5(bindings) => bindings
+                .bulwark_plugin_http_handlers()
+                .call_handle_response_decision(
+                    self.store.as_context_mut(),
+                    incoming_request_handle,
+                    incoming_response_handle,
+                    labels.as_slice(),
+                )
+                .await
+                .map(|result| result.map(Into::into).map_err(Into::into)),
+        };
+        self.reusable = result.is_ok();
         match result {
             Ok(Ok(_)) => metrics::increment_counter!(
                 "plugin_on_request_body_decision",
@@ -584,7 +841,7 @@ impl PluginInstance {
             ),
         }
 
-        Ok(result??.into())
+        Ok(self.check_limits(result)??.into())
     }
 
     /// Executes the guest's `on_decision_feedback` function.
@@ -629,17 +886,34 @@ impl PluginInstance {
             .new_incoming_response(incoming_response)?;
 
         let labels: Vec<(String, String)> = labels.into_iter().collect();
-        let result = self
-            .http_detection
-            .bulwark_plugin_http_handlers()
-            .call_handle_decision_feedback(
-                self.store.as_context_mut(),
-                incoming_request_handle,
-                incoming_response_handle,
-                labels.as_slice(),
-                &verdict.into(),
-            )
-            .await;
+        self.plugin.reset_cpu_limits(&mut self.store)?;
+        self.reusable = false;
+        let result = match &self.handlers {
+            HttpHandlers::V0_6(bindings) => {
+                bindings
+                    .bulwark_plugin_http_handlers()
+                    .call_handle_decision_feedback(
+                        self.store.as_context_mut(),
+                        incoming_request_handle,
+                        incoming_response_handle,
+                        labels.as_slice(),
+                        &verdict.into(),
+                    )
+                    .await
+            }
+            HttpHandlers::V0_5(bindings) => bindings
+                .bulwark_plugin_http_handlers()
+                .call_handle_decision_feedback(
+                    self.store.as_context_mut(),
+                    incoming_request_handle,
+                    incoming_response_handle,
+                    labels.as_slice(),
+                    &verdict.into(),
+                )
+                .await
+                .map(|result| result.map_err(Into::into)),
+        };
+        self.reusable = result.is_ok();
         match result {
             Ok(Ok(_)) => metrics::increment_counter!(
                 "plugin_on_decision_feedback",
@@ -652,7 +926,7 @@ impl PluginInstance {
         }
 
         // Decision feedback doesn't return anything unless there's an error
-        result??;
+        self.check_limits(result)??;
         Ok(())
     }
 }
@@ -0,0 +1,210 @@
+use crate::{Plugin, PluginCtx, PluginInstance, PluginInstantiationError, RedisCtx};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// A pool of pre-instantiated [`PluginInstance`]s for a single [`Plugin`].
+///
+/// Instantiating a plugin is the most expensive part of running it, so the pool keeps instances ready ahead of the
+/// requests that need them. Each instance runs the guest's `init` function once, when it's created, so pooled
+/// instances are already initialized by the time a request arrives. As long as the pool is refilled in the
+/// background, neither instantiation nor `init` runs on the request path. Only a request that finds the pool empty,
+/// because more requests arrived at once than the pool holds, instantiates and initializes an instance itself.
+///
+/// Once a request is done with an instance, it's [released](PluginPool::release) back to the pool, which keeps it for
+/// another request if it hasn't yet reached its maximum number of uses. Instances whose last call trapped or was
+/// cancelled are always discarded.
+///
+/// A reused instance is only reset on the host's side. Its guest memory and globals carry over from the requests it
+/// has already handled, so a maximum of one use is the only way to isolate requests from each other.
+///
+/// If an instance's `init` function fails, the error is kept until the pool is replaced, e.g. by a reload, and the
+/// pool stops holding on to instances. The failed instance still serves the request it was created for, but it's
+/// never pooled, so once any instances that were already idle are used up, each request creates and initializes an
+/// instance of its own.
+///
+/// A pool with a size of zero never holds on to any instances, instantiating the plugin for every request.
+pub struct PluginPool {
+    /// The plugin the pooled instances belong to.
+    plugin: Arc<Plugin>,
+    /// The environment variables made available to each instance.
+    environment: HashMap<String, String>,
+    /// The Redis connection pool shared by each instance.
+    redis_ctx: RedisCtx,
+    /// The number of idle instances the pool tries to keep ready.
+    size: usize,
+    /// The number of requests an instance may handle before it's discarded.
+    max_uses: usize,
+    /// The instances waiting for a request.
+    idle: Mutex<Vec<PluginInstance>>,
+    /// Set while the pool is being filled, so that concurrent requests don't each start filling it.
+    filling: AtomicBool,
+    /// The error from the first instance whose `init` function failed, if any.
+    init_error: Mutex<Option<String>>,
+    /// The number of times an instance's `init` function has run.
+    init_count: AtomicUsize,
+}
+
+impl PluginPool {
+    /// Creates a new, empty [`PluginPool`].
+    ///
+    /// # Arguments
+    ///
+    /// * `plugin` - The plugin to create instances of.
+    /// * `environment` - The environment variables the plugin has been granted access to.
+    /// * `redis_ctx` - The Redis connection pool.
+    /// * `size` - The number of idle instances to keep ready.
+    /// * `max_uses` - The number of requests an instance may handle before it's discarded.
+    pub fn new(
+        plugin: Arc<Plugin>,
+        environment: HashMap<String, String>,
+        redis_ctx: RedisCtx,
+        size: usize,
+        max_uses: usize,
+    ) -> Self {
+        Self {
+            plugin,
+            environment,
+            redis_ctx,
+            size,
+            max_uses,
+            idle: Mutex::new(Vec::with_capacity(size)),
+            filling: AtomicBool::new(false),
+            init_error: Mutex::new(None),
+            init_count: AtomicUsize::new(0),
+        }
+    }
+
+    /// Creates and initializes the pool's first instances, returning the error if the plugin's `init` function fails.
+    ///
+    /// A pool with a size of zero still creates one instance to run `init`, so that failures are found when the
+    /// plugin is loaded rather than on its first request.
+    pub async fn initialize(&self) -> Result<(), PluginInstantiationError> {
+        if self.size == 0 {
+            self.instantiate().await?;
+        } else {
+            self.fill().await?;
+        }
+        self.check_init()
+    }
+
+    /// Returns the error from the first instance whose `init` function failed, if any.
+    ///
+    /// Once set, the error is kept for the lifetime of the pool, even if later instances initialize successfully.
+    pub fn init_error(&self) -> Option<String> {
+        self.init_error.lock().expect("poisoned mutex").clone()
+    }
+
+    /// Returns the number of times the plugin's `init` function has run, once for each instance the pool created.
+    pub fn init_count(&self) -> usize {
+        self.init_count.load(Ordering::SeqCst)
+    }
+
+    /// Returns the plugin the pooled instances belong to.
+    pub fn plugin(&self) -> &Arc<Plugin> {
+        &self.plugin
+    }
+
+    /// Takes an idle instance from the pool, or instantiates a new one if none are available.
+    pub async fn acquire(&self) -> Result<PluginInstance, PluginInstantiationError> {
+        let idle = self.idle.lock().expect("poisoned mutex").pop();
+        let mut instance = match idle {
+            Some(instance) => {
+                metrics::increment_counter!(
+                    "plugin_pool_acquire",
+                    "ref" => self.plugin.reference().to_string(), "result" => "hit"
+                );
+                instance
+            }
+            None => {
+                metrics::increment_counter!(
+                    "plugin_pool_acquire",
+                    "ref" => self.plugin.reference().to_string(), "result" => "miss"
+                );
+                self.instantiate().await?
+            }
+        };
+        instance.uses += 1;
+        Ok(instance)
+    }
+
+    /// Returns an instance to the pool once a request is done with it.
+    ///
+    /// The instance is discarded instead if it has reached its maximum number of uses, if its guest is in an unknown
+    /// state, if the pool is already full, or if any instance of the plugin failed to initialize.
+    pub fn release(&self, mut instance: PluginInstance) {
+        if !instance.reusable || instance.uses >= self.max_uses || self.init_error().is_some() {
+            return;
+        }
+        instance.reset();
+        let mut idle = self.idle.lock().expect("poisoned mutex");
+        if idle.len() < self.size {
+            idle.push(instance);
+        }
+    }
+
+    /// True if the pool holds fewer idle instances than its configured size.
+    ///
+    /// A pool whose plugin failed to initialize is never filled.
+    pub fn needs_fill(&self) -> bool {
+        self.init_error().is_none() && self.idle.lock().expect("poisoned mutex").len() < self.size
+    }
+
+    /// Instantiates new instances until the pool reaches its configured size.
+    ///
+    /// If the pool is already being filled, this returns immediately. Filling stops at the first instance whose
+    /// `init` function fails, returning the error.
+    pub async fn fill(&self) -> Result<(), PluginInstantiationError> {
+        if self.filling.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        // Resets the flag even if the future is dropped part way through.
+        let _guard = FillGuard(&self.filling);
+        while self.needs_fill() {
+            let instance = self.instantiate().await?;
+            if self.init_error().is_none() {
+                self.idle.lock().expect("poisoned mutex").push(instance);
+            }
+        }
+        self.check_init()
+    }
+
+    /// Returns the error from [`PluginPool::init_error`], if one was recorded.
+    fn check_init(&self) -> Result<(), PluginInstantiationError> {
+        match self.init_error() {
+            Some(err) => Err(PluginInstantiationError::Initialization(err)),
+            None => Ok(()),
+        }
+    }
+
+    /// Creates a new instance with its own [`PluginCtx`] and runs the guest's `init` function.
+    ///
+    /// An instance whose `init` function fails is still returned, so that the plugin's other handlers can run, but
+    /// the failure is recorded in [`PluginPool::init_error`] unless an earlier failure already was, which keeps the
+    /// pool from holding on to any more instances.
+    async fn instantiate(&self) -> Result<PluginInstance, PluginInstantiationError> {
+        let plugin_ctx = PluginCtx::new(
+            self.plugin.clone(),
+            self.environment.clone(),
+            self.redis_ctx.clone(),
+        )?;
+        let mut instance = PluginInstance::new(self.plugin.clone(), plugin_ctx).await?;
+        self.init_count.fetch_add(1, Ordering::SeqCst);
+        if let Err(err) = instance.handle_init().await {
+            self.init_error
+                .lock()
+                .expect("poisoned mutex")
+                .get_or_insert_with(|| err.to_string());
+        }
+        Ok(instance)
+    }
+}
+
+/// Clears [`PluginPool::filling`] when dropped.
+struct FillGuard<'a>(&'a AtomicBool);
+
+impl Drop for FillGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::fetch::fetch_signature;
+use crate::PluginLoadError;
+use bulwark_config::{PluginLocation, PluginVerification};
+use bytes::Bytes;
+use ring::signature::{UnparsedPublicKey, ED25519};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// The extension appended to a plugin's path or URI to find its detached signature.
+///
+/// A signature file holds either the raw 64-byte signature or its hex encoding.
+pub const SIGNATURE_EXTENSION: &str = "sig";
+
+/// Checks a plugin's contents against its configured digest or signature.
+///
+/// Plugins are verified the same way wherever they're loaded from. If the runtime requires verification, a plugin
+/// with neither a digest nor a signature fails to load.
+///
+/// # Arguments
+///
+/// * `host_config` - The root of the Bulwark configuration structure.
+/// * `guest_config` - The configuration of the plugin being verified.
+/// * `bytes` - The plugin's contents.
+pub async fn verify_plugin(
+    host_config: &bulwark_config::Config,
+    guest_config: &bulwark_config::Plugin,
+    bytes: &[u8],
+) -> Result<(), PluginLoadError> {
+    match &guest_config.verification {
+        PluginVerification::None if host_config.runtime.require_verification => Err(
+            PluginLoadError::VerificationRequired(guest_config.reference.clone()),
+        ),
+        PluginVerification::None => Ok(()),
+        PluginVerification::Sha256(_) => verify_digest(guest_config, bytes),
+        PluginVerification::Ed25519 { trust, signature } => {
+            verify_signature(host_config, guest_config, trust, signature.as_ref(), bytes).await
+        }
+    }
+}
+
+/// Checks a plugin's contents against its expected digest, if it has one.
+pub(crate) fn verify_digest(
+    guest_config: &bulwark_config::Plugin,
+    bytes: &[u8],
+) -> Result<(), PluginLoadError> {
+    if let PluginVerification::Sha256(digest) = &guest_config.verification {
+        // The expected digest should already be in raw byte form here, not hex-encoded.
+        let plugin_digest = Sha256::digest(bytes);
+        if plugin_digest.as_slice() != &digest[..] {
+            // Need to make both sides hex-encoded to make the error message readable.
+            return Err(PluginLoadError::VerificationError(
+                "sha256".to_string(),
+                hex::encode(&digest[..]),
+                hex::encode(plugin_digest.as_slice()),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Checks a plugin's contents against its detached signature.
+///
+/// The signature is taken from the plugin's configuration if it's set there, and otherwise read from alongside the
+/// plugin. Plugins loaded from bytes or an OCI registry have nowhere to read a signature from, so theirs must be
+/// configured.
+async fn verify_signature(
+    host_config: &bulwark_config::Config,
+    guest_config: &bulwark_config::Plugin,
+    trust: &str,
+    signature: Option<&Bytes>,
+    bytes: &[u8],
+) -> Result<(), PluginLoadError> {
+    let trust = host_config
+        .trust(trust)
+        .ok_or_else(|| PluginLoadError::TrustMissing(trust.to_string()))?;
+    let signature = match signature {
+        Some(signature) => signature.clone(),
+        None => read_signature(host_config, guest_config).await?,
+    };
+
+    let trusted = trust.ed25519.iter().any(|public_key| {
+        UnparsedPublicKey::new(&ED25519, public_key)
+            .verify(bytes, &signature)
+            .is_ok()
+    });
+    if !trusted {
+        metrics::increment_counter!(
+            "plugin_signature_invalid",
+            "ref" => guest_config.reference.clone()
+        );
+        return Err(PluginLoadError::InvalidSignature(trust.reference.clone()));
+    }
+    Ok(())
+}
+
+/// Reads the detached signature that's stored alongside a plugin.
+async fn read_signature(
+    host_config: &bulwark_config::Config,
+    guest_config: &bulwark_config::Plugin,
+) -> Result<Bytes, PluginLoadError> {
+    let signature = match &guest_config.location {
+        PluginLocation::Local(path) => {
+            let path = signature_path(path);
+            tokio::fs::read(&path)
+                .await
+                .map(Bytes::from)
+                .map_err(|err| {
+                    PluginLoadError::SignatureMissing(format!("{}: {}", path.display(), err))
+                })?
+        }
+        PluginLocation::Remote(uri) => fetch_signature(host_config, guest_config, uri)
+            .await
+            .map_err(|err| PluginLoadError::SignatureMissing(err.to_string()))?,
+        PluginLocation::Bytes(_) => {
+            return Err(PluginLoadError::SignatureMissing(format!(
+                "plugin '{}' is loaded from bytes, so its signature must be configured",
+                guest_config.reference
+            )))
+        }
+        PluginLocation::Oci(_) => {
+            return Err(PluginLoadError::SignatureMissing(format!(
+                "plugin '{}' is loaded from an oci registry, so its signature must be configured",
+                guest_config.reference
+            )))
+        }
+    };
+    // A hex-encoded signature is never 64 bytes long, so raw signatures are unambiguous. Anything that's neither
+    // fails verification.
+    if signature.len() == 64 {
+        return Ok(signature);
+    }
+    Ok(hex::decode(String::from_utf8_lossy(&signature).trim())
+        .map(Bytes::from)
+        .unwrap_or(signature))
+}
+
+/// Returns the path of the detached signature for a local plugin, which is the plugin's path with `.sig` appended.
+fn signature_path(path: &Path) -> std::path::PathBuf {
+    let mut signature_path = path.as_os_str().to_owned();
+    signature_path.push(".");
+    signature_path.push(SIGNATURE_EXTENSION);
+    signature_path.into()
+}
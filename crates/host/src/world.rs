@@ -0,0 +1,109 @@
+use crate::PluginLoadError;
+use std::fmt::{Display, Formatter};
+use wasmtime::component::Component;
+use wasmtime::Engine;
+
+/// The version of the `bulwark:plugin` world that the host implements natively.
+///
+/// Must match the package version declared in the host's WIT files.
+pub const PLUGIN_WORLD_VERSION: &str = WorldVersion::CURRENT.version();
+
+/// The package that all of the `bulwark:plugin` world's interfaces belong to.
+const PLUGIN_PACKAGE: &str = "bulwark:plugin/";
+
+/// The interface that plugins export their handlers through, and the host calls into.
+const HANDLERS_INTERFACE: &str = "bulwark:plugin/http-handlers@";
+
+/// A version of the `bulwark:plugin` world that the host can load plugins built against.
+///
+/// Each version has bindings of its own, and a plugin's handlers are called through the bindings for the version
+/// it targets. Versions older than [`WorldVersion::CURRENT`] are deprecated: their plugins still load, with a
+/// warning, and their calls are adapted to and from the current version's types. A version stays supported for at
+/// least one minor release after it's superseded, so that plugins can be rebuilt after the host is upgraded rather
+/// than at the same time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WorldVersion {
+    /// `bulwark:plugin@0.6`, which added plugin-requested actions.
+    V0_6,
+    /// `bulwark:plugin@0.5`, which is deprecated. Its plugins can't request actions.
+    V0_5,
+}
+
+impl WorldVersion {
+    /// The version of the world the host's own WIT files declare.
+    pub const CURRENT: WorldVersion = WorldVersion::V0_6;
+
+    /// Every version the host can load, newest first.
+    pub const SUPPORTED: &'static [WorldVersion] = &[WorldVersion::V0_6, WorldVersion::V0_5];
+
+    /// Returns the full package version the host's bindings for this world version were generated from.
+    pub const fn version(&self) -> &'static str {
+        match self {
+            WorldVersion::V0_6 => "0.6.0",
+            WorldVersion::V0_5 => "0.5.0",
+        }
+    }
+
+    /// Returns true if plugins targeting this version should be rebuilt against the current version.
+    pub fn is_deprecated(&self) -> bool {
+        *self != Self::CURRENT
+    }
+
+    /// Finds the supported world version that is semver-compatible with a package version, if there is one.
+    ///
+    /// Pre-1.0 versions are only compatible within the same minor version, e.g. a plugin built against `0.5.2`
+    /// loads through the `0.5.0` bindings, but one built against `0.7.0` does not.
+    pub fn from_version(version: &str) -> Option<WorldVersion> {
+        let compatible_prefix = |version: &str| {
+            let mut parts = version.split('.');
+            match (parts.next(), parts.next()) {
+                (Some("0"), Some(minor)) => Some((String::from("0"), Some(minor.to_string()))),
+                (Some(major), _) => Some((major.to_string(), None)),
+                _ => None,
+            }
+        };
+        let prefix = compatible_prefix(version)?;
+        Self::SUPPORTED
+            .iter()
+            .copied()
+            .find(|supported| compatible_prefix(supported.version()).as_ref() == Some(&prefix))
+    }
+
+    /// Detects the world version a plugin component targets from the version of the handlers interface it exports.
+    ///
+    /// A component that doesn't export the handlers interface at all is assumed to target the current version, so
+    /// that linking it reports exactly what's missing.
+    ///
+    /// # Arguments
+    ///
+    /// * `engine` - The engine the component was compiled with.
+    /// * `component` - The plugin component.
+    pub(crate) fn detect(
+        engine: &Engine,
+        component: &Component,
+    ) -> Result<WorldVersion, PluginLoadError> {
+        let component_type = component.component_type();
+        let exported = component_type
+            .exports(engine)
+            .find_map(|(name, _)| name.strip_prefix(HANDLERS_INTERFACE));
+        match exported {
+            Some(version) => Self::from_version(version)
+                .ok_or_else(|| PluginLoadError::UnsupportedWorldVersion(version.to_string())),
+            None => Ok(Self::CURRENT),
+        }
+    }
+}
+
+impl Display for WorldVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "bulwark:plugin@{}", self.version())
+    }
+}
+
+/// Returns the `bulwark:plugin` package version among a component's interface names, if any of them belong to it.
+pub(crate) fn package_version<'a>(names: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    names
+        .filter_map(|name| name.strip_prefix(PLUGIN_PACKAGE))
+        .find_map(|name| name.split_once('@'))
+        .map(|(_, version)| version)
+}
@@ -5,6 +5,20 @@ extern crate proc_macro;
 
 const WIT_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/wit");
 
+/// The custom section that the names of a plugin's implemented handlers are recorded in.
+///
+/// Must match `bulwark_host::HANDLERS_SECTION`, which reads it back.
+const HANDLERS_SECTION: &str = "bulwark-handlers";
+
+/// The handlers in the `http-handlers` interface, in the order they're called.
+const HANDLERS: [&str; 5] = [
+    "handle_init",
+    "handle_request_enrichment",
+    "handle_request_decision",
+    "handle_response_decision",
+    "handle_decision_feedback",
+];
+
 /// The `bulwark_plugin` attribute generates default implementations for all handler traits in a module
 /// and produces friendly errors for common mistakes.
 ///
@@ -15,7 +29,7 @@ const WIT_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/wit");
 /// processing will continue to the next handler.
 ///
 /// # Trait Functions
-/// - `handle_init` - Rarely used. Called when the plugin is first loaded.
+/// - `handle_init` - Rarely used. Called once for each new instance of the plugin, before it handles any requests.
 /// - `handle_request_enrichment` - This handler is called for every incoming request, before any decision-making will occur.
 ///   It is typically used to perform enrichment tasks.
 /// - `handle_request_decision` - This handler is called to make an initial decision.
@@ -82,13 +96,7 @@ pub fn bulwark_plugin(_: TokenStream, input: TokenStream) -> TokenStream {
 
     let struct_type = &raw_impl.self_ty;
 
-    let mut handlers = vec![
-        "handle_init",
-        "handle_request_enrichment",
-        "handle_request_decision",
-        "handle_response_decision",
-        "handle_decision_feedback",
-    ];
+    let mut handlers = HANDLERS.to_vec();
 
     let mut new_items = Vec::with_capacity(raw_impl.items.len());
     for item in &raw_impl.items {
@@ -157,11 +165,7 @@ pub fn bulwark_plugin(_: TokenStream, input: TokenStream) -> TokenStream {
                             _: ::bulwark_sdk::Request,
                             _: ::std::collections::HashMap<String, String>
                         ) -> Result<::bulwark_sdk::HandlerOutput, ::bulwark_sdk::Error> {
-                            Ok(::bulwark_sdk::HandlerOutput {
-                                labels: ::std::collections::HashMap::new(),
-                                decision: ::bulwark_sdk::Decision::default(),
-                                tags: vec![],
-                            })
+                            Ok(::bulwark_sdk::HandlerOutput::default())
                         }
                     }
                 }
@@ -173,11 +177,7 @@ pub fn bulwark_plugin(_: TokenStream, input: TokenStream) -> TokenStream {
                             _: ::bulwark_sdk::Response,
                             _: ::std::collections::HashMap<String, String>
                         ) -> Result<::bulwark_sdk::HandlerOutput, ::bulwark_sdk::Error> {
-                            Ok(::bulwark_sdk::HandlerOutput {
-                                labels: ::std::collections::HashMap::new(),
-                                decision: ::bulwark_sdk::Decision::default(),
-                                tags: vec![],
-                            })
+                            Ok(::bulwark_sdk::HandlerOutput::default())
                         }
                     }
                 }
@@ -205,7 +205,29 @@ pub fn bulwark_plugin(_: TokenStream, input: TokenStream) -> TokenStream {
         })
         .collect::<Vec<proc_macro2::TokenStream>>();
 
+    // Record the handlers that were implemented, since the no-op defaults are indistinguishable from them once
+    // compiled. The names match the WIT function names so they can be compared against the component's exports.
+    let implemented_handlers = HANDLERS
+        .iter()
+        .filter(|handler| !handlers.contains(handler))
+        .map(|handler| handler.replace('_', "-"))
+        .collect::<Vec<String>>()
+        .join(",");
+    let implemented_handlers_len = implemented_handlers.len();
+    let implemented_handlers = syn::LitByteStr::new(
+        implemented_handlers.as_bytes(),
+        proc_macro2::Span::call_site(),
+    );
+
     let output = quote! {
+        // Statics in a link section become custom sections in WASM, but other targets have their own rules for
+        // section names.
+        #[cfg(target_family = "wasm")]
+        #[doc(hidden)]
+        #[link_section = #HANDLERS_SECTION]
+        #[used]
+        static __BULWARK_HANDLERS: [u8; #implemented_handlers_len] = *#implemented_handlers;
+
         mod handlers {
             use super::#struct_type;
 
@@ -236,13 +258,55 @@ pub fn bulwark_plugin(_: TokenStream, input: TokenStream) -> TokenStream {
             }
         }
 
+        impl From<crate::handlers::bulwark::plugin::types::Action> for ::bulwark_sdk::Action {
+            fn from(action: crate::handlers::bulwark::plugin::types::Action) -> Self {
+                match action {
+                    crate::handlers::bulwark::plugin::types::Action::Allow => Self::Allow,
+                    crate::handlers::bulwark::plugin::types::Action::Block(block) => Self::Block {
+                        status: block.status,
+                        headers: block.headers,
+                    },
+                    crate::handlers::bulwark::plugin::types::Action::Redirect(location) => {
+                        Self::Redirect { location }
+                    }
+                    crate::handlers::bulwark::plugin::types::Action::Tarpit(delay) => {
+                        Self::Tarpit { delay }
+                    }
+                    crate::handlers::bulwark::plugin::types::Action::AddHeader((name, value)) => {
+                        Self::AddHeader { name, value }
+                    }
+                }
+            }
+        }
+
+        impl From<::bulwark_sdk::Action> for crate::handlers::bulwark::plugin::types::Action {
+            fn from(action: ::bulwark_sdk::Action) -> Self {
+                match action {
+                    ::bulwark_sdk::Action::Allow => Self::Allow,
+                    ::bulwark_sdk::Action::Block { status, headers } => {
+                        Self::Block(crate::handlers::bulwark::plugin::types::BlockAction {
+                            status,
+                            headers,
+                        })
+                    }
+                    ::bulwark_sdk::Action::Redirect { location } => Self::Redirect(location),
+                    ::bulwark_sdk::Action::Tarpit { delay } => Self::Tarpit(delay),
+                    ::bulwark_sdk::Action::AddHeader { name, value } => {
+                        Self::AddHeader((name, value))
+                    }
+                }
+            }
+        }
+
         impl From<crate::handlers::exports::bulwark::plugin::http_handlers::HandlerOutput> for ::bulwark_sdk::HandlerOutput {
             fn from(handler_output: crate::handlers::exports::bulwark::plugin::http_handlers::HandlerOutput) -> Self {
-                Self {
-                    labels: handler_output.labels.iter().cloned().collect(),
-                    decision: handler_output.decision.into(),
-                    tags: handler_output.tags.clone(),
-                }
+                // The SDK's output can't be built with a struct expression outside of the SDK, since it may grow new fields.
+                let mut output = ::bulwark_sdk::HandlerOutput::default();
+                output.labels = handler_output.labels.iter().cloned().collect();
+                output.decision = handler_output.decision.into();
+                output.tags = handler_output.tags.clone();
+                output.action = handler_output.action.map(|action| action.into());
+                output
             }
         }
 
@@ -252,6 +316,7 @@ pub fn bulwark_plugin(_: TokenStream, input: TokenStream) -> TokenStream {
                     labels: handler_output.labels.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
                     decision: handler_output.decision.into(),
                     tags: handler_output.tags.clone(),
+                    action: handler_output.action.map(|action| action.into()),
                 }
             }
         }
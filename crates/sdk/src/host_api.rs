@@ -25,7 +25,11 @@ pub type Response = http::Response<Bytes>;
 // TODO: or hyper::Request<HyperIncomingBody> to match WasiHttpView's new_incoming_request?
 
 /// A `HandlerOutput` represents a decision and associated output for a single handler within a single detection.
+///
+/// New fields may be added as the host gains features, so construct it with [`HandlerOutput::default`] and set the
+/// fields that are needed.
 #[derive(Clone, Default)]
+#[non_exhaustive]
 pub struct HandlerOutput {
     /// The `labels` field contains key/value pairs used to enrich the request with additional information.
     pub labels: HashMap<String, String>,
@@ -33,6 +37,77 @@ pub struct HandlerOutput {
     pub decision: Decision,
     /// The `tags` value represents the new tags to annotate the request with.
     pub tags: Vec<String>,
+    /// The `action` value requests a specific action from the host, in addition to the `decision`.
+    ///
+    /// The host takes the strictest of the actions requested by each plugin and the action configured for
+    /// the request's outcome.
+    pub action: Option<Action>,
+}
+
+/// An `Action` is a specific way of handling the request that a handler may ask the host for.
+///
+/// From least to most strict, the actions are [`Allow`](Action::Allow), [`Tarpit`](Action::Tarpit),
+/// [`Redirect`](Action::Redirect), and [`Block`](Action::Block). [`AddHeader`](Action::AddHeader) is applied
+/// whenever the request is sent to the interior service, regardless of the other actions.
+///
+/// # Example
+///
+#[cfg_attr(doctest, doc = " ````no_test")]
+/// ```rust
+/// use bulwark_sdk::*;
+/// use std::collections::HashMap;
+///
+/// struct RateLimiter;
+///
+/// #[bulwark_plugin]
+/// impl HttpHandlers for RateLimiter {
+///     fn handle_request_decision(
+///         req: Request,
+///         _labels: HashMap<String, String>,
+///     ) -> Result<HandlerOutput, Error> {
+///         let mut output = HandlerOutput::default();
+///         if let Some(ip) = client_ip(&req) {
+///             let rate = redis::incr_rate_limit(format!("ip:{}", ip), 1, 60)?;
+///             if rate.attempts > 100 {
+///                 output.decision = RESTRICT;
+///                 output.action = Some(Action::Block {
+///                     status: 429,
+///                     headers: vec![("Retry-After".to_string(), "60".to_string())],
+///                 });
+///             }
+///         }
+///         Ok(output)
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Sends the request to the interior service.
+    Allow,
+    /// Sends the configured block response with a different status code and additional headers.
+    Block {
+        /// The HTTP status code of the block response. Codes outside of `400..=599` are ignored.
+        status: u16,
+        /// Additional headers to send with the block response.
+        headers: Vec<(String, String)>,
+    },
+    /// Redirects the client to another URL, e.g. a challenge page.
+    Redirect {
+        /// The URL sent in the `Location` header.
+        location: String,
+    },
+    /// Delays the request before sending it to the interior service.
+    Tarpit {
+        /// The delay in milliseconds.
+        delay: u32,
+    },
+    /// Adds a header to the request sent to the interior service.
+    AddHeader {
+        /// The header name.
+        name: String,
+        /// The header value.
+        value: String,
+    },
 }
 
 /// A `Verdict` represents a combined decision across multiple detections.
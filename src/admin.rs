@@ -1,8 +1,13 @@
 use super::*;
 
+use crate::reload::Reloader;
+
+use cadence::{BufferedUdpMetricSink, MetricSink, QueuingMetricSink, StatsdClient};
 use http::{HeaderMap, HeaderValue};
 pub(super) use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use metrics_exporter_statsd::{StatsdError, StatsdRecorder};
 use std::fmt;
+use std::time::{Duration, Instant};
 
 /// Axum state for the admin service.
 pub(super) struct AdminState {
@@ -10,6 +15,10 @@ pub(super) struct AdminState {
     pub health: HealthState,
     /// State for the metrics endpoint
     pub metrics: MetricsState,
+    /// Reloads the primary service's config, once the primary service has been created
+    pub reloader: Option<Arc<Reloader>>,
+    /// The primary service's processor, once it has been created, used to check that its plugins initialized
+    pub processor: Option<BulwarkProcessor>,
 }
 
 /// The health state structure tracks the health of the primary service, primarily for the benefit of
@@ -29,8 +38,9 @@ pub(super) struct HealthState {
     pub started: bool,
     /// Indicates that the primary service has successfully initialized and is ready to receive requests.
     ///
-    /// Once true, it will remain true for the lifetime of the process.
-    /// While semantically different from startup state, it's implementation logic is identical.
+    /// This becomes false again once the process begins shutting down, so that load balancers stop sending it
+    /// new traffic while in-flight requests drain. It's also reported as false while any plugin's `init` function
+    /// is failing.
     pub ready: bool,
 }
 
@@ -56,17 +66,21 @@ pub(super) async fn probe_handler(
     Path(probe): Path<String>,
 ) -> (StatusCode, Json<HealthState>) {
     let state = state.lock().expect("poisoned mutex");
+    let mut health = state.health;
+    if let Some(processor) = &state.processor {
+        health.ready = health.ready && processor.plugins_initialized();
+    }
     let status = match probe.as_str() {
         "live" => StatusCode::OK,
         "started" => {
-            if state.health.started {
+            if health.started {
                 StatusCode::OK
             } else {
                 StatusCode::SERVICE_UNAVAILABLE
             }
         }
         "ready" => {
-            if state.health.ready {
+            if health.ready {
                 StatusCode::OK
             } else {
                 StatusCode::SERVICE_UNAVAILABLE
@@ -75,7 +89,7 @@ pub(super) async fn probe_handler(
         // hint that the wrong probe value was sent
         _ => StatusCode::NOT_FOUND,
     };
-    (status, Json(state.health))
+    (status, Json(health))
 }
 
 /// The metrics handler is a crawlable endpoint that returns Prometheus metrics.
@@ -108,17 +122,21 @@ pub(super) async fn metrics_handler(
 #[derive(Clone)]
 pub(super) struct MetricsState {
     prometheus_handle: Option<PrometheusHandle>,
+    /// Flushes buffered StatsD metrics, if StatsD is in use
+    pub statsd_flusher: Option<Arc<StatsdFlusher>>,
     // collect: Arc<dyn Fn() + Send + Sync + 'static>,
 }
 
 impl MetricsState {
-    /// Creates a new [`MetricsState`] with a Prometheus recorder.
+    /// Creates a new [`MetricsState`] with either a Prometheus recorder or a StatsD flusher.
     pub(super) fn new(
         prometheus_handle: Option<PrometheusHandle>,
+        statsd_flusher: Option<Arc<StatsdFlusher>>,
         // collect: impl Fn() + Send + Sync + 'static,
     ) -> Self {
         Self {
             prometheus_handle,
+            statsd_flusher,
             // collect: Arc::new(collect),
         }
     }
@@ -135,6 +153,80 @@ impl fmt::Debug for MetricsState {
     }
 }
 
+/// The `StatsdFlusher` sends StatsD metrics that are still queued or buffered, so that they aren't lost on shutdown.
+///
+/// The StatsD exporter doesn't expose its sinks, so [`build_statsd_recorder`] assembles the same sinks itself and
+/// keeps handles to them here.
+pub(super) struct StatsdFlusher {
+    queue: QueuingMetricSink,
+    buffer: Arc<BufferedUdpMetricSink>,
+}
+
+impl StatsdFlusher {
+    /// Waits for queued metrics to reach the UDP buffer, then sends the buffer.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The maximum amount of time to wait for the queue to empty.
+    pub(super) async fn flush(&self, timeout: Duration) -> std::io::Result<()> {
+        let deadline = Instant::now() + timeout;
+        while self.queue.queued() > 0 && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        self.buffer.flush()
+    }
+}
+
+/// Forwards metrics to a UDP sink that's shared with a [`StatsdFlusher`].
+struct SharedUdpMetricSink(Arc<BufferedUdpMetricSink>);
+
+impl MetricSink for SharedUdpMetricSink {
+    fn emit(&self, metric: &str) -> std::io::Result<usize> {
+        self.0.emit(metric)
+    }
+
+    fn flush(&self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Builds a StatsD recorder equivalent to [`metrics_exporter_statsd::StatsdBuilder`]'s, with histograms sent as
+/// distributions, along with a [`StatsdFlusher`] for its buffers.
+///
+/// # Arguments
+///
+/// * `metrics` - The metrics configuration, with a StatsD host set.
+pub(super) fn build_statsd_recorder(
+    metrics: &bulwark_config::Metrics,
+) -> Result<(StatsdRecorder, StatsdFlusher), StatsdError> {
+    let host = metrics.statsd_host.as_deref().unwrap_or_default();
+    let port = metrics.statsd_port.unwrap_or(8125);
+    if host.trim().is_empty() {
+        return Err(StatsdError::InvalidHost);
+    }
+    if port == 0 {
+        return Err(StatsdError::InvalidPortZero);
+    }
+
+    let socket = std::net::UdpSocket::bind("0.0.0.0:0")?;
+    socket.set_nonblocking(true)?;
+    let buffer = Arc::new(BufferedUdpMetricSink::with_capacity(
+        (host, port),
+        socket,
+        metrics.statsd_buffer_size,
+    )?);
+    let queue = QueuingMetricSink::with_capacity(
+        SharedUdpMetricSink(buffer.clone()),
+        metrics.statsd_queue_size,
+    );
+    let client = StatsdClient::from_sink(&metrics.statsd_prefix, queue.clone());
+
+    Ok((
+        StatsdRecorder::new(client, "distribution".into()),
+        StatsdFlusher { queue, buffer },
+    ))
+}
+
 /// Checks if the incoming request advertises support for gzip compression.
 #[allow(dead_code)]
 fn accepts_gzip(headers: &http::header::HeaderMap) -> bool {
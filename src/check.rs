@@ -0,0 +1,158 @@
+//! Offline validation of a Bulwark configuration file and every plugin it references.
+//!
+//! The `check` subcommand performs the same loading steps the service would perform at startup, without
+//! binding any ports or connecting to Redis, and reports every problem it finds rather than stopping at the first.
+
+use {
+    bulwark_host::{Plugin, PluginCtx, PluginEngine, PluginInstance, RedisCtx, ScriptRegistry},
+    std::{
+        collections::HashMap,
+        fmt::{Display, Formatter},
+        path::Path,
+        sync::Arc,
+    },
+};
+
+/// The result of a single validation step.
+pub struct CheckEntry {
+    /// A description of what was checked, e.g. `plugin 'evil_bit'`.
+    pub subject: String,
+    /// The error message if the check failed.
+    pub error: Option<String>,
+}
+
+/// The collected results of validating a configuration file.
+#[derive(Default)]
+pub struct CheckReport {
+    pub entries: Vec<CheckEntry>,
+}
+
+impl CheckReport {
+    /// Records a check result.
+    fn record<E: Display>(&mut self, subject: impl Into<String>, result: Result<(), E>) {
+        self.entries.push(CheckEntry {
+            subject: subject.into(),
+            error: result.err().map(|err| err.to_string()),
+        });
+    }
+
+    /// Returns the number of checks that failed.
+    pub fn failures(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| entry.error.is_some())
+            .count()
+    }
+
+    /// Returns true if every check succeeded.
+    pub fn passed(&self) -> bool {
+        self.failures() == 0
+    }
+}
+
+impl Display for CheckReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for entry in &self.entries {
+            match &entry.error {
+                None => writeln!(f, "  ok  {}", entry.subject)?,
+                Some(error) => writeln!(f, "FAIL  {}: {}", entry.subject, error)?,
+            }
+        }
+        let failures = self.failures();
+        if failures == 0 {
+            write!(f, "\n{} checks passed", self.entries.len())
+        } else {
+            write!(f, "\n{} of {} checks failed", failures, self.entries.len())
+        }
+    }
+}
+
+/// Validates a configuration file and all of the plugins it references.
+///
+/// Loads and resolves the configuration, checks the resource routes, then compiles every
+/// plugin and verifies that it can be instantiated as the `bulwark:plugin/http-detection` world.
+///
+/// # Arguments
+///
+/// * `config_path` - The path to the root configuration file.
+/// * `unlocked` - True if the lockfile alongside the configuration file should be ignored.
+pub async fn check_config(config_path: &Path, unlocked: bool) -> CheckReport {
+    let mut report = CheckReport::default();
+
+    let config = match super::load_config(config_path, unlocked) {
+        Ok(config) => {
+            report.record::<String>(format!("config '{}'", config_path.display()), Ok(()));
+            config
+        }
+        Err(err) => {
+            report.record(format!("config '{}'", config_path.display()), Err(err));
+            return report;
+        }
+    };
+
+    for preset in &config.presets {
+        report.record(
+            format!("preset '{}'", preset.reference),
+            preset.resolve_plugins(&config).map(|_| ()),
+        );
+    }
+
+    if config.resources.is_empty() {
+        report.record("resources", Err("at least one resource required"));
+    }
+    // The service silently skips routes that conflict with earlier resources, so surface those here.
+    let mut router: matchit::Router<()> = matchit::Router::new();
+    for resource in &config.resources {
+        let subject = format!("resource {:?}", resource.routes);
+        let result = resource
+            .resolve_plugins(&config)
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+            .and_then(|_| {
+                resource
+                    .routes
+                    .iter()
+                    .try_for_each(|route| router.insert(route, ()))
+                    .map_err(|err| err.to_string())
+            });
+        report.record(subject, result);
+    }
+
+    let redis_ctx = RedisCtx {
+        pool: None,
+        registry: Arc::new(ScriptRegistry::default()),
+    };
+    match PluginEngine::new(&config) {
+        Ok(engine) => {
+            for plugin_config in &config.plugins {
+                report.record(
+                    format!("plugin '{}'", plugin_config.reference),
+                    check_plugin(&engine, &config, plugin_config, &redis_ctx).await,
+                );
+            }
+        }
+        Err(err) => report.record("engine", Err(err.to_string())),
+    }
+
+    report
+}
+
+/// Compiles a plugin and instantiates it once to verify that its imports and exports match the host's world.
+async fn check_plugin(
+    engine: &PluginEngine,
+    config: &bulwark_config::Config,
+    plugin_config: &bulwark_config::Plugin,
+    redis_ctx: &RedisCtx,
+) -> Result<(), String> {
+    let plugin = Arc::new(
+        Plugin::from_engine(engine, config, plugin_config)
+            .await
+            .map_err(|err| err.to_string())?,
+    );
+    let plugin_ctx = PluginCtx::new(plugin.clone(), HashMap::new(), redis_ctx.clone())
+        .map_err(|err| err.to_string())?;
+    PluginInstance::new(plugin, plugin_ctx)
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(())
+}
@@ -9,9 +9,37 @@ pub enum CliArgumentError {
 #[derive(thiserror::Error, Debug)]
 pub enum ServiceError {
     #[error("error starting envoy external processor service: {0}")]
-    ExtProcessorService(#[from] tonic::transport::Error),
+    ExtProcessorService(#[from] GrpcServiceError),
+    #[error("error starting envoy external authorization service: {0}")]
+    ExtAuthzService(GrpcServiceError),
+    #[error("error starting reverse proxy service: {0}")]
+    ReverseProxyService(std::io::Error),
     #[error("error starting admin service: {0}")]
     AdminService(#[from] std::io::Error),
+    #[error("error listening for reload signal: {0}")]
+    ReloadSignal(std::io::Error),
+    #[error("error watching config for changes: {0}")]
+    ConfigWatch(#[from] notify::Error),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum GrpcServiceError {
+    #[error(transparent)]
+    Transport(#[from] tonic::transport::Error),
+    #[error("could not read TLS file '{0}': {1}")]
+    TlsFile(std::path::PathBuf, std::io::Error),
+    #[error("could not listen on unix socket '{0}': {1}")]
+    UnixSocket(std::path::PathBuf, std::io::Error),
+    #[error("unix socket '{0}' is already in use by another process")]
+    UnixSocketInUse(std::path::PathBuf),
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReloadError {
+    #[error("could not load config: {0}")]
+    Config(#[from] bulwark_config::ConfigFileError),
+    #[error("could not load plugins: {0}")]
+    PluginLoad(#[from] bulwark_host::PluginLoadError),
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -26,3 +54,15 @@ pub enum MetricsError {
 
 #[derive(thiserror::Error, Debug)]
 pub enum AdminServiceError {}
+
+#[derive(thiserror::Error, Debug)]
+pub enum FixtureError {
+    #[error("could not read fixture '{0}': {1}")]
+    IO(std::path::PathBuf, std::io::Error),
+    #[error("could not parse fixture '{0}': {1}")]
+    Deserialization(std::path::PathBuf, toml::de::Error),
+    #[error("invalid fixture case '{0}': {1}")]
+    InvalidCase(String, String),
+    #[error(transparent)]
+    Evaluation(#[from] bulwark_ext_processor::EvaluationError),
+}
@@ -0,0 +1,295 @@
+//! Declarative request fixtures that can be evaluated against a Bulwark configuration.
+//!
+//! A fixture file is a TOML document containing one or more `[[case]]` tables. Each case describes a request,
+//! an optional response from the interior service, and the expected outcome and tags:
+//!
+//! ```toml
+//! [[case]]
+//! name = "evil requests are restricted"
+//! request = { method = "GET", uri = "/", headers = { Evil = "true" } }
+//! expect = { outcome = "restricted", tags = ["evil"] }
+//! ```
+
+use {
+    crate::errors::FixtureError,
+    bulwark_ext_processor::{BulwarkProcessor, Evaluation},
+    bulwark_sdk::Outcome,
+    serde::Deserialize,
+    std::{
+        collections::BTreeMap,
+        fmt::{Display, Formatter},
+        path::{Path, PathBuf},
+        str::FromStr,
+    },
+};
+
+/// The TOML serialization for a fixture file.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct FixtureFile {
+    #[serde(rename = "case", default)]
+    cases: Vec<Case>,
+}
+
+/// A single request/response pair and its expected result.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct Case {
+    name: String,
+    request: FixtureRequest,
+    response: Option<FixtureResponse>,
+    #[serde(default)]
+    expect: Expectation,
+}
+
+/// The TOML serialization for a fixture request.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct FixtureRequest {
+    #[serde(default = "default_method")]
+    method: String,
+    uri: String,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    #[serde(default)]
+    body: String,
+}
+
+/// The default fixture request method.
+fn default_method() -> String {
+    String::from("GET")
+}
+
+/// The TOML serialization for a fixture response.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct FixtureResponse {
+    #[serde(default = "default_status")]
+    status: u16,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    #[serde(default)]
+    body: String,
+}
+
+/// The default fixture response status.
+fn default_status() -> u16 {
+    200
+}
+
+/// The expected result of evaluating a fixture.
+///
+/// Tags are matched as a subset, so a case only needs to list the tags it cares about.
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct Expectation {
+    outcome: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+impl FixtureRequest {
+    fn to_request(&self) -> Result<bulwark_sdk::Request, http::Error> {
+        let mut builder = http::Request::builder()
+            .method(self.method.as_str())
+            .uri(self.uri.as_str())
+            .version(http::Version::HTTP_11);
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+        builder.body(bytes::Bytes::from(self.body.clone()))
+    }
+}
+
+impl FixtureResponse {
+    fn to_response(&self) -> Result<bulwark_sdk::Response, http::Error> {
+        let mut builder = http::Response::builder()
+            .status(self.status)
+            .version(http::Version::HTTP_11);
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+        builder.body(bytes::Bytes::from(self.body.clone()))
+    }
+}
+
+/// The result of evaluating a single fixture case.
+pub struct CaseResult {
+    /// The fixture file the case was loaded from.
+    pub path: PathBuf,
+    /// The name of the case.
+    pub name: String,
+    /// Descriptions of every expectation that was not met.
+    pub failures: Vec<String>,
+    /// The evaluation, if a resource matched the request.
+    pub evaluation: Option<Evaluation>,
+}
+
+/// The collected results of evaluating one or more fixture files.
+#[derive(Default)]
+pub struct FixtureReport {
+    pub results: Vec<CaseResult>,
+}
+
+impl FixtureReport {
+    /// Returns the number of cases that failed.
+    pub fn failures(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|result| !result.failures.is_empty())
+            .count()
+    }
+
+    /// Returns true if every case met its expectations.
+    pub fn passed(&self) -> bool {
+        self.failures() == 0
+    }
+}
+
+impl Display for FixtureReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for result in &self.results {
+            if result.failures.is_empty() {
+                writeln!(f, "test {} ... ok", result.name)?;
+                continue;
+            }
+            writeln!(f, "test {} ... FAILED", result.name)?;
+            writeln!(f, "    fixture: {}", result.path.display())?;
+            for failure in &result.failures {
+                writeln!(f, "    {}", failure)?;
+            }
+            if let Some(evaluation) = &result.evaluation {
+                let verdict = &evaluation.verdict;
+                writeln!(
+                    f,
+                    "    combined: score={:.3} outcome={} tags=[{}]",
+                    verdict.decision.pignistic().restrict,
+                    verdict.outcome,
+                    sorted(verdict.tags.iter()).join(", "),
+                )?;
+                let mut plugin_outputs: Vec<_> = evaluation.plugin_outputs.iter().collect();
+                plugin_outputs.sort_by(|a, b| a.0.cmp(b.0));
+                for (reference, output) in plugin_outputs {
+                    match output {
+                        Some(output) => writeln!(
+                            f,
+                            "    plugin '{}': accept={:.3} restrict={:.3} unknown={:.3} score={:.3} tags=[{}]",
+                            reference,
+                            output.decision.accept,
+                            output.decision.restrict,
+                            output.decision.unknown,
+                            output.decision.pignistic().restrict,
+                            sorted(output.tags.iter()).join(", "),
+                        )?,
+                        None => writeln!(f, "    plugin '{}': no decision", reference)?,
+                    }
+                }
+            }
+        }
+        let failures = self.failures();
+        write!(
+            f,
+            "\ntest result: {}. {} passed; {} failed",
+            if failures == 0 { "ok" } else { "FAILED" },
+            self.results.len() - failures,
+            failures
+        )
+    }
+}
+
+/// Sorts tags so that report output is stable.
+fn sorted<'a>(tags: impl Iterator<Item = &'a String>) -> Vec<&'a str> {
+    let mut tags: Vec<&str> = tags.map(|tag| tag.as_str()).collect();
+    tags.sort_unstable();
+    tags
+}
+
+/// Evaluates every case in the given fixture files against a processor.
+///
+/// # Arguments
+///
+/// * `processor` - The processor to evaluate requests with.
+/// * `fixture_paths` - The fixture files to load.
+pub async fn run_fixtures(
+    processor: &BulwarkProcessor,
+    fixture_paths: &[PathBuf],
+) -> Result<FixtureReport, FixtureError> {
+    let mut report = FixtureReport::default();
+    for path in fixture_paths {
+        for case in load_fixture(path)? {
+            report.results.push(run_case(processor, path, case).await?);
+        }
+    }
+    Ok(report)
+}
+
+/// Loads and parses a fixture file.
+fn load_fixture(path: &Path) -> Result<Vec<Case>, FixtureError> {
+    let contents =
+        std::fs::read_to_string(path).map_err(|err| FixtureError::IO(path.to_path_buf(), err))?;
+    let fixture: FixtureFile = toml::from_str(&contents)
+        .map_err(|err| FixtureError::Deserialization(path.to_path_buf(), err))?;
+    Ok(fixture.cases)
+}
+
+/// Evaluates a single case and compares the result against its expectations.
+async fn run_case(
+    processor: &BulwarkProcessor,
+    path: &Path,
+    case: Case,
+) -> Result<CaseResult, FixtureError> {
+    let invalid_case = |err: String| FixtureError::InvalidCase(case.name.clone(), err);
+    let expected_outcome = case
+        .expect
+        .outcome
+        .as_ref()
+        .map(|outcome| {
+            Outcome::from_str(outcome)
+                .map_err(|_| invalid_case(format!("unknown outcome '{}'", outcome)))
+        })
+        .transpose()?;
+    let request = case
+        .request
+        .to_request()
+        .map_err(|err| invalid_case(err.to_string()))?;
+    let response = case
+        .response
+        .as_ref()
+        .map(|response| response.to_response())
+        .transpose()
+        .map_err(|err| invalid_case(err.to_string()))?;
+
+    let evaluation = processor.evaluate(request, response).await?;
+
+    let mut failures = Vec::new();
+    match &evaluation {
+        None => failures.push(String::from("no resource matched request")),
+        Some(evaluation) => {
+            if let Some(expected_outcome) = expected_outcome {
+                if evaluation.verdict.outcome != expected_outcome {
+                    failures.push(format!(
+                        "expected outcome '{}', got '{}'",
+                        expected_outcome, evaluation.verdict.outcome
+                    ));
+                }
+            }
+            let missing_tags: Vec<&str> = case
+                .expect
+                .tags
+                .iter()
+                .filter(|tag| !evaluation.verdict.tags.contains(tag))
+                .map(|tag| tag.as_str())
+                .collect();
+            if !missing_tags.is_empty() {
+                failures.push(format!("missing tags: [{}]", missing_tags.join(", ")));
+            }
+        }
+    }
+
+    Ok(CaseResult {
+        path: path.to_path_buf(),
+        name: case.name,
+        failures,
+        evaluation,
+    })
+}
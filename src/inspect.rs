@@ -0,0 +1,105 @@
+//! Inspection of a compiled plugin's interface with the host.
+//!
+//! The `inspect` subcommand describes what a plugin component imports and exports, and which of its handlers do any
+//! work, so that a plugin built against an incompatible SDK can be diagnosed without reading an instantiation error.
+
+use {
+    bulwark_host::{PluginInspection, WorldVersion},
+    std::{
+        fmt::{Display, Formatter},
+        path::{Path, PathBuf},
+    },
+};
+
+/// The description of a plugin, along with any reasons the host would fail to load it.
+pub struct InspectReport {
+    /// The path the plugin was read from.
+    pub path: PathBuf,
+    /// The plugin's interface with the host.
+    pub inspection: PluginInspection,
+}
+
+impl InspectReport {
+    /// Returns a warning for each reason the host would fail to load the plugin.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = vec![];
+        match &self.inspection.world_version {
+            Some(version) => match WorldVersion::from_version(version) {
+                Some(world) if world.is_deprecated() => warnings.push(format!(
+                    "plugin targets bulwark:plugin@{}, which is deprecated; rebuild it against {}",
+                    version,
+                    WorldVersion::CURRENT
+                )),
+                Some(_) => {}
+                None => warnings.push(format!(
+                    "plugin targets bulwark:plugin@{}, but the host supports {}",
+                    version,
+                    WorldVersion::SUPPORTED
+                        .iter()
+                        .map(|world| world.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )),
+            },
+            None => warnings.push(String::from("plugin doesn't use the bulwark:plugin world")),
+        }
+        for import in &self.inspection.unsatisfied_imports {
+            warnings.push(format!(
+                "plugin imports `{}`, which the host doesn't provide: {}",
+                import.name, import.reason
+            ));
+        }
+        warnings
+    }
+}
+
+impl Display for InspectReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let inspection = &self.inspection;
+        writeln!(f, "{}", self.path.display())?;
+        writeln!(f, "  size:     {} bytes", inspection.size)?;
+        writeln!(f, "  sha256:   {}", hex::encode(inspection.digest))?;
+        match &inspection.world_version {
+            Some(version) => writeln!(f, "  world:    bulwark:plugin@{}", version)?,
+            None => writeln!(f, "  world:    none")?,
+        }
+        match &inspection.handlers {
+            Some(handlers) if handlers.is_empty() => writeln!(f, "  handlers: none")?,
+            Some(handlers) => writeln!(f, "  handlers: {}", handlers.join(", "))?,
+            None => writeln!(f, "  handlers: unknown, not recorded by the plugin's SDK")?,
+        }
+        for (heading, interfaces) in [
+            ("imports", &inspection.imports),
+            ("exports", &inspection.exports),
+        ] {
+            writeln!(f, "\n{}:", heading)?;
+            for interface in interfaces {
+                writeln!(f, "  {}", interface.name)?;
+                for function in &interface.functions {
+                    writeln!(f, "    {}", function)?;
+                }
+            }
+        }
+        let warnings = self.warnings();
+        if !warnings.is_empty() {
+            writeln!(f)?;
+        }
+        for warning in warnings {
+            writeln!(f, "warning: {}", warning)?;
+        }
+        Ok(())
+    }
+}
+
+/// Inspects a compiled plugin.
+///
+/// # Arguments
+///
+/// * `plugin_path` - The path to the plugin's `*.wasm` file.
+pub fn inspect_plugin(plugin_path: &Path) -> Result<InspectReport, Box<dyn std::error::Error>> {
+    let bytes = std::fs::read(plugin_path)?;
+    Ok(InspectReport {
+        path: plugin_path.to_path_buf(),
+        inspection: PluginInspection::new(&bytes)?,
+    })
+}
@@ -0,0 +1,40 @@
+//! Generation of the lockfile that pins the contents of every plugin in a Bulwark configuration file.
+//!
+//! The `lock` subcommand reads each plugin the same way the service would, including remote plugins, and records
+//! its digest, so that later loads of the configuration reject any plugin whose contents have changed.
+
+use {
+    bulwark_config::{Lockfile, PluginVerification},
+    sha2::{Digest, Sha256},
+    std::path::{Path, PathBuf},
+};
+
+/// Writes a lockfile alongside a configuration file, pinning the current contents of each of its plugins.
+///
+/// Any existing lockfile is ignored while the configuration is loaded, and then replaced. Returns the path the
+/// lockfile was written to, along with the lockfile itself.
+///
+/// # Arguments
+///
+/// * `config_path` - The path to the root configuration file.
+pub async fn lock_config(
+    config_path: &Path,
+) -> Result<(PathBuf, Lockfile), Box<dyn std::error::Error>> {
+    let config = bulwark_config::toml::load_unlocked_config(config_path)?;
+    let mut lockfile = Lockfile::default();
+    for plugin in &config.plugins {
+        // Signed plugins can be upgraded without changing the configuration, so pinning them would defeat the point.
+        if let PluginVerification::Ed25519 { .. } = plugin.verification {
+            continue;
+        }
+        let bytes = bulwark_host::fetch_plugin(&config, plugin).await?;
+        lockfile.plugins.insert(
+            plugin.reference.clone(),
+            bytes::Bytes::from(Sha256::digest(&bytes).to_vec()),
+        );
+    }
+
+    let lockfile_path = Lockfile::path_for(config_path);
+    lockfile.write(&lockfile_path)?;
+    Ok((lockfile_path, lockfile))
+}
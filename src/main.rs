@@ -1,31 +1,40 @@
 pub mod admin;
+pub mod check;
 pub mod ecs;
 pub mod errors;
+pub mod fixtures;
+pub mod inspect;
+pub mod lock;
+pub mod reload;
+pub mod shutdown;
+pub mod vendor;
 
 use {
     crate::admin::{AdminState, HealthState, MetricsState},
+    crate::reload::Reloader,
     axum::{
-        extract::Path, extract::State, http::StatusCode, response::Json, routing::get, Router,
-        ServiceExt,
+        extract::Path, extract::State, http::StatusCode, response::Json, routing::get,
+        routing::post, Router, ServiceExt,
     },
+    bulwark_ext_processor::protobuf::envoy::service::auth::v3::authorization_server::AuthorizationServer,
     bulwark_ext_processor::protobuf::envoy::service::ext_proc::v3::external_processor_server::ExternalProcessorServer,
-    bulwark_ext_processor::BulwarkProcessor,
+    bulwark_ext_processor::{BulwarkProcessor, BulwarkProxy},
     clap::{Parser, Subcommand},
     color_eyre::eyre::Result,
     errors::*,
     metrics_exporter_prometheus::Matcher,
-    metrics_exporter_statsd::StatsdBuilder,
     serde::Serialize,
-    std::net::{IpAddr, Ipv4Addr, SocketAddr},
     std::{
+        collections::BTreeMap,
         path::PathBuf,
         sync::{Arc, Mutex},
+        time::Duration,
     },
     tokio::task::JoinSet,
-    tonic::transport::Server,
+    tonic::transport::{Certificate, Identity, Server, ServerTlsConfig},
     tower_http::normalize_path::NormalizePathLayer,
     tower_layer::Layer,
-    tracing::error,
+    tracing::{error, warn},
     tracing_forest::ForestLayer,
     tracing_log::LogTracer,
     tracing_subscriber::layer::SubscriberExt,
@@ -39,7 +48,7 @@ use {
 struct Cli {
     /// Log levels: error, warn, info, debug, trace
     ///
-    /// Default is "info".
+    /// Default is "info", or "warn" for the check and test subcommands.
     #[arg(short = 'l', long)]
     log_level: Option<String>,
 
@@ -49,6 +58,12 @@ struct Cli {
     #[arg(short = 'f', long)]
     log_format: Option<String>,
 
+    /// Ignores the bulwark.lock file alongside the config file
+    ///
+    /// By default, plugins that are missing from the lockfile or don't match it are rejected.
+    #[arg(long, global = true)]
+    unlocked: bool,
+
     #[command(subcommand)]
     command: Option<Command>,
 }
@@ -61,10 +76,69 @@ enum Command {
         /// Sets a custom config file
         #[arg(short, long, value_name = "FILE")]
         config: PathBuf,
+
+        /// Reloads the config whenever the config file or a local plugin changes
+        #[arg(short, long)]
+        watch: bool,
+    },
+    /// Launch as an Envoy external authorization service
+    ExtAuthz {
+        /// Sets a custom config file
+        #[arg(short, long, value_name = "FILE")]
+        config: PathBuf,
+
+        /// Reloads the config whenever the config file or a local plugin changes
+        #[arg(short, long)]
+        watch: bool,
+    },
+    /// Launch as a standalone reverse proxy
+    ReverseProxy {
+        /// Sets a custom config file
+        #[arg(short, long, value_name = "FILE")]
+        config: PathBuf,
+
+        /// Reloads the config whenever the config file or a local plugin changes
+        #[arg(short, long)]
+        watch: bool,
+    },
+    /// Validate a config file and its plugins without launching a service
+    Check {
+        /// Sets a custom config file
+        #[arg(short, long, value_name = "FILE")]
+        config: PathBuf,
+    },
+    /// Evaluate request fixtures against a config file
+    Test {
+        /// Sets a custom config file
+        #[arg(short, long, value_name = "FILE")]
+        config: PathBuf,
+
+        /// The fixture files to evaluate
+        #[arg(required = true, value_name = "FIXTURE")]
+        fixtures: Vec<PathBuf>,
+    },
+    /// Pin the contents of every plugin in a config file in a bulwark.lock file alongside it
+    Lock {
+        /// Sets a custom config file
+        #[arg(short, long, value_name = "FILE")]
+        config: PathBuf,
+    },
+    /// Copy a config file, its includes, and its plugins into a directory that can be deployed offline
+    Vendor {
+        /// Sets a custom config file
+        #[arg(short, long, value_name = "FILE")]
+        config: PathBuf,
+
+        /// The directory to write the vendored config to
+        #[arg(value_name = "DIR")]
+        output: PathBuf,
+    },
+    /// Describe a compiled plugin's imports, exports, and handlers, and warn about anything the host can't provide
+    Inspect {
+        /// The plugin's WASM file
+        #[arg(value_name = "FILE")]
+        plugin: PathBuf,
     },
-    // TODO: Implement ReverseProxy subcommand
-    // TODO: Implement Check subcommand
-    // TODO: Implement Test subcommand
     /// Compile a Bulwark plugin
     Build {
         /// Sets the input directory for the build.
@@ -77,6 +151,13 @@ enum Command {
         /// Default is `./dist/name_of_plugin.wasm`.
         #[arg(short, long, value_name = "FILE")]
         output: Option<PathBuf>,
+        /// Also precompiles the plugin for the engine configured by this config file.
+        ///
+        /// The precompiled plugin is written alongside the output file with a `.pwasm` extension, and is loaded in
+        /// place of compiling the plugin when it starts, as long as the engine configuration hasn't changed and the
+        /// config sets `trust_precompiled`.
+        #[arg(long, value_name = "FILE")]
+        precompile: Option<PathBuf>,
 
         /// Additional arguments passed through to the compiler.
         #[arg(last = true)]
@@ -106,7 +187,19 @@ fn init_tracing(cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
 
     LogTracer::init().expect("log tracer init failed");
 
-    let log_level: &str = cli.log_level.as_ref().map_or("info", |ll| ll.as_str());
+    // Reporting subcommands print their own output, so only log problems by default.
+    let default_log_level = match cli.command {
+        Some(Command::Check { .. })
+        | Some(Command::Test { .. })
+        | Some(Command::Lock { .. })
+        | Some(Command::Vendor { .. })
+        | Some(Command::Inspect { .. }) => "warn",
+        _ => "info",
+    };
+    let log_level: &str = cli
+        .log_level
+        .as_ref()
+        .map_or(default_log_level, |ll| ll.as_str());
     let log_format: &str = cli.log_format.as_ref().map_or("ecs", |lf| lf.as_str());
     let mut ecs_layer = None;
     let mut forest_layer = None;
@@ -147,152 +240,510 @@ fn init_tracing(cli: &Cli) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // TODO: tokio runtime builder to control runtime parameters
+/// Installs the metrics recorder and, if enabled, spawns the admin service.
+///
+/// Returns the admin state so that the primary service can update its health once it has started.
+fn init_admin(
+    config_root: &bulwark_config::Config,
+    service_tasks: &mut JoinSet<std::result::Result<(), ServiceError>>,
+) -> Result<Arc<Mutex<AdminState>>, MetricsError> {
+    let admin_addr = config_root.service.admin_socket_addr();
+    let admin_enabled = config_root.service.admin_enabled;
+    let prometheus_handle;
+    let statsd_flusher;
 
-    let cli = Cli::parse();
-    init_tracing(&cli)?;
+    if config_root.metrics.statsd_host.is_some() {
+        prometheus_handle = None;
+        let (recorder, flusher) = admin::build_statsd_recorder(&config_root.metrics)?;
+        statsd_flusher = Some(Arc::new(flusher));
 
-    // You can check for the existence of subcommands, and if found use their
-    // matches just as you would the top level cmd
-    match &cli.command.ok_or(CliArgumentError::MissingSubcommand)? {
-        Command::ExtProcessor { config } => {
-            let mut service_tasks: JoinSet<std::result::Result<(), ServiceError>> = JoinSet::new();
-
-            let config_root = bulwark_config::toml::load_config(config)?;
-            let port = config_root.service.port;
-            let admin_port = config_root.service.admin_port;
-            let admin_enabled = config_root.service.admin_enabled;
-            let prometheus_handle;
-
-            if let Some(statsd_host) = &config_root.metrics.statsd_host {
-                prometheus_handle = None;
-                let prefix = if config_root.metrics.statsd_prefix.is_empty() {
-                    None
-                } else {
-                    Some(config_root.metrics.statsd_prefix.as_str())
-                };
-                let recorder = StatsdBuilder::from(
-                    statsd_host,
-                    config_root.metrics.statsd_port.unwrap_or(8125),
-                )
-                .with_queue_size(config_root.metrics.statsd_queue_size)
-                .with_buffer_size(config_root.metrics.statsd_buffer_size)
-                .histogram_is_distribution()
-                .build(prefix)
-                .map_err(MetricsError::from)?;
-
-                metrics::set_boxed_recorder(Box::new(recorder)).map_err(MetricsError::from)?;
-            } else {
-                let thresholds = config_root.thresholds;
-                prometheus_handle = Some(
-                    crate::admin::PrometheusBuilder::new()
-                        // Setting buckets forces histograms to be rendered as native histograms rather than summaries
-                        .set_buckets_for_metric(
-                            Matcher::Suffix("decision_score".to_string()),
-                            &[
-                                thresholds.trust,
-                                thresholds.suspicious,
-                                thresholds.restrict,
-                                1.0,
-                            ],
-                        )
-                        .map_err(MetricsError::from)?
-                        .set_buckets_for_metric(
-                            Matcher::Suffix("decision_conflict".to_string()),
-                            &[0.01, 0.1, 0.25, 0.5, 0.75, 1.0, 5.0],
-                        )
-                        .map_err(MetricsError::from)?
-                        .set_buckets_for_metric(
-                            Matcher::Full("combined_conflict".to_string()),
-                            &[0.01, 0.1, 0.25, 0.5, 0.75, 1.0, 5.0],
-                        )
-                        .map_err(MetricsError::from)?
-                        .install_recorder()
-                        .map_err(MetricsError::from)?,
-                );
-
-                // TODO: Enable process metrics collection. (libproc.h issue, maybe behind cfg feature)
-                // let process = metrics_process::Collector::default();
-                // process.describe();
-            }
+        metrics::set_boxed_recorder(Box::new(recorder))?;
+    } else {
+        statsd_flusher = None;
+        let thresholds = config_root.thresholds;
+        prometheus_handle = Some(
+            crate::admin::PrometheusBuilder::new()
+                // Setting buckets forces histograms to be rendered as native histograms rather than summaries
+                .set_buckets_for_metric(
+                    Matcher::Suffix("decision_score".to_string()),
+                    &[
+                        thresholds.trust,
+                        thresholds.suspicious,
+                        thresholds.restrict,
+                        1.0,
+                    ],
+                )?
+                .set_buckets_for_metric(
+                    Matcher::Suffix("decision_conflict".to_string()),
+                    &[0.01, 0.1, 0.25, 0.5, 0.75, 1.0, 5.0],
+                )?
+                .set_buckets_for_metric(
+                    Matcher::Full("combined_conflict".to_string()),
+                    &[0.01, 0.1, 0.25, 0.5, 0.75, 1.0, 5.0],
+                )?
+                .install_recorder()?,
+        );
 
-            let admin_state = Arc::new(Mutex::new(AdminState {
-                health: HealthState {
-                    live: true,
-                    started: false,
-                    ready: false,
-                },
-                metrics: MetricsState::new(
-                    prometheus_handle,
-                    // TODO: Enable process metrics collection. (libproc.h issue, maybe behind cfg feature)
-                    // collect: move || process.collect(),
+        // TODO: Enable process metrics collection. (libproc.h issue, maybe behind cfg feature)
+        // let process = metrics_process::Collector::default();
+        // process.describe();
+    }
+
+    let admin_state = Arc::new(Mutex::new(AdminState {
+        health: HealthState {
+            live: true,
+            started: false,
+            ready: false,
+        },
+        metrics: MetricsState::new(
+            prometheus_handle,
+            statsd_flusher,
+            // TODO: Enable process metrics collection. (libproc.h issue, maybe behind cfg feature)
+            // collect: move || process.collect(),
+        ),
+        reloader: None,
+        processor: None,
+    }));
+
+    // TODO: need a reference to the bulwark processor to pass to the admin service but that doesn't exist yet
+
+    if admin_enabled {
+        let admin_state = admin_state.clone();
+
+        service_tasks.spawn(async move {
+            // And run our service using `hyper`.
+            let app = ServiceExt::<axum::extract::Request>::into_make_service(
+                NormalizePathLayer::trim_trailing_slash().layer(
+                    Router::new()
+                        .route("/health", get(admin::default_probe_handler)) // :probe is optional and defaults to liveness probe
+                        .route("/health/:probe", get(admin::probe_handler))
+                        .route("/metrics", get(admin::metrics_handler))
+                        .route("/reload", post(reload::reload_handler))
+                        .with_state(admin_state),
                 ),
-            }));
-
-            // TODO: need a reference to the bulwark processor to pass to the admin service but that doesn't exist yet
-
-            if admin_enabled {
-                let admin_state = admin_state.clone();
-
-                service_tasks.spawn(async move {
-                    // And run our service using `hyper`.
-                    let addr = SocketAddr::from((IpAddr::V4(Ipv4Addr::UNSPECIFIED), admin_port));
-                    let app = ServiceExt::<axum::extract::Request>::into_make_service(
-                        NormalizePathLayer::trim_trailing_slash().layer(
-                            Router::new()
-                                .route("/health", get(admin::default_probe_handler)) // :probe is optional and defaults to liveness probe
-                                .route("/health/:probe", get(admin::probe_handler))
-                                .route("/metrics", get(admin::metrics_handler))
-                                .with_state(admin_state),
-                        ),
-                    );
-
-                    let listener = tokio::net::TcpListener::bind(&addr).await?;
-                    axum::serve(listener, app)
-                        .await
-                        .map_err(ServiceError::AdminService)
-                });
+            );
+
+            let listener = tokio::net::TcpListener::bind(&admin_addr).await?;
+            axum::serve(listener, app)
+                .await
+                .map_err(ServiceError::AdminService)
+        });
+    }
+
+    Ok(admin_state)
+}
+
+/// Creates a reloader for the primary service and spawns the tasks that trigger it.
+///
+/// Reloads are triggered by `SIGHUP`, by the admin service's reload endpoint, and, if watched paths are given, by
+/// changes to files within them. The processor is also handed to the admin service, so that readiness reflects
+/// whether its plugins initialized.
+fn init_reload(
+    config_path: &std::path::Path,
+    unlocked: bool,
+    processor: BulwarkProcessor,
+    watched_paths: Option<BTreeMap<PathBuf, notify::RecursiveMode>>,
+    admin_state: &Arc<Mutex<AdminState>>,
+    service_tasks: &mut JoinSet<std::result::Result<(), ServiceError>>,
+) {
+    let reloader = Arc::new(Reloader::new(
+        config_path.to_path_buf(),
+        unlocked,
+        processor.clone(),
+    ));
+    {
+        let mut admin_state = admin_state.lock().expect("poisoned mutex");
+        admin_state.reloader = Some(reloader.clone());
+        admin_state.processor = Some(processor);
+    }
+
+    #[cfg(unix)]
+    {
+        let reloader = reloader.clone();
+        service_tasks.spawn(async move { reloader.reload_on_hangup().await });
+    }
+    if let Some(watched_paths) = watched_paths {
+        service_tasks.spawn(async move { reloader.reload_on_change(watched_paths).await });
+    }
+}
+
+/// Launches a primary service alongside the admin service and the reload triggers, and runs it until shutdown.
+///
+/// The admin service is started before the primary service is built, so that the metrics recorder is installed
+/// before plugins are loaded. The service is reported as started and ready once its server is running.
+///
+/// # Arguments
+///
+/// * `config_path` - The config file the service was loaded from, which is re-read by each reload.
+/// * `config_root` - The loaded config.
+/// * `watch` - True if the config should be reloaded whenever the config file or a local plugin changes.
+/// * `unlocked` - True if the config should be reloaded without its lockfile.
+/// * `build` - Builds the primary service from the config and the receiver that announces shutdown, returning the
+///   processor handling its requests along with the future that serves it.
+async fn run_service<B, BF, S>(
+    config_path: &std::path::Path,
+    config_root: bulwark_config::Config,
+    watch: bool,
+    unlocked: bool,
+    build: B,
+) -> Result<(), Box<dyn std::error::Error>>
+where
+    B: FnOnce(bulwark_config::Config, tokio::sync::watch::Receiver<bool>) -> BF,
+    BF: std::future::Future<Output = Result<(BulwarkProcessor, S), Box<dyn std::error::Error>>>,
+    S: std::future::Future<Output = std::result::Result<(), ServiceError>> + Send + 'static,
+{
+    let mut service_tasks: JoinSet<std::result::Result<(), ServiceError>> = JoinSet::new();
+
+    let admin_state = init_admin(&config_root, &mut service_tasks)?;
+    let watched_paths = watch.then(|| reload::watched_paths(config_path, &config_root));
+    let drain_timeout = Duration::from_millis(config_root.service.drain_timeout);
+    let (shutdown_sender, shutdown_receiver) = tokio::sync::watch::channel(false);
+
+    let (bulwark_processor, serve) = build(config_root, shutdown_receiver).await?;
+    init_reload(
+        config_path,
+        unlocked,
+        bulwark_processor.clone(),
+        watched_paths,
+        &admin_state,
+        &mut service_tasks,
+    );
+
+    {
+        let admin_state = admin_state.clone();
+
+        service_tasks.spawn(async move {
+            {
+                let mut admin_state = admin_state.lock().expect("poisoned mutex");
+                admin_state.health.started = true;
+                admin_state.health.ready = true;
             }
+            serve.await
+        });
+    }
 
-            let bulwark_processor = BulwarkProcessor::new(config_root).await?;
-            let ext_processor = ExternalProcessorServer::new(bulwark_processor);
+    shutdown::run_until_shutdown(
+        service_tasks,
+        admin_state,
+        bulwark_processor,
+        drain_timeout,
+        shutdown_sender,
+    )
+    .await;
+    Ok(())
+}
 
+/// Creates a gRPC server for the primary service, tuned by the runtime config and configured for TLS if the service
+/// config enables it.
+///
+/// # Arguments
+///
+/// * `service_config` - The service config for the primary service.
+/// * `runtime_config` - The runtime config, which holds the gRPC connection settings.
+fn grpc_server(
+    service_config: &bulwark_config::Service,
+    runtime_config: &bulwark_config::Runtime,
+) -> std::result::Result<Server, GrpcServiceError> {
+    let server = Server::builder()
+        .http2_keepalive_interval(
+            runtime_config
+                .http2_keepalive_interval
+                .map(Duration::from_millis),
+        )
+        .http2_keepalive_timeout(
+            runtime_config
+                .http2_keepalive_timeout
+                .map(Duration::from_millis),
+        )
+        .max_concurrent_streams(runtime_config.max_concurrent_streams)
+        .tcp_nodelay(runtime_config.tcp_nodelay);
+    let Some(tls) = &service_config.tls else {
+        return Ok(server);
+    };
+
+    let read = |path: &PathBuf| {
+        std::fs::read(path).map_err(|err| GrpcServiceError::TlsFile(path.clone(), err))
+    };
+    let mut tls_config =
+        ServerTlsConfig::new().identity(Identity::from_pem(read(&tls.cert)?, read(&tls.key)?));
+    if let Some(client_ca) = &tls.client_ca {
+        tls_config = tls_config.client_ca_root(Certificate::from_pem(read(client_ca)?));
+    }
+    Ok(server.tls_config(tls_config)?)
+}
+
+/// Serves a gRPC router on the primary service's Unix domain socket, if configured, or its socket address
+/// otherwise, until the `signal` future completes.
+///
+/// # Arguments
+///
+/// * `router` - The gRPC router for the primary service.
+/// * `service_config` - The service config for the primary service.
+/// * `signal` - A future that completes when the service should stop accepting connections.
+async fn serve_grpc(
+    router: tonic::transport::server::Router,
+    service_config: &bulwark_config::Service,
+    signal: impl std::future::Future<Output = ()>,
+) -> std::result::Result<(), GrpcServiceError> {
+    match &service_config.socket {
+        #[cfg(unix)]
+        Some(socket) => {
+            use std::os::unix::fs::FileTypeExt;
+
+            // A socket left behind by a previous process would cause the bind to fail. It's only removed if nothing
+            // accepts connections on it, so that a second instance can't take over a live socket.
+            if std::fs::symlink_metadata(socket)
+                .is_ok_and(|metadata| metadata.file_type().is_socket())
             {
-                let admin_state = admin_state.clone();
+                if tokio::net::UnixStream::connect(socket).await.is_ok() {
+                    return Err(GrpcServiceError::UnixSocketInUse(socket.clone()));
+                }
+                std::fs::remove_file(socket)
+                    .map_err(|err| GrpcServiceError::UnixSocket(socket.clone(), err))?;
+            }
+            let listener = tokio::net::UnixListener::bind(socket)
+                .map_err(|err| GrpcServiceError::UnixSocket(socket.clone(), err))?;
+            Ok(router
+                .serve_with_incoming_shutdown(
+                    tokio_stream::wrappers::UnixListenerStream::new(listener),
+                    signal,
+                )
+                .await?)
+        }
+        #[cfg(not(unix))]
+        Some(socket) => Err(GrpcServiceError::UnixSocket(
+            socket.clone(),
+            std::io::Error::from(std::io::ErrorKind::Unsupported),
+        )),
+        None => Ok(router
+            .serve_with_shutdown(service_config.socket_addr(), signal)
+            .await?),
+    }
+}
 
-                service_tasks.spawn(async move {
-                    {
-                        let mut admin_state = admin_state.lock().expect("poisoned mutex");
-                        admin_state.health.started = true;
-                        admin_state.health.ready = true;
-                    }
-                    Server::builder()
-                        .add_service(ext_processor)
-                        .serve(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port)) // TODO: make socket addr configurable?
+/// Waits for all services to exit, logging any errors they return.
+async fn join_services(service_tasks: &mut JoinSet<std::result::Result<(), ServiceError>>) {
+    while let Some(r) = service_tasks.join_next().await {
+        match r {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => error!(
+                message = "service could not start",
+                error_message = ?e,
+            ),
+            Err(e) => error!(
+                message = "join error on service initialization",
+                error_message = ?e,
+            ),
+        }
+    }
+}
+
+/// Builds the async runtime, tuned by the `[runtime]` config section.
+///
+/// # Arguments
+///
+/// * `runtime_config` - The runtime config, or the defaults for subcommands that don't launch a service.
+fn build_runtime(
+    runtime_config: &bulwark_config::Runtime,
+) -> std::io::Result<tokio::runtime::Runtime> {
+    let mut builder = tokio::runtime::Builder::new_multi_thread();
+    builder
+        .enable_all()
+        .max_blocking_threads(runtime_config.max_blocking_threads)
+        .thread_name(runtime_config.thread_name.clone());
+    if let Some(worker_threads) = runtime_config.worker_threads {
+        builder.worker_threads(worker_threads);
+    }
+    builder.build()
+}
+
+/// Precompiles a built plugin for the engine configured by a config file, writing the artifact alongside the plugin.
+///
+/// # Arguments
+///
+/// * `wasm_path` - The path to the built plugin.
+/// * `config_path` - The config file that determines the engine's configuration.
+fn precompile_plugin(
+    wasm_path: &std::path::Path,
+    config_path: &std::path::Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Only the engine settings matter here, and the freshly built plugin won't be in the lockfile yet.
+    let config_root = bulwark_config::toml::load_unlocked_config(config_path)?;
+    let engine = bulwark_host::PluginEngine::new(&config_root)?;
+    let artifact = engine.precompile(&std::fs::read(wasm_path)?)?;
+    std::fs::write(
+        wasm_path.with_extension(bulwark_host::PRECOMPILED_EXTENSION),
+        artifact,
+    )?;
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+    init_tracing(&cli)?;
+    let command = cli.command.ok_or(CliArgumentError::MissingSubcommand)?;
+
+    // The runtime can't be changed once it's running, so services load their config before it starts.
+    let config_root = match &command {
+        Command::ExtProcessor { config, .. }
+        | Command::ExtAuthz { config, .. }
+        | Command::ReverseProxy { config, .. } => Some(load_config(config, cli.unlocked)?),
+        _ => None,
+    };
+    let runtime_config = config_root
+        .as_ref()
+        .map(|config_root| config_root.runtime.clone())
+        .unwrap_or_default();
+    build_runtime(&runtime_config)?.block_on(run(command, config_root, cli.unlocked))
+}
+
+/// Loads a config file, pinning its plugins to the lockfile alongside it unless `unlocked` is set.
+///
+/// See [`bulwark_config::toml::load_config`].
+fn load_config(
+    config_path: &std::path::Path,
+    unlocked: bool,
+) -> Result<bulwark_config::Config, bulwark_config::ConfigFileError> {
+    if unlocked {
+        bulwark_config::toml::load_unlocked_config(config_path)
+    } else {
+        bulwark_config::toml::load_config(config_path)
+    }
+}
+
+/// Runs a subcommand.
+///
+/// # Arguments
+///
+/// * `command` - The subcommand to run.
+/// * `config_root` - The loaded config for subcommands that launch a service, or `None` otherwise.
+/// * `unlocked` - True if config files should be loaded without their lockfiles.
+async fn run(
+    command: Command,
+    config_root: Option<bulwark_config::Config>,
+    unlocked: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let service_config_root = || config_root.expect("config should be loaded for services");
+
+    // You can check for the existence of subcommands, and if found use their
+    // matches just as you would the top level cmd
+    match &command {
+        Command::ExtProcessor { config, watch } => {
+            run_service(
+                config,
+                service_config_root(),
+                *watch,
+                unlocked,
+                |config_root, shutdown_receiver| async move {
+                    let service_config = config_root.service.clone();
+                    let mut server = grpc_server(&service_config, &config_root.runtime)?;
+                    let bulwark_processor = BulwarkProcessor::new(config_root).await?;
+                    let ext_processor = ExternalProcessorServer::new(bulwark_processor.clone());
+                    let serve = async move {
+                        serve_grpc(
+                            server.add_service(ext_processor),
+                            &service_config,
+                            shutdown::requested(shutdown_receiver),
+                        )
                         .await
                         .map_err(ServiceError::ExtProcessorService)
-                });
+                    };
+                    Ok::<_, Box<dyn std::error::Error>>((bulwark_processor, serve))
+                },
+            )
+            .await?;
+        }
+        Command::ExtAuthz { config, watch } => {
+            run_service(
+                config,
+                service_config_root(),
+                *watch,
+                unlocked,
+                |config_root, shutdown_receiver| async move {
+                    let service_config = config_root.service.clone();
+                    let mut server = grpc_server(&service_config, &config_root.runtime)?;
+                    let bulwark_processor = BulwarkProcessor::new(config_root).await?;
+                    let ext_authz = AuthorizationServer::new(bulwark_processor.clone());
+                    let serve = async move {
+                        serve_grpc(
+                            server.add_service(ext_authz),
+                            &service_config,
+                            shutdown::requested(shutdown_receiver),
+                        )
+                        .await
+                        .map_err(ServiceError::ExtAuthzService)
+                    };
+                    Ok::<_, Box<dyn std::error::Error>>((bulwark_processor, serve))
+                },
+            )
+            .await?;
+        }
+        Command::ReverseProxy { config, watch } => {
+            run_service(
+                config,
+                service_config_root(),
+                *watch,
+                unlocked,
+                |config_root, shutdown_receiver| async move {
+                    let service_config = config_root.service.clone();
+                    if service_config.socket.is_some() || service_config.tls.is_some() {
+                        warn!(
+                            message =
+                                "ignoring socket and tls settings unsupported by the reverse proxy"
+                        );
+                    }
+                    let bulwark_proxy = BulwarkProxy::new(config_root).await?;
+                    let bulwark_processor = bulwark_proxy.processor().clone();
+                    let serve = async move {
+                        bulwark_proxy
+                            .serve_with_shutdown(
+                                service_config.socket_addr(),
+                                shutdown::requested(shutdown_receiver),
+                            )
+                            .await
+                            .map_err(ServiceError::ReverseProxyService)
+                    };
+                    Ok::<_, Box<dyn std::error::Error>>((bulwark_processor, serve))
+                },
+            )
+            .await?;
+        }
+        Command::Check { config } => {
+            let report = check::check_config(config, unlocked).await;
+            println!("{}", report);
+            if !report.passed() {
+                std::process::exit(1);
             }
-
-            while let Some(r) = service_tasks.join_next().await {
-                match r {
-                    Ok(Ok(_)) => {}
-                    Ok(Err(e)) => error!(
-                        message = "service could not start",
-                        error_message = ?e,
-                    ),
-                    Err(e) => error!(
-                        message = "join error on service initialization",
-                        error_message = ?e,
-                    ),
-                }
+        }
+        Command::Test { config, fixtures } => {
+            let config_root = load_config(config, unlocked)?;
+            let bulwark_processor = BulwarkProcessor::new(config_root).await?;
+            let report = fixtures::run_fixtures(&bulwark_processor, fixtures).await?;
+            println!("{}", report);
+            if !report.passed() {
+                std::process::exit(1);
+            }
+        }
+        Command::Lock { config } => {
+            let (lockfile_path, lockfile) = lock::lock_config(config).await?;
+            println!(
+                "locked {} plugins in {}",
+                lockfile.plugins.len(),
+                lockfile_path.display()
+            );
+        }
+        Command::Vendor { config, output } => {
+            for path in vendor::vendor_config(config, output, unlocked).await? {
+                println!("wrote {}", path.display());
             }
         }
+        Command::Inspect { plugin } => {
+            println!("{}", inspect::inspect_plugin(plugin)?);
+        }
         Command::Build {
             path,
             output,
+            precompile,
             compiler_args,
         } => {
             let current_dir = std::env::current_dir()?;
@@ -305,7 +756,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             if output.is_dir() {
                 output = output.join(wasm_filename);
             }
-            bulwark_build::interactive_build_plugin(&path, output, compiler_args)?;
+            bulwark_build::interactive_build_plugin(&path, &output, compiler_args)?;
+            if let Some(config) = precompile {
+                precompile_plugin(&output, config)?;
+            }
         }
     }
 
@@ -0,0 +1,254 @@
+use super::*;
+
+use notify::{RecursiveMode, Watcher};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// How long to wait after a change is detected before reloading, so that a burst of writes triggers a single reload.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// The result of a reload, returned by the admin service's reload endpoint.
+#[derive(Serialize)]
+pub(super) struct ReloadState {
+    /// Indicates that the new config was loaded and swapped in.
+    pub reloaded: bool,
+    /// The reason the reload failed, if it did.
+    pub error: Option<String>,
+}
+
+/// The `Reloader` re-reads a running service's config file and swaps its resources and plugins in place.
+///
+/// Reloads may be triggered by a `SIGHUP`, by changes to the config file or local plugins when watching is enabled,
+/// or by the admin service's reload endpoint.
+pub(super) struct Reloader {
+    config_path: PathBuf,
+    /// True if the config should be loaded without its lockfile.
+    unlocked: bool,
+    processor: BulwarkProcessor,
+    /// Serializes reloads so that overlapping triggers can't swap in an older config after a newer one.
+    lock: tokio::sync::Mutex<()>,
+}
+
+impl Reloader {
+    /// Creates a new [`Reloader`].
+    ///
+    /// # Arguments
+    ///
+    /// * `config_path` - The config file to reload from.
+    /// * `unlocked` - True if the config should be loaded without its lockfile.
+    /// * `processor` - The processor whose router will be replaced.
+    pub(super) fn new(config_path: PathBuf, unlocked: bool, processor: BulwarkProcessor) -> Self {
+        Self {
+            config_path,
+            unlocked,
+            processor,
+            lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Loads the config file and swaps in its resources and plugins.
+    ///
+    /// If either the config or its plugins fail to load, the current config stays in place. Either way, the result
+    /// is logged and counted in the `config_reload` metric.
+    ///
+    /// # Arguments
+    ///
+    /// * `trigger` - What caused the reload, used in logs and metrics.
+    pub(super) async fn reload(&self, trigger: &'static str) -> Result<(), ReloadError> {
+        let _guard = self.lock.lock().await;
+        info!(
+            message = "reload config",
+            trigger = trigger,
+            config = tracing::field::display(self.config_path.display()),
+        );
+        let result = match super::load_config(&self.config_path, self.unlocked) {
+            Ok(config) => self
+                .processor
+                .reload(config)
+                .await
+                .map_err(ReloadError::from),
+            Err(err) => Err(ReloadError::from(err)),
+        };
+        match &result {
+            Ok(_) => {
+                metrics::increment_counter!(
+                    "config_reload",
+                    "trigger" => trigger,
+                    "result" => "ok"
+                );
+                info!(message = "config reloaded", trigger = trigger);
+            }
+            Err(err) => {
+                metrics::increment_counter!(
+                    "config_reload",
+                    "trigger" => trigger,
+                    "result" => "error"
+                );
+                error!(
+                    message = "config reload failed, keeping current config",
+                    trigger = trigger,
+                    error_message = %err,
+                );
+            }
+        }
+        result
+    }
+
+    /// Reloads the config each time the process receives a `SIGHUP`.
+    #[cfg(unix)]
+    pub(super) async fn reload_on_hangup(&self) -> Result<(), ServiceError> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut hangup = signal(SignalKind::hangup()).map_err(ServiceError::ReloadSignal)?;
+        while hangup.recv().await.is_some() {
+            // Failures are already logged and counted, and the service keeps running on the current config.
+            self.reload("signal").await.ok();
+        }
+        Ok(())
+    }
+
+    /// Reloads the config whenever a file changes in one of the watched directories.
+    ///
+    /// Directories are watched rather than files because editors and Kubernetes config maps typically replace files
+    /// instead of writing to them.
+    ///
+    /// # Arguments
+    ///
+    /// * `paths` - The directories to watch, as returned by [`watched_paths`] for the config at startup.
+    pub(super) async fn reload_on_change(
+        &self,
+        paths: BTreeMap<PathBuf, RecursiveMode>,
+    ) -> Result<(), ServiceError> {
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+                // Reading the config and plugins during a reload must not trigger another reload.
+                Ok(event) if event.kind.is_access() => {}
+                Ok(_) => {
+                    sender.send(()).ok();
+                }
+                Err(err) => warn!(message = "config watch error", error_message = %err),
+            })?;
+        for (path, mode) in paths {
+            watcher.watch(&path, mode)?;
+        }
+
+        while receiver.recv().await.is_some() {
+            tokio::time::sleep(WATCH_DEBOUNCE).await;
+            while receiver.try_recv().is_ok() {}
+            // Failures are already logged and counted, and the service keeps running on the current config.
+            self.reload("watch").await.ok();
+        }
+        Ok(())
+    }
+}
+
+/// Determines the directories to watch for changes to the config and its local plugins.
+///
+/// The config file's directory is watched recursively to pick up included files. Plugin directories are only
+/// watched directly, and are skipped if they're already covered by the config directory.
+///
+/// # Arguments
+///
+/// * `config_path` - The config file.
+/// * `config` - The config loaded from the config file.
+pub(super) fn watched_paths(
+    config_path: &Path,
+    config: &bulwark_config::Config,
+) -> BTreeMap<PathBuf, RecursiveMode> {
+    let config_dir = parent_dir(config_path);
+    let mut paths = BTreeMap::new();
+    for plugin in &config.plugins {
+        if let bulwark_config::PluginLocation::Local(path) = &plugin.location {
+            let plugin_dir = parent_dir(path);
+            if !plugin_dir.starts_with(&config_dir) {
+                paths.insert(plugin_dir, RecursiveMode::NonRecursive);
+            }
+        }
+    }
+    paths.insert(config_dir, RecursiveMode::Recursive);
+    paths
+}
+
+/// Returns the directory containing a file, treating a bare file name as being in the current directory.
+fn parent_dir(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// The reload handler reloads the primary service's resources and plugins from its config file.
+///
+/// Returns an HTTP OK status if the new config was swapped in, an Internal Server Error status if it failed to load
+/// and the current config was kept, and a Service Unavailable status if the primary service hasn't started yet.
+pub(super) async fn reload_handler(
+    State(state): State<Arc<Mutex<AdminState>>>,
+) -> (StatusCode, Json<ReloadState>) {
+    let reloader = state.lock().expect("poisoned mutex").reloader.clone();
+    let Some(reloader) = reloader else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ReloadState {
+                reloaded: false,
+                error: Some(String::from("service has not started")),
+            }),
+        );
+    };
+    match reloader.reload("admin").await {
+        Ok(_) => (
+            StatusCode::OK,
+            Json(ReloadState {
+                reloaded: true,
+                error: None,
+            }),
+        ),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ReloadState {
+                reloaded: false,
+                error: Some(err.to_string()),
+            }),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watched_paths() -> Result<(), Box<dyn std::error::Error>> {
+        let mut config = bulwark_config::toml::load_config("tests/bulwark.toml")?;
+        config.plugins.push(bulwark_config::Plugin {
+            reference: String::from("elsewhere"),
+            location: bulwark_config::PluginLocation::Local(PathBuf::from(
+                "/opt/bulwark/plugins/elsewhere.wasm",
+            )),
+            ..Default::default()
+        });
+        config.plugins.push(bulwark_config::Plugin {
+            reference: String::from("relative"),
+            location: bulwark_config::PluginLocation::Local(PathBuf::from("relative.wasm")),
+            ..Default::default()
+        });
+
+        let paths = watched_paths(Path::new("tests/bulwark.toml"), &config);
+        assert_eq!(
+            paths.get(Path::new("tests")),
+            Some(&RecursiveMode::Recursive)
+        );
+        assert_eq!(
+            paths.get(Path::new("/opt/bulwark/plugins")),
+            Some(&RecursiveMode::NonRecursive)
+        );
+        assert_eq!(
+            paths.get(Path::new(".")),
+            Some(&RecursiveMode::NonRecursive)
+        );
+
+        Ok(())
+    }
+}
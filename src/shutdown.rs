@@ -0,0 +1,106 @@
+use super::*;
+
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+/// How long to wait for queued StatsD metrics to be sent after requests have drained.
+const STATSD_FLUSH_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Waits for the process to be asked to shut down by a `SIGTERM` or a `SIGINT`.
+///
+/// If the signal handlers can't be installed, the error is logged and this never completes, leaving the process to
+/// be stopped without draining.
+pub(super) async fn signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut terminate = match signal(SignalKind::terminate()) {
+            Ok(terminate) => terminate,
+            Err(err) => {
+                error!(message = "could not listen for shutdown signal", error_message = %err);
+                return std::future::pending().await;
+            }
+        };
+        tokio::select! {
+            _ = terminate.recv() => {}
+            result = tokio::signal::ctrl_c() => {
+                if let Err(err) = result {
+                    error!(message = "could not listen for shutdown signal", error_message = %err);
+                    return std::future::pending().await;
+                }
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            error!(message = "could not listen for shutdown signal", error_message = %err);
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+/// Completes once shutdown has been requested through the given receiver.
+///
+/// Passed to the primary service so that it stops accepting new connections when shutdown begins.
+///
+/// # Arguments
+///
+/// * `receiver` - The receiving side of the channel that [`run_until_shutdown`] uses to announce shutdown.
+pub(super) async fn requested(mut receiver: watch::Receiver<bool>) {
+    // An error means the sender was dropped, which only happens once the process is exiting anyway.
+    receiver.wait_for(|requested| *requested).await.ok();
+}
+
+/// Runs the services until they all exit or a shutdown signal is received, then shuts down gracefully.
+///
+/// A graceful shutdown marks the service as no longer ready, tells the primary service to stop accepting
+/// connections, waits up to `drain_timeout` for in-flight requests and their decision feedback to finish, and then
+/// flushes any buffered StatsD metrics. Any services still running afterwards are aborted.
+///
+/// # Arguments
+///
+/// * `service_tasks` - The admin, primary, and reload services.
+/// * `admin_state` - The admin state, used to report readiness and to find the StatsD flusher.
+/// * `processor` - The processor handling requests for the primary service.
+/// * `drain_timeout` - The maximum amount of time to wait for in-flight requests.
+/// * `sender` - The sending side of the channel passed to [`requested`].
+pub(super) async fn run_until_shutdown(
+    mut service_tasks: JoinSet<std::result::Result<(), ServiceError>>,
+    admin_state: Arc<Mutex<AdminState>>,
+    processor: BulwarkProcessor,
+    drain_timeout: Duration,
+    sender: watch::Sender<bool>,
+) {
+    tokio::select! {
+        _ = join_services(&mut service_tasks) => return,
+        _ = signal() => {}
+    }
+
+    info!(
+        message = "shutting down",
+        drain_timeout = drain_timeout.as_millis()
+    );
+    let statsd_flusher = {
+        let mut admin_state = admin_state.lock().expect("poisoned mutex");
+        admin_state.health.ready = false;
+        admin_state.metrics.statsd_flusher.clone()
+    };
+    sender.send_replace(true);
+
+    if processor.drain(drain_timeout).await {
+        info!(message = "in-flight requests drained");
+    } else {
+        warn!(message = "drain timeout expired, dropping in-flight requests");
+    }
+
+    if let Some(statsd_flusher) = statsd_flusher {
+        if let Err(err) = statsd_flusher.flush(STATSD_FLUSH_TIMEOUT).await {
+            warn!(message = "could not flush statsd metrics", error_message = %err);
+        }
+    }
+
+    service_tasks.shutdown().await;
+}
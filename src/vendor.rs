@@ -0,0 +1,46 @@
+//! Vendoring of a Bulwark configuration file and its plugins for deployments without network access.
+//!
+//! The `vendor` subcommand fetches and verifies every plugin the same way the service would, then writes a copy of
+//! the configuration that loads each of them from a local file instead.
+
+use {
+    bulwark_config::PluginLocation,
+    std::{
+        collections::BTreeMap,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Fetches and verifies every plugin in a configuration file, then writes a copy of the configuration and its
+/// includes to a directory, rewritten to load the plugins from local copies pinned to their digests.
+///
+/// Plugins embedded in the configuration as bytes are already local, so they're left as they are.
+///
+/// Returns the paths of the configuration files that were written.
+///
+/// # Arguments
+///
+/// * `config_path` - The path to the root configuration file.
+/// * `output_dir` - The directory to write the vendored configuration to.
+/// * `unlocked` - True if the lockfile alongside the configuration file should be ignored.
+pub async fn vendor_config(
+    config_path: &Path,
+    output_dir: &Path,
+    unlocked: bool,
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let config = super::load_config(config_path, unlocked)?;
+    let mut plugins = BTreeMap::new();
+    for plugin in &config.plugins {
+        if let PluginLocation::Bytes(_) = plugin.location {
+            continue;
+        }
+        let bytes = bulwark_host::fetch_plugin(&config, plugin).await?;
+        bulwark_host::verify_plugin(&config, plugin, &bytes).await?;
+        plugins.insert(plugin.reference.clone(), bytes);
+    }
+    Ok(bulwark_config::vendor_config(
+        config_path,
+        output_dir,
+        &plugins,
+    )?)
+}
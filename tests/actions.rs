@@ -0,0 +1,116 @@
+use bulwark_ext_processor::{Action, BulwarkProcessor};
+use std::path::Path;
+
+fn build_plugins(base: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    bulwark_build::build_plugin(
+        base.join("../crates/sdk/examples/evil-bit"),
+        base.join("dist/plugins/bulwark_evil_bit.wasm"),
+        &[],
+        true,
+    )?;
+    assert!(base.join("dist/plugins/bulwark_evil_bit.wasm").exists());
+    bulwark_build::build_plugin(
+        base.join("plugins/rate-limit-plugin"),
+        base.join("dist/plugins/rate_limit_plugin.wasm"),
+        &[],
+        true,
+    )?;
+    assert!(base.join("dist/plugins/rate_limit_plugin.wasm").exists());
+    Ok(())
+}
+
+fn request(uri: &str, headers: &[(&str, &str)]) -> Result<bulwark_sdk::Request, http::Error> {
+    let mut request = http::Request::builder().uri(uri);
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    request.body(bytes::Bytes::new())
+}
+
+#[tokio::test]
+async fn test_plugin_requested_actions() -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+    build_plugins(base)?;
+
+    let config_root = bulwark_config::toml::load_config(&base.join("actions.toml"))?;
+    let bulwark_processor = BulwarkProcessor::new(config_root).await?;
+
+    // A plugin can block a request without restricting it.
+    let evaluation = bulwark_processor
+        .evaluate(request("/api", &[("X-Rate-Limited", "true")])?, None)
+        .await?
+        .expect("resource should match");
+    assert_eq!(evaluation.verdict.outcome, bulwark_sdk::Outcome::Accepted);
+    let response = evaluation
+        .action_response
+        .expect("request should be blocked");
+    assert_eq!(response.status(), 429);
+    assert_eq!(response.headers()["retry-after"], "60");
+    assert_eq!(response.body(), "Access Denied\n");
+
+    // The requested block replaces the configured one when the request is also restricted.
+    let evaluation = bulwark_processor
+        .evaluate(
+            request("/api", &[("X-Rate-Limited", "true"), ("Evil", "true")])?,
+            None,
+        )
+        .await?
+        .expect("resource should match");
+    assert_eq!(evaluation.verdict.outcome, bulwark_sdk::Outcome::Restricted);
+    let response = evaluation
+        .action_response
+        .expect("request should be blocked");
+    assert_eq!(response.status(), 429);
+
+    // Added headers are forwarded with allowed requests.
+    let evaluation = bulwark_processor
+        .evaluate(request("/api", &[("X-Challenge-Passed", "true")])?, None)
+        .await?
+        .expect("resource should match");
+    assert_eq!(evaluation.action.action, Action::Allow);
+    assert_eq!(
+        evaluation.action.added_headers,
+        vec![("Bulwark-Challenge".to_string(), "passed".to_string())]
+    );
+    assert!(evaluation.action_response.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_resource_actions() -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+    build_plugins(base)?;
+
+    let config_root = bulwark_config::toml::load_config(&base.join("actions.toml"))?;
+    let bulwark_processor = BulwarkProcessor::new(config_root).await?;
+
+    // Restricted requests for this resource are redirected rather than blocked.
+    let evaluation = bulwark_processor
+        .evaluate(request("/login", &[("Evil", "true")])?, None)
+        .await?
+        .expect("resource should match");
+    assert_eq!(evaluation.verdict.outcome, bulwark_sdk::Outcome::Restricted);
+    assert_eq!(
+        evaluation.action.action,
+        Action::Redirect {
+            location: "/challenge".to_string(),
+            status: 302
+        }
+    );
+    let response = evaluation
+        .action_response
+        .expect("request should be redirected");
+    assert_eq!(response.status(), 302);
+    assert_eq!(response.headers()["location"], "/challenge");
+
+    // Accepted requests are still allowed.
+    let evaluation = bulwark_processor
+        .evaluate(request("/login", &[])?, None)
+        .await?
+        .expect("resource should match");
+    assert_eq!(evaluation.action.action, Action::Allow);
+    assert!(evaluation.action_response.is_none());
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn test_check_valid_config() -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+
+    bulwark_build::build_plugin(
+        base.join("../crates/sdk/examples/evil-bit"),
+        base.join("dist/plugins/bulwark_evil_bit.wasm"),
+        &[],
+        true,
+    )?;
+    assert!(base.join("dist/plugins/bulwark_evil_bit.wasm").exists());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_bulwark-cli"))
+        .arg("check")
+        .arg("-c")
+        .arg(base.join("bulwark.toml"))
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("  ok  plugin 'evil_bit'"));
+    assert!(stdout.contains("checks passed"));
+
+    Ok(())
+}
+
+#[test]
+fn test_check_invalid_config() -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+
+    bulwark_build::build_plugin(
+        base.join("../crates/sdk/examples/evil-bit"),
+        base.join("dist/plugins/bulwark_evil_bit.wasm"),
+        &[],
+        true,
+    )?;
+    assert!(base.join("dist/plugins/bulwark_evil_bit.wasm").exists());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_bulwark-cli"))
+        .arg("check")
+        .arg("-c")
+        .arg(base.join("check_invalid.toml"))
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert!(stdout.contains("conflict with previously registered route"));
+    assert!(stdout.contains("  ok  plugin 'evil_bit'"));
+    assert!(stdout.contains(
+        "FAIL  plugin 'empty_component': does not implement bulwark:plugin/http-detection"
+    ));
+    assert!(stdout.contains("2 of 5 checks failed"));
+
+    Ok(())
+}
+
+#[test]
+fn test_check_invalid_thresholds() -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_bulwark-cli"))
+        .arg("check")
+        .arg("-c")
+        .arg(base.join("check_invalid_thresholds.toml"))
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert!(stdout.contains("FAIL  config"));
+    assert!(stdout.contains("invalid thresholds config: invalid threshold order"));
+
+    Ok(())
+}
+
+#[test]
+fn test_check_missing_config() -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_bulwark-cli"))
+        .arg("check")
+        .arg("-c")
+        .arg(base.join("does_not_exist.toml"))
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert!(stdout.contains("FAIL  config"));
+
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use bulwark_host::{
+    Plugin, PluginCtx, PluginExecutionError, PluginInstance, RedisCtx, ScriptRegistry,
+};
+use std::{collections::HashMap, path::Path, sync::Arc, time::Duration};
+
+/// Instantiates the busy loop plugin with the given CPU limits and calls its never-ending request decision handler.
+async fn exec_busy_loop(
+    max_fuel: Option<u64>,
+    epoch_deadline: Option<u64>,
+) -> Result<PluginExecutionError, Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+
+    bulwark_build::build_plugin(
+        base.join("plugins/busy-loop-plugin"),
+        base.join("dist/plugins/busy_loop_plugin.wasm"),
+        &[],
+        true,
+    )?;
+    assert!(base.join("dist/plugins/busy_loop_plugin.wasm").exists());
+
+    let plugin = Arc::new(Plugin::from_file(
+        base.join("dist/plugins/busy_loop_plugin.wasm"),
+        // None of this config will get read during this test.
+        &bulwark_config::Config {
+            service: bulwark_config::Service::default(),
+            runtime: bulwark_config::Runtime::default(),
+            state: bulwark_config::State::default(),
+            thresholds: bulwark_config::Thresholds::default(),
+            headers: bulwark_config::Headers::default(),
+            block: bulwark_config::BlockResponse::default(),
+            actions: bulwark_config::Actions::default(),
+            metrics: bulwark_config::Metrics::default(),
+            secrets: vec![],
+            trust: vec![],
+            plugins: vec![],
+            presets: vec![],
+            resources: vec![],
+        },
+        &bulwark_config::Plugin {
+            reference: String::from("busy_loop_plugin"),
+            max_fuel,
+            epoch_deadline,
+            ..Default::default()
+        },
+    )?);
+    let request = Arc::new(
+        http::Request::builder()
+            .method("GET")
+            .uri("/")
+            .version(http::Version::HTTP_11)
+            .body(bytes::Bytes::new())?,
+    );
+    let redis_ctx = RedisCtx {
+        pool: None,
+        registry: Arc::new(ScriptRegistry::default()),
+    };
+    let plugin_ctx = PluginCtx::new(plugin.clone(), HashMap::new(), redis_ctx)?;
+    let mut plugin_instance = PluginInstance::new(plugin, plugin_ctx).await?;
+
+    // The limits should stop the plugin long before this timeout would.
+    let result = tokio::time::timeout(
+        Duration::from_secs(30),
+        plugin_instance.handle_request_decision(request, HashMap::new()),
+    )
+    .await?;
+    Ok(result.err().expect("plugin should not return"))
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_max_fuel() -> Result<(), Box<dyn std::error::Error>> {
+    let err = exec_busy_loop(Some(10_000_000), None).await?;
+    assert!(matches!(err, PluginExecutionError::CpuLimitExceeded(limit) if limit == "fuel"));
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_epoch_deadline() -> Result<(), Box<dyn std::error::Error>> {
+    let err = exec_busy_loop(None, Some(50)).await?;
+    assert!(matches!(err, PluginExecutionError::CpuLimitExceeded(limit) if limit == "epoch"));
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use bulwark_host::{Plugin, PluginCtx, PluginInstance, RedisCtx, ScriptRegistry};
+use bulwark_host::{Plugin, PluginCtx, PluginInstance, RedisCtx, ScriptRegistry, WorldVersion};
 use std::{collections::HashMap, path::Path, sync::Arc};
 
 #[test]
@@ -21,14 +21,19 @@ fn test_blank_slate_exec() -> Result<(), Box<dyn std::error::Error>> {
             runtime: bulwark_config::Runtime::default(),
             state: bulwark_config::State::default(),
             thresholds: bulwark_config::Thresholds::default(),
+            headers: bulwark_config::Headers::default(),
+            block: bulwark_config::BlockResponse::default(),
+            actions: bulwark_config::Actions::default(),
             metrics: bulwark_config::Metrics::default(),
             secrets: vec![],
+            trust: vec![],
             plugins: vec![],
             presets: vec![],
             resources: vec![],
         },
         &bulwark_config::Plugin::default(),
     )?);
+    assert_eq!(plugin.world_version(), WorldVersion::CURRENT);
     let request = Arc::new(
         http::Request::builder()
             .method("GET")
@@ -117,8 +122,12 @@ fn test_evil_bit_benign_exec() -> Result<(), Box<dyn std::error::Error>> {
             runtime: bulwark_config::Runtime::default(),
             state: bulwark_config::State::default(),
             thresholds: bulwark_config::Thresholds::default(),
+            headers: bulwark_config::Headers::default(),
+            block: bulwark_config::BlockResponse::default(),
+            actions: bulwark_config::Actions::default(),
             metrics: bulwark_config::Metrics::default(),
             secrets: vec![],
+            trust: vec![],
             plugins: vec![],
             presets: vec![],
             resources: vec![],
@@ -212,8 +221,12 @@ fn test_evil_bit_evil_exec() -> Result<(), Box<dyn std::error::Error>> {
             runtime: bulwark_config::Runtime::default(),
             state: bulwark_config::State::default(),
             thresholds: bulwark_config::Thresholds::default(),
+            headers: bulwark_config::Headers::default(),
+            block: bulwark_config::BlockResponse::default(),
+            actions: bulwark_config::Actions::default(),
             metrics: bulwark_config::Metrics::default(),
             secrets: vec![],
+            trust: vec![],
             plugins: vec![],
             presets: vec![],
             resources: vec![],
@@ -0,0 +1,161 @@
+use bulwark_ext_processor::protobuf::envoy::service::auth::v3::{
+    attribute_context, authorization_server::Authorization, check_response, AttributeContext,
+    CheckRequest,
+};
+use bulwark_ext_processor::BulwarkProcessor;
+use std::collections::HashMap;
+use std::path::Path;
+
+fn check_request(path: &str, headers: &[(&str, &str)]) -> tonic::Request<CheckRequest> {
+    let mut header_map: HashMap<String, String> = HashMap::from([
+        (":authority".to_string(), "example.com".to_string()),
+        (":method".to_string(), "GET".to_string()),
+        (":path".to_string(), path.to_string()),
+    ]);
+    for (key, value) in headers {
+        header_map.insert(key.to_string(), value.to_string());
+    }
+    tonic::Request::new(CheckRequest {
+        attributes: Some(AttributeContext {
+            request: Some(attribute_context::Request {
+                time: None,
+                http: Some(attribute_context::HttpRequest {
+                    method: "GET".to_string(),
+                    path: path.to_string(),
+                    host: "example.com".to_string(),
+                    scheme: "http".to_string(),
+                    headers: header_map,
+                    ..Default::default()
+                }),
+            }),
+            ..Default::default()
+        }),
+    })
+}
+
+#[tokio::test]
+async fn test_ext_authz_evil_bit() -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+
+    bulwark_build::build_plugin(
+        base.join("../crates/sdk/examples/evil-bit"),
+        base.join("dist/plugins/bulwark_evil_bit.wasm"),
+        &[],
+        true,
+    )?;
+    assert!(base.join("dist/plugins/bulwark_evil_bit.wasm").exists());
+
+    let config_root = bulwark_config::toml::load_config(&base.join("bulwark.toml"))?;
+    let bulwark_processor = BulwarkProcessor::new(config_root).await?;
+
+    // A friendly request is allowed and the decision is forwarded to the interior service.
+    let response = bulwark_processor
+        .check(check_request("/", &[("x-forwarded-for", "203.0.113.1")]))
+        .await?
+        .into_inner();
+    assert_eq!(response.status.map(|status| status.code), Some(0));
+    match response.http_response {
+        Some(check_response::HttpResponse::OkResponse(ok_response)) => {
+            let headers: Vec<(String, String)> = ok_response
+                .headers
+                .into_iter()
+                .filter_map(|option| option.header)
+                .map(|header| (header.key, header.value))
+                .collect();
+            assert_eq!(
+                headers,
+                vec![(
+                    "bulwark-decision".to_string(),
+                    "accept=0.0, restrict=0.0, unknown=1.0, score=0.5, outcome=\"accepted\""
+                        .to_string()
+                )]
+            );
+        }
+        _ => panic!("expected ok response"),
+    }
+
+    // An evil request is denied.
+    let response = bulwark_processor
+        .check(check_request("/", &[("evil", "true")]))
+        .await?
+        .into_inner();
+    assert_eq!(response.status.map(|status| status.code), Some(7));
+    match response.http_response {
+        Some(check_response::HttpResponse::DeniedResponse(denied_response)) => {
+            assert_eq!(denied_response.status.map(|status| status.code), Some(403));
+            assert_eq!(denied_response.body, "Access Denied\n");
+            let headers: Vec<(String, String)> = denied_response
+                .headers
+                .into_iter()
+                .filter_map(|option| option.header)
+                .map(|header| (header.key, header.value))
+                .collect();
+            assert_eq!(
+                headers,
+                vec![
+                    (
+                        "bulwark-decision".to_string(),
+                        "accept=0.0, restrict=1.0, unknown=0.0, score=1.0, outcome=\"restricted\""
+                            .to_string()
+                    ),
+                    ("bulwark-tags".to_string(), "evil".to_string()),
+                    (
+                        "content-type".to_string(),
+                        "text/plain; charset=utf-8".to_string()
+                    )
+                ]
+            );
+        }
+        _ => panic!("expected denied response"),
+    }
+
+    // A request without http attributes is rejected.
+    let result = bulwark_processor
+        .check(tonic::Request::new(CheckRequest { attributes: None }))
+        .await;
+    assert_eq!(
+        result.err().map(|status| status.code()),
+        Some(tonic::Code::InvalidArgument)
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_ext_authz_unmatched_route() -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+
+    bulwark_build::build_plugin(
+        base.join("../crates/sdk/examples/evil-bit"),
+        base.join("dist/plugins/bulwark_evil_bit.wasm"),
+        &[],
+        true,
+    )?;
+    assert!(base.join("dist/plugins/bulwark_evil_bit.wasm").exists());
+
+    // Only `/protected` and `/api` are routed in this config.
+    let config_root = bulwark_config::toml::load_config(&base.join("reverse_proxy.toml"))?;
+    let bulwark_processor = BulwarkProcessor::new(config_root).await?;
+
+    // A request to an unmatched route has any spoofed Bulwark headers removed.
+    let response = bulwark_processor
+        .check(check_request(
+            "/unprotected",
+            &[("bulwark-decision", "outcome=\"trusted\"")],
+        ))
+        .await?
+        .into_inner();
+    assert_eq!(response.status.map(|status| status.code), Some(0));
+    match response.http_response {
+        Some(check_response::HttpResponse::OkResponse(ok_response)) => {
+            assert!(ok_response.headers.is_empty());
+            assert_eq!(
+                ok_response.headers_to_remove,
+                vec!["bulwark-decision".to_string(), "bulwark-tags".to_string()]
+            );
+        }
+        _ => panic!("expected ok response"),
+    }
+
+    Ok(())
+}
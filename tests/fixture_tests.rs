@@ -0,0 +1,64 @@
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn test_fixtures_pass() -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+
+    bulwark_build::build_plugin(
+        base.join("../crates/sdk/examples/evil-bit"),
+        base.join("dist/plugins/bulwark_evil_bit.wasm"),
+        &[],
+        true,
+    )?;
+    assert!(base.join("dist/plugins/bulwark_evil_bit.wasm").exists());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_bulwark-cli"))
+        .arg("test")
+        .arg("-c")
+        .arg(base.join("bulwark.toml"))
+        .arg(base.join("fixtures/evil_bit.toml"))
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("test friendly requests are accepted ... ok"));
+    assert!(stdout.contains("test evil requests are restricted ... ok"));
+    assert!(stdout.contains("test evil posts are restricted ... ok"));
+    assert!(stdout.contains("test result: ok. 3 passed; 0 failed"));
+
+    Ok(())
+}
+
+#[test]
+fn test_fixtures_fail() -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+
+    bulwark_build::build_plugin(
+        base.join("../crates/sdk/examples/evil-bit"),
+        base.join("dist/plugins/bulwark_evil_bit.wasm"),
+        &[],
+        true,
+    )?;
+    assert!(base.join("dist/plugins/bulwark_evil_bit.wasm").exists());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_bulwark-cli"))
+        .arg("test")
+        .arg("-c")
+        .arg(base.join("bulwark.toml"))
+        .arg(base.join("fixtures/evil_bit_failing.toml"))
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+    assert!(stdout.contains("test evil requests are accepted ... FAILED"));
+    assert!(stdout.contains("expected outcome 'accepted', got 'restricted'"));
+    assert!(stdout.contains("missing tags: [friendly]"));
+    // Per-plugin decisions are printed for failed cases.
+    assert!(stdout.contains(
+        "plugin 'evil_bit': accept=0.000 restrict=1.000 unknown=0.000 score=1.000 tags=[evil]"
+    ));
+    assert!(stdout.contains("test result: FAILED. 0 passed; 1 failed"));
+
+    Ok(())
+}
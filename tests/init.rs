@@ -0,0 +1,46 @@
+use bulwark_ext_processor::BulwarkProcessor;
+use std::path::Path;
+
+#[tokio::test]
+async fn test_plugins_initialized() -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+
+    bulwark_build::build_plugin(
+        base.join("../crates/sdk/examples/evil-bit"),
+        base.join("dist/plugins/bulwark_evil_bit.wasm"),
+        &[],
+        true,
+    )?;
+    assert!(base.join("dist/plugins/bulwark_evil_bit.wasm").exists());
+
+    let config_root = bulwark_config::toml::load_config(&base.join("bulwark.toml"))?;
+    let bulwark_processor = BulwarkProcessor::new(config_root).await?;
+    assert!(bulwark_processor.plugins_initialized());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_plugin_init_failure() -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+
+    bulwark_build::build_plugin(
+        base.join("plugins/redis-plugin"),
+        base.join("dist/plugins/redis_plugin.wasm"),
+        &[],
+        true,
+    )?;
+    assert!(base.join("dist/plugins/redis_plugin.wasm").exists());
+
+    // A plugin whose init function fails is still loaded, but the failure is reported rather than retried per request.
+    let config_root = bulwark_config::toml::load_config(&base.join("init_failing.toml"))?;
+    let bulwark_processor = BulwarkProcessor::new(config_root).await?;
+    assert!(!bulwark_processor.plugins_initialized());
+
+    // Reloading a working config clears the failure.
+    let config_root = bulwark_config::toml::load_config(&base.join("bulwark.toml"))?;
+    bulwark_processor.reload(config_root).await?;
+    assert!(bulwark_processor.plugins_initialized());
+
+    Ok(())
+}
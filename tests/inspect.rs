@@ -0,0 +1,69 @@
+use std::path::Path;
+use std::process::Command;
+
+/// Runs the `inspect` subcommand, returning its exit status and output.
+fn inspect(plugin_path: &Path) -> Result<(Option<i32>, String), Box<dyn std::error::Error>> {
+    let output = Command::new(env!("CARGO_BIN_EXE_bulwark-cli"))
+        .arg("inspect")
+        .arg(plugin_path)
+        .output()?;
+    Ok((output.status.code(), String::from_utf8(output.stdout)?))
+}
+
+#[test]
+fn test_inspect() -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+
+    bulwark_build::build_plugin(
+        base.join("../crates/sdk/examples/evil-bit"),
+        base.join("dist/plugins/bulwark_evil_bit.wasm"),
+        &[],
+        true,
+    )?;
+    let plugin_path = base.join("dist/plugins/bulwark_evil_bit.wasm");
+
+    let (status, stdout) = inspect(&plugin_path)?;
+    assert_eq!(status, Some(0), "{}", stdout);
+    assert!(stdout.contains(&format!(
+        "world:    bulwark:plugin@{}",
+        bulwark_host::PLUGIN_WORLD_VERSION
+    )));
+    // The SDK's no-op defaults for the other handlers aren't reported.
+    assert!(stdout.contains("handlers: handle-request-decision\n"));
+    assert!(stdout.contains(&format!(
+        "  bulwark:plugin/http-handlers@{}\n    handle-init\n",
+        bulwark_host::PLUGIN_WORLD_VERSION
+    )));
+    assert!(stdout.contains("  bulwark:plugin/config@"));
+    assert!(!stdout.contains("warning:"), "{}", stdout);
+
+    // A plugin built against an older SDK imports and exports interfaces the host no longer has.
+    let stale_path =
+        std::env::temp_dir().join(format!("bulwark-inspect-{}.wasm", std::process::id()));
+    std::fs::write(
+        &stale_path,
+        wat::parse_str(
+            r#"(component
+                (import "bulwark:plugin/config@0.4.0" (instance
+                    (export "config-keys" (func (result (list string))))
+                ))
+                (import "wasi:cli/environment@0.2.0" (instance
+                    (export "get-arguments" (func (result (list string))))
+                ))
+            )"#,
+        )?,
+    )?;
+    let (status, stdout) = inspect(&stale_path)?;
+    assert_eq!(status, Some(0), "{}", stdout);
+    assert!(stdout.contains("world:    bulwark:plugin@0.4.0"));
+    assert!(stdout.contains("handlers: unknown"));
+    assert!(stdout.contains(&format!(
+        "warning: plugin targets bulwark:plugin@0.4.0, but the host supports bulwark:plugin@{}",
+        bulwark_host::PLUGIN_WORLD_VERSION
+    )));
+    assert!(stdout.contains("warning: plugin imports `bulwark:plugin/config@0.4.0`"));
+    assert!(!stdout.contains("warning: plugin imports `wasi:cli/environment@0.2.0`"));
+
+    std::fs::remove_file(&stale_path)?;
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn test_lock() -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+
+    bulwark_build::build_plugin(
+        base.join("../crates/sdk/examples/blank-slate"),
+        base.join("dist/plugins/bulwark_blank_slate.wasm"),
+        &[],
+        true,
+    )?;
+    let plugin_path = std::fs::canonicalize(base.join("dist/plugins/bulwark_blank_slate.wasm"))?;
+
+    // The lockfile is written alongside the config, so the config gets a directory of its own.
+    let config_dir = std::env::temp_dir().join(format!("bulwark-lock-{}", std::process::id()));
+    std::fs::create_dir_all(&config_dir)?;
+    let config_path = config_dir.join("bulwark.toml");
+    let lockfile_path = config_dir.join(bulwark_config::LOCKFILE_NAME);
+    std::fs::write(
+        &config_path,
+        format!(
+            "[[plugin]]\nref = \"blank_slate\"\npath = \"{}\"\n\n[[resource]]\nroutes = [\"/\"]\nplugins = [\"blank_slate\"]\n",
+            plugin_path.display()
+        ),
+    )?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_bulwark-cli"))
+        .arg("lock")
+        .arg("-c")
+        .arg(&config_path)
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("locked 1 plugins"));
+
+    let lockfile = bulwark_config::Lockfile::read(&lockfile_path)?.expect("lockfile should exist");
+    let digest = lockfile
+        .plugins
+        .get("blank_slate")
+        .expect("plugin should be locked");
+    assert_eq!(digest.len(), 32);
+
+    let check = |unlocked: bool| -> Result<(Option<i32>, String), Box<dyn std::error::Error>> {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_bulwark-cli"));
+        command.arg("check").arg("-c").arg(&config_path);
+        if unlocked {
+            command.arg("--unlocked");
+        }
+        let output = command.output()?;
+        Ok((output.status.code(), String::from_utf8(output.stdout)?))
+    };
+
+    let (status, stdout) = check(false)?;
+    assert_eq!(status, Some(0), "{}", stdout);
+
+    // A plugin that no longer matches the lockfile is rejected, unless the lockfile is ignored.
+    let mut stale = lockfile.clone();
+    stale
+        .plugins
+        .insert(String::from("blank_slate"), bytes::Bytes::from(vec![0; 32]));
+    stale.write(&lockfile_path)?;
+    let (status, stdout) = check(false)?;
+    assert_eq!(status, Some(1), "{}", stdout);
+    assert!(stdout.contains("plugin 'blank_slate' does not match the lockfile"));
+    let (status, stdout) = check(true)?;
+    assert_eq!(status, Some(0), "{}", stdout);
+
+    std::fs::remove_dir_all(&config_dir)?;
+    Ok(())
+}
@@ -0,0 +1,119 @@
+use bulwark_host::{
+    Plugin, PluginCtx, PluginExecutionError, PluginInstance, RedisCtx, ScriptRegistry,
+};
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+/// Loads the greedy plugin with the given limits.
+fn load_greedy_plugin(
+    guest_config: bulwark_config::Plugin,
+) -> Result<Arc<Plugin>, Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+
+    bulwark_build::build_plugin(
+        base.join("plugins/greedy-plugin"),
+        base.join("dist/plugins/greedy_plugin.wasm"),
+        &[],
+        true,
+    )?;
+    assert!(base.join("dist/plugins/greedy_plugin.wasm").exists());
+
+    Ok(Arc::new(Plugin::from_file(
+        base.join("dist/plugins/greedy_plugin.wasm"),
+        // None of this config will get read during this test.
+        &bulwark_config::Config {
+            service: bulwark_config::Service::default(),
+            runtime: bulwark_config::Runtime::default(),
+            state: bulwark_config::State::default(),
+            thresholds: bulwark_config::Thresholds::default(),
+            headers: bulwark_config::Headers::default(),
+            block: bulwark_config::BlockResponse::default(),
+            actions: bulwark_config::Actions::default(),
+            metrics: bulwark_config::Metrics::default(),
+            secrets: vec![],
+            trust: vec![],
+            plugins: vec![],
+            presets: vec![],
+            resources: vec![],
+        },
+        &bulwark_config::Plugin {
+            reference: String::from("greedy_plugin"),
+            ..guest_config
+        },
+    )?))
+}
+
+/// Instantiates a plugin.
+async fn instantiate(plugin: Arc<Plugin>) -> Result<PluginInstance, Box<dyn std::error::Error>> {
+    let redis_ctx = RedisCtx {
+        pool: None,
+        registry: Arc::new(ScriptRegistry::default()),
+    };
+    let plugin_ctx = PluginCtx::new(plugin.clone(), HashMap::new(), redis_ctx)?;
+    Ok(PluginInstance::new(plugin, plugin_ctx).await?)
+}
+
+/// Calls the greedy plugin's request decision handler for a request to the given path.
+async fn exec_greedy(
+    plugin_instance: &mut PluginInstance,
+    path: &str,
+) -> Result<(), PluginExecutionError> {
+    let request = Arc::new(
+        http::Request::builder()
+            .method("GET")
+            .uri(path)
+            .version(http::Version::HTTP_11)
+            .body(bytes::Bytes::new())
+            .unwrap(),
+    );
+    plugin_instance
+        .handle_request_decision(request, HashMap::new())
+        .await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_max_memory() -> Result<(), Box<dyn std::error::Error>> {
+    let plugin = load_greedy_plugin(bulwark_config::Plugin {
+        max_memory: Some(16 * 1024 * 1024),
+        ..Default::default()
+    })?;
+    let mut plugin_instance = instantiate(plugin).await?;
+
+    // Requests that stay within the limit are unaffected.
+    exec_greedy(&mut plugin_instance, "/").await?;
+
+    let err = exec_greedy(&mut plugin_instance, "/memory")
+        .await
+        .err()
+        .expect("plugin should exceed its memory limit");
+    assert!(matches!(err, PluginExecutionError::MemoryLimitExceeded(limit) if limit == "memory"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_max_stdio_size() -> Result<(), Box<dyn std::error::Error>> {
+    let plugin = load_greedy_plugin(bulwark_config::Plugin {
+        max_stdio_size: Some(64 * 1024),
+        ..Default::default()
+    })?;
+    let mut plugin_instance = instantiate(plugin).await?;
+
+    let err = exec_greedy(&mut plugin_instance, "/stdout")
+        .await
+        .err()
+        .expect("plugin should exceed its stdout limit");
+    assert!(matches!(err, PluginExecutionError::MemoryLimitExceeded(limit) if limit == "stdout"));
+    assert!(plugin_instance.stdio().stdout_buffer().len() <= 64 * 1024);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_max_instances() -> Result<(), Box<dyn std::error::Error>> {
+    // Every plugin component is made up of several core instances, so it can't be instantiated with a limit of one.
+    let plugin = load_greedy_plugin(bulwark_config::Plugin {
+        max_instances: Some(1),
+        ..Default::default()
+    })?;
+    assert!(instantiate(plugin).await.is_err());
+    Ok(())
+}
@@ -0,0 +1,221 @@
+use axum::{
+    extract::Path as UrlPath,
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use bulwark_host::{Plugin, PluginLoadError};
+use sha2::{Digest, Sha256};
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    sync::Arc,
+};
+
+/// The token the stand-in registry requires.
+const REGISTRY_TOKEN: &str = "Bearer registry-token";
+
+/// Creates a config that loads a single plugin from an OCI registry, caching it in the given directory.
+fn config(
+    reference: &str,
+    access: bulwark_config::PluginAccess,
+    verification: bulwark_config::PluginVerification,
+    cache_dir: &Path,
+) -> Result<bulwark_config::Config, Box<dyn std::error::Error>> {
+    Ok(bulwark_config::Config {
+        service: bulwark_config::Service::default(),
+        runtime: bulwark_config::Runtime {
+            plugin_cache_dir: Some(cache_dir.to_path_buf()),
+            plugin_fetch_timeout: 5000,
+            plugin_fetch_retries: 0,
+            ..Default::default()
+        },
+        state: bulwark_config::State::default(),
+        thresholds: bulwark_config::Thresholds::default(),
+        headers: bulwark_config::Headers::default(),
+        block: bulwark_config::BlockResponse::default(),
+        actions: bulwark_config::Actions::default(),
+        metrics: bulwark_config::Metrics::default(),
+        secrets: vec![bulwark_config::Secret {
+            reference: String::from("registry_token"),
+            location: bulwark_config::SecretLocation::EnvVar(String::from(
+                "BULWARK_TEST_REGISTRY_TOKEN",
+            )),
+        }],
+        trust: vec![],
+        plugins: vec![bulwark_config::Plugin {
+            reference: String::from("blank_slate"),
+            location: bulwark_config::PluginLocation::Oci(reference.parse()?),
+            access,
+            verification,
+            ..Default::default()
+        }],
+        presets: vec![],
+        resources: vec![],
+    })
+}
+
+/// Loads the only plugin in the config.
+async fn load(config: &bulwark_config::Config) -> Result<Plugin, PluginLoadError> {
+    Plugin::from_config(config, &config.plugins[0]).await
+}
+
+#[tokio::test]
+async fn test_oci_plugin() -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+
+    bulwark_build::build_plugin(
+        base.join("../crates/sdk/examples/blank-slate"),
+        base.join("dist/plugins/bulwark_blank_slate.wasm"),
+        &[],
+        true,
+    )?;
+    let wasm_bytes = std::fs::read(base.join("dist/plugins/bulwark_blank_slate.wasm"))?;
+    let wasm_digest = Sha256::digest(&wasm_bytes);
+    let layer_digest = format!("sha256:{}", hex::encode(wasm_digest));
+    let manifest = serde_json::to_vec(&serde_json::json!({
+        "schemaVersion": 2,
+        "mediaType": "application/vnd.oci.image.manifest.v1+json",
+        "config": {
+            "mediaType": "application/vnd.oci.empty.v1+json",
+            "digest": "sha256:44136fa355b3678a1146ad16f7e8649e94fb4fc21fe77e8310c060f61caaff8a",
+            "size": 2,
+        },
+        "layers": [{
+            "mediaType": "application/wasm",
+            "digest": layer_digest,
+            "size": wasm_bytes.len(),
+        }],
+    }))?;
+    let manifest_digest = hex::encode(Sha256::digest(&manifest));
+
+    std::env::set_var("BULWARK_TEST_REGISTRY_TOKEN", REGISTRY_TOKEN);
+    let cache_dir: PathBuf =
+        std::env::temp_dir().join(format!("bulwark-oci-plugin-{}", std::process::id()));
+    std::fs::remove_dir_all(&cache_dir).ok();
+
+    // A stand-in for a registry's distribution API. It serves the same manifest for any tag or digest, so pinned
+    // digests have to be checked by the client.
+    let blob_requests = Arc::new(AtomicUsize::new(0));
+    let app = {
+        let blob_requests = blob_requests.clone();
+        let authorized = |headers: &HeaderMap| {
+            headers
+                .get(header::AUTHORIZATION)
+                .is_some_and(|value| value == REGISTRY_TOKEN)
+        };
+        Router::new()
+            .route(
+                "/v2/team/blank_slate/manifests/:reference",
+                get(move |headers: HeaderMap| async move {
+                    if !authorized(&headers) {
+                        return Err(StatusCode::UNAUTHORIZED);
+                    }
+                    Ok((
+                        [(
+                            header::CONTENT_TYPE,
+                            "application/vnd.oci.image.manifest.v1+json",
+                        )],
+                        manifest,
+                    )
+                        .into_response())
+                }),
+            )
+            .route(
+                "/v2/team/blank_slate/blobs/:digest",
+                get(
+                    move |UrlPath(digest): UrlPath<String>, headers: HeaderMap| async move {
+                        blob_requests.fetch_add(1, Ordering::SeqCst);
+                        if !authorized(&headers) {
+                            return Err(StatusCode::UNAUTHORIZED);
+                        }
+                        if digest != layer_digest {
+                            return Err(StatusCode::NOT_FOUND);
+                        }
+                        Ok(wasm_bytes)
+                    },
+                ),
+            )
+    };
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let registry = listener.local_addr()?.to_string();
+    let server = tokio::spawn(async move { axum::serve(listener, app).await });
+
+    let authorized = bulwark_config::PluginAccess::Header(String::from("registry_token"));
+    let tagged = config(
+        &format!("{}/team/blank_slate:1.0", registry),
+        authorized.clone(),
+        bulwark_config::PluginVerification::None,
+        &cache_dir,
+    )?;
+
+    // The registry's credentials come from the plugin's authorization secret.
+    load(&tagged).await?;
+    assert_eq!(blob_requests.load(Ordering::SeqCst), 1);
+    let anonymous = config(
+        &format!("{}/team/blank_slate:1.0", registry),
+        bulwark_config::PluginAccess::None,
+        bulwark_config::PluginVerification::None,
+        &cache_dir,
+    )?;
+    assert!(matches!(
+        load(&anonymous).await,
+        Err(PluginLoadError::HttpError(err))
+            if err.status().map(|status| status.as_u16()) == Some(401)
+    ));
+
+    // Layers are addressed by digest, so they're loaded from the cache once they've been fetched.
+    load(&tagged).await?;
+    assert_eq!(blob_requests.load(Ordering::SeqCst), 1);
+
+    // A reference pinned to a manifest digest only loads that manifest.
+    let pinned = config(
+        &format!(
+            "{}/team/blank_slate:1.0@sha256:{}",
+            registry, manifest_digest
+        ),
+        authorized.clone(),
+        bulwark_config::PluginVerification::None,
+        &cache_dir,
+    )?;
+    load(&pinned).await?;
+    let mispinned = config(
+        &format!(
+            "{}/team/blank_slate:1.0@sha256:{}",
+            registry,
+            "0".repeat(64)
+        ),
+        authorized.clone(),
+        bulwark_config::PluginVerification::None,
+        &cache_dir,
+    )?;
+    assert!(matches!(
+        load(&mispinned).await,
+        Err(PluginLoadError::VerificationError(..))
+    ));
+
+    // The plugin's own verification still applies to the layer.
+    let verified = config(
+        &format!("{}/team/blank_slate:1.0", registry),
+        authorized.clone(),
+        bulwark_config::PluginVerification::Sha256(bytes::Bytes::from(wasm_digest.to_vec())),
+        &cache_dir,
+    )?;
+    load(&verified).await?;
+    let misverified = config(
+        &format!("{}/team/blank_slate:1.0", registry),
+        authorized,
+        bulwark_config::PluginVerification::Sha256(bytes::Bytes::from(vec![0; 32])),
+        &cache_dir,
+    )?;
+    assert!(matches!(
+        load(&misverified).await,
+        Err(PluginLoadError::VerificationError(..))
+    ));
+
+    server.abort();
+    server.await.ok();
+    std::fs::remove_dir_all(&cache_dir)?;
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use bulwark_host::{Plugin, PluginCtx, PluginEngine, PluginInstance, RedisCtx, ScriptRegistry};
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+/// Counts the compiled plugins in the cache directory.
+fn cached_artifacts(cache_dir: &Path) -> Result<usize, std::io::Error> {
+    let mut count = 0;
+    for entry in std::fs::read_dir(cache_dir)? {
+        if entry?.path().extension() == Some("cwasm".as_ref()) {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+#[tokio::test]
+async fn test_plugin_cache() -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+
+    bulwark_build::build_plugin(
+        base.join("../crates/sdk/examples/blank-slate"),
+        base.join("dist/plugins/bulwark_blank_slate.wasm"),
+        &[],
+        true,
+    )?;
+    assert!(base.join("dist/plugins/bulwark_blank_slate.wasm").exists());
+
+    let cache_dir =
+        std::env::temp_dir().join(format!("bulwark-plugin-cache-{}", std::process::id()));
+    std::fs::remove_dir_all(&cache_dir).ok();
+
+    let plugin_config = bulwark_config::Plugin {
+        reference: String::from("blank_slate"),
+        location: bulwark_config::PluginLocation::Local(
+            base.join("dist/plugins/bulwark_blank_slate.wasm"),
+        ),
+        ..Default::default()
+    };
+    // A second reference to the same plugin contents.
+    let duplicate_config = bulwark_config::Plugin {
+        reference: String::from("duplicate_slate"),
+        ..plugin_config.clone()
+    };
+    let config = bulwark_config::Config {
+        service: bulwark_config::Service::default(),
+        runtime: bulwark_config::Runtime {
+            plugin_cache_dir: Some(cache_dir.clone()),
+            ..Default::default()
+        },
+        state: bulwark_config::State::default(),
+        thresholds: bulwark_config::Thresholds::default(),
+        headers: bulwark_config::Headers::default(),
+        block: bulwark_config::BlockResponse::default(),
+        actions: bulwark_config::Actions::default(),
+        metrics: bulwark_config::Metrics::default(),
+        secrets: vec![],
+        trust: vec![],
+        plugins: vec![plugin_config.clone(), duplicate_config.clone()],
+        presets: vec![],
+        resources: vec![],
+    };
+
+    // Loading the same contents twice compiles and caches them once.
+    let engine = PluginEngine::new(&config)?;
+    Plugin::from_engine(&engine, &config, &plugin_config).await?;
+    Plugin::from_engine(&engine, &config, &duplicate_config).await?;
+    assert_eq!(cached_artifacts(&cache_dir)?, 1);
+
+    // A new engine, as created by a reload or restart, loads the plugin from the cache.
+    let engine = PluginEngine::new(&config)?;
+    let plugin = Arc::new(Plugin::from_engine(&engine, &config, &plugin_config).await?);
+    assert_eq!(cached_artifacts(&cache_dir)?, 1);
+
+    let redis_ctx = RedisCtx {
+        pool: None,
+        registry: Arc::new(ScriptRegistry::default()),
+    };
+    let plugin_ctx = PluginCtx::new(plugin.clone(), HashMap::new(), redis_ctx)?;
+    let mut plugin_instance = PluginInstance::new(plugin, plugin_ctx).await?;
+    plugin_instance.handle_init().await?;
+
+    std::fs::remove_dir_all(&cache_dir)?;
+    Ok(())
+}
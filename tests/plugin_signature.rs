@@ -0,0 +1,137 @@
+use bulwark_host::{Plugin, PluginLoadError, SIGNATURE_EXTENSION};
+use ring::rand::SystemRandom;
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use std::path::Path;
+
+/// Generates a new Ed25519 key pair.
+fn generate_key_pair() -> Result<Ed25519KeyPair, Box<dyn std::error::Error>> {
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())?;
+    Ok(Ed25519KeyPair::from_pkcs8(pkcs8.as_ref())?)
+}
+
+/// Creates a config that trusts the given key pairs and loads a single plugin with ed25519 verification.
+fn config(
+    trusted: &[&Ed25519KeyPair],
+    location: bulwark_config::PluginLocation,
+    signature: Option<&[u8]>,
+) -> bulwark_config::Config {
+    bulwark_config::Config {
+        service: bulwark_config::Service::default(),
+        runtime: bulwark_config::Runtime::default(),
+        state: bulwark_config::State::default(),
+        thresholds: bulwark_config::Thresholds::default(),
+        headers: bulwark_config::Headers::default(),
+        block: bulwark_config::BlockResponse::default(),
+        actions: bulwark_config::Actions::default(),
+        metrics: bulwark_config::Metrics::default(),
+        secrets: vec![],
+        trust: vec![bulwark_config::Trust {
+            reference: String::from("release"),
+            ed25519: trusted
+                .iter()
+                .map(|key_pair| bytes::Bytes::copy_from_slice(key_pair.public_key().as_ref()))
+                .collect(),
+        }],
+        plugins: vec![bulwark_config::Plugin {
+            reference: String::from("blank_slate"),
+            location,
+            verification: bulwark_config::PluginVerification::Ed25519 {
+                trust: String::from("release"),
+                signature: signature.map(bytes::Bytes::copy_from_slice),
+            },
+            ..Default::default()
+        }],
+        presets: vec![],
+        resources: vec![],
+    }
+}
+
+/// Loads the only plugin in the config.
+async fn load(config: &bulwark_config::Config) -> Result<Plugin, PluginLoadError> {
+    Plugin::from_config(config, &config.plugins[0]).await
+}
+
+#[tokio::test]
+async fn test_plugin_signature() -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+    let wasm_path = base.join("dist/plugins/signed_blank_slate.wasm");
+    bulwark_build::build_plugin(
+        base.join("../crates/sdk/examples/blank-slate"),
+        &wasm_path,
+        &[],
+        true,
+    )?;
+    let wasm_bytes = bytes::Bytes::from(std::fs::read(&wasm_path)?);
+
+    let old_key = generate_key_pair()?;
+    let new_key = generate_key_pair()?;
+    let untrusted_key = generate_key_pair()?;
+    let signature = new_key.sign(&wasm_bytes);
+    let bytes = bulwark_config::PluginLocation::Bytes(wasm_bytes.clone());
+
+    // A signature from any trusted key is accepted.
+    load(&config(
+        &[&old_key, &new_key],
+        bytes.clone(),
+        Some(signature.as_ref()),
+    ))
+    .await?;
+
+    // A signature from an untrusted key is rejected.
+    assert!(matches!(
+        load(&config(
+            &[&old_key, &untrusted_key],
+            bytes.clone(),
+            Some(signature.as_ref()),
+        ))
+        .await,
+        Err(PluginLoadError::InvalidSignature(trust)) if trust == "release"
+    ));
+
+    // So is a plugin that doesn't match its signature.
+    let mut tampered = wasm_bytes.to_vec();
+    tampered.extend_from_slice(b"tampered");
+    assert!(matches!(
+        load(&config(
+            &[&new_key],
+            bulwark_config::PluginLocation::Bytes(tampered.into()),
+            Some(signature.as_ref()),
+        ))
+        .await,
+        Err(PluginLoadError::InvalidSignature(_))
+    ));
+
+    // Plugins loaded from bytes have no signature file to fall back on.
+    assert!(matches!(
+        load(&config(&[&new_key], bytes.clone(), None)).await,
+        Err(PluginLoadError::SignatureMissing(_))
+    ));
+
+    // A local plugin's signature is read from alongside it, either hex-encoded or raw.
+    let local = bulwark_config::PluginLocation::Local(wasm_path.clone());
+    let mut signature_path = wasm_path.clone().into_os_string();
+    signature_path.push(format!(".{}", SIGNATURE_EXTENSION));
+    assert!(matches!(
+        load(&config(&[&new_key], local.clone(), None)).await,
+        Err(PluginLoadError::SignatureMissing(_))
+    ));
+    std::fs::write(&signature_path, hex::encode(signature.as_ref()) + "\n")?;
+    load(&config(&[&new_key], local.clone(), None)).await?;
+    std::fs::write(&signature_path, signature.as_ref())?;
+    load(&config(&[&new_key], local.clone(), None)).await?;
+    assert!(matches!(
+        load(&config(&[&untrusted_key], local.clone(), None)).await,
+        Err(PluginLoadError::InvalidSignature(_))
+    ));
+
+    // The trust must exist.
+    let mut missing_trust = config(&[&new_key], bytes, Some(signature.as_ref()));
+    missing_trust.trust.clear();
+    assert!(matches!(
+        load(&missing_trust).await,
+        Err(PluginLoadError::TrustMissing(trust)) if trust == "release"
+    ));
+
+    std::fs::remove_file(&signature_path)?;
+    Ok(())
+}
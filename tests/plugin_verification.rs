@@ -0,0 +1,100 @@
+use bulwark_host::{Plugin, PluginLoadError};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// Creates a config that loads a single plugin with the given verification.
+fn config(
+    location: bulwark_config::PluginLocation,
+    verification: bulwark_config::PluginVerification,
+    require_verification: bool,
+) -> bulwark_config::Config {
+    bulwark_config::Config {
+        service: bulwark_config::Service::default(),
+        runtime: bulwark_config::Runtime {
+            require_verification,
+            ..Default::default()
+        },
+        state: bulwark_config::State::default(),
+        thresholds: bulwark_config::Thresholds::default(),
+        headers: bulwark_config::Headers::default(),
+        block: bulwark_config::BlockResponse::default(),
+        actions: bulwark_config::Actions::default(),
+        metrics: bulwark_config::Metrics::default(),
+        secrets: vec![],
+        trust: vec![],
+        plugins: vec![bulwark_config::Plugin {
+            reference: String::from("blank_slate"),
+            location,
+            verification,
+            ..Default::default()
+        }],
+        presets: vec![],
+        resources: vec![],
+    }
+}
+
+/// Loads the only plugin in the config.
+async fn load(config: &bulwark_config::Config) -> Result<Plugin, PluginLoadError> {
+    Plugin::from_config(config, &config.plugins[0]).await
+}
+
+#[tokio::test]
+async fn test_plugin_verification() -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+    let wasm_path = base.join("dist/plugins/bulwark_blank_slate.wasm");
+    bulwark_build::build_plugin(
+        base.join("../crates/sdk/examples/blank-slate"),
+        &wasm_path,
+        &[],
+        true,
+    )?;
+    let wasm_bytes = bytes::Bytes::from(std::fs::read(&wasm_path)?);
+    let digest = bytes::Bytes::from(Sha256::digest(&wasm_bytes).to_vec());
+    let mut wrong_digest = digest.to_vec();
+    wrong_digest[0] ^= 0xff;
+    let wrong_digest = bytes::Bytes::from(wrong_digest);
+
+    let locations = [
+        bulwark_config::PluginLocation::Local(wasm_path.clone()),
+        bulwark_config::PluginLocation::Bytes(wasm_bytes.clone()),
+    ];
+    for location in locations {
+        // Every location is checked against its digest.
+        let plugin = load(&config(
+            location.clone(),
+            bulwark_config::PluginVerification::Sha256(digest.clone()),
+            true,
+        ))
+        .await?;
+        assert_eq!(plugin.digest(), &digest[..]);
+        assert!(matches!(
+            load(&config(
+                location.clone(),
+                bulwark_config::PluginVerification::Sha256(wrong_digest.clone()),
+                false,
+            ))
+            .await,
+            Err(PluginLoadError::VerificationError(..))
+        ));
+
+        // Unverified plugins only load if verification isn't required, and still report their digest.
+        let plugin = load(&config(
+            location.clone(),
+            bulwark_config::PluginVerification::None,
+            false,
+        ))
+        .await?;
+        assert_eq!(plugin.digest(), &digest[..]);
+        assert!(matches!(
+            load(&config(
+                location,
+                bulwark_config::PluginVerification::None,
+                true
+            ))
+            .await,
+            Err(PluginLoadError::VerificationRequired(reference)) if reference == "blank_slate"
+        ));
+    }
+
+    Ok(())
+}
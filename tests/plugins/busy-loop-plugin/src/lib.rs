@@ -0,0 +1,18 @@
+use bulwark_sdk::*;
+use std::collections::HashMap;
+
+pub struct BusyLoopPlugin;
+
+#[bulwark_plugin]
+impl HttpHandlers for BusyLoopPlugin {
+    /// Never returns, without ever yielding to the host.
+    fn handle_request_decision(
+        _request: Request,
+        _labels: HashMap<String, String>,
+    ) -> Result<HandlerOutput, Error> {
+        let mut counter: u64 = 0;
+        loop {
+            counter = std::hint::black_box(counter.wrapping_add(1));
+        }
+    }
+}
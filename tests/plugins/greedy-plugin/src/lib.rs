@@ -0,0 +1,28 @@
+use bulwark_sdk::*;
+use std::collections::HashMap;
+
+pub struct GreedyPlugin;
+
+#[bulwark_plugin]
+impl HttpHandlers for GreedyPlugin {
+    /// Uses far more of whichever resource the request path names than any reasonable limit allows.
+    fn handle_request_decision(
+        request: Request,
+        _labels: HashMap<String, String>,
+    ) -> Result<HandlerOutput, Error> {
+        match request.uri().path() {
+            "/memory" => {
+                let buffer = vec![1u8; 64 * 1024 * 1024];
+                std::hint::black_box(buffer);
+            }
+            "/stdout" => {
+                let line = "x".repeat(1023);
+                for _ in 0..1024 {
+                    println!("{line}");
+                }
+            }
+            _ => {}
+        }
+        Ok(HandlerOutput::default())
+    }
+}
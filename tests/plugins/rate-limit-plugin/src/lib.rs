@@ -0,0 +1,28 @@
+use bulwark_sdk::*;
+use std::collections::HashMap;
+
+pub struct RateLimitPlugin;
+
+#[bulwark_plugin]
+impl HttpHandlers for RateLimitPlugin {
+    /// Pretends the client has exceeded a rate limit when it sends an `X-Rate-Limited` header.
+    fn handle_request_decision(
+        request: Request,
+        _labels: HashMap<String, String>,
+    ) -> Result<HandlerOutput, Error> {
+        let mut output = HandlerOutput::default();
+        if request.headers().contains_key("X-Rate-Limited") {
+            output.tags = vec!["rate-limited".to_string()];
+            output.action = Some(Action::Block {
+                status: 429,
+                headers: vec![("Retry-After".to_string(), "60".to_string())],
+            });
+        } else if request.headers().contains_key("X-Challenge-Passed") {
+            output.action = Some(Action::AddHeader {
+                name: "Bulwark-Challenge".to_string(),
+                value: "passed".to_string(),
+            });
+        }
+        Ok(output)
+    }
+}
@@ -0,0 +1,182 @@
+use bulwark_ext_processor::BulwarkProcessor;
+use bulwark_host::{Plugin, PluginInstantiationError, PluginPool, RedisCtx, ScriptRegistry};
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+fn request(uri: &str, headers: &[(&str, &str)]) -> Result<bulwark_sdk::Request, http::Error> {
+    let mut request = http::Request::builder().uri(uri);
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    request.body(bytes::Bytes::new())
+}
+
+/// A plugin whose `init` function always fails with the message `broken`.
+///
+/// Handlers return pointers to results laid out in memory by the canonical ABI. The result at 512 is all zeroes,
+/// which is a successful, empty result for every handler other than `init`. The result at 1024 is an `other` error.
+const FAILING_INIT_PLUGIN: &str = r#"(module
+    (memory (export "memory") 2)
+    (global $heap (mut i32) (i32.const 4096))
+    (data (i32.const 1024) "\01\00\00\00\00\00\00\00\00\08\00\00\06\00\00\00")
+    (data (i32.const 2048) "broken")
+    (func (export "cabi_realloc") (param i32 i32 i32 i32) (result i32)
+        (local $ptr i32)
+        (local.set $ptr
+            (i32.and
+                (i32.add (global.get $heap) (i32.sub (local.get 2) (i32.const 1)))
+                (i32.sub (i32.const 0) (local.get 2))))
+        (global.set $heap (i32.add (local.get $ptr) (local.get 3)))
+        (local.get $ptr))
+    (func (export "bulwark:plugin/http-handlers@0.6.0#handle-init") (result i32)
+        (i32.const 1024))
+    (func (export "bulwark:plugin/http-handlers@0.6.0#handle-request-enrichment")
+        (param i32 i32 i32) (result i32)
+        (i32.const 512))
+    (func (export "bulwark:plugin/http-handlers@0.6.0#handle-request-decision")
+        (param i32 i32 i32) (result i32)
+        (i32.const 512))
+    (func (export "bulwark:plugin/http-handlers@0.6.0#handle-response-decision")
+        (param i32 i32 i32 i32) (result i32)
+        (i32.const 512))
+    (func (export "bulwark:plugin/http-handlers@0.6.0#handle-decision-feedback")
+        (param i32 i32 i32 i32 f64 f64 f64 i32 i32 i32) (result i32)
+        (i32.const 512))
+)"#;
+
+#[tokio::test]
+async fn test_pooled_instances() -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+
+    bulwark_build::build_plugin(
+        base.join("../crates/sdk/examples/evil-bit"),
+        base.join("dist/plugins/bulwark_evil_bit.wasm"),
+        &[],
+        true,
+    )?;
+    assert!(base.join("dist/plugins/bulwark_evil_bit.wasm").exists());
+
+    let config_root = bulwark_config::toml::load_config(&base.join("pool.toml"))?;
+    let bulwark_processor = BulwarkProcessor::new(config_root).await?;
+
+    // Enough requests that instances are both reused and replaced, alternating so that any state carried over
+    // from the previous request would change the outcome.
+    for _ in 0..5 {
+        let evaluation = bulwark_processor
+            .evaluate(request("/", &[("Evil", "true")])?, None)
+            .await?
+            .expect("resource should match");
+        assert_eq!(evaluation.verdict.outcome, bulwark_sdk::Outcome::Restricted);
+        assert!(evaluation.action_response.is_some());
+
+        let evaluation = bulwark_processor
+            .evaluate(request("/", &[])?, None)
+            .await?
+            .expect("resource should match");
+        assert_eq!(evaluation.verdict.outcome, bulwark_sdk::Outcome::Accepted);
+        assert!(evaluation.action_response.is_none());
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_init_runs_once_per_instance() -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+
+    bulwark_build::build_plugin(
+        base.join("../crates/sdk/examples/evil-bit"),
+        base.join("dist/plugins/bulwark_evil_bit.wasm"),
+        &[],
+        true,
+    )?;
+    let config_root = bulwark_config::toml::load_config(&base.join("pool.toml"))?;
+    let plugin = Arc::new(Plugin::from_file(
+        base.join("dist/plugins/bulwark_evil_bit.wasm"),
+        &config_root,
+        &config_root.plugins[0],
+    )?);
+    let redis_ctx = RedisCtx {
+        pool: None,
+        registry: Arc::new(ScriptRegistry::default()),
+    };
+    let pool = PluginPool::new(plugin, HashMap::new(), redis_ctx, 2, 3);
+
+    // Loading the plugin fills the pool, initializing each instance.
+    pool.initialize().await?;
+    assert_eq!(pool.init_count(), 2);
+
+    // Requests served by pooled instances don't run init again, until the instances are used up.
+    for _ in 0..6 {
+        let mut instance = pool.acquire().await?;
+        let output = instance
+            .handle_request_decision(Arc::new(request("/", &[("Evil", "true")])?), HashMap::new())
+            .await?;
+        assert_eq!(output.decision.restrict, 1.0);
+        pool.release(instance);
+    }
+    assert_eq!(pool.init_count(), 2);
+    assert!(pool.needs_fill());
+
+    // Refilling the pool initializes the new instances off the request path.
+    pool.fill().await?;
+    assert_eq!(pool.init_count(), 4);
+    pool.acquire().await?;
+    assert_eq!(pool.init_count(), 4);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_init_failure_sticks() -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+
+    let mut resolve = wit_parser::Resolve::default();
+    let (package, _) = resolve.push_dir(&base.join("../wit"))?;
+    let world = resolve.select_world(package, Some("http-detection"))?;
+    let mut module = wat::parse_str(FAILING_INIT_PLUGIN)?;
+    wit_component::embed_component_metadata(
+        &mut module,
+        &resolve,
+        world,
+        wit_component::StringEncoding::UTF8,
+    )?;
+    let component = wit_component::ComponentEncoder::default()
+        .module(&module)?
+        .validate(true)
+        .encode()?;
+
+    let config_root = bulwark_config::toml::load_config(&base.join("pool.toml"))?;
+    let plugin = Arc::new(Plugin::from_bytes(
+        String::from("failing-init"),
+        &component,
+        &config_root,
+        &bulwark_config::Plugin::default(),
+    )?);
+    let redis_ctx = RedisCtx {
+        pool: None,
+        registry: Arc::new(ScriptRegistry::default()),
+    };
+    let pool = PluginPool::new(plugin, HashMap::new(), redis_ctx, 2, 3);
+
+    // Filling the pool stops at the first failure, and the instance that failed isn't pooled.
+    let result = pool.initialize().await;
+    assert!(matches!(
+        result,
+        Err(PluginInstantiationError::Initialization(message)) if message.contains("broken")
+    ));
+    assert_eq!(pool.init_count(), 1);
+    assert!(!pool.needs_fill());
+
+    // Each request gets an instance of its own, and the error is kept even though the pool is never refilled.
+    for count in 2..=3 {
+        let mut instance = pool.acquire().await?;
+        instance
+            .handle_request_decision(Arc::new(request("/", &[])?), HashMap::new())
+            .await?;
+        pool.release(instance);
+        assert_eq!(pool.init_count(), count);
+        assert!(pool.init_error().is_some());
+    }
+
+    Ok(())
+}
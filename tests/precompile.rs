@@ -0,0 +1,106 @@
+use bulwark_host::{
+    Plugin, PluginCtx, PluginEngine, PluginInstance, RedisCtx, ScriptRegistry,
+    PRECOMPILED_EXTENSION,
+};
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+/// Builds a copy of the blank slate plugin under its own name, so that its precompiled artifact doesn't affect
+/// other tests.
+fn build_blank_slate(base: &Path) -> Result<std::path::PathBuf, Box<dyn std::error::Error>> {
+    let wasm_path = base.join("dist/plugins/precompiled_blank_slate.wasm");
+    bulwark_build::build_plugin(
+        base.join("../crates/sdk/examples/blank-slate"),
+        &wasm_path,
+        &[],
+        true,
+    )?;
+    assert!(wasm_path.exists());
+    Ok(wasm_path)
+}
+
+/// Creates a config that loads the plugin at the given path, trusting its precompiled artifact, with an optional
+/// fuel limit.
+fn config(wasm_path: &Path, max_fuel: Option<u64>) -> bulwark_config::Config {
+    bulwark_config::Config {
+        service: bulwark_config::Service::default(),
+        runtime: bulwark_config::Runtime {
+            trust_precompiled: true,
+            ..Default::default()
+        },
+        state: bulwark_config::State::default(),
+        thresholds: bulwark_config::Thresholds::default(),
+        headers: bulwark_config::Headers::default(),
+        block: bulwark_config::BlockResponse::default(),
+        actions: bulwark_config::Actions::default(),
+        metrics: bulwark_config::Metrics::default(),
+        secrets: vec![],
+        trust: vec![],
+        plugins: vec![bulwark_config::Plugin {
+            reference: String::from("blank_slate"),
+            location: bulwark_config::PluginLocation::Local(wasm_path.to_path_buf()),
+            max_fuel,
+            ..Default::default()
+        }],
+        presets: vec![],
+        resources: vec![],
+    }
+}
+
+/// Loads the plugin described by the config and runs its `init` function.
+async fn load_and_init(config: &bulwark_config::Config) -> Result<(), Box<dyn std::error::Error>> {
+    let engine = PluginEngine::new(config)?;
+    let plugin = Arc::new(Plugin::from_engine(&engine, config, &config.plugins[0]).await?);
+    let redis_ctx = RedisCtx {
+        pool: None,
+        registry: Arc::new(ScriptRegistry::default()),
+    };
+    let plugin_ctx = PluginCtx::new(plugin.clone(), HashMap::new(), redis_ctx)?;
+    let mut plugin_instance = PluginInstance::new(plugin, plugin_ctx).await?;
+    plugin_instance.handle_init().await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_precompiled_plugin() -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+    let wasm_path = build_blank_slate(base)?;
+    let precompiled_path = wasm_path.with_extension(PRECOMPILED_EXTENSION);
+    let config = config(&wasm_path, None);
+
+    let wasm_bytes = std::fs::read(&wasm_path)?;
+    let artifact = PluginEngine::new(&config)?.precompile(&wasm_bytes)?;
+    assert!(artifact.starts_with(b"bulwark-precompiled-v1\nwasmtime "));
+    std::fs::write(&precompiled_path, &artifact)?;
+
+    // The precompiled plugin is loaded by an engine with the same configuration.
+    load_and_init(&config).await?;
+
+    // An engine with a different configuration compiles the plugin instead.
+    load_and_init(&self::config(&wasm_path, Some(u64::MAX))).await?;
+
+    // So does an engine that finds an artifact with a valid header that wasmtime can't load.
+    let header_len = artifact
+        .windows(2)
+        .position(|window| window == b"\n\n")
+        .expect("artifact should have a header")
+        + 2;
+    let mut invalid = artifact[..header_len].to_vec();
+    invalid.extend_from_slice(b"not a compiled component");
+    std::fs::write(&precompiled_path, &invalid)?;
+    load_and_init(&config).await?;
+
+    // The header can be forged by anyone who can write the artifact, so a component that doesn't implement the
+    // plugin's handlers is loaded in its place when the artifact is trusted, and ignored when it isn't.
+    let mut forged = artifact[..header_len].to_vec();
+    forged.extend_from_slice(
+        &PluginEngine::new(&config)?.precompile(&wat::parse_str("(component)")?)?[header_len..],
+    );
+    std::fs::write(&precompiled_path, &forged)?;
+    assert!(load_and_init(&config).await.is_err());
+    let mut untrusted = config.clone();
+    untrusted.runtime.trust_precompiled = false;
+    load_and_init(&untrusted).await?;
+
+    std::fs::remove_file(&precompiled_path)?;
+    Ok(())
+}
@@ -32,8 +32,12 @@ async fn test_redis_plugin() -> Result<(), Box<dyn std::error::Error>> {
             ..Default::default()
         },
         thresholds: bulwark_config::Thresholds::default(),
+        headers: bulwark_config::Headers::default(),
+        block: bulwark_config::BlockResponse::default(),
+        actions: bulwark_config::Actions::default(),
         metrics: bulwark_config::Metrics::default(),
         secrets: vec![],
+        trust: vec![],
         plugins: vec![bulwark_config::Plugin {
             reference: "redis_plugin".to_string(),
             location: bulwark_config::PluginLocation::Local(PathBuf::from(
@@ -51,6 +55,12 @@ async fn test_redis_plugin() -> Result<(), Box<dyn std::error::Error>> {
                 http: vec![],
                 state: vec!["test".to_string(), "bulwark".to_string()],
             },
+            max_fuel: None,
+            epoch_deadline: None,
+            max_memory: None,
+            max_table_elements: None,
+            max_instances: None,
+            max_stdio_size: None,
         }],
         presets: vec![],
         resources: vec![],
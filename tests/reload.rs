@@ -0,0 +1,79 @@
+use bulwark_ext_processor::BulwarkProcessor;
+use std::path::Path;
+
+fn request(uri: &str, headers: &[(&str, &str)]) -> Result<bulwark_sdk::Request, http::Error> {
+    let mut request = http::Request::builder().uri(uri);
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    request.body(bytes::Bytes::new())
+}
+
+#[tokio::test]
+async fn test_reload() -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+
+    bulwark_build::build_plugin(
+        base.join("../crates/sdk/examples/evil-bit"),
+        base.join("dist/plugins/bulwark_evil_bit.wasm"),
+        &[],
+        true,
+    )?;
+    assert!(base.join("dist/plugins/bulwark_evil_bit.wasm").exists());
+
+    let config_root = bulwark_config::toml::load_config(&base.join("reverse_proxy.toml"))?;
+    let bulwark_processor = BulwarkProcessor::new(config_root).await?;
+    // Services hold their own clone of the processor, which must see the reloaded router too.
+    let service_processor = bulwark_processor.clone();
+
+    assert!(service_processor
+        .evaluate(request("/", &[])?, None)
+        .await?
+        .is_none());
+    let evaluation = service_processor
+        .evaluate(request("/api", &[("Evil", "true")])?, None)
+        .await?
+        .expect("resource should match");
+    let response = evaluation
+        .action_response
+        .expect("request should be blocked");
+    assert_eq!(response.status(), 429);
+
+    let config_root = bulwark_config::toml::load_config(&base.join("bulwark.toml"))?;
+    bulwark_processor.reload(config_root).await?;
+
+    // The new resources replace the old ones.
+    assert!(service_processor
+        .evaluate(request("/", &[])?, None)
+        .await?
+        .is_some());
+    let evaluation = service_processor
+        .evaluate(request("/api", &[("Evil", "true")])?, None)
+        .await?
+        .expect("resource should match");
+    let response = evaluation
+        .action_response
+        .expect("request should be blocked");
+    assert_eq!(response.status(), 403);
+
+    // Thresholds are swapped in along with the resources. A plugin with no opinion has a score of 0.5.
+    let mut config_root = bulwark_config::toml::load_config(&base.join("bulwark.toml"))?;
+    config_root.thresholds.suspicious = 0.4;
+    bulwark_processor.reload(config_root).await?;
+    let evaluation = service_processor
+        .evaluate(request("/", &[])?, None)
+        .await?
+        .expect("resource should match");
+    assert_eq!(evaluation.verdict.outcome, bulwark_sdk::Outcome::Suspected);
+
+    // A config whose plugins fail to load leaves the current one in place.
+    let config_root = bulwark_config::toml::load_config(&base.join("reload_invalid.toml"))?;
+    assert!(bulwark_processor.reload(config_root).await.is_err());
+    let evaluation = service_processor
+        .evaluate(request("/api", &[("Evil", "true")])?, None)
+        .await?
+        .expect("resource should match");
+    assert_eq!(evaluation.verdict.outcome, bulwark_sdk::Outcome::Restricted);
+
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use axum::{http::StatusCode, routing::get, Router};
+use bulwark_host::{Plugin, PluginLoadError};
+use sha2::{Digest, Sha256};
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    sync::Arc,
+};
+
+/// Creates a config that loads a single remote plugin, caching it in the given directory.
+fn config(
+    url: &str,
+    verification: bulwark_config::PluginVerification,
+    cache_dir: &Path,
+) -> Result<bulwark_config::Config, Box<dyn std::error::Error>> {
+    Ok(bulwark_config::Config {
+        service: bulwark_config::Service::default(),
+        runtime: bulwark_config::Runtime {
+            plugin_cache_dir: Some(cache_dir.to_path_buf()),
+            plugin_fetch_timeout: 5000,
+            plugin_fetch_retries: 3,
+            ..Default::default()
+        },
+        state: bulwark_config::State::default(),
+        thresholds: bulwark_config::Thresholds::default(),
+        headers: bulwark_config::Headers::default(),
+        block: bulwark_config::BlockResponse::default(),
+        actions: bulwark_config::Actions::default(),
+        metrics: bulwark_config::Metrics::default(),
+        secrets: vec![],
+        trust: vec![],
+        plugins: vec![bulwark_config::Plugin {
+            reference: String::from("blank_slate"),
+            location: bulwark_config::PluginLocation::Remote(url.parse()?),
+            verification,
+            ..Default::default()
+        }],
+        presets: vec![],
+        resources: vec![],
+    })
+}
+
+/// Loads the only plugin in the config.
+async fn load(config: &bulwark_config::Config) -> Result<Plugin, PluginLoadError> {
+    Plugin::from_config(config, &config.plugins[0]).await
+}
+
+#[tokio::test]
+async fn test_remote_plugin() -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+
+    bulwark_build::build_plugin(
+        base.join("../crates/sdk/examples/blank-slate"),
+        base.join("dist/plugins/bulwark_blank_slate.wasm"),
+        &[],
+        true,
+    )?;
+    let wasm_bytes = std::fs::read(base.join("dist/plugins/bulwark_blank_slate.wasm"))?;
+    let digest = bytes::Bytes::from(Sha256::digest(&wasm_bytes).to_vec());
+
+    let cache_dir: PathBuf =
+        std::env::temp_dir().join(format!("bulwark-remote-plugin-{}", std::process::id()));
+    std::fs::remove_dir_all(&cache_dir).ok();
+
+    // The server fails the first two requests it receives, to exercise retries.
+    let requests = Arc::new(AtomicUsize::new(0));
+    let app = {
+        let requests = requests.clone();
+        Router::new().route(
+            "/blank_slate.wasm",
+            get(move || async move {
+                if requests.fetch_add(1, Ordering::SeqCst) < 2 {
+                    Err(StatusCode::SERVICE_UNAVAILABLE)
+                } else {
+                    Ok(wasm_bytes)
+                }
+            }),
+        )
+    };
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}/blank_slate.wasm", listener.local_addr()?);
+    let server = tokio::spawn(async move { axum::serve(listener, app).await });
+
+    let unverified = config(&url, bulwark_config::PluginVerification::None, &cache_dir)?;
+    let verified = config(
+        &url,
+        bulwark_config::PluginVerification::Sha256(digest.clone()),
+        &cache_dir,
+    )?;
+    let mut wrong_digest = digest.to_vec();
+    wrong_digest[0] ^= 0xff;
+    let misverified = config(
+        &url,
+        bulwark_config::PluginVerification::Sha256(bytes::Bytes::from(wrong_digest)),
+        &cache_dir,
+    )?;
+
+    // Server errors are retried.
+    load(&unverified).await?;
+    assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+    // Verification is still applied to fetched plugins.
+    assert!(matches!(
+        load(&misverified).await,
+        Err(PluginLoadError::VerificationError(..))
+    ));
+
+    load(&verified).await?;
+    let fetched = requests.load(Ordering::SeqCst);
+    // A plugin with a digest is loaded from the cache without fetching it again.
+    load(&verified).await?;
+    assert_eq!(requests.load(Ordering::SeqCst), fetched);
+
+    // Once the remote is unreachable, the last verified copy is used instead.
+    server.abort();
+    server.await.ok();
+    load(&unverified).await?;
+    load(&verified).await?;
+    assert!(load(&misverified).await.is_err());
+
+    std::fs::remove_dir_all(&cache_dir)?;
+    Ok(())
+}
@@ -0,0 +1,194 @@
+use axum::{http::HeaderMap, routing::get, Router};
+use bulwark_ext_processor::BulwarkProxy;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::Path;
+use tokio::task::JoinSet;
+
+const SERVER_LAUNCH_DELAY: std::time::Duration = std::time::Duration::from_millis(100);
+
+#[tokio::test]
+async fn test_reverse_proxy_evil_bit() -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+
+    bulwark_build::build_plugin(
+        base.join("../crates/sdk/examples/evil-bit"),
+        base.join("dist/plugins/bulwark_evil_bit.wasm"),
+        &[],
+        true,
+    )?;
+    assert!(base.join("dist/plugins/bulwark_evil_bit.wasm").exists());
+
+    let mut tasks: JoinSet<std::result::Result<(), anyhow::Error>> = JoinSet::new();
+
+    let config_root = bulwark_config::toml::load_config(&base.join("reverse_proxy.toml"))?;
+    let port = config_root.service.port;
+    let upstream_addr = config_root
+        .service
+        .upstream
+        .as_ref()
+        .and_then(|upstream| upstream.socket_addrs(|| None).ok())
+        .and_then(|addrs| addrs.first().copied())
+        .expect("upstream should be set");
+    let bulwark_proxy = BulwarkProxy::new(config_root).await?;
+
+    {
+        tasks.spawn(async move {
+            let app = Router::new()
+                .route(
+                    "/protected",
+                    get(|headers: HeaderMap| async move {
+                        // echo the forwarded headers back so they can be checked
+                        let header = |name| {
+                            headers
+                                .get(name)
+                                .and_then(|value| value.to_str().ok())
+                                .unwrap_or("none")
+                                .to_string()
+                        };
+                        format!(
+                            "hello-world\ndecision: {}\ntags: {}\n",
+                            header("bulwark-decision"),
+                            header("bulwark-tags")
+                        )
+                    }),
+                )
+                .route("/api", get(|| async { "api" }))
+                .route(
+                    "/protected/slow",
+                    get(|| async {
+                        tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+                        "slow"
+                    }),
+                )
+                .route(
+                    "/unprotected",
+                    get(|headers: HeaderMap| async move {
+                        format!(
+                            "unprotected\ndecision: {}\n",
+                            headers
+                                .get("bulwark-decision")
+                                .and_then(|value| value.to_str().ok())
+                                .unwrap_or("none")
+                        )
+                    }),
+                );
+            let listener = tokio::net::TcpListener::bind(upstream_addr).await?;
+            axum::serve(listener, app)
+                .await
+                .map_err(anyhow::Error::from)
+        });
+        tasks.spawn(async move {
+            bulwark_proxy
+                .serve(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port))
+                .await
+                .map_err(anyhow::Error::from)
+        });
+    }
+
+    // Avoid test flakiness by making sure everything has started up before making assertions.
+    let mut tries = 0;
+    loop {
+        tries += 1;
+
+        // wait for the servers to finish starting before sending requests
+        tokio::time::sleep(SERVER_LAUNCH_DELAY).await;
+
+        // send a throw-away request to make sure everything's launched correctly
+        let result = reqwest::get(format!("http://127.0.0.1:{}/", port)).await;
+        if result.is_ok_and(|response| !response.status().is_server_error()) || tries > 20 {
+            break;
+        }
+    }
+
+    // send a friendly request through the proxy
+    let response = reqwest::get(format!("http://127.0.0.1:{}/protected", port)).await?;
+    assert!(response.status().is_success());
+    let body = response.text().await?;
+    assert!(body.contains("hello-world"));
+    assert!(body.contains(
+        "decision: accept=0.0, restrict=0.0, unknown=1.0, score=0.5, outcome=\"accepted\"\n"
+    ));
+
+    // headers with no value are removed rather than passed through from the client
+    let client = reqwest::Client::new();
+    let response = client
+        .get(format!("http://127.0.0.1:{}/protected", port))
+        .header("Bulwark-Tags", "spoofed")
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+    let body = response.text().await?;
+    assert!(body.contains("tags: none\n"));
+
+    // send an evil request through the proxy
+    let response = client
+        .get(format!("http://127.0.0.1:{}/protected", port))
+        .header("Evil", "true")
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 403);
+    let body = response.text().await?;
+    assert!(body.contains("Access Denied"));
+
+    // resources can override the block response
+    let response = client
+        .get(format!("http://127.0.0.1:{}/api", port))
+        .header("Evil", "true")
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 429);
+    assert_eq!(response.headers()["content-type"], "application/json");
+    assert_eq!(response.headers()["retry-after"], "60");
+    let body = response.text().await?;
+    assert_eq!(body, r#"{"error": "blocked", "tags": "evil"}"#);
+
+    // send a friendly request through the proxy over HTTP/2
+    let h2_client = reqwest::Client::builder().http2_prior_knowledge().build()?;
+    let response = h2_client
+        .get(format!("http://127.0.0.1:{}/protected", port))
+        .send()
+        .await?;
+    assert_eq!(response.version(), reqwest::Version::HTTP_2);
+    assert!(response.status().is_success());
+    let body = response.text().await?;
+    assert!(body.contains("hello-world"));
+
+    // request bodies larger than the maximum size are rejected
+    let response = client
+        .post(format!("http://127.0.0.1:{}/protected", port))
+        .body(vec![b'a'; 2048])
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 413);
+
+    // upstreams that take too long to respond time out
+    let response = client
+        .get(format!("http://127.0.0.1:{}/protected/slow", port))
+        .send()
+        .await?;
+    assert_eq!(response.status().as_u16(), 504);
+
+    // requests that don't match a resource are forwarded without evaluation
+    let response = client
+        .get(format!("http://127.0.0.1:{}/unprotected", port))
+        .header("Evil", "true")
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+    let body = response.text().await?;
+    assert!(body.contains("unprotected"));
+
+    // but Bulwark's headers are still stripped so that they can't be spoofed
+    let response = client
+        .get(format!("http://127.0.0.1:{}/unprotected", port))
+        .header("Bulwark-Decision", "outcome=\"trusted\"")
+        .send()
+        .await?;
+    assert!(response.status().is_success());
+    let body = response.text().await?;
+    assert!(body.contains("decision: none\n"));
+
+    tasks.abort_all();
+
+    Ok(())
+}
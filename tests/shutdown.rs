@@ -0,0 +1,63 @@
+use bulwark_ext_processor::protobuf::envoy::service::auth::v3::{
+    attribute_context, authorization_server::Authorization, AttributeContext, CheckRequest,
+};
+use bulwark_ext_processor::BulwarkProcessor;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+fn check_request(path: &str) -> tonic::Request<CheckRequest> {
+    let headers: HashMap<String, String> = HashMap::from([
+        (":authority".to_string(), "example.com".to_string()),
+        (":method".to_string(), "GET".to_string()),
+        (":path".to_string(), path.to_string()),
+    ]);
+    tonic::Request::new(CheckRequest {
+        attributes: Some(AttributeContext {
+            request: Some(attribute_context::Request {
+                time: None,
+                http: Some(attribute_context::HttpRequest {
+                    method: "GET".to_string(),
+                    path: path.to_string(),
+                    host: "example.com".to_string(),
+                    scheme: "http".to_string(),
+                    headers,
+                    ..Default::default()
+                }),
+            }),
+            ..Default::default()
+        }),
+    })
+}
+
+#[tokio::test]
+async fn test_drain() -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+
+    bulwark_build::build_plugin(
+        base.join("../crates/sdk/examples/evil-bit"),
+        base.join("dist/plugins/bulwark_evil_bit.wasm"),
+        &[],
+        true,
+    )?;
+    assert!(base.join("dist/plugins/bulwark_evil_bit.wasm").exists());
+
+    let config_root = bulwark_config::toml::load_config(&base.join("bulwark.toml"))?;
+    let bulwark_processor = BulwarkProcessor::new(config_root).await?;
+    // Services hold their own clone of the processor, which must see that draining has started.
+    let service_processor = bulwark_processor.clone();
+
+    assert!(service_processor.check(check_request("/")).await.is_ok());
+
+    // Nothing is in flight, so draining finishes immediately.
+    assert!(bulwark_processor.drain(Duration::from_secs(1)).await);
+
+    // New requests are turned away once draining has started.
+    let status = service_processor
+        .check(check_request("/"))
+        .await
+        .expect_err("request should be rejected");
+    assert_eq!(status.code(), tonic::Code::Unavailable);
+
+    Ok(())
+}
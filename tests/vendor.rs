@@ -0,0 +1,50 @@
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn test_vendor() -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+
+    bulwark_build::build_plugin(
+        base.join("../crates/sdk/examples/evil-bit"),
+        base.join("dist/plugins/bulwark_evil_bit.wasm"),
+        &[],
+        true,
+    )?;
+    let plugin_bytes = std::fs::read(base.join("dist/plugins/bulwark_evil_bit.wasm"))?;
+
+    let output_dir =
+        std::env::temp_dir().join(format!("bulwark-vendor-cli-{}", std::process::id()));
+    let output = Command::new(env!("CARGO_BIN_EXE_bulwark-cli"))
+        .arg("vendor")
+        .arg("-c")
+        .arg(base.join("bulwark.toml"))
+        .arg(&output_dir)
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("bulwark.toml"));
+
+    assert_eq!(
+        std::fs::read(output_dir.join("plugins/evil_bit.wasm"))?,
+        plugin_bytes
+    );
+    let config = bulwark_config::toml::load_config(&output_dir.join("bulwark.toml"))?;
+    assert!(matches!(
+        config.plugins[0].verification,
+        bulwark_config::PluginVerification::Sha256(_)
+    ));
+
+    // The vendored config stands on its own.
+    let output = Command::new(env!("CARGO_BIN_EXE_bulwark-cli"))
+        .arg("check")
+        .arg("-c")
+        .arg(output_dir.join("bulwark.toml"))
+        .output()?;
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(output.status.success(), "{}", stdout);
+    assert!(stdout.contains("  ok  plugin 'evil_bit'"));
+
+    std::fs::remove_dir_all(&output_dir)?;
+    Ok(())
+}
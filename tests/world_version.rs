@@ -0,0 +1,180 @@
+use bulwark_host::{
+    Plugin, PluginCtx, PluginInstance, PluginLoadError, RedisCtx, ScriptRegistry, WorldVersion,
+};
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+fn config() -> bulwark_config::Config {
+    bulwark_config::Config {
+        service: bulwark_config::Service::default(),
+        runtime: bulwark_config::Runtime::default(),
+        state: bulwark_config::State::default(),
+        thresholds: bulwark_config::Thresholds::default(),
+        headers: bulwark_config::Headers::default(),
+        block: bulwark_config::BlockResponse::default(),
+        actions: bulwark_config::Actions::default(),
+        metrics: bulwark_config::Metrics::default(),
+        secrets: vec![],
+        trust: vec![],
+        plugins: vec![],
+        presets: vec![],
+        resources: vec![],
+    }
+}
+
+/// A plugin built against `bulwark:plugin@0.5.0`, which restricts every request and tags it `legacy`.
+///
+/// Handlers return pointers to results laid out in memory by the canonical ABI. The result at 512 is all zeroes,
+/// which is a successful, empty result for every handler that doesn't return a decision. The result at 1024 is a
+/// successful `handler-output` with a `restricted` value of 1.0 and a single tag.
+const PREVIOUS_WORLD_PLUGIN: &str = r#"(module
+    (memory (export "memory") 2)
+    (global $heap (mut i32) (i32.const 4096))
+    (data (i32.const 1048) "\00\00\00\00\00\00\f0\3f")
+    (data (i32.const 1064) "\00\08\00\00\01\00\00\00")
+    (data (i32.const 2048) "\08\08\00\00\06\00\00\00legacy")
+    (func (export "cabi_realloc") (param i32 i32 i32 i32) (result i32)
+        (local $ptr i32)
+        (local.set $ptr
+            (i32.and
+                (i32.add (global.get $heap) (i32.sub (local.get 2) (i32.const 1)))
+                (i32.sub (i32.const 0) (local.get 2))))
+        (global.set $heap (i32.add (local.get $ptr) (local.get 3)))
+        (local.get $ptr))
+    (func (export "bulwark:plugin/http-handlers@0.5.0#handle-init") (result i32)
+        (i32.const 512))
+    (func (export "bulwark:plugin/http-handlers@0.5.0#handle-request-enrichment")
+        (param i32 i32 i32) (result i32)
+        (i32.const 512))
+    (func (export "bulwark:plugin/http-handlers@0.5.0#handle-request-decision")
+        (param i32 i32 i32) (result i32)
+        (i32.const 1024))
+    (func (export "bulwark:plugin/http-handlers@0.5.0#handle-response-decision")
+        (param i32 i32 i32 i32) (result i32)
+        (i32.const 1024))
+    (func (export "bulwark:plugin/http-handlers@0.5.0#handle-decision-feedback")
+        (param i32 i32 i32 i32 f64 f64 f64 i32 i32 i32) (result i32)
+        (i32.const 512))
+)"#;
+
+#[test]
+fn test_world_version_compatibility() {
+    assert_eq!(
+        WorldVersion::from_version(WorldVersion::CURRENT.version()),
+        Some(WorldVersion::CURRENT)
+    );
+    // Later patch versions of a supported version load through the same bindings.
+    assert_eq!(
+        WorldVersion::from_version("0.5.3"),
+        Some(WorldVersion::V0_5)
+    );
+    assert_eq!(
+        WorldVersion::from_version("0.6.1"),
+        Some(WorldVersion::V0_6)
+    );
+    assert_eq!(WorldVersion::from_version("0.4.0"), None);
+    assert_eq!(WorldVersion::from_version("0.7.0"), None);
+    assert_eq!(WorldVersion::from_version("5.0.0"), None);
+    assert!(!WorldVersion::CURRENT.is_deprecated());
+    assert!(WorldVersion::V0_5.is_deprecated());
+    assert!(WorldVersion::SUPPORTED.contains(&WorldVersion::CURRENT));
+}
+
+#[test]
+fn test_unsupported_world_version() -> Result<(), Box<dyn std::error::Error>> {
+    // A plugin's handlers are exported under the world version it was built against.
+    let result = Plugin::from_wat(
+        String::from("stale"),
+        r#"(component
+            (instance $handlers)
+            (export "bulwark:plugin/http-handlers@0.4.0" (instance $handlers))
+        )"#,
+        &config(),
+        &bulwark_config::Plugin::default(),
+    );
+    assert!(matches!(
+        result,
+        Err(PluginLoadError::UnsupportedWorldVersion(version)) if version == "0.4.0"
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_previous_world_version() -> Result<(), Box<dyn std::error::Error>> {
+    let base = Path::new(file!()).parent().unwrap_or(Path::new("."));
+
+    // Build the plugin against the previous version's WIT, as an SDK from that release would have.
+    let mut resolve = wit_parser::Resolve::default();
+    let (package, _) = resolve.push_dir(&base.join("../crates/host/wit-0.5"))?;
+    let world = resolve.select_world(package, Some("http-detection"))?;
+    let mut module = wat::parse_str(PREVIOUS_WORLD_PLUGIN)?;
+    wit_component::embed_component_metadata(
+        &mut module,
+        &resolve,
+        world,
+        wit_component::StringEncoding::UTF8,
+    )?;
+    let component = wit_component::ComponentEncoder::default()
+        .module(&module)?
+        .validate(true)
+        .encode()?;
+
+    let config = config();
+    let plugin = Arc::new(Plugin::from_bytes(
+        String::from("previous"),
+        &component,
+        &config,
+        &bulwark_config::Plugin::default(),
+    )?);
+    assert_eq!(plugin.world_version(), WorldVersion::V0_5);
+    assert!(plugin.world_version().is_deprecated());
+
+    let redis_ctx = RedisCtx {
+        pool: None,
+        registry: Arc::new(ScriptRegistry::default()),
+    };
+    let plugin_ctx = PluginCtx::new(plugin.clone(), HashMap::new(), redis_ctx)?;
+    let mut plugin_instance = tokio_test::block_on(PluginInstance::new(plugin, plugin_ctx))?;
+    let request = Arc::new(
+        http::Request::builder()
+            .uri("/")
+            .body(bytes::Bytes::new())?,
+    );
+    let response = Arc::new(
+        http::Response::builder()
+            .status(200)
+            .body(bytes::Bytes::new())?,
+    );
+    let labels = HashMap::from([(String::from("client"), String::from("test"))]);
+
+    // Every handler is called through the previous version's bindings and adapted to the current version's types.
+    tokio_test::block_on(plugin_instance.handle_init())?;
+    let new_labels = tokio_test::block_on(
+        plugin_instance.handle_request_enrichment(request.clone(), labels.clone()),
+    )?;
+    assert!(new_labels.is_empty());
+    let handler_output = tokio_test::block_on(
+        plugin_instance.handle_request_decision(request.clone(), labels.clone()),
+    )?;
+    assert_eq!(handler_output.decision.restrict, 1.0);
+    assert!(handler_output.tags.contains("legacy"));
+    // The previous version can't request actions.
+    assert!(handler_output.action.is_none());
+    let handler_output = tokio_test::block_on(plugin_instance.handle_response_decision(
+        request.clone(),
+        response.clone(),
+        labels.clone(),
+    ))?;
+    assert_eq!(handler_output.decision.restrict, 1.0);
+    tokio_test::block_on(plugin_instance.handle_decision_feedback(
+        request,
+        response,
+        labels,
+        bulwark_sdk::Verdict {
+            decision: handler_output.decision,
+            outcome: bulwark_sdk::Outcome::Restricted,
+            tags: vec![String::from("legacy")],
+        },
+    ))?;
+
+    Ok(())
+}